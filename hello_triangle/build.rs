@@ -1,8 +1,37 @@
+/// 着色器源码及其 `#include` 的头文件都要复制到可执行文件旁边。
+const SHADER_FILES: [&str; 2] = ["shaders.hlsl", "common.hlsli"];
+
+/// 设置了这个环境变量时，用 fxc 把三角形的着色器预编译成 .cso，程序启动时直接加载字节码。
+/// fxc 要在 PATH 中，例如在 Visual Studio 的开发者命令提示符里构建。
+const PRECOMPILE_ENV: &str = "PRECOMPILE_SHADERS";
+
+/// 入口函数、目标和输出的文件名，文件名与 `devices::PRECOMPILED_*_SHADER` 一致。
+const PRECOMPILED_SHADERS: [(&str, &str, &str); 2] = [
+    ("VSMain", "vs_5_0", "shaders_vs.cso"),
+    ("PSMain", "ps_5_0", "shaders_ps.cso"),
+];
+
 fn main() {
-    println!("!cargo:rerun-if-changed=src/shaders.hlsl");
-    std::fs::copy(
-        "src/shaders.hlsl",
-        std::env::var("OUT_DIR").unwrap() + "/../../../shaders.hlsl",
-    )
-    .expect("Copy");
+    let target_dir = std::env::var("OUT_DIR").unwrap() + "/../../../";
+    for file in SHADER_FILES {
+        println!("cargo:rerun-if-changed=src/{}", file);
+        std::fs::copy(format!("src/{}", file), target_dir.clone() + file).expect("Copy");
+    }
+
+    println!("cargo:rerun-if-env-changed={}", PRECOMPILE_ENV);
+    let precompile = std::env::var_os(PRECOMPILE_ENV).is_some();
+    for (entry_point, target, output) in PRECOMPILED_SHADERS {
+        let output = target_dir.clone() + output;
+        if precompile {
+            let status = std::process::Command::new("fxc")
+                .args(["/nologo", "/T", target, "/E", entry_point, "/Fo", &output])
+                .arg("src/shaders.hlsl")
+                .status()
+                .expect("failed to run fxc, is it in PATH?");
+            assert!(status.success(), "fxc failed to compile {}", entry_point);
+        } else {
+            // 删掉之前预编译的结果，否则程序会一直加载过期的字节码
+            let _ = std::fs::remove_file(output);
+        }
+    }
 }
@@ -0,0 +1,240 @@
+use crate::constant_buffer::ConstantBuffer;
+use crate::cube_sample::{
+    create_offscreen_render_target, draw_cube, CubeResources, CubeSample, CubeScene, BUFFER_COUNT,
+};
+use crate::devices::create_pipeline_state;
+use crate::hello_triangle::ObjectConstants;
+use crate::pipeline_state::RenderTargetFormats;
+use crate::vertex::VertexPC;
+use crate::{
+    BlendMode, Camera, CoordinateSystem, GameTimer, InputState, Key, Mat4, SampleCommandLine,
+    StencilMode,
+};
+use windows::{core::*, Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::Common::*};
+
+/// `-gbuffer` 时位置渲染目标的 RTV 紧跟在后台缓冲区的 RTV 之后。
+const POSITION_RTV_INDEX: u32 = BUFFER_COUNT;
+/// 位置需要负数和超过 1 的值，用浮点格式保存。
+const POSITION_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R16G16B16A16_FLOAT;
+/// 用 WASD 移动相机的速度，单位每秒。
+const CAMERA_SPEED: f32 = 2.0;
+
+/// 三角形之后的下一步：用索引缓冲区绘制 8 个顶点组成的立方体，开启深度测试，
+/// 每帧用相机的观察投影矩阵和绕 y 轴的旋转更新 MVP 常量缓冲区。
+///
+/// `-gbuffer` 时像素着色器同时写入两个渲染目标：法线写入后台缓冲区，模型空间的位置写入另一个浮点渲染目标。
+pub type Sample = CubeSample<BoxScene>;
+
+pub struct BoxScene {
+    pso: ID3D12PipelineState,
+    object_constants: ConstantBuffer<ObjectConstants>,
+    /// G-buffer 中的位置，只在 `-gbuffer` 时创建。与后台缓冲区一样平时处于 COMMON 状态。
+    position_target: Option<ID3D12Resource>,
+}
+
+impl BoxScene {
+    fn create_position_target(
+        device: &ID3D12Device,
+        resources: &CubeResources,
+    ) -> Result<ID3D12Resource> {
+        let (width, height) = resources.size();
+        create_offscreen_render_target(
+            device,
+            POSITION_FORMAT,
+            width as u64,
+            height,
+            &resources.rtv_heap,
+            POSITION_RTV_INDEX,
+        )
+    }
+}
+
+impl CubeScene for BoxScene {
+    const TITLE: &'static str = "D3D12 Box";
+    const EXTRA_RENDER_TARGETS: u32 = 1;
+
+    fn new(
+        device: &ID3D12Device,
+        resources: &CubeResources,
+        command_line: &SampleCommandLine,
+    ) -> Result<Self> {
+        let position_target = command_line
+            .gbuffer
+            .then(|| Self::create_position_target(device, resources))
+            .transpose()?;
+
+        // 渲染目标的顺序与像素着色器的 SV_Target0、SV_Target1 对应
+        let rtv_formats: &[DXGI_FORMAT] = if command_line.gbuffer {
+            &[resources.format, POSITION_FORMAT]
+        } else {
+            &[resources.format]
+        };
+        let targets = RenderTargetFormats::multiple(rtv_formats).with_depth(resources.depth_mode);
+        // 顶点的索引顺序按左手坐标系排列，所以忽略 -coords，总是以顺时针为正面
+        let defines: &[(&str, &str)] = if command_line.gbuffer {
+            &[("GBUFFER", "1")]
+        } else {
+            &[]
+        };
+        let pso = create_pipeline_state::<VertexPC>(
+            device,
+            &resources.root_signature,
+            defines,
+            command_line.shader_model,
+            false,
+            CoordinateSystem::LeftHanded,
+            D3D12_FILL_MODE_SOLID,
+            // 立方体是封闭的，只画朝向相机的三个面
+            D3D12_CULL_MODE_BACK,
+            BlendMode::Opaque,
+            StencilMode::Disabled,
+            &targets,
+            command_line.verbose,
+        )?;
+
+        let object_constants = ConstantBuffer::new(device)?;
+        object_constants.update(&ObjectConstants::IDENTITY);
+
+        Ok(BoxScene {
+            pso,
+            object_constants,
+            position_target,
+        })
+    }
+
+    fn update(&mut self, camera: &mut Camera, timer: &GameTimer, input: &InputState) {
+        // W/S 前后、A/D 左右、Q/E 上下移动相机，按住 Shift 加速。乘以帧间隔，移动速度与帧率无关
+        let speed = if input.is_held(Key::Shift) {
+            CAMERA_SPEED * 4.0
+        } else {
+            CAMERA_SPEED
+        } * timer.delta_time();
+        camera.fly(
+            input.axis(Key::S, Key::W) * speed,
+            input.axis(Key::A, Key::D) * speed,
+            input.axis(Key::Q, Key::E) * speed,
+        );
+
+        let model = Mat4::rotation_y(timer.total_time());
+        self.object_constants.update(&ObjectConstants {
+            mvp: (model * camera.view_projection()).rows,
+        });
+    }
+
+    fn resize(&mut self, device: &ID3D12Device, resources: &CubeResources) -> Result<()> {
+        if self.position_target.is_some() {
+            self.position_target = Some(Self::create_position_target(device, resources)?);
+        }
+        Ok(())
+    }
+
+    /// 没有被立方体覆盖的像素位置清除为 0，w 也为 0，可以据此与几何体区分开。
+    fn extra_render_targets(&self) -> &[ID3D12Resource] {
+        self.position_target.as_slice()
+    }
+
+    fn draw(&self, command_list: &ID3D12GraphicsCommandList) {
+        unsafe {
+            command_list.SetPipelineState(&self.pso);
+            command_list
+                .SetGraphicsRootConstantBufferView(1, self.object_constants.gpu_virtual_address());
+        }
+        draw_cube(command_list);
+    }
+}
+
+#[test]
+fn back_faces_are_culled_from_the_camera() {
+    use crate::cube_sample::{BOX_INDICES, BOX_VERTICES};
+    use crate::Vec3;
+
+    // 与示例相同的相机，在斜上方看向立方体：只有朝向 -z 的正面和顶面这 4 个三角形在屏幕上是顺时针的，
+    // 背面剔除（左手系以顺时针为正面）后其余 8 个都不会被光栅化
+    let camera = Camera {
+        position: Vec3::new(0.0, 1.5, -3.0),
+        ..Default::default()
+    };
+    let view_projection = camera.view_projection();
+    let project = |i: u16| {
+        let [x, y, z] = BOX_VERTICES[i as usize].position;
+        let clip = crate::Vec4::point(Vec3::new(x, y, z)) * view_projection;
+        (clip.x / clip.w, clip.y / clip.w)
+    };
+    let front_facing = BOX_INDICES
+        .chunks_exact(3)
+        .filter(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| project(triangle[i]));
+            // NDC 中 y 朝上，顺时针的三角形叉积为负
+            (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0) < 0.0
+        })
+        .count();
+    assert_eq!(front_facing, 4);
+}
+
+#[test]
+fn box_renders_headless() {
+    use crate::DXSample;
+
+    let command_line = SampleCommandLine {
+        use_warp_device: true,
+        ..Default::default()
+    };
+    let mut sample = Sample::new(&command_line).unwrap();
+    let (width, height) = sample.window_size();
+    let pixels = crate::render_headless(&mut sample, (width, height), 2).unwrap();
+    assert_eq!(pixels.len(), (width * height * 4) as usize);
+
+    let pixel = |x: i32, y: i32| {
+        let offset = ((y * width + x) * 4) as usize;
+        [0, 1, 2, 3].map(|i| pixels[offset + i])
+    };
+    let clear = sample.clear_color().map(|c| (c * 255.0).round() as u8);
+    let matches_clear = |p: [u8; 4]| p.iter().zip(clear).all(|(a, b)| a.abs_diff(b) <= 1);
+    // 立方体位于画面中央，角落里只有清除颜色
+    assert!(matches_clear(pixel(0, 0)));
+    assert!(!matches_clear(pixel(width / 2, height / 2)));
+}
+
+#[test]
+fn srgb_brightens_the_clear_color() {
+    use crate::DXSample;
+
+    let command_line = SampleCommandLine {
+        use_warp_device: true,
+        srgb: true,
+        ..Default::default()
+    };
+    let mut sample = Sample::new(&command_line).unwrap();
+    let (width, height) = sample.window_size();
+    let pixels = crate::render_headless(&mut sample, (width, height), 2).unwrap();
+
+    // 清除颜色按线性值写入 sRGB 视图，0 和 1 之间的分量经过伽马编码后都会变大
+    for (channel, linear) in sample.clear_color()[..3].iter().enumerate() {
+        if *linear > 0.0 && *linear < 1.0 {
+            assert!(pixels[channel] > (linear * 255.0).round() as u8 + 1);
+        }
+    }
+}
+
+#[test]
+fn gbuffer_writes_normals_to_the_back_buffer() {
+    use crate::{DXSample, Vec3};
+
+    let command_line = SampleCommandLine {
+        use_warp_device: true,
+        gbuffer: true,
+        ..Default::default()
+    };
+    let mut sample = Sample::new(&command_line).unwrap();
+    let (width, height) = sample.window_size();
+    let pixels = crate::render_headless(&mut sample, (width, height), 2).unwrap();
+
+    // 画面中央总是立方体的某个面，从颜色还原出的模型空间法线应当是单位向量
+    let offset = ((height / 2 * width + width / 2) * 4) as usize;
+    let normal = Vec3::new(
+        pixels[offset] as f32 / 255.0 * 2.0 - 1.0,
+        pixels[offset + 1] as f32 / 255.0 * 2.0 - 1.0,
+        pixels[offset + 2] as f32 / 255.0 * 2.0 - 1.0,
+    );
+    assert!((normal.length() - 1.0).abs() < 0.05, "{:?}", normal);
+}
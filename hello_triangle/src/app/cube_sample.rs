@@ -0,0 +1,604 @@
+use crate::command::CommandContext;
+use crate::descriptor::{set_render_targets, DescriptorHeap};
+use crate::devices::{check_back_buffer_format, create_device, create_root_signature};
+use crate::fence::Fence;
+use crate::hello_triangle::{
+    create_depth_stencil, create_index_buffer, create_render_targets, viewport_and_scissor,
+};
+use crate::info_queue::{info_queue, FrameMessages};
+use crate::readback::{read_back_texture, ReadbackImage};
+use crate::resource_state::transition_barrier;
+use crate::upload::upload_buffer_to_default_heap;
+use crate::vertex::VertexPC;
+use crate::{
+    client_size, explain_error, typeless_format, Camera, DXSample, DepthMode, GameTimer,
+    InputState, SampleCommandLine, Vec3,
+};
+use windows::{
+    core::*, Win32::Foundation::*, Win32::Graphics::Direct3D::*, Win32::Graphics::Direct3D12::*,
+    Win32::Graphics::Dxgi::Common::*, Win32::Graphics::Dxgi::*,
+};
+
+/// 立方体示例只需要 2 个后台缓冲区：每帧都等待 GPU 完成，不会有更多的帧在排队。
+pub const BUFFER_COUNT: u32 = 2;
+
+/// 立方体的 8 个角，每个角一种颜色，相邻面上的颜色在光栅化时插值。
+pub const BOX_VERTICES: [VertexPC; 8] = [
+    VertexPC {
+        position: [-0.5, -0.5, -0.5],
+        color: [1.0, 1.0, 1.0, 1.0],
+    },
+    VertexPC {
+        position: [-0.5, 0.5, -0.5],
+        color: [0.0, 0.0, 0.0, 1.0],
+    },
+    VertexPC {
+        position: [0.5, 0.5, -0.5],
+        color: [1.0, 0.0, 0.0, 1.0],
+    },
+    VertexPC {
+        position: [0.5, -0.5, -0.5],
+        color: [0.0, 1.0, 0.0, 1.0],
+    },
+    VertexPC {
+        position: [-0.5, -0.5, 0.5],
+        color: [0.0, 0.0, 1.0, 1.0],
+    },
+    VertexPC {
+        position: [-0.5, 0.5, 0.5],
+        color: [1.0, 1.0, 0.0, 1.0],
+    },
+    VertexPC {
+        position: [0.5, 0.5, 0.5],
+        color: [0.0, 1.0, 1.0, 1.0],
+    },
+    VertexPC {
+        position: [0.5, -0.5, 0.5],
+        color: [1.0, 0.0, 1.0, 1.0],
+    },
+];
+
+/// 6 个面各由 2 个三角形组成。从立方体外面看过去每个三角形都是顺时针的，与左手坐标系的正面约定一致。
+pub const BOX_INDICES: [u16; 36] = [
+    0, 1, 2, 0, 2, 3, // 前
+    4, 6, 5, 4, 7, 6, // 后
+    4, 5, 1, 4, 1, 0, // 左
+    3, 2, 6, 3, 6, 7, // 右
+    1, 5, 6, 1, 6, 2, // 上
+    4, 0, 3, 4, 3, 7, // 下
+];
+
+/// 在旋转的立方体上演示某种技术的示例只需要实现的部分：PSO、常量缓冲区和绘制命令。
+///
+/// 窗口或离屏渲染目标、深度/模板缓冲区、命令列表、立方体的顶点和索引缓冲区以及每帧的 GPU 同步
+/// 都由 [`CubeSample`] 负责，参见 [`CubeResources`]。
+pub trait CubeScene: Sized {
+    /// 窗口标题。
+    const TITLE: &'static str;
+    /// 除后台缓冲区外最多还要绘制多少个渲染目标，为它们预留 RTV：第 `i` 个位于 `rtv_heap` 的 `BUFFER_COUNT + i` 处。
+    const EXTRA_RENDER_TARGETS: u32 = 0;
+
+    /// `resources` 创建好之后调用，根签名、后台缓冲区的格式和大小都可以从中取得。
+    fn new(
+        device: &ID3D12Device,
+        resources: &CubeResources,
+        command_line: &SampleCommandLine,
+    ) -> Result<Self>;
+
+    /// 每帧在录制命令之前调用，相机的宽高比已经按视口更新过。
+    /// 每帧都等待过 GPU，常量缓冲区此时没有在被读取，可以直接写入。
+    fn update(&mut self, camera: &mut Camera, timer: &GameTimer, input: &InputState);
+
+    /// 后台缓冲区改变大小之后调用，重新创建与它一样大的资源。
+    fn resize(&mut self, _device: &ID3D12Device, _resources: &CubeResources) -> Result<()> {
+        Ok(())
+    }
+
+    /// 与后台缓冲区一起绘制的渲染目标，不超过 [`CubeScene::EXTRA_RENDER_TARGETS`] 个，平时处于 COMMON 状态，每帧清除为 0。
+    fn extra_render_targets(&self) -> &[ID3D12Resource] {
+        &[]
+    }
+
+    /// 录制绘制命令。渲染目标已经清除，根签名、视口、裁剪矩形以及立方体的顶点和索引缓冲区都已经设置好，
+    /// PSO 和根参数 1 的常量缓冲区要自己设置。
+    fn draw(&self, command_list: &ID3D12GraphicsCommandList);
+}
+
+/// 用 [`CubeScene`] 绘制旋转立方体的示例，相机从斜上方看向立方体。
+///
+/// 为了让代码尽量简单，每帧结束时都等待 GPU 执行完毕，只用一个命令上下文。
+pub struct CubeSample<S> {
+    dxgi_factory: IDXGIFactory4,
+    device: ID3D12Device,
+    command_line: SampleCommandLine,
+    camera: Camera,
+    /// 开启了调试层时，每帧结束打印这一帧产生的调试层消息。
+    frame_messages: Option<FrameMessages>,
+    resources: Option<CubeResources>,
+    /// 与 `resources` 同时创建和释放。
+    scene: Option<S>,
+}
+
+/// 所有立方体示例共用的资源。
+pub struct CubeResources {
+    command_queue: ID3D12CommandQueue,
+    /// 无窗口渲染时为 `None`，这时 `render_targets` 中只有一个离屏渲染目标。
+    swap_chain: Option<IDXGISwapChain3>,
+    frame_index: u32,
+    render_targets: Vec<ID3D12Resource>,
+    /// 渲染目标视图和 PSO 使用的格式，`-srgb` 时是后台缓冲区格式对应的 sRGB 格式。
+    pub format: DXGI_FORMAT,
+    pub rtv_heap: DescriptorHeap,
+    /// `-reversez` 时为反转 Z，场景的 PSO 要用同样的约定。
+    pub depth_mode: DepthMode,
+    depth_stencil: ID3D12Resource,
+    dsv_heap: ID3D12DescriptorHeap,
+    viewport: D3D12_VIEWPORT,
+    scissor_rect: RECT,
+    command_context: CommandContext,
+    /// 与三角形共用根签名和着色器：b0 是时间根常量，b1 是 MVP 常量缓冲区。
+    pub root_signature: ID3D12RootSignature,
+    /// 顶点和索引缓冲区只通过视图中的 GPU 虚拟地址访问，这里持有它们只是为了让资源保持存活。
+    _vertex_buffer: ID3D12Resource,
+    vbv: D3D12_VERTEX_BUFFER_VIEW,
+    _index_buffer: ID3D12Resource,
+    ibv: D3D12_INDEX_BUFFER_VIEW,
+    fence: Fence,
+}
+
+impl<S: CubeScene> CubeSample<S> {
+    fn bind(&mut self, hwnd: Option<&HWND>, width: i32, height: i32) -> Result<()> {
+        let resources = CubeResources::new(
+            &self.device,
+            &self.dxgi_factory,
+            hwnd,
+            width,
+            height,
+            S::EXTRA_RENDER_TARGETS,
+            &self.command_line,
+        )?;
+        self.scene = Some(S::new(&self.device, &resources, &self.command_line)?);
+        self.resources = Some(resources);
+        Ok(())
+    }
+}
+
+impl<S: CubeScene> DXSample for CubeSample<S> {
+    fn new(command_line: &SampleCommandLine) -> Result<Self> {
+        let (dxgi_factory, device) = create_device(command_line)?;
+        Ok(CubeSample {
+            dxgi_factory,
+            frame_messages: info_queue(&device).map(FrameMessages::new),
+            device,
+            command_line: command_line.clone(),
+            // 从斜上方看过去，能同时看到立方体的顶面和侧面
+            camera: Camera {
+                position: Vec3::new(0.0, 1.5, -3.0),
+                depth_mode: command_line.depth_mode,
+                ..Default::default()
+            },
+            resources: None,
+            scene: None,
+        })
+    }
+
+    fn bind_to_window(&mut self, hwnd: &HWND) -> Result<()> {
+        let (width, height) = client_size(hwnd).unwrap_or_else(|| self.window_size());
+        self.bind(Some(hwnd), width, height)
+    }
+
+    fn bind_headless(&mut self, width: u32, height: u32) -> Result<()> {
+        self.bind(None, width as i32, height as i32)
+    }
+
+    fn read_back_frame(&self) -> Result<ReadbackImage> {
+        let resources = self.resources.as_ref().ok_or_else(|| {
+            Error::new(E_UNEXPECTED, "the sample is not bound to a target".into())
+        })?;
+        // render 结束时已经等待过 GPU，渲染目标回到了 PRESENT 状态
+        read_back_texture(
+            &self.device,
+            &resources.command_queue,
+            &resources.render_targets[resources.frame_index as usize],
+            D3D12_RESOURCE_STATE_PRESENT,
+        )
+    }
+
+    fn title(&self) -> String {
+        S::TITLE.into()
+    }
+
+    fn update(&mut self, timer: &GameTimer, input: &InputState) {
+        if let (Some(resources), Some(scene)) = (&self.resources, &mut self.scene) {
+            self.camera.aspect_ratio = resources.viewport.Width / resources.viewport.Height;
+            scene.update(&mut self.camera, timer, input);
+        }
+    }
+
+    fn on_resize(&mut self, width: u32, height: u32) {
+        if let (Some(resources), Some(scene)) = (&mut self.resources, &mut self.scene) {
+            let resized = resources
+                .resize(&self.device, width, height)
+                .unwrap_or_else(|e| panic!("resize failed: {}", explain_error(&e)));
+            if resized {
+                scene
+                    .resize(&self.device, resources)
+                    .unwrap_or_else(|e| panic!("resize failed: {}", explain_error(&e)));
+            }
+        }
+    }
+
+    fn render(&mut self) {
+        let sync_interval = self.present_interval();
+        let clear_color = self.clear_color();
+        if let (Some(resources), Some(scene)) = (&mut self.resources, &self.scene) {
+            resources
+                .populate_command_list(scene, &clear_color)
+                .unwrap_or_else(|e| panic!("populate_command_list failed: {}", explain_error(&e)));
+            let command_list = ID3D12CommandList::from(resources.command_context.command_list());
+            unsafe {
+                resources
+                    .command_queue
+                    .ExecuteCommandLists(&[Some(command_list)])
+            };
+            if let Some(swap_chain) = &resources.swap_chain {
+                unsafe { swap_chain.Present(sync_interval, 0) }
+                    .ok()
+                    .unwrap_or_else(|e| panic!("Present failed: {}", explain_error(&e)));
+            }
+            resources
+                .wait_for_gpu()
+                .unwrap_or_else(|e| panic!("wait for GPU failed: {}", explain_error(&e)));
+            if let Some(swap_chain) = &resources.swap_chain {
+                resources.frame_index = unsafe { swap_chain.GetCurrentBackBufferIndex() };
+            }
+        }
+        if let Some(frame_messages) = &mut self.frame_messages {
+            frame_messages
+                .end_frame()
+                .unwrap_or_else(|e| panic!("reading info queue failed: {}", explain_error(&e)));
+        }
+    }
+
+    fn on_destroy(&mut self) {
+        // 取走资源后 drop 看到的是 None，不会再等一次
+        if let Some(mut resources) = self.resources.take() {
+            if let Err(e) = resources.wait_for_gpu() {
+                eprintln!(
+                    "waiting for the GPU before releasing resources failed: {}",
+                    explain_error(&e)
+                );
+            }
+        }
+        self.scene = None;
+    }
+}
+
+impl<S> Drop for CubeSample<S> {
+    fn drop(&mut self) {
+        // 释放资源之前，GPU 必须已经不再使用它们。窗口关闭时 on_destroy 已经做过，这里处理无窗口渲染
+        if let Some(resources) = &mut self.resources {
+            let _ = resources.wait_for_gpu();
+        }
+    }
+}
+
+impl CubeResources {
+    /// 没有 `hwnd` 时不创建交换链，改为渲染到一个同样大小的离屏渲染目标。
+    fn new(
+        device: &ID3D12Device,
+        dxgi_factory: &IDXGIFactory4,
+        hwnd: Option<&HWND>,
+        width: i32,
+        height: i32,
+        extra_render_targets: u32,
+        command_line: &SampleCommandLine,
+    ) -> Result<Self> {
+        let command_queue: ID3D12CommandQueue = unsafe {
+            device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
+                Type: D3D12_COMMAND_LIST_TYPE_DIRECT,
+                ..Default::default()
+            })?
+        };
+
+        let buffer_format = command_line.back_buffer_format.dxgi_format();
+        check_back_buffer_format(device, buffer_format)?;
+        // 与三角形示例一样，-srgb 时交换链缓冲区仍是线性格式，只有渲染目标视图和 PSO 使用 sRGB 格式
+        let format = match command_line.back_buffer_format.srgb_format() {
+            Some(srgb_format) if command_line.srgb => srgb_format,
+            None if command_line.srgb => {
+                eprintln!(
+                    "srgb: {} has no sRGB view format, rendering without gamma correction",
+                    command_line.back_buffer_format.name()
+                );
+                buffer_format
+            }
+            _ => buffer_format,
+        };
+        let swap_chain = match hwnd {
+            Some(hwnd) => {
+                let swap_chain: IDXGISwapChain3 = unsafe {
+                    dxgi_factory.CreateSwapChainForHwnd(
+                        &command_queue,
+                        *hwnd,
+                        &DXGI_SWAP_CHAIN_DESC1 {
+                            BufferCount: BUFFER_COUNT,
+                            Width: width as u32,
+                            Height: height as u32,
+                            Format: buffer_format,
+                            BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+                            SwapEffect: DXGI_SWAP_EFFECT_FLIP_DISCARD,
+                            SampleDesc: DXGI_SAMPLE_DESC {
+                                Count: 1,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        None,
+                        None,
+                    )?
+                }
+                .cast()?;
+                unsafe {
+                    dxgi_factory.MakeWindowAssociation(*hwnd, DXGI_MWA_NO_ALT_ENTER)?;
+                }
+                Some(swap_chain)
+            }
+            None => None,
+        };
+        let frame_index = swap_chain.as_ref().map_or(0, |swap_chain| unsafe {
+            swap_chain.GetCurrentBackBufferIndex()
+        });
+
+        let rtv_heap = DescriptorHeap::new(
+            device,
+            D3D12_DESCRIPTOR_HEAP_TYPE_RTV,
+            BUFFER_COUNT + extra_render_targets,
+            false,
+        )?;
+        let render_targets = match &swap_chain {
+            Some(swap_chain) => {
+                create_render_targets(device, swap_chain, &rtv_heap, BUFFER_COUNT, format)?
+            }
+            None => vec![create_offscreen_render_target(
+                device,
+                format,
+                width as u64,
+                height as u32,
+                &rtv_heap,
+                0,
+            )?],
+        };
+        let (viewport, scissor_rect) = viewport_and_scissor(width, height);
+        let sample_desc = DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        };
+        let depth_mode = command_line.depth_mode;
+        let (depth_stencil, dsv_heap) =
+            create_depth_stencil(device, width as u64, height as u32, sample_desc, depth_mode)?;
+
+        let root_signature = create_root_signature(device, false)?;
+        let command_context = CommandContext::new(device, D3D12_COMMAND_LIST_TYPE_DIRECT)?;
+
+        // 立方体的顶点不会再变化，放进默认堆；索引缓冲区很小，直接留在上传堆中
+        let vertex_buffer = upload_buffer_to_default_heap(
+            device,
+            &command_queue,
+            &BOX_VERTICES,
+            D3D12_RESOURCE_STATE_VERTEX_AND_CONSTANT_BUFFER,
+        )?;
+        let vbv = D3D12_VERTEX_BUFFER_VIEW {
+            BufferLocation: unsafe { vertex_buffer.GetGPUVirtualAddress() },
+            StrideInBytes: std::mem::size_of::<VertexPC>() as u32,
+            SizeInBytes: std::mem::size_of_val(&BOX_VERTICES) as u32,
+        };
+        let (index_buffer, ibv) = create_index_buffer(device, &BOX_INDICES)?;
+
+        Ok(CubeResources {
+            command_queue,
+            swap_chain,
+            frame_index,
+            render_targets,
+            format,
+            rtv_heap,
+            depth_mode,
+            depth_stencil,
+            dsv_heap,
+            viewport,
+            scissor_rect,
+            command_context,
+            root_signature,
+            _vertex_buffer: vertex_buffer,
+            vbv,
+            _index_buffer: index_buffer,
+            ibv,
+            fence: Fence::new(device)?,
+        })
+    }
+
+    /// 后台缓冲区的宽和高。
+    pub fn size(&self) -> (u32, u32) {
+        (self.viewport.Width as u32, self.viewport.Height as u32)
+    }
+
+    /// 在直接队列上 Signal 一个新的围栏值，并在 CPU 端等待 GPU 执行到那里。
+    fn wait_for_gpu(&mut self) -> Result<()> {
+        let fence_value = self.fence.signal(&self.command_queue)?;
+        self.command_context.set_fence_value(fence_value);
+        self.fence.wait(fence_value)
+    }
+
+    /// 返回是否重新创建了后台缓冲区，大小没有变化或者无窗口渲染时什么也不做。
+    fn resize(&mut self, device: &ID3D12Device, width: u32, height: u32) -> Result<bool> {
+        if self.size() == (width, height) {
+            return Ok(false);
+        }
+
+        // 无窗口渲染时没有窗口会改变大小
+        let Some(swap_chain) = self.swap_chain.clone() else {
+            return Ok(false);
+        };
+
+        // ResizeBuffers 要求交换链的缓冲区没有任何外部引用
+        self.wait_for_gpu()?;
+        self.render_targets.clear();
+        unsafe { swap_chain.ResizeBuffers(0, width, height, DXGI_FORMAT_UNKNOWN, 0) }?;
+        self.frame_index = unsafe { swap_chain.GetCurrentBackBufferIndex() };
+        // ResizeBuffers 传入 DXGI_FORMAT_UNKNOWN 保留了原来的格式，RTV 继续使用它
+        self.render_targets = create_render_targets(
+            device,
+            &swap_chain,
+            &self.rtv_heap,
+            BUFFER_COUNT,
+            self.format,
+        )?;
+        let sample_desc = DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        };
+        (self.depth_stencil, self.dsv_heap) =
+            create_depth_stencil(device, width as u64, height, sample_desc, self.depth_mode)?;
+        (self.viewport, self.scissor_rect) = viewport_and_scissor(width as i32, height as i32);
+        Ok(true)
+    }
+
+    fn populate_command_list(&self, scene: &impl CubeScene, clear_color: &[f32; 4]) -> Result<()> {
+        let command_list = self.command_context.reset(self.fence.fence(), None)?;
+        let back_buffer = &self.render_targets[self.frame_index as usize];
+        let rtv_handle = self.rtv_heap.cpu_handle(self.frame_index);
+        let extra_targets = scene.extra_render_targets();
+        let targets: Vec<_> = std::iter::once(back_buffer).chain(extra_targets).collect();
+        let rtv_handles: Vec<_> = std::iter::once(rtv_handle)
+            .chain(
+                (0..extra_targets.len() as u32).map(|i| self.rtv_heap.cpu_handle(BUFFER_COUNT + i)),
+            )
+            .collect();
+        let dsv_handle = unsafe { self.dsv_heap.GetCPUDescriptorHandleForHeapStart() };
+
+        unsafe {
+            command_list.SetGraphicsRootSignature(&self.root_signature);
+            // 时间为 0 时像素着色器不做颜色脉动，保持顶点的原色
+            command_list.SetGraphicsRoot32BitConstant(0, 0f32.to_bits(), 0);
+            command_list.RSSetViewports(&[self.viewport]);
+            command_list.RSSetScissorRects(&[self.scissor_rect]);
+
+            let barriers: Vec<_> = targets
+                .iter()
+                .map(|target| {
+                    transition_barrier(
+                        target,
+                        D3D12_RESOURCE_STATE_PRESENT,
+                        D3D12_RESOURCE_STATE_RENDER_TARGET,
+                    )
+                })
+                .collect();
+            command_list.ResourceBarrier(&barriers);
+            set_render_targets(command_list, &rtv_handles, Some(&dsv_handle));
+            command_list.ClearRenderTargetView(rtv_handle, clear_color.as_ptr(), &[]);
+            for rtv_handle in &rtv_handles[1..] {
+                command_list.ClearRenderTargetView(*rtv_handle, [0.0f32; 4].as_ptr(), &[]);
+            }
+            // 深度清除为最远处，离相机更近的面才能通过深度测试；模板清除为 0
+            command_list.ClearDepthStencilView(
+                dsv_handle,
+                D3D12_CLEAR_FLAG_DEPTH | D3D12_CLEAR_FLAG_STENCIL,
+                self.depth_mode.clear_value(),
+                0,
+                &[],
+            );
+
+            command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+            command_list.IASetVertexBuffers(0, Some(&[self.vbv]));
+            command_list.IASetIndexBuffer(Some(&self.ibv));
+            scene.draw(command_list);
+
+            let barriers: Vec<_> = targets
+                .iter()
+                .map(|target| {
+                    transition_barrier(
+                        target,
+                        D3D12_RESOURCE_STATE_RENDER_TARGET,
+                        D3D12_RESOURCE_STATE_PRESENT,
+                    )
+                })
+                .collect();
+            command_list.ResourceBarrier(&barriers);
+            command_list.Close()
+        }
+    }
+}
+
+/// 绘制一次立方体，顶点和索引缓冲区已经由 [`CubeSample`] 设置好。
+pub fn draw_cube(command_list: &ID3D12GraphicsCommandList) {
+    unsafe { command_list.DrawIndexedInstanced(BOX_INDICES.len() as u32, 1, 0, 0, 0) };
+}
+
+/// 无窗口渲染时代替后台缓冲区的渲染目标，以及 [`CubeScene::extra_render_targets`]，RTV 放在 `rtv_heap` 的第 `rtv_index` 个位置。
+///
+/// 以 COMMON 状态创建，它与 PRESENT 是同一个状态，每帧的资源屏障因此与交换链的后台缓冲区完全一样。
+/// `format` 为 sRGB 格式时资源以无类型格式创建，这样才能用 sRGB 视图渲染。
+pub fn create_offscreen_render_target(
+    device: &ID3D12Device,
+    format: DXGI_FORMAT,
+    width: u64,
+    height: u32,
+    rtv_heap: &DescriptorHeap,
+    rtv_index: u32,
+) -> Result<ID3D12Resource> {
+    let mut render_target: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: D3D12_HEAP_TYPE_DEFAULT,
+                ..Default::default()
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &D3D12_RESOURCE_DESC {
+                Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+                Width: width,
+                Height: height,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                Format: typeless_format(format),
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Flags: D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET,
+                ..Default::default()
+            },
+            D3D12_RESOURCE_STATE_COMMON,
+            None,
+            &mut render_target,
+        )?
+    };
+    let render_target = render_target.unwrap();
+    unsafe {
+        device.CreateRenderTargetView(
+            &render_target,
+            Some(&D3D12_RENDER_TARGET_VIEW_DESC {
+                Format: format,
+                ViewDimension: D3D12_RTV_DIMENSION_TEXTURE2D,
+                ..Default::default()
+            }),
+            rtv_heap.cpu_handle(rtv_index),
+        )
+    };
+    Ok(render_target)
+}
+
+#[test]
+fn box_faces_point_outwards() {
+    // 从外面看顺时针的三角形，(b - a) × (c - a) 朝向观察者，也就是指向立方体外部
+    let position = |i: u16| {
+        let [x, y, z] = BOX_VERTICES[i as usize].position;
+        Vec3::new(x, y, z)
+    };
+    for triangle in BOX_INDICES.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| position(triangle[i]));
+        let normal = (b - a).cross(c - a);
+        let center = Vec3::new(a.x + b.x + c.x, a.y + b.y + c.y, a.z + b.z + c.z);
+        assert!(normal.dot(center) > 0.0, "{:?}", triangle);
+    }
+}
@@ -1,42 +1,157 @@
-use crate::devices::{create_device, create_pipeline_state, create_root_signature};
-use crate::{DXSample, SampleCommandLine};
+use crate::adapter::{adapter_name, hardware_adapters};
+use crate::command::{Bundle, CommandContextPool};
+use crate::compute::{create_compute_pipeline_state, create_uav_buffer};
+use crate::constant_buffer::ConstantBuffer;
+use crate::descriptor::{set_render_targets, DescriptorHeap};
+use crate::devices::{
+    check_back_buffer_format, check_sample_support, compile_shader, create_device,
+    create_device_on_adapter, create_pipeline_state, create_root_signature,
+    create_root_signature_from_desc, object_constants_root_descriptor, shader_path,
+    time_root_constant, CompiledShader,
+};
+use crate::dred::{is_device_lost, log_device_removed};
+use crate::fence::{Fence, FenceValue};
+use crate::frame_pacing::FramePacing;
+use crate::info_queue::{info_queue, FrameMessages};
+use crate::latency::LatencyMeter;
+use crate::memory_usage::print_memory_usage;
+use crate::multi_node::run_multi_node_demo;
+use crate::particles::Particles;
+use crate::pipeline_state::RenderTargetFormats;
+use crate::point_sprites::PointSprites;
+use crate::present::{
+    check_tearing_support, find_closest_mode, format_name, output_supports_hdr,
+    print_containing_output, print_display_modes, print_present_info, refresh_rate,
+    set_hdr10_color_space, supports_display,
+};
+use crate::readback::read_back_texture;
+use crate::resource_state::{resource_barriers, ResourceState};
+use crate::root_signature::set_graphics_root_constants;
+use crate::textured_quad::TexturedQuad;
+use crate::upload::{upload_buffer_to_default_heap, UploadRingBuffer};
+use crate::vertex::{InstanceData, Instanced, VertexPC};
+use crate::{
+    begin_event, borderless_fullscreen_placement, client_size, end_event, explain_error, pix_color,
+    resolve_asset, set_name, typeless_format, write_png, BackBufferFormat, BlendMode, Camera,
+    CoordinateSystem, DXSample, DepthMode, GameTimer, InputState, Mat4, MouseButton,
+    SampleCommandLine, ShaderModel, StencilMode, WindowPlacement,
+};
 use windows::{
     core::*, Win32::Foundation::*, Win32::Graphics::Direct3D::*, Win32::Graphics::Direct3D12::*,
     Win32::Graphics::Dxgi::Common::*, Win32::Graphics::Dxgi::*, Win32::System::Threading::*,
-    Win32::System::WindowsProgramming::*,
+    Win32::UI::WindowsAndMessaging::*,
 };
 
-const FRAME_COUNT: u32 = 2;
+/// `-msaa` 时每个像素的采样数。
+const MSAA_SAMPLE_COUNT: u32 = 4;
+
+/// F5 键的虚拟键码（`VK_F5`）。
+const VK_F5: u8 = 0x74;
+/// F9 键的虚拟键码（`VK_F9`）。
+const VK_F9: u8 = 0x78;
+
+/// 没有指定 `-texture` 时，在资源目录中查找的纹理文件名。
+const DEFAULT_TEXTURE: &str = "texture.dds";
 
 pub struct Sample {
     dxgi_factory: IDXGIFactory4,
     device: ID3D12Device,
+    command_line: SampleCommandLine,
     resources: Option<Resources>,
+    adapter_cycle: Option<AdapterCycle>,
+    /// `-msaa` 且后台缓冲区格式支持 4X MSAA 时为 true。
+    msaa_enabled: bool,
+    /// 设备对后台缓冲区格式在 4X MSAA 下支持的质量级别数量，可用的质量级别是 0 到 `msaa_quality - 1`。
+    msaa_quality: u32,
+    /// 处于无边框全屏时，保存进入全屏之前的窗口样式和位置。
+    windowed_placement: Option<WindowPlacement>,
+    /// 左键拖动绕原点旋转、滚轮拉近拉远的相机。
+    camera: Camera,
+    /// 左键按下时为上一次的鼠标位置，用来计算拖动的距离。
+    drag_origin: Option<(i32, i32)>,
 }
 
 struct Resources {
     command_queue: ID3D12CommandQueue,
     swap_chain: IDXGISwapChain3,
     frame_index: u32,
-    render_targets: [ID3D12Resource; FRAME_COUNT as usize],
-    rtv_heap: ID3D12DescriptorHeap,
-    rtv_descriptor_size: usize,
+    /// 交换链的每个后台缓冲区，数量由 `-buffers` 决定。
+    render_targets: Vec<ID3D12Resource>,
+    /// 创建交换链时的标志，`ResizeBuffers` 时要原样传入。
+    swap_chain_flags: u32,
+    rtv_heap: DescriptorHeap,
+    /// 清除渲染目标所用的颜色，来自 `DXSample::clear_color`，也是多重采样渲染目标的优化清除值。
+    clear_color: [f32; 4],
+    /// 与后台缓冲区一样大的深度/模板缓冲区。
+    depth_stencil: ID3D12Resource,
+    /// 只存放一个深度/模板视图（DSV）的描述符堆。
+    dsv_heap: ID3D12DescriptorHeap,
+    /// 渲染目标、深度/模板缓冲区和各个 PSO 共同使用的格式与采样设置。
+    targets: RenderTargetFormats,
+    /// `-msaa` 时实际绘制的多重采样渲染目标，它的 RTV 位于 `rtv_heap` 中所有后台缓冲区之后。
+    /// 每帧结束时解析到当前的后台缓冲区，交换链本身始终是单采样的。
+    msaa_render_target: Option<ID3D12Resource>,
+    /// 后台缓冲区和多重采样渲染目标最后已知的状态，`populate_command_list` 据此生成转换屏障。
+    resource_states: ResourceState,
     viewport: D3D12_VIEWPORT,
     scissor_rect: RECT,
-    command_allocator: ID3D12CommandAllocator,
+    /// 每个在飞行中的帧各有一个命令分配器和命令列表，数量由 `-framesinflight` 决定，与后台缓冲区数量无关。
+    command_contexts: CommandContextPool,
+    /// 当前帧使用的命令上下文下标。
+    frame_slot: usize,
     root_signature: ID3D12RootSignature,
     pso: ID3D12PipelineState,
-    command_list: ID3D12GraphicsCommandList,
+    /// 用 `CHECKERBOARD` 宏编译的像素着色器变体，输出程序化的棋盘格调试图案。
+    checkerboard_pso: ID3D12PipelineState,
+    checkerboard: bool,
+    /// 只绘制三角形边的 PSO，除填充模式外与 `pso` 完全相同。
+    wireframe_pso: ID3D12PipelineState,
+    wireframe: bool,
+    /// 初始化时录制好的绘制三角形的 bundle，`-nobundle` 或 `-dynamicvertices` 时为 `None`，每帧直接录制。
+    bundles: Option<TriangleBundles>,
+    /// 传给 `Present` 的同步间隔，0 表示不等待垂直同步，1~4 表示等待相应次数的垂直空白。
+    sync_interval: u32,
+    /// 按下 F9 后，下一帧在呈现之前把后台缓冲区保存为这个 PNG 文件。
+    capture_path: Option<std::path::PathBuf>,
+    /// 半透明时绘制两个实例，第二个与第一个重叠，混合的效果才看得出来。
+    blend_mode: BlendMode,
+    /// 交换链带有 `DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING`，同步间隔为 0 时可以用 `DXGI_PRESENT_ALLOW_TEARING` 呈现。
+    allow_tearing: bool,
 
-    // we need to keep this around to keep the reference alive, even though
-    // nothing reads from it
-    #[allow(dead_code)]
+    // we need to keep this around to keep the reference alive; only the
+    // vertex buffer view is used for drawing
     vertex_buffer: ID3D12Resource,
+    /// `-defaultheap` 时顶点缓冲区位于默认堆，窗口大小变化时要用同样的方式重新创建。
+    vertex_buffer_in_default_heap: bool,
+    /// `-dynamicvertices` 时每帧的顶点写在这里，`vbv` 每帧指向新写入的那一段。
+    dynamic_vertices: Option<UploadRingBuffer>,
 
     vbv: D3D12_VERTEX_BUFFER_VIEW,
-    fence: ID3D12Fence,
-    fence_value: u64,
-    fence_event: HANDLE,
+    /// `-instances` 大于 1 时第 1 个输入槽的实例缓冲区。
+    instances: Option<InstanceBuffer>,
+    index_buffer: ID3D12Resource,
+    ibv: D3D12_INDEX_BUFFER_VIEW,
+    /// 每个在飞行中的帧各一个存放 MVP 矩阵的常量缓冲区，CPU 写入当前帧的那个时，GPU 可能还在读取其他帧的。
+    object_constants: Vec<ConstantBuffer<ObjectConstants>>,
+    async_compute: Option<AsyncCompute>,
+    particles: Option<Particles>,
+    point_sprites: Option<PointSprites>,
+    textured_quad: Option<TexturedQuad>,
+    /// 直接队列的围栏。
+    fence: Fence,
+    /// 传给像素着色器的时间根常量从这里开始计时。
+    start_time: std::time::Instant,
+    hwnd: HWND,
+    /// 窗口原本的标题，平均延迟会附加在它后面。
+    window_title: String,
+    /// `-waitable` 时交换链提供的帧延迟事件。
+    frame_latency_waitable_object: Option<HANDLE>,
+    /// `-waitable` 时估算输入到呈现的延迟，并显示在窗口标题上。
+    latency: Option<LatencyMeter>,
+    /// `-framestats` 时统计帧间隔和围栏等待时间。
+    frame_pacing: Option<FramePacing>,
+    /// 开启调试层时，每帧结束后打印这一帧新产生的 info queue 消息。
+    frame_messages: Option<FrameMessages>,
 }
 
 /// 1. 用 `D3D12CreateDevice` 函数创建 `ID3D12Device` 接口实例。
@@ -54,28 +169,411 @@ impl DXSample for Sample {
         Self: Sized,
     {
         let (dxgi_factory, device) = create_device(command_line)?;
+        // -hdr 在创建任何资源之前决定后台缓冲区格式，MSAA 查询、交换链和各个 PSO 都使用这个格式
+        let mut command_line = command_line.clone();
+        if command_line.hdr {
+            if output_supports_hdr(&dxgi_factory, &device)
+                && supports_display(&device, DXGI_FORMAT_R10G10B10A2_UNORM)
+            {
+                command_line.back_buffer_format = BackBufferFormat::Rgb10a2;
+            } else {
+                eprintln!(
+                    "hdr: the display is not in HDR mode, falling back to SDR R8G8B8A8_UNORM"
+                );
+                command_line.hdr = false;
+                command_line.back_buffer_format = BackBufferFormat::Rgba8;
+            }
+        }
+        if command_line.srgb && command_line.back_buffer_format.srgb_format().is_none() {
+            eprintln!(
+                "srgb: {} has no sRGB view format, rendering without gamma correction",
+                command_line.back_buffer_format.name()
+            );
+            command_line.srgb = false;
+        }
+        let command_line = &command_line;
+        if command_line.print_present_info {
+            print_present_info(&dxgi_factory, &device)?;
+        }
+        if command_line.multi_node {
+            run_multi_node_demo(&device)?;
+        }
+        if command_line.list_display_modes {
+            print_display_modes(
+                &dxgi_factory,
+                &device,
+                command_line.back_buffer_format.dxgi_format(),
+            )?;
+        }
+        let adapter_cycle = match command_line.cycle_adapters {
+            Some(seconds) => AdapterCycle::new(&dxgi_factory, &device, seconds)?,
+            None => None,
+        };
+        let (msaa_enabled, msaa_quality) = query_msaa_support(&device, command_line)?;
         Ok(Sample {
             dxgi_factory,
             device,
+            command_line: command_line.clone(),
             resources: None,
+            adapter_cycle,
+            msaa_enabled,
+            msaa_quality,
+            windowed_placement: None,
+            camera: Camera {
+                depth_mode: command_line.depth_mode,
+                ..Default::default()
+            },
+            drag_origin: None,
         })
     }
 
     fn bind_to_window(&mut self, hwnd: &HWND) -> Result<()> {
+        // 后台缓冲区与窗口的客户区一样大，窗口可能被放到了某个显示器上并铺满它的工作区
+        let (width, height) = client_size(hwnd).unwrap_or_else(|| self.window_size());
+        self.resources = Some(Resources::new(
+            &self.device,
+            &self.dxgi_factory,
+            hwnd,
+            width,
+            height,
+            &self.command_line,
+            self.sample_desc(),
+            self.clear_color(),
+        )?);
+        set_name(&self.device, "hello_triangle device");
+        if let Some(resources) = &self.resources {
+            resources.set_debug_names();
+        }
+        if self.command_line.print_vram_usage {
+            if let Some(resources) = &self.resources {
+                print_memory_usage(&self.device, &resources.memory_usage());
+            }
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, timer: &GameTimer, _input: &InputState) {
+        if let Some(resources) = &mut self.resources {
+            // 三角形绕 z 轴每 4 秒转一圈。上一次使用这个常量缓冲区的帧已经执行完毕，可以直接写入
+            let angle = timer.total_time() * std::f32::consts::FRAC_PI_2;
+            let aspect_ratio = resources.viewport.Width / resources.viewport.Height;
+            self.camera.aspect_ratio = aspect_ratio;
+            resources.object_constants[resources.frame_slot].update(&ObjectConstants::with_camera(
+                angle,
+                aspect_ratio,
+                &self.camera.view_projection(),
+            ));
+
+            if let Some(dynamic_vertices) = &mut resources.dynamic_vertices {
+                // 三角形每 2 秒一胀一缩。这一帧的顶点写在环形缓冲区的新位置，之前的帧仍可以读取各自的那一段
+                let scale = 1.0 + 0.25 * (timer.total_time() * std::f32::consts::PI).sin();
+                let mut vertices =
+                    triangle_vertices(aspect_ratio, self.command_line.coordinate_system);
+                for vertex in &mut vertices {
+                    vertex.position[0] *= scale;
+                    vertex.position[1] *= scale;
+                }
+                resources.vbv.BufferLocation = dynamic_vertices
+                    .upload(&resources.fence, &vertices)
+                    .unwrap_or_else(|e| {
+                        panic!("uploading dynamic vertices failed: {}", explain_error(&e))
+                    });
+            }
+        }
+
+        let Some(adapter) = self.adapter_cycle.as_mut().and_then(AdapterCycle::tick) else {
+            return;
+        };
+        self.switch_adapter(&adapter)
+            .unwrap_or_else(|e| panic!("failed to switch adapter: {}", explain_error(&e)));
+        // 重新创建设备和资源的时间不计入下一个适配器的帧率
+        if let Some(adapter_cycle) = &mut self.adapter_cycle {
+            adapter_cycle.restart();
+        }
+    }
+
+    fn on_destroy(&mut self) {
+        // Resources 的 drop 会等待 GPU 空闲并退出独占全屏，在窗口还有效时就释放它们；
+        // 之后 Sample 被 drop 时 resources 已经是 None，不会再等一次
+        self.resources = None;
+    }
+
+    fn on_resize(&mut self, width: u32, height: u32) {
+        if let Some(resources) = &mut self.resources {
+            resources
+                .resize(
+                    &self.device,
+                    width,
+                    height,
+                    self.command_line.coordinate_system,
+                )
+                .unwrap_or_else(|e| panic!("resize failed: {}", explain_error(&e)));
+        }
+    }
+
+    /// 初始为 1，`-novsync` 时为 0，运行中可以用数字键 0~4 调整。
+    fn present_interval(&self) -> u32 {
+        self.resources
+            .as_ref()
+            .map_or(1, |resources| resources.sync_interval)
+    }
+
+    fn toggle_fullscreen(&mut self) -> Option<WindowPlacement> {
+        let resources = self.resources.as_ref()?;
+        // -mode 已经让交换链进入了独占全屏
+        if self.command_line.display_mode.is_some() {
+            return None;
+        }
+        // 已经是无边框全屏时恢复原来的窗口，否则记下当前的窗口再进入全屏
+        if let Some(windowed) = self.windowed_placement.take() {
+            return Some(windowed);
+        }
+        let (windowed, borderless) = borderless_fullscreen_placement(resources.hwnd)
+            .unwrap_or_else(|e| panic!("fullscreen toggle failed: {}", explain_error(&e)));
+        self.windowed_placement = Some(windowed);
+        Some(borderless)
+    }
+
+    fn on_mouse_down(&mut self, button: MouseButton, x: i32, y: i32) {
+        if button == MouseButton::Left {
+            self.drag_origin = Some((x, y));
+        }
+    }
+
+    fn on_mouse_up(&mut self, button: MouseButton, _x: i32, _y: i32) {
+        if button == MouseButton::Left {
+            self.drag_origin = None;
+        }
+    }
+
+    fn on_mouse_move(&mut self, x: i32, y: i32) {
+        // 每个像素转 0.01 弧度，向右拖动时相机向左绕，看起来像是把场景往右转
+        if let Some((last_x, last_y)) = self.drag_origin.replace((x, y)) {
+            let (dx, dy) = ((x - last_x) as f32, (y - last_y) as f32);
+            self.camera.orbit(-dx * 0.01, dy * 0.01);
+        }
+    }
+
+    fn on_mouse_wheel(&mut self, delta: i32) {
+        // 滚轮每格移动 0.25 个单位
+        self.camera.dolly(delta as f32 / WHEEL_DELTA as f32 * 0.25);
+    }
+
+    fn on_key_down(&mut self, key: u8) {
+        // F5 从磁盘重新编译着色器，修改 shaders.hlsl 后不必重启程序
+        if key == VK_F5 {
+            match self.reload_pipeline_state() {
+                Ok(()) => println!("shaders reloaded"),
+                Err(e) => eprintln!(
+                    "shader reload failed, keeping the old pipeline state:\n{}",
+                    e.message()
+                ),
+            }
+        }
+        if let Some(resources) = &mut self.resources {
+            // C 键切换棋盘格调试图案，便于发现视口、裁剪矩形和采样上的问题
+            if key == b'C' {
+                resources.checkerboard = !resources.checkerboard;
+            }
+            // W 键切换线框模式，只绘制三角形的边
+            if key == b'W' {
+                resources.wireframe = !resources.wireframe;
+            }
+            // P 键在扩展后的点精灵和原始的 1 像素点之间切换
+            if key == b'P' {
+                if let Some(point_sprites) = &mut resources.point_sprites {
+                    point_sprites.expand = !point_sprites.expand;
+                }
+            }
+            // F9 截图。按下时上一帧已经呈现，翻转模型下它的内容不再可靠，所以等下一帧渲染完再读取
+            if key == VK_F9 {
+                let seconds = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                resources.capture_path = Some(format!("screenshot-{}.png", seconds).into());
+            }
+            // 数字键 0~4 实时调整呈现的同步间隔
+            if (b'0'..=b'4').contains(&key) {
+                resources.sync_interval = (key - b'0') as u32;
+                println!("present sync interval: {}", resources.sync_interval);
+            }
+        }
+    }
+
+    fn render(&mut self) {
+        let sync_interval = self.present_interval().min(4);
+        if let Some(resources) = &mut self.resources {
+            // 等待交换链示意可以开始新的一帧，最多等待 1 秒，以免呈现出问题时窗口失去响应
+            if let Some(waitable_object) = resources.frame_latency_waitable_object {
+                unsafe { WaitForSingleObjectEx(waitable_object, 1000, true) };
+            }
+            if let Some(latency) = &mut resources.latency {
+                latency.begin_frame(&resources.swap_chain);
+            }
+
+            // 计算队列先生成这一帧的顶点偏移，直接队列在 GPU 端等待它完成后才开始绘制。
+            // CPU 并不会在这里阻塞，Wait 只是向直接队列插入一条等待围栏的命令。
+            if let Some(async_compute) = &mut resources.async_compute {
+                let compute_fence_value = async_compute
+                    .dispatch(
+                        resources.frame_slot,
+                        resources.fence.fence(),
+                        resources.fence.last_signaled(),
+                    )
+                    .unwrap_or_else(|e| panic!("compute dispatch failed: {}", explain_error(&e)));
+                unsafe {
+                    resources
+                        .command_queue
+                        .Wait(async_compute.fence.fence(), compute_fence_value.get())
+                }
+                .unwrap_or_else(|e| panic!("queue wait failed: {}", explain_error(&e)));
+            }
+            if let Some(particles) = &mut resources.particles {
+                let particles_fence_value = particles
+                    .dispatch(
+                        resources.frame_slot,
+                        resources.fence.fence(),
+                        resources.fence.last_signaled(),
+                    )
+                    .unwrap_or_else(|e| panic!("particle update failed: {}", explain_error(&e)));
+                unsafe {
+                    resources
+                        .command_queue
+                        .Wait(particles.fence(), particles_fence_value.get())
+                }
+                .unwrap_or_else(|e| panic!("queue wait failed: {}", explain_error(&e)));
+            }
+
+            let record_start = std::time::Instant::now();
+            populate_command_list(resources)
+                .unwrap_or_else(|e| panic!("command list recording failed: {}", explain_error(&e)));
+            let record_time = record_start.elapsed();
+
+            // Execute the command list.
+            let command_list = ID3D12CommandList::from(
+                resources
+                    .command_contexts
+                    .context(resources.frame_slot)
+                    .command_list(),
+            );
+
+            unsafe {
+                resources
+                    .command_queue
+                    .ExecuteCommandLists(&[Some(command_list)])
+            };
+
+            if let Some(path) = resources.capture_path.take() {
+                match resources.capture_frame(&self.device, &path) {
+                    Ok((width, height)) => {
+                        println!("saved {} ({}x{})", path.display(), width, height)
+                    }
+                    Err(e) => eprintln!("screenshot failed: {}", explain_error(&e)),
+                }
+            }
+
+            // Present the frame.
+            // 不等待垂直同步时，窗口模式下还要允许撕裂，否则 DWM 合成仍会把帧率限制在刷新率上
+            let present_flags = if sync_interval == 0 && resources.allow_tearing {
+                DXGI_PRESENT_ALLOW_TEARING
+            } else {
+                0
+            };
+            unsafe { resources.swap_chain.Present(sync_interval, present_flags) }
+                .ok()
+                .unwrap_or_else(|e| {
+                    if is_device_lost(&e) {
+                        log_device_removed(&self.device);
+                    }
+                    panic!("Present failed: {}", explain_error(&e))
+                });
+            if let Some(latency_ms) = resources
+                .latency
+                .as_mut()
+                .and_then(|latency| latency.end_frame(&resources.swap_chain))
+            {
+                let title = format!(
+                    "{} - input-to-present latency: {:.1} ms",
+                    resources.window_title, latency_ms
+                );
+                unsafe { SetWindowTextW(resources.hwnd, &HSTRING::from(title)) };
+            }
+            let fence_wait = move_to_next_frame(&self.device, resources);
+            if let Some(frame_pacing) = &mut resources.frame_pacing {
+                if let Some(report) = frame_pacing.end_frame(fence_wait, record_time) {
+                    println!(
+                        "frame pacing ({} frame(s) in flight): {}",
+                        resources.command_contexts.frame_count(),
+                        report
+                    );
+                }
+            }
+
+            if let Some(frame_messages) = &mut resources.frame_messages {
+                frame_messages
+                    .end_frame()
+                    .unwrap_or_else(|e| panic!("reading info queue failed: {}", explain_error(&e)));
+            }
+        }
+    }
+}
+
+impl Resources {
+    /// 创建与窗口绑定的全部资源：命令队列、交换链、渲染目标、PSO、顶点缓冲区和围栏等。
+    /// 它只依赖设备、工厂和窗口，不依赖 `Sample` 本身，窗口大小变化或设备重建时可以直接重新调用。
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        device: &ID3D12Device,
+        dxgi_factory: &IDXGIFactory4,
+        hwnd: &HWND,
+        mut width: i32,
+        mut height: i32,
+        command_line: &SampleCommandLine,
+        sample_desc: DXGI_SAMPLE_DESC,
+        clear_color: [f32; 4],
+    ) -> Result<Self> {
         let command_queue: ID3D12CommandQueue = unsafe {
-            self.device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
+            device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
                 Type: D3D12_COMMAND_LIST_TYPE_DIRECT,
                 ..Default::default()
             })?
         };
-        let (width, height) = self.window_size();
 
+        // 后台缓冲区格式只在这里确定一次，交换链、渲染目标视图和各个 PSO 都使用它
+        let format = command_line.back_buffer_format.dxgi_format();
+        check_back_buffer_format(device, format)?;
+        // 翻转模型的交换链不能使用 sRGB 格式，-srgb 时缓冲区仍是线性格式，只有渲染目标视图和 PSO 使用 sRGB 格式。
+        // 硬件在写入时把着色器输出的线性值编码为 sRGB，所以同样的清除颜色看起来更亮，颜色的混合和插值也是伽马正确的
+        let rtv_format = match command_line.back_buffer_format.srgb_format() {
+            Some(srgb_format) if command_line.srgb => srgb_format,
+            _ => format,
+        };
+        let targets = RenderTargetFormats {
+            sample_desc,
+            ..RenderTargetFormats::color_only(rtv_format).with_depth(command_line.depth_mode)
+        };
+
+        // 可等待交换链：DXGI 提供一个事件，在可以开始新的一帧时被激发。CPU 在帧开始前等待它，
+        // 就不会比屏幕领先太多帧，输入到呈现的延迟也就更低。
+        let mut swap_chain_flags = if command_line.waitable {
+            DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT.0 as u32
+        } else {
+            0
+        };
+        // 支持撕裂时总是带上 ALLOW_TEARING，运行中用数字键 0 切换到不等待垂直同步也能撕裂。
+        // 独占全屏（-mode）下不能使用 DXGI_PRESENT_ALLOW_TEARING，也不需要它
+        let allow_tearing =
+            command_line.display_mode.is_none() && check_tearing_support(dxgi_factory);
+        if allow_tearing {
+            swap_chain_flags |= DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING.0 as u32;
+        }
         let swap_chain_desc = DXGI_SWAP_CHAIN_DESC1 {
-            // 交换链中所用的缓冲区数量。我们将它指定为2，即采用双缓冲。
-            BufferCount: FRAME_COUNT,
+            // 交换链中所用的缓冲区数量，由 -buffers 决定，默认为 2，即采用双缓冲。
+            BufferCount: command_line.buffer_count(),
             Width: width as u32,
             Height: height as u32,
-            Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+            Format: format,
             // 由于我们要将数据渲染至后台缓冲区（即用它作为渲染目标），因此将此参数指定为 DXGI_USAGE_RENDER_TARGET_OUTPUT。
             BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
             // 一个 DXGI_SWAP_EFFECT 类型的值描述了交换链所使用的表现模式，以及在呈现一个 surface 后处理表现缓冲区内容的选项。
@@ -86,11 +584,12 @@ impl DXSample for Sample {
                 Count: 1,
                 ..Default::default()
             },
+            Flags: swap_chain_flags,
             ..Default::default()
         };
 
         let swap_chain: IDXGISwapChain3 = unsafe {
-            self.dxgi_factory.CreateSwapChainForHwnd(
+            dxgi_factory.CreateSwapChainForHwnd(
                 &command_queue,
                 *hwnd,
                 &swap_chain_desc,
@@ -100,160 +599,536 @@ impl DXSample for Sample {
         }
         .cast()?;
 
-        // This sample does not support fullscreen transitions
+        let frame_latency_waitable_object = if command_line.waitable {
+            unsafe {
+                swap_chain.SetMaximumFrameLatency(1)?;
+                Some(swap_chain.GetFrameLatencyWaitableObject())
+            }
+        } else {
+            None
+        };
+
+        // HDR10 色彩空间中，后台缓冲区的值按 ST.2084（PQ）曲线编码绝对亮度并使用 BT.2020 色域，
+        // 不再是 sRGB：清除颜色和着色器输出都按这个扩展的范围解读，1.0 对应 10000 尼特
+        if command_line.hdr && !set_hdr10_color_space(&swap_chain)? {
+            eprintln!("hdr: the swap chain cannot present in the HDR10 color space, using SDR");
+        }
+
+        // 只是打印信息，查询失败（例如取不到显示设置）不影响渲染
+        if let Err(e) = print_containing_output(&swap_chain) {
+            eprintln!("output: cannot query the window's output: {}", e.message());
+        }
+
+        // 不让 DXGI 在 Alt+Enter 时切换到独占全屏，Alt+Enter 由 `toggle_fullscreen` 切换无边框全屏
         unsafe {
-            self.dxgi_factory
-                .MakeWindowAssociation(*hwnd, DXGI_MWA_NO_ALT_ENTER)?;
+            dxgi_factory.MakeWindowAssociation(*hwnd, DXGI_MWA_NO_ALT_ENTER)?;
+        }
+
+        // 指定了 -mode 时，先把目标窗口调整到最接近的显示模式，再进入独占全屏，
+        // 最后把后台缓冲区调整为同样的大小。这些都要在取出后台缓冲区之前完成。
+        if let Some(display_mode) = command_line.display_mode {
+            let output = unsafe { swap_chain.GetContainingOutput() }?;
+            let mode = find_closest_mode(&output, device, display_mode, format)?;
+            println!(
+                "fullscreen: requested {}, using {}x{}@{:.2}",
+                display_mode.name(),
+                mode.Width,
+                mode.Height,
+                refresh_rate(&mode)
+            );
+            unsafe {
+                swap_chain.ResizeTarget(&mode)?;
+                swap_chain.SetFullscreenState(true, &output)?;
+                swap_chain.ResizeBuffers(
+                    command_line.buffer_count(),
+                    mode.Width,
+                    mode.Height,
+                    format,
+                    swap_chain_flags,
+                )?;
+            }
+            width = mode.Width as i32;
+            height = mode.Height as i32;
         }
         // 用来记录当前后台缓冲区的索引（由于利用页面翻转技术来交换前台缓冲区和后台缓冲区，
         // 所以我们需要对其进行记录，以便搞清楚哪个缓冲区才是当前正在用于渲染数据的后台缓冲区）。
         let frame_index = unsafe { swap_chain.GetCurrentBackBufferIndex() };
 
-        // 我们将为交换链中 NumDescriptors 个用于渲染数据的缓冲区资源创建对应的渲染目标视图（Render Target View，RTV）
-        let rtv_heap: ID3D12DescriptorHeap = unsafe {
-            self.device
-                .CreateDescriptorHeap(&D3D12_DESCRIPTOR_HEAP_DESC {
-                    NumDescriptors: FRAME_COUNT,
-                    Type: D3D12_DESCRIPTOR_HEAP_TYPE_RTV,
-                    ..Default::default()
-                })
-        }?;
-        let rtv_descriptor_size = unsafe {
-            self.device
-                .GetDescriptorHandleIncrementSize(D3D12_DESCRIPTOR_HEAP_TYPE_RTV)
-        } as usize;
-        // 创建描述符堆之后，还要能访问其中所存的描述符。在程序中，我们是通过句柄来引用描述符的，
-        // 并以 ID3D12DescriptorHeap::GetCPUDescriptorHandleForHeapStart 方法来获得描述符堆中第一个描述符的句柄。
-        let rtv_handle = unsafe { rtv_heap.GetCPUDescriptorHandleForHeapStart() };
-
-        // 资源不能与渲染流水线中的阶段直接绑定，所以我们必须先为资源创建视图（描述符），并将其绑定到流水线阶段。
-        // 例如，为了将后台缓冲区绑定到流水线的输出合并阶段（output merger stage，这样Direct3D才能向其渲染），
-        // 便需要为该后台缓冲区创建一个渲染目标视图。而这第一个步骤就是要获得存于交换链中的缓冲区资源。
-        let render_targets: [ID3D12Resource; FRAME_COUNT as usize] =
-            array_init::try_array_init(|i: usize| -> Result<ID3D12Resource> {
-                // i 是希望获得的特定后台缓冲区的索引（有时后台缓冲区并不只一个，所以需要用索引来指明）。
-                let render_target: ID3D12Resource = unsafe { swap_chain.GetBuffer(i as u32) }?;
-                unsafe {
-                    // 为获取的后台缓冲区创建渲染目标视图
-                    self.device.CreateRenderTargetView(
-                        // 指定用作渲染目标的资源。这里是后台缓冲区（即为后台缓冲区创建了一个渲染目标视图）。
-                        &render_target,
-                        // 指向 D3D12_RENDER_TARGET_VIEW_DESC 数据结构实例的指针。该结构体描述了资源中元素的数据类型（格式）。
-                        // 如果该资源在创建时已指定了具体格式（即此资源不是无类型格式，not typeless），那么就可以把这个参数设为空指针，
-                        // 表示采用该资源创建时的格式，为它的第一个 mipmap 层级（后台缓冲区只有一种 mipmap 层级，
-                        // 有关 mipmap 的内容将在第 9 章展开讨论）创建一个视图。由于已经指定了后台缓冲区的格式，因此就将这个参数设置为空指针。
-                        None,
-                        // 引用所创建渲染目标视图的描述符句柄
-                        D3D12_CPU_DESCRIPTOR_HANDLE {
-                            ptr: rtv_handle.ptr + i * rtv_descriptor_size,
-                        },
-                    )
-                };
-                Ok(render_target)
-            })?;
-
-        let viewport = D3D12_VIEWPORT {
-            TopLeftX: 0.0,
-            TopLeftY: 0.0,
-            Width: width as f32,
-            Height: height as f32,
-            MinDepth: D3D12_MIN_DEPTH,
-            MaxDepth: D3D12_MAX_DEPTH,
+        // 我们将为交换链中每个用于渲染数据的缓冲区资源创建对应的渲染目标视图（Render Target View，RTV），
+        // 最后再多留一个给多重采样渲染目标
+        let rtv_heap = DescriptorHeap::new(
+            device,
+            D3D12_DESCRIPTOR_HEAP_TYPE_RTV,
+            command_line.buffer_count() + 1,
+            false,
+        )?;
+        let render_targets = create_render_targets(
+            device,
+            &swap_chain,
+            &rtv_heap,
+            command_line.buffer_count(),
+            targets.rtv_format(),
+        )?;
+        let (viewport, scissor_rect) = viewport_and_scissor(width, height);
+
+        let (depth_stencil, dsv_heap) = create_depth_stencil(
+            device,
+            width as u64,
+            height as u32,
+            sample_desc,
+            targets.depth_mode,
+        )?;
+        let msaa_render_target = create_msaa_render_target(
+            device,
+            &targets,
+            &clear_color,
+            width as u64,
+            height as u32,
+            &rtv_heap,
+            command_line.buffer_count(),
+        )?;
+
+        let frames_in_flight = command_line.max_frames_in_flight() as usize;
+        let command_contexts =
+            CommandContextPool::new(device, D3D12_COMMAND_LIST_TYPE_DIRECT, frames_in_flight)?;
+
+        let async_compute = if command_line.async_compute {
+            Some(AsyncCompute::new(device, frames_in_flight)?)
+        } else {
+            None
         };
 
-        let scissor_rect = RECT {
-            left: 0,
-            top: 0,
-            right: width,
-            bottom: height,
+        let particles = if command_line.particles > 0 {
+            Some(Particles::new(
+                device,
+                command_line.particles,
+                frames_in_flight,
+                &targets,
+                command_line.verbose,
+            )?)
+        } else {
+            None
         };
 
-        let command_allocator = unsafe {
-            self.device
-                .CreateCommandAllocator(D3D12_COMMAND_LIST_TYPE_DIRECT)
-        }?;
+        let point_sprites = if command_line.points > 0 {
+            Some(PointSprites::new(
+                device,
+                command_line.points,
+                &targets,
+                command_line.verbose,
+            )?)
+        } else {
+            None
+        };
 
-        let root_signature = create_root_signature(&self.device)?;
+        let texture_path = match &command_line.texture {
+            Some(texture) => Some(resolve_asset(texture)),
+            None => Some(resolve_asset(DEFAULT_TEXTURE)).filter(|path| path.exists()),
+        };
+        let textured_quad = match texture_path {
+            Some(texture_path) => Some(TexturedQuad::new(
+                device,
+                &command_queue,
+                &texture_path,
+                &targets,
+                command_line.verbose,
+            )?),
+            None => None,
+        };
 
-        let pso = create_pipeline_state(&self.device, &root_signature)?;
-        let command_list: ID3D12GraphicsCommandList = unsafe {
-            self.device.CreateCommandList(
-                0,
-                D3D12_COMMAND_LIST_TYPE_DIRECT,
-                &command_allocator,
-                &pso,
-            )
-        }?;
-        unsafe {
-            command_list.Close()?;
+        // 开启异步计算时，顶点着色器要通过根 SRV 读取计算队列写出的顶点偏移
+        let root_signature = if async_compute.is_some() {
+            create_vertex_offsets_root_signature(device)?
+        } else {
+            create_root_signature(device, false)?
         };
 
+        let instances = create_instance_buffer(device, &command_queue, command_line.instances)?;
+
+        let coordinate_system = command_line.coordinate_system;
+        let (pso, checkerboard_pso, wireframe_pso) = create_triangle_pipeline_states(
+            device,
+            &root_signature,
+            async_compute.is_some(),
+            instances.is_some(),
+            command_line.shader_model,
+            // 第一次创建时可以使用构建时预编译的着色器
+            true,
+            coordinate_system,
+            command_line.blend_mode,
+            &targets,
+            command_line.verbose,
+        )?;
         let aspect_ratio = width as f32 / height as f32;
 
-        let (vertex_buffer, vbv) = create_vertex_buffer(&self.device, aspect_ratio)?;
+        let dynamic_vertices = if command_line.dynamic_vertices {
+            Some(UploadRingBuffer::new(device, DYNAMIC_VERTEX_RING_SIZE)?)
+        } else {
+            None
+        };
+        let (vertex_buffer, vbv) = create_vertex_buffer(
+            device,
+            aspect_ratio,
+            coordinate_system,
+            command_line.use_default_heap.then_some(&command_queue),
+        )?;
 
-        let fence = unsafe { self.device.CreateFence(0, D3D12_FENCE_FLAG_NONE) }?;
+        // 三角形的三个顶点依次用一次，索引缓冲区在这里只是演示，网格中共享的顶点才能真正省下空间
+        let (index_buffer, ibv) = if command_line.index32 {
+            create_index_buffer(device, &[0u32, 1, 2])?
+        } else {
+            create_index_buffer(device, &[0u16, 1, 2])?
+        };
 
-        let fence_value = 1;
+        let object_constants = (0..frames_in_flight)
+            .map(|_| {
+                let constant_buffer = ConstantBuffer::new(device)?;
+                constant_buffer.update(&ObjectConstants::IDENTITY);
+                Ok(constant_buffer)
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-        let fence_event = unsafe { CreateEventA(None, false, false, None)? };
+        let fence = Fence::new(device)?;
 
-        self.resources = Some(Resources {
+        let mut resources = Resources {
             command_queue,
             swap_chain,
             frame_index,
             render_targets,
+            swap_chain_flags,
             rtv_heap,
-            rtv_descriptor_size,
+            clear_color,
+            depth_stencil,
+            dsv_heap,
+            targets,
+            msaa_render_target,
+            resource_states: ResourceState::new(),
             viewport,
             scissor_rect,
-            command_allocator,
+            command_contexts,
+            frame_slot: 0,
             root_signature,
             pso,
-            command_list,
+            checkerboard_pso,
+            checkerboard: false,
+            wireframe_pso,
+            wireframe: false,
+            bundles: None,
+            sync_interval: if command_line.no_vsync { 0 } else { 1 },
+            capture_path: None,
+            blend_mode: command_line.blend_mode,
+            allow_tearing,
             vertex_buffer,
+            vertex_buffer_in_default_heap: command_line.use_default_heap,
+            dynamic_vertices,
             vbv,
+            instances,
+            index_buffer,
+            ibv,
+            object_constants,
+            async_compute,
+            particles,
+            point_sprites,
+            textured_quad,
             fence,
-            fence_value,
-            fence_event,
-        });
+            start_time: std::time::Instant::now(),
+            hwnd: *hwnd,
+            window_title: window_title(hwnd),
+            frame_latency_waitable_object,
+            latency: command_line.waitable.then(LatencyMeter::default),
+            frame_pacing: command_line.frame_stats.then(FramePacing::default),
+            frame_messages: info_queue(device).map(FrameMessages::new),
+        };
+        resources.track_render_targets();
+        // 每帧新生成的顶点位于环形缓冲区中的不同位置，顶点缓冲区视图无法事先录制进 bundle
+        if !command_line.no_bundle && !command_line.dynamic_vertices {
+            resources.record_bundles(device)?;
+        }
+        Ok(resources)
+    }
 
+    /// 按当前的 PSO、顶点缓冲区视图和实例缓冲区重新录制三个 bundle。
+    /// 顶点缓冲区或 PSO 重新创建之后要调用，旧的 bundle 此时必须已经不在 GPU 上执行。
+    fn record_bundles(&mut self, device: &ID3D12Device) -> Result<()> {
+        let record = |pso| {
+            Bundle::record(device, Some(pso), |command_list| {
+                // 与直接命令列表相同的根签名，这样才能继承那里每帧设置的根参数
+                unsafe { command_list.SetGraphicsRootSignature(&self.root_signature) };
+                record_triangle_draw(command_list, self);
+            })
+        };
+        let bundles = TriangleBundles {
+            solid: record(&self.pso)?,
+            checkerboard: record(&self.checkerboard_pso)?,
+            wireframe: record(&self.wireframe_pso)?,
+        };
+        self.bundles = Some(bundles);
         Ok(())
     }
 
-    fn update(&mut self) {}
+    /// 把当前的后台缓冲区保存为 PNG，返回图像的宽高。要在这一帧的命令提交之后、呈现之前调用，
+    /// 读回的复制命令排在这一帧的命令之后执行。后台缓冲区此时处于 PRESENT 状态。
+    fn capture_frame(&self, device: &ID3D12Device, path: &std::path::Path) -> Result<(u32, u32)> {
+        let image = read_back_texture(
+            device,
+            &self.command_queue,
+            &self.render_targets[self.frame_index as usize],
+            D3D12_RESOURCE_STATE_PRESENT,
+        )?;
+        // 后台缓冲区与客户区（也就是视口）一样大
+        debug_assert_eq!(
+            (image.width, image.height),
+            (self.viewport.Width as u32, self.viewport.Height as u32)
+        );
+        write_png(path, image.width, image.height, &image.to_rgba8()?)
+            .map_err(|e| Error::new(E_FAIL, e.to_string().into()))?;
+        Ok((image.width, image.height))
+    }
 
-    fn render(&mut self) {
-        if let Some(resources) = &mut self.resources {
-            populate_command_list(resources).unwrap();
+    /// 重新跟踪后台缓冲区和多重采样渲染目标：前者刚创建时处于 PRESENT 状态，后者处于 RENDER_TARGET 状态。
+    /// 重新创建它们之后要再调用一次，旧资源的记录会被清除。
+    fn track_render_targets(&mut self) {
+        self.resource_states.clear();
+        for render_target in &self.render_targets {
+            self.resource_states
+                .track(render_target, D3D12_RESOURCE_STATE_PRESENT);
+        }
+        if let Some(msaa_render_target) = &self.msaa_render_target {
+            self.resource_states
+                .track(msaa_render_target, D3D12_RESOURCE_STATE_RENDER_TARGET);
+        }
+    }
 
-            // Execute the command list.
-            let command_list = ID3D12CommandList::from(&resources.command_list);
+    /// 给主要的对象起调试名称，调试层的消息和 PIX 捕获中就能看出是哪个对象。
+    /// 重新创建某些对象（改变窗口大小、重新加载着色器）之后要再调用一次。
+    fn set_debug_names(&self) {
+        set_name(&self.command_queue, "direct queue");
+        for (i, render_target) in self.render_targets.iter().enumerate() {
+            set_name(render_target, &format!("back buffer {}", i));
+        }
+        if let Some(msaa_render_target) = &self.msaa_render_target {
+            set_name(msaa_render_target, "msaa render target");
+        }
+        set_name(&self.depth_stencil, "depth stencil");
+        set_name(self.rtv_heap.heap(), "rtv heap");
+        set_name(&self.dsv_heap, "dsv heap");
+        for frame_slot in 0..self.command_contexts.frame_count() {
+            self.command_contexts
+                .context(frame_slot)
+                .set_name(&format!("frame {} command", frame_slot));
+        }
+        set_name(&self.root_signature, "triangle root signature");
+        set_name(&self.pso, "triangle pso");
+        set_name(&self.checkerboard_pso, "triangle checkerboard pso");
+        set_name(&self.wireframe_pso, "triangle wireframe pso");
+        if let Some(bundles) = &self.bundles {
+            bundles.solid.set_name("triangle bundle");
+            bundles
+                .checkerboard
+                .set_name("triangle checkerboard bundle");
+            bundles.wireframe.set_name("triangle wireframe bundle");
+        }
+        set_name(&self.vertex_buffer, "triangle vertex buffer");
+        if let Some(instances) = &self.instances {
+            set_name(&instances.buffer, "triangle instance buffer");
+        }
+        set_name(&self.index_buffer, "triangle index buffer");
+        for (i, constant_buffer) in self.object_constants.iter().enumerate() {
+            set_name(
+                constant_buffer.resource(),
+                &format!("object constants {}", i),
+            );
+        }
+        set_name(self.fence.fence(), "direct queue fence");
+    }
 
-            unsafe {
-                resources
-                    .command_queue
-                    .ExecuteCommandLists(&[Some(command_list)])
-            };
+    /// 在直接队列上 Signal 一个新的围栏值，并等待 GPU 执行到它，此后 GPU 不再使用任何资源。
+    fn wait_for_gpu(&mut self) -> Result<()> {
+        self.fence.flush(&self.command_queue)
+    }
 
-            // Present the frame.
-            unsafe { resources.swap_chain.Present(1, 0) }.ok().unwrap();
-            wait_for_previous_frame(resources);
+    /// 窗口大小改变后，按新的客户区大小重新创建后台缓冲区及其 RTV、深度/模板缓冲区，
+    /// 并更新视口和裁剪矩形。三角形的顶点按宽高比生成，也要重新创建，这样它不会被拉伸。
+    fn resize(
+        &mut self,
+        device: &ID3D12Device,
+        width: u32,
+        height: u32,
+        coordinate_system: CoordinateSystem,
+    ) -> Result<()> {
+        let current = (self.viewport.Width as u32, self.viewport.Height as u32);
+        if current == (width, height) {
+            return Ok(());
         }
+
+        // 在飞行中的帧可能还在使用后台缓冲区，而且 ResizeBuffers 要求交换链的缓冲区没有任何外部引用
+        self.wait_for_gpu()?;
+        let buffer_count = self.render_targets.len() as u32;
+        self.render_targets.clear();
+        self.resource_states.clear();
+        unsafe {
+            // 缓冲区数量传 0、格式传 UNKNOWN，表示保持原来的设置
+            self.swap_chain.ResizeBuffers(
+                0,
+                width,
+                height,
+                DXGI_FORMAT_UNKNOWN,
+                self.swap_chain_flags,
+            )
+        }?;
+        self.frame_index = unsafe { self.swap_chain.GetCurrentBackBufferIndex() };
+        self.render_targets = create_render_targets(
+            device,
+            &self.swap_chain,
+            &self.rtv_heap,
+            buffer_count,
+            self.targets.rtv_format(),
+        )?;
+        (self.depth_stencil, self.dsv_heap) = create_depth_stencil(
+            device,
+            width as u64,
+            height,
+            self.targets.sample_desc,
+            self.targets.depth_mode,
+        )?;
+        if self.msaa_render_target.is_some() {
+            self.msaa_render_target = None;
+            self.msaa_render_target = create_msaa_render_target(
+                device,
+                &self.targets,
+                &self.clear_color,
+                width as u64,
+                height,
+                &self.rtv_heap,
+                buffer_count,
+            )?;
+        }
+        self.track_render_targets();
+        (self.viewport, self.scissor_rect) = viewport_and_scissor(width as i32, height as i32);
+        (self.vertex_buffer, self.vbv) = create_vertex_buffer(
+            device,
+            width as f32 / height as f32,
+            coordinate_system,
+            self.vertex_buffer_in_default_heap
+                .then_some(&self.command_queue),
+        )?;
+        // bundle 中记录的是旧的顶点缓冲区视图
+        if self.bundles.is_some() {
+            self.record_bundles(device)?;
+        }
+        // 新建的缓冲区还没有名字
+        self.set_debug_names();
+        Ok(())
+    }
+
+    /// 所有由本示例创建的资源及其类别，供 `-vram` 汇总显存占用。
+    fn memory_usage(&self) -> Vec<(&'static str, &ID3D12Resource)> {
+        let mut resources: Vec<(&'static str, &ID3D12Resource)> = self
+            .render_targets
+            .iter()
+            .map(|render_target| ("render targets", render_target))
+            .collect();
+        if let Some(msaa_render_target) = &self.msaa_render_target {
+            resources.push(("render targets", msaa_render_target));
+        }
+        resources.push(("depth stencil", &self.depth_stencil));
+        // 位于上传堆的缓冲区实际占用的是 GPU 可见的系统内存
+        resources.push((
+            if self.vertex_buffer_in_default_heap {
+                "vertex buffers"
+            } else {
+                "vertex buffers (upload heap)"
+            },
+            &self.vertex_buffer,
+        ));
+        if let Some(instances) = &self.instances {
+            resources.push(("vertex buffers", &instances.buffer));
+        }
+        resources.push(("index buffers (upload heap)", &self.index_buffer));
+        for constant_buffer in &self.object_constants {
+            resources.push(("constant buffers (upload heap)", constant_buffer.resource()));
+        }
+        if let Some(async_compute) = &self.async_compute {
+            resources.push(("compute buffers", &async_compute.vertex_offsets));
+        }
+        if let Some(particles) = &self.particles {
+            resources.push(("compute buffers", particles.buffer()));
+        }
+        if let Some(point_sprites) = &self.point_sprites {
+            resources.push((
+                "vertex buffers (upload heap)",
+                point_sprites.vertex_buffer(),
+            ));
+        }
+        if let Some(textured_quad) = &self.textured_quad {
+            resources.push(("textures", textured_quad.texture()));
+            resources.push(("vertex buffers", textured_quad.vertex_buffer()));
+        }
+        resources
     }
 }
 
-fn populate_command_list(resources: &Resources) -> Result<()> {
+/// 释放示例时会阻塞，直到直接队列上提交的所有命令都执行完，之后才释放资源、关闭事件句柄。
+impl Drop for Resources {
+    fn drop(&mut self) {
+        // 多帧在飞行中时，GPU 可能还在使用这些资源，释放之前先等待直接队列空闲
+        if let Err(e) = self.wait_for_gpu() {
+            eprintln!(
+                "waiting for the GPU before releasing resources failed: {}",
+                explain_error(&e)
+            );
+        }
+        // 交换链不能在独占全屏状态下被释放，先切回窗口模式
+        let _ = unsafe { self.swap_chain.SetFullscreenState(false, None) };
+        if let Some(waitable_object) = self.frame_latency_waitable_object {
+            unsafe { CloseHandle(waitable_object) };
+        }
+    }
+}
+
+/// 绘制三角形的输入装配器设置和绘制调用。每帧直接录制到命令列表中，或者在初始化时录制进 bundle。
+/// bundle 不继承调用者的图元拓扑，所以拓扑也在这里设置。
+fn record_triangle_draw(command_list: &ID3D12GraphicsCommandList, resources: &Resources) {
+    unsafe {
+        command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        // 在顶点缓冲区及其对应视图创建完成后，便可以将它与渲染流水线上的一个输入槽（input slot）相绑定。
+        // 这样一来，我们就能向流水线中的输入装配器阶段传递顶点数据了。
+        command_list.IASetVertexBuffers(0, Some(&[resources.vbv]));
+        // 实例数据绑定到第 1 个输入槽，输入布局中这个槽的元素是逐实例读取的
+        if let Some(instances) = &resources.instances {
+            command_list.IASetVertexBuffers(1, Some(&[instances.view]));
+        }
+        // 索引缓冲区视图描述了索引的位置、大小和格式（16 位或 32 位），一次只能绑定一个。
+        command_list.IASetIndexBuffer(Some(&resources.ibv));
+        // 将顶点缓冲区设置到输入槽上并不会对其执行实际的绘制操作，而是仅为顶点数据送至渲染流水线做好准备而已。
+        // 这最后一步才是通过 ID3D12GraphicsCommandList::DrawInstanced 方法真正地绘制顶点。
+        // 1. VertexCountPerInstance：每个实例要绘制的顶点数量。
+        // 2. InstanceCount：用于实现一种被称作实例化（instancing）的高级技术。通常只绘制一个实例，
+        //    半透明时绘制两个，顶点着色器按实例编号把第二个错开；`-instances` 时绘制实例缓冲区中的每一个。
+        // 3. StartVertexLocation：指定顶点缓冲区内第一个被绘制顶点的索引（该索引值以 0 为基准）。
+        // 4. StartInstanceLocation：第一个实例在实例缓冲区中的位置，从头开始绘制时为 0。
+        // VertexCountPerInstance 和 StartVertexLocation 两个参数定义了顶点缓冲区中将要被绘制的一组连续顶点，
+        // 使用索引缓冲区时改用 DrawIndexedInstanced：第一个参数变为每个实例的索引数量，
+        // 第三个参数是第一个索引在索引缓冲区中的位置，第四个参数是加到每个索引上的基准顶点位置。
+        let instance_count = if let Some(instances) = &resources.instances {
+            instances.count
+        } else if resources.blend_mode.is_translucent() {
+            2
+        } else {
+            1
+        };
+        command_list.DrawIndexedInstanced(3, instance_count, 0, 0, 0);
+    }
+}
+
+fn populate_command_list(resources: &mut Resources) -> Result<()> {
     // Command list allocators can only be reset when the associated
     // command lists have finished execution on the GPU; apps should use
     // fences to determine GPU execution progress.
     // 向 GPU 提交了一整帧的渲染命令后，我们可能还要为了绘制下一帧而复用命令分配器中的内存。
     // 由于命令队列可能会引用命令分配器中的数据，所以在没有确定 GPU 执行完命令分配器中的所有命令之前，千万不要重置命令分配器！
-    unsafe {
-        resources.command_allocator.Reset()?;
-    }
-
-    let command_list = &resources.command_list;
+    // move_to_next_frame 已经等过这个上下文上次提交的那一帧，CommandContext::reset 还会再检查一遍。
+    let command_context = resources.command_contexts.context(resources.frame_slot);
 
     // However, when ExecuteCommandList() is called on a particular
     // command list, that command list can then be reset at any time and
@@ -261,14 +1136,32 @@ fn populate_command_list(resources: &Resources) -> Result<()> {
     // 此方法将命令列表恢复为刚创建时的初始状态，我们可以借此继续复用其低层内存，也可以避免释放旧列表再创建新列表这一系列的烦琐操作。
     // 注意，重置命令列表并不会影响命令队列中的命令，因为相关的命令分配器仍在维护着其内存中被命令队列引用的系列命令。
     // 向 GPU 提交了一整帧的渲染命令后，我们可能还要为了绘制下一帧而复用命令分配器中的内存。
-    unsafe {
-        command_list.Reset(&resources.command_allocator, &resources.pso)?;
-    }
+    let pso = if resources.wireframe {
+        &resources.wireframe_pso
+    } else if resources.checkerboard {
+        &resources.checkerboard_pso
+    } else {
+        &resources.pso
+    };
+    let command_list = command_context.reset(resources.fence.fence(), Some(pso))?;
 
     // Set necessary state.
     unsafe {
         // 将根签名设置到命令列表上
         command_list.SetGraphicsRootSignature(&resources.root_signature);
+        // 像素着色器用经过的时间让颜色随时间变化
+        let time = resources.start_time.elapsed().as_secs_f32();
+        set_graphics_root_constants(command_list, 0, &time);
+        command_list.SetGraphicsRootConstantBufferView(
+            1,
+            resources.object_constants[resources.frame_slot].gpu_virtual_address(),
+        );
+        if let Some(async_compute) = &resources.async_compute {
+            command_list.SetGraphicsRootShaderResourceView(
+                2,
+                async_compute.vertex_offsets.GetGPUVirtualAddress(),
+            );
+        }
         // 设置一个视口，将场景绘至整个后台缓冲区
         // 第一个参数是要绑定的视口数量（有些高级效果需要使用多个视口），第二个参数是一个指向视口数组的指针。
         command_list.RSSetViewports(&[resources.viewport]);
@@ -278,100 +1171,477 @@ fn populate_command_list(resources: &Resources) -> Result<()> {
         command_list.RSSetScissorRects(&[resources.scissor_rect]);
     }
 
-    // Indicate that the back buffer will be used as a render target.
-    // 这段代码将以图片形式显示在屏幕中的纹理，从呈现状态转换为渲染目标状态。
-    let barrier = transition_barrier(
-        &resources.render_targets[resources.frame_index as usize],
-        D3D12_RESOURCE_STATE_PRESENT,
-        D3D12_RESOURCE_STATE_RENDER_TARGET,
-    );
-    unsafe { command_list.ResourceBarrier(&[barrier]) };
-
-    // 从描述符堆中获取描述符
-    let rtv_handle = D3D12_CPU_DESCRIPTOR_HANDLE {
-        // 在程序中，我们是通过句柄来引用描述符的
-        // 下面通过 GetCPUDescriptorHandleForHeapStart 方法来获得描述符堆中第一个描述符的句柄
-        ptr: unsafe { resources.rtv_heap.GetCPUDescriptorHandleForHeapStart() }.ptr
-            + resources.frame_index as usize * resources.rtv_descriptor_size,
+    let back_buffer = &resources.render_targets[resources.frame_index as usize];
+    // 开启 MSAA 时绘制到多重采样渲染目标，它的 RTV 紧跟在所有后台缓冲区之后；
+    // 它一直处于渲染目标状态，后台缓冲区要到最后解析时才会用到
+    let rtv_index = if resources.msaa_render_target.is_some() {
+        resources.render_targets.len()
+    } else {
+        // Indicate that the back buffer will be used as a render target.
+        // 这段代码将以图片形式显示在屏幕中的纹理，从呈现状态转换为渲染目标状态。
+        // 前一个状态由 resource_states 记录，不用在这里手写
+        resource_barriers(
+            command_list,
+            [resources
+                .resource_states
+                .transition(back_buffer, D3D12_RESOURCE_STATE_RENDER_TARGET)],
+        );
+        resources.frame_index as usize
     };
-    // 指定将要渲染的缓冲区
-    unsafe { command_list.OMSetRenderTargets(1, Some(&rtv_handle), false, None) };
+
+    // 从描述符堆中获取描述符，在程序中，我们是通过句柄来引用描述符的
+    let rtv_handle = resources.rtv_heap.cpu_handle(rtv_index as u32);
+    let dsv_handle = unsafe { resources.dsv_heap.GetCPUDescriptorHandleForHeapStart() };
+    // 指定将要渲染的缓冲区，以及深度测试所用的深度/模板缓冲区
+    set_render_targets(command_list, &[rtv_handle], Some(&dsv_handle));
 
     // Record commands.
+    // 用 PIX 抓帧时，事件把命令分成有名字的区域
+    begin_event(command_list, pix_color(0, 128, 255), "Clear");
     unsafe {
         // 清除后台缓冲区
-        command_list.ClearRenderTargetView(rtv_handle, [0.0, 0.2, 0.4, 1.0].as_ptr(), &[]);
-        command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
-        // 在顶点缓冲区及其对应视图创建完成后，便可以将它与渲染流水线上的一个输入槽（input slot）相绑定。
-        // 这样一来，我们就能向流水线中的输入装配器阶段传递顶点数据了。
-        command_list.IASetVertexBuffers(0, Some(&[resources.vbv]));
-        // 将顶点缓冲区设置到输入槽上并不会对其执行实际的绘制操作，而是仅为顶点数据送至渲染流水线做好准备而已。
-        // 这最后一步才是通过 ID3D12GraphicsCommandList::DrawInstanced 方法真正地绘制顶点。
-        // 1. VertexCountPerInstance：每个实例要绘制的顶点数量。
-        // 2. InstanceCount：用于实现一种被称作实例化（instancing）的高级技术。就目前来说，我们只绘制一个实例，因而将此参数设置为 1。
-        // 3. StartVertexLocation：指定顶点缓冲区内第一个被绘制顶点的索引（该索引值以 0 为基准）。
-        // 4. StartInstanceLocation：用于实现一种被称作实例化的高级技术，暂时只需将其设置为 0。
-        // VertexCountPerInstance 和 StartVertexLocation 两个参数定义了顶点缓冲区中将要被绘制的一组连续顶点，
-        command_list.DrawInstanced(3, 1, 0, 0);
+        command_list.ClearRenderTargetView(rtv_handle, resources.clear_color.as_ptr(), &[]);
+        // 深度清除为最远处（反转 Z 时是 0.0，否则是 1.0），模板清除为 0，与创建时的优化清除值一致
+        command_list.ClearDepthStencilView(
+            dsv_handle,
+            D3D12_CLEAR_FLAG_DEPTH | D3D12_CLEAR_FLAG_STENCIL,
+            resources.targets.depth_mode.clear_value(),
+            0,
+            &[],
+        );
+    }
+    end_event(command_list);
+
+    begin_event(command_list, pix_color(255, 128, 0), "DrawTriangle");
+    match &resources.bundles {
+        // bundle 的根签名与这里相同，继承上面设置的时间、MVP 等根参数
+        Some(bundles) => unsafe {
+            command_list.ExecuteBundle(
+                bundles
+                    .select(resources.wireframe, resources.checkerboard)
+                    .command_list(),
+            )
+        },
+        None => record_triangle_draw(command_list, resources),
+    }
+    end_event(command_list);
 
+    // 粒子在三角形之后绘制，会切换 PSO 和根签名
+    if let Some(particles) = &resources.particles {
+        particles.draw(command_list);
+    }
+    if let Some(point_sprites) = &resources.point_sprites {
+        point_sprites.draw(command_list, &resources.viewport);
+    }
+    if let Some(textured_quad) = &resources.textured_quad {
+        textured_quad.draw(command_list);
+    }
+
+    if let Some(msaa_render_target) = &resources.msaa_render_target {
+        // 把每个像素的多个采样平均成一个值写入后台缓冲区，两者的格式和大小必须一致
+        let states = &mut resources.resource_states;
+        resource_barriers(
+            command_list,
+            [
+                states.transition(msaa_render_target, D3D12_RESOURCE_STATE_RESOLVE_SOURCE),
+                states.transition(back_buffer, D3D12_RESOURCE_STATE_RESOLVE_DEST),
+            ],
+        );
+        unsafe {
+            // 两个资源都有类型时解析格式必须与它们一致，所以用后台缓冲区的格式，而不是可能为 sRGB 的 RTV 格式
+            command_list.ResolveSubresource(
+                back_buffer,
+                0,
+                msaa_render_target,
+                0,
+                back_buffer.GetDesc().Format,
+            );
+        }
+        resource_barriers(
+            command_list,
+            [
+                states.transition(msaa_render_target, D3D12_RESOURCE_STATE_RENDER_TARGET),
+                states.transition(back_buffer, D3D12_RESOURCE_STATE_PRESENT),
+            ],
+        );
+    } else {
         // Indicate that the back buffer will now be used to present.
-        command_list.ResourceBarrier(&[transition_barrier(
-            &resources.render_targets[resources.frame_index as usize],
-            D3D12_RESOURCE_STATE_RENDER_TARGET,
-            D3D12_RESOURCE_STATE_PRESENT,
-        )]);
+        resource_barriers(
+            command_list,
+            [resources
+                .resource_states
+                .transition(back_buffer, D3D12_RESOURCE_STATE_PRESENT)],
+        );
     }
 
     unsafe { command_list.Close() }
 }
 
-/// 通过命令列表设置转换资源屏障（transition resource barrier）数组，即可指定资源的转换；当我们希
-/// 望以一次 API 调用来转换多个资源的时候，这种数组就派上了用场。
-/// 我们可以将此资源屏障转换看作是一条告知 GPU 某资源状态正在进行转换的命令。所以在执行后续的命令时，GPU 便会采取必要措施以防资源冒险。
-fn transition_barrier(
-    resource: &ID3D12Resource,
-    state_before: D3D12_RESOURCE_STATES,
-    state_after: D3D12_RESOURCE_STATES,
-) -> D3D12_RESOURCE_BARRIER {
-    D3D12_RESOURCE_BARRIER {
-        Type: D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
-        Flags: D3D12_RESOURCE_BARRIER_FLAG_NONE,
-        Anonymous: D3D12_RESOURCE_BARRIER_0 {
-            Transition: std::mem::ManuallyDrop::new(D3D12_RESOURCE_TRANSITION_BARRIER {
-                pResource: Some(resource.clone()),
-                StateBefore: state_before,
-                StateAfter: state_after,
-                Subresource: D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+/// 为交换链的每个后台缓冲区创建渲染目标视图，依次放在 `rtv_heap` 中。
+/// `rtv_format` 是视图的格式，可以是缓冲区格式本身，也可以是对应的 sRGB 格式。
+pub fn create_render_targets(
+    device: &ID3D12Device,
+    swap_chain: &IDXGISwapChain3,
+    rtv_heap: &DescriptorHeap,
+    buffer_count: u32,
+    rtv_format: DXGI_FORMAT,
+) -> Result<Vec<ID3D12Resource>> {
+    // 资源不能与渲染流水线中的阶段直接绑定，所以我们必须先为资源创建视图（描述符），并将其绑定到流水线阶段。
+    // 例如，为了将后台缓冲区绑定到流水线的输出合并阶段（output merger stage，这样Direct3D才能向其渲染），
+    // 便需要为该后台缓冲区创建一个渲染目标视图。而这第一个步骤就是要获得存于交换链中的缓冲区资源。
+    (0..buffer_count as usize)
+        .map(|i| -> Result<ID3D12Resource> {
+            // i 是希望获得的特定后台缓冲区的索引（有时后台缓冲区并不只一个，所以需要用索引来指明）。
+            let render_target: ID3D12Resource = unsafe { swap_chain.GetBuffer(i as u32) }?;
+            unsafe {
+                // 为获取的后台缓冲区创建渲染目标视图
+                device.CreateRenderTargetView(
+                    // 指定用作渲染目标的资源。这里是后台缓冲区（即为后台缓冲区创建了一个渲染目标视图）。
+                    &render_target,
+                    // 指向 D3D12_RENDER_TARGET_VIEW_DESC 数据结构实例的指针。该结构体描述了资源中元素的数据类型（格式）。
+                    // 如果该资源在创建时已指定了具体格式（即此资源不是无类型格式，not typeless），那么就可以把这个参数设为空指针，
+                    // 表示采用该资源创建时的格式，为它的第一个 mipmap 层级（后台缓冲区只有一种 mipmap 层级，
+                    // 有关 mipmap 的内容将在第 9 章展开讨论）创建一个视图。
+                    // 这里显式给出格式：交换链的缓冲区可以用对应的 sRGB 格式创建视图，-srgb 时就用这种方式做伽马校正。
+                    Some(&D3D12_RENDER_TARGET_VIEW_DESC {
+                        Format: rtv_format,
+                        ViewDimension: D3D12_RTV_DIMENSION_TEXTURE2D,
+                        ..Default::default()
+                    }),
+                    // 引用所创建渲染目标视图的描述符句柄，第 i 个后台缓冲区的 RTV 放在堆中第 i 个位置
+                    rtv_heap.cpu_handle(i as u32),
+                )
+            };
+            Ok(render_target)
+        })
+        .collect()
+}
+
+/// 覆盖整个后台缓冲区的视口和裁剪矩形。
+pub fn viewport_and_scissor(width: i32, height: i32) -> (D3D12_VIEWPORT, RECT) {
+    let viewport = D3D12_VIEWPORT {
+        TopLeftX: 0.0,
+        TopLeftY: 0.0,
+        Width: width as f32,
+        Height: height as f32,
+        MinDepth: D3D12_MIN_DEPTH,
+        MaxDepth: D3D12_MAX_DEPTH,
+    };
+
+    let scissor_rect = RECT {
+        left: 0,
+        top: 0,
+        right: width,
+        bottom: height,
+    };
+
+    (viewport, scissor_rect)
+}
+
+/// 创建与后台缓冲区一样大的深度/模板缓冲区，以及存放其视图的 DSV 描述符堆。
+/// 深度/模板缓冲区是一种 2D 纹理，只能放在默认堆中，由 GPU 读写。它的采样设置必须与同时绑定的渲染目标一致。
+/// 格式和优化清除值由 `depth_mode` 决定。
+pub fn create_depth_stencil(
+    device: &ID3D12Device,
+    width: u64,
+    height: u32,
+    sample_desc: DXGI_SAMPLE_DESC,
+    depth_mode: DepthMode,
+) -> Result<(ID3D12Resource, ID3D12DescriptorHeap)> {
+    // DSV 和 RTV 一样要放在专门的描述符堆中，这里只需要一个描述符
+    let dsv_heap: ID3D12DescriptorHeap = unsafe {
+        device.CreateDescriptorHeap(&D3D12_DESCRIPTOR_HEAP_DESC {
+            NumDescriptors: 1,
+            Type: D3D12_DESCRIPTOR_HEAP_TYPE_DSV,
+            ..Default::default()
+        })
+    }?;
+
+    let mut depth_stencil: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: D3D12_HEAP_TYPE_DEFAULT,
+                ..Default::default()
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &D3D12_RESOURCE_DESC {
+                Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+                Width: width,
+                Height: height,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                Format: depth_mode.format(),
+                SampleDesc: sample_desc,
+                Layout: D3D12_TEXTURE_LAYOUT_UNKNOWN,
+                Flags: D3D12_RESOURCE_FLAG_ALLOW_DEPTH_STENCIL,
+                ..Default::default()
+            },
+            D3D12_RESOURCE_STATE_DEPTH_WRITE,
+            // 优化清除值：以这个值清除时，驱动可以走更快的路径
+            Some(&D3D12_CLEAR_VALUE {
+                Format: depth_mode.format(),
+                Anonymous: D3D12_CLEAR_VALUE_0 {
+                    DepthStencil: D3D12_DEPTH_STENCIL_VALUE {
+                        Depth: depth_mode.clear_value(),
+                        Stencil: 0,
+                    },
+                },
             }),
-        },
+            &mut depth_stencil,
+        )?
+    };
+    let depth_stencil = depth_stencil.unwrap();
+
+    unsafe {
+        // 资源创建时已指定了格式，视图描述可以传空，表示为整个资源的第一个 mipmap 层级创建视图
+        device.CreateDepthStencilView(
+            &depth_stencil,
+            None,
+            dsv_heap.GetCPUDescriptorHandleForHeapStart(),
+        )
+    };
+
+    Ok((depth_stencil, dsv_heap))
+}
+
+/// `targets` 为多重采样时，创建与后台缓冲区一样大、格式相同的多重采样渲染目标，
+/// 并把它的 RTV 放在 `rtv_heap` 的第 `rtv_index` 个位置；单采样时返回 `None`。
+/// `clear_color` 作为优化清除值，应与每帧清除时的颜色一致。
+/// RTV 格式为 sRGB 时资源以无类型格式创建，这样才能用 sRGB 视图渲染，再以线性格式解析到后台缓冲区。
+fn create_msaa_render_target(
+    device: &ID3D12Device,
+    targets: &RenderTargetFormats,
+    clear_color: &[f32; 4],
+    width: u64,
+    height: u32,
+    rtv_heap: &DescriptorHeap,
+    rtv_index: u32,
+) -> Result<Option<ID3D12Resource>> {
+    if targets.sample_desc.Count <= 1 {
+        return Ok(None);
     }
+
+    let mut render_target: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: D3D12_HEAP_TYPE_DEFAULT,
+                ..Default::default()
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &D3D12_RESOURCE_DESC {
+                Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+                Width: width,
+                Height: height,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                Format: typeless_format(targets.rtv_format()),
+                SampleDesc: targets.sample_desc,
+                Layout: D3D12_TEXTURE_LAYOUT_UNKNOWN,
+                Flags: D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET,
+                ..Default::default()
+            },
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+            Some(&D3D12_CLEAR_VALUE {
+                Format: targets.rtv_format(),
+                Anonymous: D3D12_CLEAR_VALUE_0 {
+                    Color: *clear_color,
+                },
+            }),
+            &mut render_target,
+        )?
+    };
+    let render_target = render_target.unwrap();
+
+    unsafe {
+        device.CreateRenderTargetView(
+            &render_target,
+            Some(&D3D12_RENDER_TARGET_VIEW_DESC {
+                Format: targets.rtv_format(),
+                ViewDimension: D3D12_RTV_DIMENSION_TEXTURE2DMS,
+                ..Default::default()
+            }),
+            rtv_heap.cpu_handle(rtv_index),
+        )
+    };
+
+    Ok(Some(render_target))
 }
 
+/// 与 shaders.hlsl 中的 `ObjectConstants` 保持一致。矩阵逐行存放，配合 HLSL 中的 `row_major` 与 `mul(v, M)` 使用。
 #[repr(C)]
-struct Vertex {
-    position: [f32; 3],
-    color: [f32; 4],
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObjectConstants {
+    pub mvp: [[f32; 4]; 4],
 }
 
-fn create_vertex_buffer(
+impl ObjectConstants {
+    pub const IDENTITY: ObjectConstants = ObjectConstants {
+        mvp: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    /// 绕 z 轴旋转 `angle` 弧度。顶点缓冲区中的 y 坐标已经乘过宽高比，所以模型矩阵先把 y 除以宽高比还原成模型坐标，
+    /// 再经过相机的观察和透视投影，宽高比改由投影矩阵处理，三角形在屏幕上旋转时才不会变形。
+    pub fn with_camera(angle: f32, aspect_ratio: f32, view_projection: &Mat4) -> ObjectConstants {
+        let (sin, cos) = angle.sin_cos();
+        let model = Mat4 {
+            rows: [
+                [cos, sin, 0.0, 0.0],
+                [-sin / aspect_ratio, cos / aspect_ratio, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+        ObjectConstants {
+            mvp: (model * *view_projection).rows,
+        }
+    }
+}
+
+/// `-dynamicvertices` 时环形缓冲区的大小，远大于三个顶点乘以在飞行中的帧数，基本不会等待 GPU。
+const DYNAMIC_VERTEX_RING_SIZE: u64 = 64 * 1024;
+
+/// 绘制三角形的 bundle。bundle 不继承调用者的 PSO，三个 PSO 各录制一个，按当前的显示模式选用。
+struct TriangleBundles {
+    solid: Bundle,
+    checkerboard: Bundle,
+    wireframe: Bundle,
+}
+
+impl TriangleBundles {
+    /// 与 `populate_command_list` 选择 PSO 的顺序一致：线框优先于棋盘格。
+    fn select(&self, wireframe: bool, checkerboard: bool) -> &Bundle {
+        if wireframe {
+            &self.wireframe
+        } else if checkerboard {
+            &self.checkerboard
+        } else {
+            &self.solid
+        }
+    }
+}
+
+/// `-instances` 时的实例缓冲区：每个实例一个 [`InstanceData`]，绑定到第 1 个输入槽。
+struct InstanceBuffer {
+    buffer: ID3D12Resource,
+    view: D3D12_VERTEX_BUFFER_VIEW,
+    count: u32,
+}
+
+/// `count` 大于 1 时把 [`instance_grid`] 复制到默认堆中的实例缓冲区，否则不需要实例数据，返回 `None`。
+fn create_instance_buffer(
     device: &ID3D12Device,
-    aspect_ratio: f32,
-) -> Result<(ID3D12Resource, D3D12_VERTEX_BUFFER_VIEW)> {
-    let vertices = [
-        Vertex {
+    command_queue: &ID3D12CommandQueue,
+    count: u32,
+) -> Result<Option<InstanceBuffer>> {
+    if count <= 1 {
+        return Ok(None);
+    }
+    let instances = instance_grid(count);
+    let buffer = upload_buffer_to_default_heap(
+        device,
+        command_queue,
+        &instances,
+        D3D12_RESOURCE_STATE_VERTEX_AND_CONSTANT_BUFFER,
+    )?;
+    let view = D3D12_VERTEX_BUFFER_VIEW {
+        BufferLocation: unsafe { buffer.GetGPUVirtualAddress() },
+        StrideInBytes: std::mem::size_of::<InstanceData>() as u32,
+        // 命令行已经把实例数限制在 MAX_INSTANCES 以内，大小不会超出 u32
+        SizeInBytes: std::mem::size_of_val(instances.as_slice()) as u32,
+    };
+    Ok(Some(InstanceBuffer {
+        buffer,
+        view,
+        count,
+    }))
+}
+
+/// 把 `count` 个三角形从左上角开始逐行排成接近正方形的网格，铺满 [-1, 1] 的范围。
+///
+/// 三角形约 0.5 宽，缩放到格子大小的八成，颜色从第一个到最后一个由青渐变到品红。
+fn instance_grid(count: u32) -> Vec<InstanceData> {
+    let columns = (count as f32).sqrt().ceil() as u32;
+    let rows = count.div_ceil(columns);
+    let (cell_width, cell_height) = (2.0 / columns as f32, 2.0 / rows as f32);
+    let scale = (0.8 * cell_width.min(cell_height) / 0.5).min(1.0);
+    (0..count)
+        .map(|i| {
+            let (column, row) = (i % columns, i / columns);
+            let t = i as f32 / (count - 1).max(1) as f32;
+            InstanceData {
+                offset: [
+                    -1.0 + (column as f32 + 0.5) * cell_width,
+                    1.0 - (row as f32 + 0.5) * cell_height,
+                ],
+                scale,
+                color: [t, 1.0 - t, 1.0, 1.0],
+            }
+        })
+        .collect()
+}
+
+/// 三角形的三个顶点，y 坐标乘以宽高比，这样窗口不是正方形时三角形也不会被拉伸。
+fn triangle_vertices(aspect_ratio: f32, coordinate_system: CoordinateSystem) -> [VertexPC; 3] {
+    // 顶点按顺时针（左手系的正面）给出
+    let mut vertices = [
+        VertexPC {
             position: [0.0, 0.25 * aspect_ratio, 0.0],
             color: [1.0, 0.0, 0.0, 1.0],
         },
-        Vertex {
+        VertexPC {
             position: [0.25, -0.25 * aspect_ratio, 0.0],
             color: [0.0, 1.0, 0.0, 1.0],
         },
-        Vertex {
+        VertexPC {
             position: [-0.25, -0.25 * aspect_ratio, 0.0],
             color: [0.0, 0.0, 1.0, 1.0],
         },
     ];
+    // 右手系的模型按逆时针组织正面，交换后两个顶点即可得到同一个三角形的右手系版本
+    if coordinate_system == CoordinateSystem::RightHanded {
+        vertices.swap(1, 2);
+    }
+    vertices
+}
+
+/// 创建三角形的顶点缓冲区。给出 `default_heap_queue` 时经由它把顶点复制到默认堆，否则直接放在上传堆。
+pub fn create_vertex_buffer(
+    device: &ID3D12Device,
+    aspect_ratio: f32,
+    coordinate_system: CoordinateSystem,
+    default_heap_queue: Option<&ID3D12CommandQueue>,
+) -> Result<(ID3D12Resource, D3D12_VERTEX_BUFFER_VIEW)> {
+    let vertices = triangle_vertices(aspect_ratio, coordinate_system);
+    let vertex_buffer = match default_heap_queue {
+        Some(command_queue) => upload_buffer_to_default_heap(
+            device,
+            command_queue,
+            &vertices,
+            D3D12_RESOURCE_STATE_VERTEX_AND_CONSTANT_BUFFER,
+        )?,
+        None => create_upload_vertex_buffer(device, &vertices)?,
+    };
+
+    let vbv = D3D12_VERTEX_BUFFER_VIEW {
+        BufferLocation: unsafe { vertex_buffer.GetGPUVirtualAddress() },
+        StrideInBytes: std::mem::size_of::<VertexPC>() as u32,
+        SizeInBytes: std::mem::size_of_val(&vertices) as u32,
+    };
 
+    Ok((vertex_buffer, vbv))
+}
+
+fn create_upload_vertex_buffer(
+    device: &ID3D12Device,
+    vertices: &[VertexPC],
+) -> Result<ID3D12Resource> {
     // Note: using upload heaps to transfer static data like vert buffers is
     // not recommended. Every time the GPU needs it, the upload heap will be
     // marshalled over. Please read up on Default Heap usage. An upload heap
@@ -389,7 +1659,7 @@ fn create_vertex_buffer(
             D3D12_HEAP_FLAG_NONE,
             &D3D12_RESOURCE_DESC {
                 Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
-                Width: std::mem::size_of_val(&vertices) as u64,
+                Width: std::mem::size_of_val(vertices) as u64,
                 Height: 1,
                 DepthOrArraySize: 1,
                 MipLevels: 1,
@@ -411,54 +1681,556 @@ fn create_vertex_buffer(
     unsafe {
         let mut data = std::ptr::null_mut();
         vertex_buffer.Map(0, None, Some(&mut data))?;
-        std::ptr::copy_nonoverlapping(
-            vertices.as_ptr(),
-            data as *mut Vertex,
-            std::mem::size_of_val(&vertices),
-        );
+        std::ptr::copy_nonoverlapping(vertices.as_ptr(), data as *mut VertexPC, vertices.len());
         vertex_buffer.Unmap(0, None);
     }
 
-    let vbv = D3D12_VERTEX_BUFFER_VIEW {
-        BufferLocation: unsafe { vertex_buffer.GetGPUVirtualAddress() },
-        StrideInBytes: std::mem::size_of::<Vertex>() as u32,
-        SizeInBytes: std::mem::size_of_val(&vertices) as u32,
+    Ok(vertex_buffer)
+}
+
+/// 索引的类型，决定了索引缓冲区视图的格式。
+pub trait IndexFormat: Copy {
+    const FORMAT: DXGI_FORMAT;
+}
+
+/// 16 位索引最多能引用 65536 个顶点，但只占 32 位索引一半的空间。
+impl IndexFormat for u16 {
+    const FORMAT: DXGI_FORMAT = DXGI_FORMAT_R16_UINT;
+}
+
+impl IndexFormat for u32 {
+    const FORMAT: DXGI_FORMAT = DXGI_FORMAT_R32_UINT;
+}
+
+/// 描述 `indices` 上传到 `buffer_location` 处之后的索引缓冲区视图。
+fn index_buffer_view<I: IndexFormat>(
+    buffer_location: u64,
+    indices: &[I],
+) -> D3D12_INDEX_BUFFER_VIEW {
+    D3D12_INDEX_BUFFER_VIEW {
+        BufferLocation: buffer_location,
+        SizeInBytes: std::mem::size_of_val(indices) as u32,
+        Format: I::FORMAT,
+    }
+}
+
+/// 与 `create_vertex_buffer` 一样把索引放在上传堆中，返回索引缓冲区及其视图。
+pub fn create_index_buffer<I: IndexFormat>(
+    device: &ID3D12Device,
+    indices: &[I],
+) -> Result<(ID3D12Resource, D3D12_INDEX_BUFFER_VIEW)> {
+    let mut index_buffer: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: D3D12_HEAP_TYPE_UPLOAD,
+                ..Default::default()
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &D3D12_RESOURCE_DESC {
+                Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+                Width: std::mem::size_of_val(indices) as u64,
+                Height: 1,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                ..Default::default()
+            },
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+            None,
+            &mut index_buffer,
+        )?
     };
+    let index_buffer = index_buffer.unwrap();
 
-    Ok((vertex_buffer, vbv))
+    unsafe {
+        let mut data = std::ptr::null_mut();
+        index_buffer.Map(0, None, Some(&mut data))?;
+        std::ptr::copy_nonoverlapping(indices.as_ptr(), data as *mut I, indices.len());
+        index_buffer.Unmap(0, None);
+    }
+
+    let ibv = index_buffer_view(unsafe { index_buffer.GetGPUVirtualAddress() }, indices);
+    Ok((index_buffer, ibv))
 }
 
-fn wait_for_previous_frame(resources: &mut Resources) {
-    // WAITING FOR THE FRAME TO COMPLETE BEFORE CONTINUING IS NOT BEST
-    // PRACTICE. This is code implemented as such for simplicity. The
-    // D3D12HelloFrameBuffering sample illustrates how to use fences for
-    // efficient resource usage and to maximize GPU utilization.
-
-    // Signal and increment the fence value.
-    let fence = resources.fence_value;
-    // 向命令队列中添加一条用来设置新围栏点的命令。
-    // 由于这条命令要交由 GPU 处理（即由 GPU 端来修改围栏值），
-    // 所以在 GPU 处理完命令队列中此 Signal() 以前的所有命令之前，它并不会设置新的围栏点
-    unsafe { resources.command_queue.Signal(&resources.fence, fence) }
-        .ok()
-        .unwrap();
-    // 增加围栏值
-    resources.fence_value += 1;
-
-    // 在 CPU 端等待 GPU，直到后者执行完这个围栏点之前的所有命令
-    if unsafe { resources.fence.GetCompletedValue() } < fence {
-        // 若 GPU 命中当前的围栏（即执行到 Signal()指令，修改了围栏值），则激发预定事件
+/// 与 `create_root_signature` 相同的时间根常量和 MVP 根 CBV 之外，
+/// 顶点着色器还要通过 t0 上的根 SRV（根参数 2）读取计算队列生成的顶点偏移。
+fn create_vertex_offsets_root_signature(device: &ID3D12Device) -> Result<ID3D12RootSignature> {
+    let parameters = [
+        time_root_constant(),
+        object_constants_root_descriptor(),
+        D3D12_ROOT_PARAMETER {
+            ParameterType: D3D12_ROOT_PARAMETER_TYPE_SRV,
+            Anonymous: D3D12_ROOT_PARAMETER_0 {
+                Descriptor: D3D12_ROOT_DESCRIPTOR {
+                    ShaderRegister: 0,
+                    RegisterSpace: 0,
+                },
+            },
+            ShaderVisibility: D3D12_SHADER_VISIBILITY_VERTEX,
+        },
+    ];
+    let desc = D3D12_ROOT_SIGNATURE_DESC {
+        NumParameters: parameters.len() as u32,
+        pParameters: parameters.as_ptr(),
+        Flags: D3D12_ROOT_SIGNATURE_FLAG_ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT,
+        ..Default::default()
+    };
+    create_root_signature_from_desc(device, &desc)
+}
+
+impl Sample {
+    /// 在另一个适配器上重新创建设备，再为同一个窗口重新创建交换链和所有资源。
+    fn switch_adapter(&mut self, adapter: &IDXGIAdapter1) -> Result<()> {
+        let Some(resources) = self.resources.take() else {
+            return Ok(());
+        };
+        let hwnd = resources.hwnd;
+        // 恢复窗口原本的标题，新的资源会重新读取它
+        unsafe { SetWindowTextW(hwnd, &HSTRING::from(resources.window_title.as_str())) };
+        // 先释放旧的资源（会等待 GPU 空闲），窗口同一时间只能关联一个交换链
+        drop(resources);
+        self.device = create_device_on_adapter(adapter)?;
+        // 不同适配器支持的 MSAA 质量级别可能不同
+        (self.msaa_enabled, self.msaa_quality) =
+            query_msaa_support(&self.device, &self.command_line)?;
+        self.bind_to_window(&hwnd)
+    }
+
+    /// 从磁盘重新编译 `shaders.hlsl`，重新创建三角形的各个 PSO 并替换旧的。
+    /// 编译或创建失败时返回错误，旧的 PSO 保持不变，程序可以继续运行。
+    fn reload_pipeline_state(&mut self) -> Result<()> {
+        let Some(resources) = &mut self.resources else {
+            return Ok(());
+        };
+        let (pso, checkerboard_pso, wireframe_pso) = create_triangle_pipeline_states(
+            &self.device,
+            &resources.root_signature,
+            resources.async_compute.is_some(),
+            resources.instances.is_some(),
+            self.command_line.shader_model,
+            // 预编译的 .cso 是构建时的旧版本，重载总是编译磁盘上的 shaders.hlsl
+            false,
+            self.command_line.coordinate_system,
+            self.command_line.blend_mode,
+            &resources.targets,
+            self.command_line.verbose,
+        )?;
+        // 在飞行中的帧可能还在使用旧的 PSO，等 GPU 空闲后才能释放它们
+        resources.wait_for_gpu()?;
+        resources.pso = pso;
+        resources.checkerboard_pso = checkerboard_pso;
+        resources.wireframe_pso = wireframe_pso;
+        if resources.bundles.is_some() {
+            resources.record_bundles(&self.device)?;
+        }
+        resources.set_debug_names();
+        Ok(())
+    }
+
+    /// 渲染目标、深度/模板缓冲区和 PSO 使用的采样设置，未开启 MSAA 时为单采样。
+    fn sample_desc(&self) -> DXGI_SAMPLE_DESC {
+        if self.msaa_enabled {
+            DXGI_SAMPLE_DESC {
+                Count: MSAA_SAMPLE_COUNT,
+                Quality: self.msaa_quality - 1,
+            }
+        } else {
+            DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            }
+        }
+    }
+}
+
+/// 编译三角形的着色器并创建 PSO，返回普通的、输出棋盘格图案（`CHECKERBOARD`）的和线框模式的三个变体。
+/// 它们使用同一个根签名和输入布局，绘制时切换 PSO 不需要重新绑定其他状态。
+/// `async_compute` 时顶点着色器从根 SRV 读取顶点偏移，根签名要与之对应。
+/// `instanced` 时输入布局是 `Instanced<VertexPC, InstanceData>`，顶点着色器用 `INSTANCED` 宏编译，读取第 1 个槽中的实例数据。
+/// 半透明的 `blend_mode` 下着色器输出半透明的颜色，第二个实例向右错开，与第一个重叠。
+/// `prefer_precompiled` 只影响不带宏的变体，含义见 [`create_pipeline_state`]。
+#[allow(clippy::too_many_arguments)]
+fn create_triangle_pipeline_states(
+    device: &ID3D12Device,
+    root_signature: &ID3D12RootSignature,
+    async_compute: bool,
+    instanced: bool,
+    shader_model: ShaderModel,
+    prefer_precompiled: bool,
+    coordinate_system: CoordinateSystem,
+    blend_mode: BlendMode,
+    targets: &RenderTargetFormats,
+    verbose: bool,
+) -> Result<(
+    ID3D12PipelineState,
+    ID3D12PipelineState,
+    ID3D12PipelineState,
+)> {
+    let mut defines = vec![];
+    if async_compute {
+        defines.push(("ASYNC_COMPUTE", "1"));
+    }
+    if blend_mode.is_translucent() {
+        defines.push(("TRANSLUCENT", "1"));
+    }
+    if instanced {
+        defines.push(("INSTANCED", "1"));
+    }
+    let create_pipeline_state = if instanced {
+        create_pipeline_state::<Instanced<VertexPC, InstanceData>>
+    } else {
+        create_pipeline_state::<VertexPC>
+    };
+    let create = |defines: &[(&str, &str)], fill_mode| {
+        create_pipeline_state(
+            device,
+            root_signature,
+            defines,
+            shader_model,
+            prefer_precompiled,
+            coordinate_system,
+            fill_mode,
+            // 三角形从两面都应该能看到（例如用鼠标把相机转到背后），所以不做剔除
+            D3D12_CULL_MODE_NONE,
+            blend_mode,
+            StencilMode::Disabled,
+            targets,
+            verbose,
+        )
+    };
+    let pso = create(&defines, D3D12_FILL_MODE_SOLID)?;
+    let wireframe_pso = create(&defines, D3D12_FILL_MODE_WIREFRAME)?;
+    defines.push(("CHECKERBOARD", "1"));
+    let checkerboard_pso = create(&defines, D3D12_FILL_MODE_SOLID)?;
+    Ok((pso, checkerboard_pso, wireframe_pso))
+}
+
+/// `-msaa` 时检测后台缓冲区格式对 4X MSAA 的支持，返回是否开启以及质量级别数量。
+fn query_msaa_support(
+    device: &ID3D12Device,
+    command_line: &SampleCommandLine,
+) -> Result<(bool, u32)> {
+    if !command_line.msaa {
+        return Ok((false, 0));
+    }
+    let format = command_line.back_buffer_format.dxgi_format();
+    let quality = check_sample_support(device, format)?;
+    if quality == 0 {
+        println!(
+            "msaa: {} does not support 4x MSAA, falling back to single sampling",
+            format_name(format)
+        );
+    } else {
+        println!("msaa: 4x MSAA with {} quality level(s)", quality);
+    }
+    Ok((quality > 0, quality))
+}
+
+/// `-cycleadapters`：每隔一段时间切换到下一个硬件适配器，并打印上一个适配器在这段时间内的平均帧率。
+struct AdapterCycle {
+    adapters: Vec<IDXGIAdapter1>,
+    /// 下一次要切换到的适配器下标。
+    next: usize,
+    /// 当前设备所在适配器的名称。
+    name: String,
+    interval: std::time::Duration,
+    started: std::time::Instant,
+    frames: u32,
+}
+
+impl AdapterCycle {
+    /// 只有一个硬件适配器时无从比较，返回 `None`。
+    fn new(factory: &IDXGIFactory4, device: &ID3D12Device, seconds: u32) -> Result<Option<Self>> {
+        let adapters = hardware_adapters(factory)?;
+        if adapters.len() <= 1 {
+            println!(
+                "cycleadapters: found {} hardware adapter(s), nothing to cycle",
+                adapters.len()
+            );
+            return Ok(None);
+        }
+
+        // 启动时的设备可能在任意一个适配器上（也可能是 WARP），按 LUID 找到它
+        let luid = unsafe { device.GetAdapterLuid() };
+        let current: IDXGIAdapter1 = unsafe { factory.EnumAdapterByLuid(luid) }?;
+        let position = adapters.iter().position(|adapter| {
+            unsafe { adapter.GetDesc1() }.is_ok_and(|desc| desc.AdapterLuid == luid)
+        });
+        Ok(Some(AdapterCycle {
+            next: position.map_or(0, |position| (position + 1) % adapters.len()),
+            name: adapter_name(&current)?,
+            adapters,
+            interval: std::time::Duration::from_secs(seconds as u64),
+            started: std::time::Instant::now(),
+            frames: 0,
+        }))
+    }
+
+    /// 每帧调用一次。到了切换的时候，打印当前适配器的帧率并返回下一个适配器。
+    fn tick(&mut self) -> Option<IDXGIAdapter1> {
+        self.frames += 1;
+        let elapsed = self.started.elapsed();
+        if elapsed < self.interval {
+            return None;
+        }
+        println!(
+            "cycleadapters: {}: {:.1} fps over {:.1} s",
+            self.name,
+            self.frames as f64 / elapsed.as_secs_f64(),
+            elapsed.as_secs_f64()
+        );
+
+        let adapter = self.adapters[self.next].clone();
+        self.next = (self.next + 1) % self.adapters.len();
+        self.name = adapter_name(&adapter).unwrap_or_default();
+        println!("cycleadapters: switching to {}", self.name);
+        Some(adapter)
+    }
+
+    fn restart(&mut self) {
+        self.started = std::time::Instant::now();
+        self.frames = 0;
+    }
+}
+
+/// 演示异步计算：独立的计算队列每帧把三角形三个顶点的偏移写入一个 UAV 缓冲区，
+/// 然后通过自己的围栏通知直接队列。两个队列各自维护围栏值，直接队列只等待本帧对应的那个值。
+///
+/// 与直接队列一样，每个在飞行中的帧各有一个计算上下文。直接队列上的第 N 帧在 GPU 端等待了第 N 帧的计算，
+/// 所以 CPU 等到直接队列执行完第 N 帧、再次轮到这个帧的下标时，对应的计算上下文也一定已经执行完，不需要另外等待。
+struct AsyncCompute {
+    queue: ID3D12CommandQueue,
+    command_contexts: CommandContextPool,
+    root_signature: ID3D12RootSignature,
+    pso: ID3D12PipelineState,
+    vertex_offsets: ID3D12Resource,
+    fence: Fence,
+    start_time: std::time::Instant,
+}
+
+impl AsyncCompute {
+    fn new(device: &ID3D12Device, frames_in_flight: usize) -> Result<Self> {
+        let queue: ID3D12CommandQueue = unsafe {
+            device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
+                Type: D3D12_COMMAND_LIST_TYPE_COMPUTE,
+                ..Default::default()
+            })?
+        };
+        let command_contexts =
+            CommandContextPool::new(device, D3D12_COMMAND_LIST_TYPE_COMPUTE, frames_in_flight)?;
+
+        // 根参数 0：1 个 32 位根常量（经过的时间），对应 b0；根参数 1：u0 上的根 UAV。
+        let parameters = [
+            D3D12_ROOT_PARAMETER {
+                ParameterType: D3D12_ROOT_PARAMETER_TYPE_32BIT_CONSTANTS,
+                Anonymous: D3D12_ROOT_PARAMETER_0 {
+                    Constants: D3D12_ROOT_CONSTANTS {
+                        ShaderRegister: 0,
+                        RegisterSpace: 0,
+                        Num32BitValues: 1,
+                    },
+                },
+                ShaderVisibility: D3D12_SHADER_VISIBILITY_ALL,
+            },
+            D3D12_ROOT_PARAMETER {
+                ParameterType: D3D12_ROOT_PARAMETER_TYPE_UAV,
+                Anonymous: D3D12_ROOT_PARAMETER_0 {
+                    Descriptor: D3D12_ROOT_DESCRIPTOR {
+                        ShaderRegister: 0,
+                        RegisterSpace: 0,
+                    },
+                },
+                ShaderVisibility: D3D12_SHADER_VISIBILITY_ALL,
+            },
+        ];
+        let root_signature = create_root_signature_from_desc(
+            device,
+            &D3D12_ROOT_SIGNATURE_DESC {
+                NumParameters: parameters.len() as u32,
+                pParameters: parameters.as_ptr(),
+                ..Default::default()
+            },
+        )?;
+
+        let compute_shader = compile_shader(
+            &shader_path("shaders.hlsl"),
+            &[("ASYNC_COMPUTE", "1")],
+            s!("CSMain"),
+            s!("cs_5_0"),
+        )?;
+        let pso = create_compute_pipeline_state(
+            device,
+            &root_signature,
+            &CompiledShader::Dxbc(compute_shader),
+        )?;
+
+        // 每个顶点一个 float4 偏移
+        let vertex_offsets = create_uav_buffer(device, 3 * std::mem::size_of::<[f32; 4]>() as u64)?;
+        let fence = Fence::new(device)?;
+
+        Ok(AsyncCompute {
+            queue,
+            command_contexts,
+            root_signature,
+            pso,
+            vertex_offsets,
+            fence,
+            start_time: std::time::Instant::now(),
+        })
+    }
+
+    /// 用第 `frame_slot` 帧的计算上下文录制并提交这一帧的计算命令，返回计算完成时围栏将到达的值。
+    ///
+    /// `direct_fence` 和 `direct_fence_value` 是直接队列上最近一次 Signal 的围栏值：
+    /// 多帧在飞行中时，之前的帧可能还在读取顶点偏移，计算队列要先在 GPU 端等它们读完再覆盖。
+    fn dispatch(
+        &mut self,
+        frame_slot: usize,
+        direct_fence: &ID3D12Fence,
+        direct_fence_value: FenceValue,
+    ) -> Result<FenceValue> {
+        let command_context = self.command_contexts.context_mut(frame_slot);
+        let command_list = command_context.reset(self.fence.fence(), Some(&self.pso))?;
         unsafe {
-            resources
-                .fence
-                .SetEventOnCompletion(fence, resources.fence_event)
+            self.queue.Wait(direct_fence, direct_fence_value.get())?;
+            command_list.SetComputeRootSignature(&self.root_signature);
+            let time = self.start_time.elapsed().as_secs_f32();
+            command_list.SetComputeRoot32BitConstant(0, time.to_bits(), 0);
+            command_list
+                .SetComputeRootUnorderedAccessView(1, self.vertex_offsets.GetGPUVirtualAddress());
+            command_list.Dispatch(1, 1, 1);
+            command_list.Close()?;
+
+            self.queue
+                .ExecuteCommandLists(&[Some(ID3D12CommandList::from(command_list))]);
         }
-        .ok()
-        .unwrap();
+        let fence_value = self.fence.signal(&self.queue)?;
+        command_context.set_fence_value(fence_value);
+        Ok(fence_value)
+    }
+}
 
-        // 等待 GPU 命中围栏，激发事件
-        unsafe { WaitForSingleObject(resources.fence_event, INFINITE) };
+fn window_title(hwnd: &HWND) -> String {
+    let mut title = [0u16; 256];
+    let len = unsafe { GetWindowTextW(*hwnd, &mut title) };
+    String::from_utf16_lossy(&title[..len.max(0) as usize])
+}
+
+/// 结束当前帧并切换到下一帧。
+///
+/// 每帧结束时在直接队列上 Signal 一个新的围栏值，记到当前帧的命令上下文名下；
+/// 切换到下一个命令上下文之前，只需等待它上一次被使用的那一帧执行完，而不必等待 GPU 完全空闲。
+/// 这样 CPU 最多可以领先 GPU `-framesinflight` 帧。只有 1 帧在飞行中时，这就退化为每帧都等待 GPU 完成。
+/// 返回 CPU 在这里等待围栏所花的时间，供 `-framestats` 统计。
+fn move_to_next_frame(device: &ID3D12Device, resources: &mut Resources) -> std::time::Duration {
+    // 向命令队列中添加一条用来设置新围栏点的命令，并记到当前的命令上下文名下
+    let fence = resources
+        .fence
+        .signal(&resources.command_queue)
+        .unwrap_or_else(|e| {
+            if is_device_lost(&e) {
+                log_device_removed(device);
+            }
+            panic!("Signal failed: {}", explain_error(&e))
+        });
+    resources
+        .command_contexts
+        .context_mut(resources.frame_slot)
+        .set_fence_value(fence);
+    if let Some(dynamic_vertices) = &mut resources.dynamic_vertices {
+        dynamic_vertices.end_frame(fence);
     }
 
+    resources.frame_slot = (resources.frame_slot + 1) % resources.command_contexts.frame_count();
+    let slot_fence = resources.command_contexts.fence_value(resources.frame_slot);
+
+    // 在 CPU 端等待 GPU，直到后者执行完下一个命令分配器上一次录制的那一帧
+    let wait_start = std::time::Instant::now();
+    resources
+        .fence
+        .wait(slot_fence)
+        .unwrap_or_else(|e| panic!("fence wait failed: {}", explain_error(&e)));
+
     resources.frame_index = unsafe { resources.swap_chain.GetCurrentBackBufferIndex() };
+    wait_start.elapsed()
+}
+
+#[test]
+fn depth_stencil_is_created() {
+    use crate::devices::warp_device;
+
+    let device = warp_device();
+    let (depth_stencil, dsv_heap) = create_depth_stencil(
+        &device,
+        64,
+        32,
+        DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        DepthMode::ReversedZ,
+    )
+    .unwrap();
+    assert_ne!(
+        unsafe { dsv_heap.GetCPUDescriptorHandleForHeapStart() }.ptr,
+        0
+    );
+    let desc = unsafe { depth_stencil.GetDesc() };
+    assert_eq!((desc.Width, desc.Height), (64, 32));
+    assert_eq!(desc.Format, DXGI_FORMAT_D32_FLOAT_S8X24_UINT);
+}
+
+#[test]
+fn index_buffer_view_matches_index_type() {
+    let ibv = index_buffer_view(0x1000, &[0u16, 1, 2]);
+    assert_eq!(ibv.BufferLocation, 0x1000);
+    assert_eq!(ibv.SizeInBytes, 6);
+    assert_eq!(ibv.Format, DXGI_FORMAT_R16_UINT);
+
+    let ibv = index_buffer_view(0x1000, &[0u32, 1, 2]);
+    assert_eq!(ibv.SizeInBytes, 12);
+    assert_eq!(ibv.Format, DXGI_FORMAT_R32_UINT);
+}
+
+#[test]
+fn rotation_keeps_aspect_ratio() {
+    // 不旋转时只把顶点缓冲区中乘过宽高比的 y 除回去
+    let [row_x, row_y, _, _] = ObjectConstants::with_camera(0.0, 2.0, &Mat4::IDENTITY).mvp;
+    assert_eq!((row_x, row_y), ([1.0, 0.0, 0.0, 0.0], [0.0, 0.5, 0.0, 0.0]));
+    // 转 90 度后，x 轴上的点 (0.5, 0) 应该落在模型的 y 轴上
+    let [row_x, row_y, _, _] =
+        ObjectConstants::with_camera(std::f32::consts::FRAC_PI_2, 2.0, &Mat4::IDENTITY).mvp;
+    let (x, y) = (0.5 * row_x[0], 0.5 * row_x[1]);
+    assert!(x.abs() < 1e-6 && (y - 0.5).abs() < 1e-6);
+    // (0, 1) 在模型中的高度是 0.5，转 90 度后落到 x = -0.5
+    let (x, y) = (row_y[0], row_y[1]);
+    assert!((x + 0.5).abs() < 1e-6 && y.abs() < 1e-6);
+
+    // 模型矩阵之后才是相机的观察和投影
+    let view_projection = Camera::default().view_projection();
+    assert_eq!(
+        ObjectConstants::with_camera(0.0, 1.0, &view_projection).mvp,
+        view_projection.rows
+    );
+}
+
+#[test]
+fn instance_grid_covers_the_viewport() {
+    let grid = instance_grid(14);
+    assert_eq!(grid.len(), 14);
+    // 14 个实例排成 4 列 4 行，最后一行只有两个
+    assert_eq!(grid[0].offset, [-0.75, 0.75]);
+    assert_eq!(grid[4].offset, [-0.75, 0.25]);
+    assert_eq!(grid[13].offset, [-0.25, -0.75]);
+    assert!(grid.iter().all(|instance| instance.offset[0].abs() < 1.0
+        && instance.offset[1].abs() < 1.0
+        && instance.scale <= 1.0));
+    assert_eq!(grid[0].color, [0.0, 1.0, 1.0, 1.0]);
+    assert_eq!(grid[13].color, [1.0, 0.0, 1.0, 1.0]);
 }
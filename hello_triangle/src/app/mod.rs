@@ -1 +1,73 @@
+pub mod box_sample;
+pub mod cube_sample;
 pub mod hello_triangle;
+pub mod outline_sample;
+pub mod particles;
+pub mod point_sprites;
+pub mod textured_quad;
+
+use crate::{capture_headless, init_sample, SampleCommandLine, SampleKind};
+use std::path::Path;
+use windows::{core::*, Win32::Foundation::E_INVALIDARG};
+
+/// 创建窗口并运行一个示例，直到窗口关闭。
+pub type RunSample = fn(&SampleCommandLine) -> Result<()>;
+/// 不创建窗口，离屏渲染一个示例并把结果保存到文件，供 `-headless` 使用。
+pub type CaptureSample = fn(&SampleCommandLine, &Path) -> Result<()>;
+
+/// 创建窗口并运行 `kind` 的函数。
+fn windowed_sample(kind: SampleKind) -> RunSample {
+    match kind {
+        SampleKind::Triangle => init_sample::<hello_triangle::Sample>,
+        SampleKind::Box => init_sample::<box_sample::Sample>,
+        SampleKind::Outline => init_sample::<outline_sample::Sample>,
+    }
+}
+
+/// 离屏渲染 `kind` 的函数，不支持离屏渲染时为 `None`。
+fn headless_sample(kind: SampleKind) -> Option<CaptureSample> {
+    match kind {
+        // 三角形示例的资源都围绕交换链创建（帧延迟、全屏切换、呈现统计等），不支持离屏渲染
+        SampleKind::Triangle => None,
+        SampleKind::Box => Some(capture_headless::<box_sample::Sample>),
+        SampleKind::Outline => Some(capture_headless::<outline_sample::Sample>),
+    }
+}
+
+/// 运行 `-sample` 选择的示例。
+///
+/// 给出了 `-headless` 时不创建窗口，离屏渲染后保存到文件；示例不支持离屏渲染时返回错误，而不是静默地打开窗口。
+pub fn run_sample(command_line: &SampleCommandLine) -> Result<()> {
+    let Some(path) = &command_line.headless else {
+        return windowed_sample(command_line.sample)(command_line);
+    };
+    match headless_sample(command_line.sample) {
+        Some(capture) => capture(command_line, Path::new(path)),
+        None => {
+            let supported: Vec<&str> = SampleKind::ALL
+                .into_iter()
+                .filter(|&kind| headless_sample(kind).is_some())
+                .map(SampleKind::name)
+                .collect();
+            Err(Error::new(
+                E_INVALIDARG,
+                format!(
+                    "sample {} does not support -headless, use one of: {}",
+                    command_line.sample.name(),
+                    supported.join(", ")
+                )
+                .into(),
+            ))
+        }
+    }
+}
+
+#[test]
+fn headless_is_rejected_for_windowed_samples() {
+    let command_line = SampleCommandLine {
+        sample: SampleKind::Triangle,
+        headless: Some("triangle.png".into()),
+        ..Default::default()
+    };
+    assert_eq!(run_sample(&command_line).unwrap_err().code(), E_INVALIDARG);
+}
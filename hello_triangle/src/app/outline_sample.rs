@@ -0,0 +1,135 @@
+use crate::constant_buffer::ConstantBuffer;
+use crate::cube_sample::{draw_cube, CubeResources, CubeSample, CubeScene};
+use crate::devices::create_pipeline_state;
+use crate::hello_triangle::ObjectConstants;
+use crate::pipeline_state::RenderTargetFormats;
+use crate::vertex::VertexPC;
+use crate::{
+    BlendMode, Camera, CoordinateSystem, GameTimer, InputState, Mat4, SampleCommandLine,
+    StencilMode,
+};
+use windows::{core::*, Win32::Graphics::Direct3D12::*};
+
+/// 第一遍写入模板缓冲区的参考值，清除后的模板值是 0，所以任何非 0 的值都可以。
+const STENCIL_REF: u32 = 1;
+/// 第二遍绘制的立方体比原来大多少，决定轮廓的粗细。
+const OUTLINE_SCALE: f32 = 1.08;
+
+/// 用模板缓冲区给旋转的立方体描边，分两遍绘制同一个立方体：
+///
+/// 1. 正常绘制立方体，PSO 的模板测试总是通过并把参考值写入（`REPLACE`）立方体覆盖的每个像素；
+/// 2. 把立方体放大一点，用纯色再画一遍，这次模板测试只在模板值不等于参考值的像素上通过，
+///    也就是第一遍没有画到的地方，于是只剩下露在原立方体外面的一圈轮廓。
+///
+/// 模板参考值不属于 PSO，要在绘制前用 `OMSetStencilRef` 设置，两遍使用同一个值。
+/// 深度/模板缓冲区每帧把模板清除为 0。
+pub type Sample = CubeSample<OutlineScene>;
+
+pub struct OutlineScene {
+    /// 第一遍：正常绘制并写入模板。
+    object_pso: ID3D12PipelineState,
+    /// 第二遍：用 `OUTLINE` 宏编译的纯色像素着色器，只在模板值不等于参考值的像素上绘制。
+    outline_pso: ID3D12PipelineState,
+    object_constants: ConstantBuffer<ObjectConstants>,
+    /// 放大后的立方体的 MVP，两遍各用一个常量缓冲区，绘制时切换根 CBV。
+    outline_constants: ConstantBuffer<ObjectConstants>,
+}
+
+impl CubeScene for OutlineScene {
+    const TITLE: &'static str = "D3D12 Stencil Outline";
+
+    fn new(
+        device: &ID3D12Device,
+        resources: &CubeResources,
+        command_line: &SampleCommandLine,
+    ) -> Result<Self> {
+        // 两种深度约定的深度/模板格式都为每个像素保留 8 位模板值
+        let targets =
+            RenderTargetFormats::color_only(resources.format).with_depth(resources.depth_mode);
+        let create_pso = |defines: &[(&str, &str)], stencil_mode| {
+            create_pipeline_state::<VertexPC>(
+                device,
+                &resources.root_signature,
+                defines,
+                command_line.shader_model,
+                false,
+                CoordinateSystem::LeftHanded,
+                D3D12_FILL_MODE_SOLID,
+                D3D12_CULL_MODE_BACK,
+                BlendMode::Opaque,
+                stencil_mode,
+                &targets,
+                command_line.verbose,
+            )
+        };
+        let object_pso = create_pso(&[], StencilMode::Write)?;
+        let outline_pso = create_pso(&[("OUTLINE", "1")], StencilMode::NotEqual)?;
+
+        let object_constants = ConstantBuffer::new(device)?;
+        object_constants.update(&ObjectConstants::IDENTITY);
+        let outline_constants = ConstantBuffer::new(device)?;
+        outline_constants.update(&ObjectConstants::IDENTITY);
+
+        Ok(OutlineScene {
+            object_pso,
+            outline_pso,
+            object_constants,
+            outline_constants,
+        })
+    }
+
+    fn update(&mut self, camera: &mut Camera, timer: &GameTimer, _input: &InputState) {
+        let model = Mat4::rotation_y(timer.total_time());
+        let view_projection = camera.view_projection();
+        self.object_constants.update(&ObjectConstants {
+            mvp: (model * view_projection).rows,
+        });
+        // 先在模型空间中放大，再做同样的旋转，两个立方体的中心始终重合
+        let outline_model = Mat4::scale(OUTLINE_SCALE, OUTLINE_SCALE, OUTLINE_SCALE) * model;
+        self.outline_constants.update(&ObjectConstants {
+            mvp: (outline_model * view_projection).rows,
+        });
+    }
+
+    fn draw(&self, command_list: &ID3D12GraphicsCommandList) {
+        unsafe {
+            // 参考值是命令列表的状态，切换 PSO 后仍然有效，两遍都用它
+            command_list.OMSetStencilRef(STENCIL_REF);
+
+            // 第一遍：正常绘制立方体，覆盖到的像素模板值变为 STENCIL_REF
+            command_list.SetPipelineState(&self.object_pso);
+            command_list
+                .SetGraphicsRootConstantBufferView(1, self.object_constants.gpu_virtual_address());
+        }
+        draw_cube(command_list);
+
+        // 第二遍：放大的立方体只在模板值不等于 STENCIL_REF 的像素上通过，画出来的就是轮廓
+        unsafe {
+            command_list.SetPipelineState(&self.outline_pso);
+            command_list
+                .SetGraphicsRootConstantBufferView(1, self.outline_constants.gpu_virtual_address());
+        }
+        draw_cube(command_list);
+    }
+}
+
+#[test]
+fn outline_surrounds_the_box() {
+    use crate::DXSample;
+
+    let command_line = SampleCommandLine {
+        use_warp_device: true,
+        ..Default::default()
+    };
+    let mut sample = Sample::new(&command_line).unwrap();
+    let (width, height) = sample.window_size();
+    let pixels = crate::render_headless(&mut sample, (width, height), 2).unwrap();
+
+    // 与 shaders.hlsl 中 OUTLINE 的颜色 (1, 0.8, 0) 一致
+    let is_outline = |p: &[u8]| p[0] >= 250 && p[1].abs_diff(204) <= 2 && p[2] <= 5;
+    let outline_pixels = pixels.chunks_exact(4).filter(|p| is_outline(p)).count();
+    assert!(outline_pixels > 0);
+    // 画面中央是立方体本身，模板测试挡住了放大的那一遍
+    let center = ((height / 2 * width + width / 2) * 4) as usize;
+    assert!(!is_outline(&pixels[center..center + 4]));
+}
@@ -0,0 +1,242 @@
+use crate::command::CommandContextPool;
+use crate::compute::{create_compute_pipeline_state, create_uav_buffer};
+use crate::devices::{
+    compile_shader, create_root_signature_from_desc, shader_path, CompiledShader,
+};
+use crate::fence::{Fence, FenceValue};
+use crate::pipeline_state::{create_graphics_pipeline_state, RenderTargetFormats};
+use crate::BlendMode;
+use windows::{core::*, Win32::Graphics::Direct3D::*, Win32::Graphics::Direct3D12::*};
+
+/// 与 shaders.hlsl 中的 `Particle` 结构体保持一致：float2 位置 + float2 速度。
+const PARTICLE_STRIDE: u64 = 4 * std::mem::size_of::<f32>() as u64;
+/// 与 `CSParticles` 的 `numthreads` 保持一致。
+const THREADS_PER_GROUP: u32 = 64;
+
+/// 一个最小的粒子系统：计算队列每帧在 UAV 缓冲区中积分粒子的位置和速度，
+/// 直接队列等待计算完成后，把同一个缓冲区作为 SRV，用实例化绘制每个粒子一个小方块。
+///
+/// 粒子缓冲区以 COMMON 状态创建，计算队列写入时隐式提升为 UNORDERED_ACCESS，
+/// 执行完毕后衰退回 COMMON；直接队列读取时再隐式提升为 NON_PIXEL_SHADER_RESOURCE，
+/// 所以两个队列之间只需要围栏同步，不需要显式的转换屏障。
+///
+/// 计算上下文与 `AsyncCompute` 一样按帧的下标取用，直接队列等待了哪一帧的更新，
+/// 它执行完那一帧时对应的计算上下文也就可以重用了。
+pub struct Particles {
+    count: u32,
+    queue: ID3D12CommandQueue,
+    command_contexts: CommandContextPool,
+    compute_root_signature: ID3D12RootSignature,
+    compute_pso: ID3D12PipelineState,
+    root_signature: ID3D12RootSignature,
+    pso: ID3D12PipelineState,
+    buffer: ID3D12Resource,
+    fence: Fence,
+    initialized: bool,
+    last_update: std::time::Instant,
+}
+
+impl Particles {
+    pub fn new(
+        device: &ID3D12Device,
+        count: u32,
+        frames_in_flight: usize,
+        targets: &RenderTargetFormats,
+        verbose: bool,
+    ) -> Result<Self> {
+        let queue: ID3D12CommandQueue = unsafe {
+            device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
+                Type: D3D12_COMMAND_LIST_TYPE_COMPUTE,
+                ..Default::default()
+            })?
+        };
+        let command_contexts =
+            CommandContextPool::new(device, D3D12_COMMAND_LIST_TYPE_COMPUTE, frames_in_flight)?;
+
+        let shaders_hlsl = shader_path("shaders.hlsl");
+        let defines = [("PARTICLES", "1")];
+
+        // 根参数 0：3 个 32 位根常量（时间步长、粒子数量、是否重置），对应 b1（b0 留给三角形的时间常量）；根参数 1：u0 上的根 UAV。
+        let compute_parameters = [
+            D3D12_ROOT_PARAMETER {
+                ParameterType: D3D12_ROOT_PARAMETER_TYPE_32BIT_CONSTANTS,
+                Anonymous: D3D12_ROOT_PARAMETER_0 {
+                    Constants: D3D12_ROOT_CONSTANTS {
+                        ShaderRegister: 1,
+                        RegisterSpace: 0,
+                        Num32BitValues: 3,
+                    },
+                },
+                ShaderVisibility: D3D12_SHADER_VISIBILITY_ALL,
+            },
+            D3D12_ROOT_PARAMETER {
+                ParameterType: D3D12_ROOT_PARAMETER_TYPE_UAV,
+                Anonymous: D3D12_ROOT_PARAMETER_0 {
+                    Descriptor: D3D12_ROOT_DESCRIPTOR {
+                        ShaderRegister: 0,
+                        RegisterSpace: 0,
+                    },
+                },
+                ShaderVisibility: D3D12_SHADER_VISIBILITY_ALL,
+            },
+        ];
+        let compute_root_signature = create_root_signature_from_desc(
+            device,
+            &D3D12_ROOT_SIGNATURE_DESC {
+                NumParameters: compute_parameters.len() as u32,
+                pParameters: compute_parameters.as_ptr(),
+                ..Default::default()
+            },
+        )?;
+        let compute_shader =
+            compile_shader(&shaders_hlsl, &defines, s!("CSParticles"), s!("cs_5_0"))?;
+        let compute_pso = create_compute_pipeline_state(
+            device,
+            &compute_root_signature,
+            &CompiledShader::Dxbc(compute_shader),
+        )?;
+
+        // 顶点着色器通过 t0 上的根 SRV 读取粒子，顶点完全由 SV_VertexID 生成，不需要输入布局。
+        let parameters = [D3D12_ROOT_PARAMETER {
+            ParameterType: D3D12_ROOT_PARAMETER_TYPE_SRV,
+            Anonymous: D3D12_ROOT_PARAMETER_0 {
+                Descriptor: D3D12_ROOT_DESCRIPTOR {
+                    ShaderRegister: 0,
+                    RegisterSpace: 0,
+                },
+            },
+            ShaderVisibility: D3D12_SHADER_VISIBILITY_VERTEX,
+        }];
+        let root_signature = create_root_signature_from_desc(
+            device,
+            &D3D12_ROOT_SIGNATURE_DESC {
+                NumParameters: parameters.len() as u32,
+                pParameters: parameters.as_ptr(),
+                ..Default::default()
+            },
+        )?;
+        let vertex_shader =
+            compile_shader(&shaders_hlsl, &defines, s!("VSParticle"), s!("vs_5_0"))?;
+        let pixel_shader = compile_shader(&shaders_hlsl, &defines, s!("PSParticle"), s!("ps_5_0"))?;
+        let pso = create_particle_pipeline_state(
+            device,
+            &root_signature,
+            &vertex_shader,
+            &pixel_shader,
+            targets,
+            verbose,
+        )?;
+
+        let buffer = create_uav_buffer(device, count.max(1) as u64 * PARTICLE_STRIDE)?;
+        let fence = Fence::new(device)?;
+
+        Ok(Particles {
+            count,
+            queue,
+            command_contexts,
+            compute_root_signature,
+            compute_pso,
+            root_signature,
+            pso,
+            buffer,
+            fence,
+            initialized: false,
+            last_update: std::time::Instant::now(),
+        })
+    }
+
+    /// 保存所有粒子的缓冲区。
+    pub fn buffer(&self) -> &ID3D12Resource {
+        &self.buffer
+    }
+
+    /// 计算队列的围栏，直接队列等待 [`Particles::dispatch`] 返回的值。
+    pub fn fence(&self) -> &ID3D12Fence {
+        self.fence.fence()
+    }
+
+    /// 用第 `frame_slot` 帧的计算上下文录制并提交这一帧的粒子更新，返回计算完成时围栏将到达的值。
+    /// 计算队列会先在 GPU 端等待直接队列到达 `direct_fence_value`，以免覆盖之前的帧还在绘制的粒子。
+    pub fn dispatch(
+        &mut self,
+        frame_slot: usize,
+        direct_fence: &ID3D12Fence,
+        direct_fence_value: FenceValue,
+    ) -> Result<FenceValue> {
+        let now = std::time::Instant::now();
+        // 窗口被拖动等情况会让两帧之间隔很久，限制时间步长以免粒子一下子飞出去
+        let delta_time = (now - self.last_update).as_secs_f32().min(0.1);
+        self.last_update = now;
+        let reset = !self.initialized as u32;
+        self.initialized = true;
+
+        let command_context = self.command_contexts.context_mut(frame_slot);
+        let command_list = command_context.reset(self.fence.fence(), Some(&self.compute_pso))?;
+        unsafe {
+            self.queue.Wait(direct_fence, direct_fence_value.get())?;
+            command_list.SetComputeRootSignature(&self.compute_root_signature);
+            command_list.SetComputeRoot32BitConstant(0, delta_time.to_bits(), 0);
+            command_list.SetComputeRoot32BitConstant(0, self.count, 1);
+            command_list.SetComputeRoot32BitConstant(0, reset, 2);
+            command_list.SetComputeRootUnorderedAccessView(1, self.buffer.GetGPUVirtualAddress());
+            command_list.Dispatch(self.count.div_ceil(THREADS_PER_GROUP), 1, 1);
+            command_list.Close()?;
+
+            self.queue
+                .ExecuteCommandLists(&[Some(ID3D12CommandList::from(command_list))]);
+        }
+        let fence_value = self.fence.signal(&self.queue)?;
+        command_context.set_fence_value(fence_value);
+        Ok(fence_value)
+    }
+
+    /// 在直接命令列表上绘制所有粒子，调用前需要已经设置好视口、裁剪矩形和渲染目标。
+    pub fn draw(&self, command_list: &ID3D12GraphicsCommandList) {
+        unsafe {
+            command_list.SetPipelineState(&self.pso);
+            command_list.SetGraphicsRootSignature(&self.root_signature);
+            command_list.SetGraphicsRootShaderResourceView(0, self.buffer.GetGPUVirtualAddress());
+            command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+            // 每个实例 6 个顶点（两个三角形），实例数就是粒子数
+            command_list.DrawInstanced(6, self.count, 0, 0);
+        }
+    }
+}
+
+/// 粒子不使用输入布局，也不做背面剔除和深度测试，其余状态与三角形的 PSO 相同。
+/// 即使不做深度测试，`DSVFormat` 也要与绘制时绑定的深度/模板视图一致。
+fn create_particle_pipeline_state(
+    device: &ID3D12Device,
+    root_signature: &ID3D12RootSignature,
+    vertex_shader: &ID3DBlob,
+    pixel_shader: &ID3DBlob,
+    targets: &RenderTargetFormats,
+    verbose: bool,
+) -> Result<ID3D12PipelineState> {
+    let mut desc = D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+        pRootSignature: Some(root_signature.clone()),
+        VS: D3D12_SHADER_BYTECODE {
+            pShaderBytecode: unsafe { vertex_shader.GetBufferPointer() },
+            BytecodeLength: unsafe { vertex_shader.GetBufferSize() },
+        },
+        PS: D3D12_SHADER_BYTECODE {
+            pShaderBytecode: unsafe { pixel_shader.GetBufferPointer() },
+            BytecodeLength: unsafe { pixel_shader.GetBufferSize() },
+        },
+        RasterizerState: D3D12_RASTERIZER_DESC {
+            FillMode: D3D12_FILL_MODE_SOLID,
+            CullMode: D3D12_CULL_MODE_NONE,
+            ..Default::default()
+        },
+        BlendState: BlendMode::Opaque.blend_desc(),
+        SampleMask: u32::MAX,
+        PrimitiveTopologyType: D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
+        NumRenderTargets: targets.num_render_targets,
+        SampleDesc: targets.sample_desc,
+        ..Default::default()
+    };
+    desc.RTVFormats = targets.rtv_formats;
+    desc.DSVFormat = targets.dsv_format;
+
+    create_graphics_pipeline_state(device, &desc, verbose)
+}
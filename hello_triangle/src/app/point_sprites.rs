@@ -0,0 +1,231 @@
+use crate::devices::{compile_shader, shader_path};
+use crate::pipeline_state::{create_graphics_pipeline_state, RenderTargetFormats};
+use crate::root_signature::{root_constants, set_graphics_root_constants, RootSignatureBuilder};
+use crate::vertex::{checked_input_layout, per_vertex_element, InputLayout};
+use crate::BlendMode;
+use std::mem::offset_of;
+use windows::{
+    core::*, Win32::Graphics::Direct3D::*, Win32::Graphics::Direct3D12::*,
+    Win32::Graphics::Dxgi::Common::*,
+};
+
+/// 每个点精灵在屏幕上的边长（像素）。
+const SPRITE_SIZE: f32 = 8.0;
+
+#[repr(C)]
+struct PointVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+impl InputLayout for PointVertex {
+    fn input_layout() -> Vec<D3D12_INPUT_ELEMENT_DESC> {
+        checked_input_layout::<PointVertex>(&[
+            per_vertex_element(
+                s!("POSITION"),
+                DXGI_FORMAT_R32G32_FLOAT,
+                offset_of!(PointVertex, position),
+            ),
+            per_vertex_element(
+                s!("COLOR"),
+                DXGI_FORMAT_R32G32B32A32_FLOAT,
+                offset_of!(PointVertex, color),
+            ),
+        ])
+    }
+}
+
+/// 点精灵演示：上传一组点，用 `D3D_PRIMITIVE_TOPOLOGY_POINTLIST` 和拓扑类型为 POINT 的 PSO 绘制。
+///
+/// D3D12（从 D3D10 开始）没有固定功能的点大小，光栅器总是把点画成 1 个像素。
+/// 要得到有大小的点，需要在几何着色器中把每个点扩展成一个与屏幕对齐的方块（两个三角形组成的三角形带）。
+/// 按 P 键可以在扩展后的精灵和原始的 1 像素点之间切换，对比两者的差别。
+pub struct PointSprites {
+    count: u32,
+    root_signature: ID3D12RootSignature,
+    sprite_pso: ID3D12PipelineState,
+    point_pso: ID3D12PipelineState,
+    vertex_buffer: ID3D12Resource,
+    vbv: D3D12_VERTEX_BUFFER_VIEW,
+    pub expand: bool,
+}
+
+impl PointSprites {
+    pub fn new(
+        device: &ID3D12Device,
+        count: u32,
+        targets: &RenderTargetFormats,
+        verbose: bool,
+    ) -> Result<Self> {
+        // 根参数 0：2 个 32 位根常量，即精灵在 NDC 中的半宽和半高，对应几何着色器的 b2
+        let root_signature = RootSignatureBuilder::default()
+            .parameter(root_constants(2, 2, D3D12_SHADER_VISIBILITY_GEOMETRY))
+            .build(device)?;
+
+        let (sprite_pso, point_pso) =
+            create_point_pipeline_states(device, &root_signature, targets, verbose)?;
+
+        let (vertex_buffer, vbv) = create_point_buffer(device, count)?;
+
+        Ok(PointSprites {
+            count,
+            root_signature,
+            sprite_pso,
+            point_pso,
+            vertex_buffer,
+            vbv,
+            expand: true,
+        })
+    }
+
+    /// 保存所有点的顶点缓冲区。
+    pub fn vertex_buffer(&self) -> &ID3D12Resource {
+        &self.vertex_buffer
+    }
+
+    /// 在直接命令列表上绘制所有点，调用前需要已经设置好视口、裁剪矩形和渲染目标。
+    pub fn draw(&self, command_list: &ID3D12GraphicsCommandList, viewport: &D3D12_VIEWPORT) {
+        // NDC 的范围是 [-1, 1]，一个像素对应 2 / 视口尺寸
+        let half_size = [SPRITE_SIZE / viewport.Width, SPRITE_SIZE / viewport.Height];
+        unsafe {
+            command_list.SetPipelineState(if self.expand {
+                &self.sprite_pso
+            } else {
+                &self.point_pso
+            });
+            command_list.SetGraphicsRootSignature(&self.root_signature);
+            set_graphics_root_constants(command_list, 0, &half_size);
+            command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_POINTLIST);
+            command_list.IASetVertexBuffers(0, Some(&[self.vbv]));
+            command_list.DrawInstanced(self.count, 1, 0, 0);
+        }
+    }
+}
+
+/// 按黄金角把点排成螺旋，颜色随角度变化。
+fn point_vertices(count: u32) -> Vec<PointVertex> {
+    let golden_angle = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+    (0..count)
+        .map(|i| {
+            let t = (i as f32 + 0.5) / count as f32;
+            let radius = 0.9 * t.sqrt();
+            let angle = i as f32 * golden_angle;
+            PointVertex {
+                position: [radius * angle.cos(), radius * angle.sin()],
+                color: [
+                    0.5 + 0.5 * angle.cos(),
+                    0.5 + 0.5 * (angle + 2.094).cos(),
+                    0.5 + 0.5 * (angle + 4.189).cos(),
+                    1.0,
+                ],
+            }
+        })
+        .collect()
+}
+
+/// 与三角形的顶点缓冲区一样放在上传堆，点的数据创建后就不再变化。
+fn create_point_buffer(
+    device: &ID3D12Device,
+    count: u32,
+) -> Result<(ID3D12Resource, D3D12_VERTEX_BUFFER_VIEW)> {
+    let vertices = point_vertices(count.max(1));
+    let size = std::mem::size_of_val(vertices.as_slice());
+
+    let mut vertex_buffer: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: D3D12_HEAP_TYPE_UPLOAD,
+                ..Default::default()
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &D3D12_RESOURCE_DESC {
+                Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+                Width: size as u64,
+                Height: 1,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                ..Default::default()
+            },
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+            None,
+            &mut vertex_buffer,
+        )?
+    };
+    let vertex_buffer = vertex_buffer.unwrap();
+
+    unsafe {
+        let mut data = std::ptr::null_mut();
+        vertex_buffer.Map(0, None, Some(&mut data))?;
+        std::ptr::copy_nonoverlapping(vertices.as_ptr(), data as *mut PointVertex, vertices.len());
+        vertex_buffer.Unmap(0, None);
+    }
+
+    let vbv = D3D12_VERTEX_BUFFER_VIEW {
+        BufferLocation: unsafe { vertex_buffer.GetGPUVirtualAddress() },
+        StrideInBytes: std::mem::size_of::<PointVertex>() as u32,
+        SizeInBytes: size as u32,
+    };
+    Ok((vertex_buffer, vbv))
+}
+
+/// 创建两个 PSO：带几何着色器、把点扩展成精灵的，以及不带几何着色器、光栅器把每个点画成 1 个像素的。
+/// 拓扑类型必须是 POINT 才能用 POINTLIST 绘制。点精灵不做深度测试，但 `DSVFormat` 要与绑定的深度/模板视图一致。
+fn create_point_pipeline_states(
+    device: &ID3D12Device,
+    root_signature: &ID3D12RootSignature,
+    targets: &RenderTargetFormats,
+    verbose: bool,
+) -> Result<(ID3D12PipelineState, ID3D12PipelineState)> {
+    let shaders_hlsl = shader_path("shaders.hlsl");
+    let defines = [("POINT_SPRITES", "1")];
+    let vertex_shader = compile_shader(&shaders_hlsl, &defines, s!("VSPointSprite"), s!("vs_5_0"))?;
+    let geometry_shader =
+        compile_shader(&shaders_hlsl, &defines, s!("GSPointSprite"), s!("gs_5_0"))?;
+    let pixel_shader = compile_shader(&shaders_hlsl, &defines, s!("PSPointSprite"), s!("ps_5_0"))?;
+
+    let input_element_descs = PointVertex::input_layout();
+    let mut desc = D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+        InputLayout: D3D12_INPUT_LAYOUT_DESC {
+            pInputElementDescs: input_element_descs.as_ptr(),
+            NumElements: input_element_descs.len() as u32,
+        },
+        pRootSignature: Some(root_signature.clone()),
+        VS: D3D12_SHADER_BYTECODE {
+            pShaderBytecode: unsafe { vertex_shader.GetBufferPointer() },
+            BytecodeLength: unsafe { vertex_shader.GetBufferSize() },
+        },
+        GS: D3D12_SHADER_BYTECODE {
+            pShaderBytecode: unsafe { geometry_shader.GetBufferPointer() },
+            BytecodeLength: unsafe { geometry_shader.GetBufferSize() },
+        },
+        PS: D3D12_SHADER_BYTECODE {
+            pShaderBytecode: unsafe { pixel_shader.GetBufferPointer() },
+            BytecodeLength: unsafe { pixel_shader.GetBufferSize() },
+        },
+        RasterizerState: D3D12_RASTERIZER_DESC {
+            FillMode: D3D12_FILL_MODE_SOLID,
+            CullMode: D3D12_CULL_MODE_NONE,
+            ..Default::default()
+        },
+        BlendState: BlendMode::Opaque.blend_desc(),
+        SampleMask: u32::MAX,
+        // 几何着色器输出的是三角形带，但 PSO 的拓扑类型描述的是输入装配器的图元，所以仍然是 POINT
+        PrimitiveTopologyType: D3D12_PRIMITIVE_TOPOLOGY_TYPE_POINT,
+        NumRenderTargets: targets.num_render_targets,
+        SampleDesc: targets.sample_desc,
+        ..Default::default()
+    };
+    desc.RTVFormats = targets.rtv_formats;
+    desc.DSVFormat = targets.dsv_format;
+
+    let sprite_pso = create_graphics_pipeline_state(device, &desc, verbose)?;
+    desc.GS = D3D12_SHADER_BYTECODE::default();
+    let point_pso = create_graphics_pipeline_state(device, &desc, verbose)?;
+    Ok((sprite_pso, point_pso))
+}
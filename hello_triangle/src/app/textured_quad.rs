@@ -0,0 +1,196 @@
+use crate::devices::{compile_shader, create_root_signature, shader_path};
+use crate::pipeline_state::{create_graphics_pipeline_state, RenderTargetFormats};
+use crate::texture::load_dds;
+use crate::upload::upload_buffer_to_default_heap;
+use crate::vertex::{checked_input_layout, per_vertex_element, InputLayout};
+use crate::BlendMode;
+use std::mem::offset_of;
+use std::path::Path;
+use windows::{
+    core::*, Win32::Graphics::Direct3D::*, Win32::Graphics::Direct3D12::*,
+    Win32::Graphics::Dxgi::Common::*,
+};
+
+/// `create_root_signature(device, true)` 中纹理描述符表所在的根参数。
+const TEXTURE_ROOT_PARAMETER: u32 = 2;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TexturedVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+}
+
+impl InputLayout for TexturedVertex {
+    fn input_layout() -> Vec<D3D12_INPUT_ELEMENT_DESC> {
+        checked_input_layout::<TexturedVertex>(&[
+            per_vertex_element(
+                s!("POSITION"),
+                DXGI_FORMAT_R32G32_FLOAT,
+                offset_of!(TexturedVertex, position),
+            ),
+            per_vertex_element(
+                s!("TEXCOORD"),
+                DXGI_FORMAT_R32G32_FLOAT,
+                offset_of!(TexturedVertex, uv),
+            ),
+        ])
+    }
+}
+
+/// 在窗口右下角绘制一个贴上 DDS 纹理的方块。
+///
+/// 着色器通过描述符表访问纹理：根签名只记录描述符表在着色器可见的描述符堆中的位置，
+/// SRV 本身存放在 `srv_heap` 中。绘制前要先用 `SetDescriptorHeaps` 绑定这个堆。
+pub struct TexturedQuad {
+    root_signature: ID3D12RootSignature,
+    pso: ID3D12PipelineState,
+    texture: ID3D12Resource,
+    srv_heap: ID3D12DescriptorHeap,
+    vertex_buffer: ID3D12Resource,
+    vbv: D3D12_VERTEX_BUFFER_VIEW,
+}
+
+impl TexturedQuad {
+    pub fn new(
+        device: &ID3D12Device,
+        command_queue: &ID3D12CommandQueue,
+        texture_path: &Path,
+        targets: &RenderTargetFormats,
+        verbose: bool,
+    ) -> Result<Self> {
+        let (texture, srv_desc) = load_dds(device, command_queue, texture_path)?;
+
+        // 着色器只能访问着色器可见的描述符堆中的描述符，RTV/DSV 堆则不能设为着色器可见
+        let srv_heap: ID3D12DescriptorHeap = unsafe {
+            device.CreateDescriptorHeap(&D3D12_DESCRIPTOR_HEAP_DESC {
+                Type: D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
+                NumDescriptors: 1,
+                Flags: D3D12_DESCRIPTOR_HEAP_FLAG_SHADER_VISIBLE,
+                ..Default::default()
+            })
+        }?;
+        unsafe {
+            device.CreateShaderResourceView(
+                &texture,
+                Some(&srv_desc),
+                srv_heap.GetCPUDescriptorHandleForHeapStart(),
+            )
+        };
+
+        // 与三角形共用根签名的布局，纹理的描述符表是根参数 2，采样器是 s0 上的静态采样器。
+        // 方块的着色器不读取 b0 和 b1，不绑定它们也没有关系
+        let root_signature = create_root_signature(device, true)?;
+        let pso = create_textured_pipeline_state(device, &root_signature, targets, verbose)?;
+
+        // 纹理坐标的原点在左上角，v 轴向下
+        let vertices = [
+            TexturedVertex {
+                position: [0.5, -0.5],
+                uv: [0.0, 0.0],
+            },
+            TexturedVertex {
+                position: [0.95, -0.5],
+                uv: [1.0, 0.0],
+            },
+            TexturedVertex {
+                position: [0.5, -0.95],
+                uv: [0.0, 1.0],
+            },
+            TexturedVertex {
+                position: [0.95, -0.95],
+                uv: [1.0, 1.0],
+            },
+        ];
+        let vertex_buffer = upload_buffer_to_default_heap(
+            device,
+            command_queue,
+            &vertices,
+            D3D12_RESOURCE_STATE_VERTEX_AND_CONSTANT_BUFFER,
+        )?;
+        let vbv = D3D12_VERTEX_BUFFER_VIEW {
+            BufferLocation: unsafe { vertex_buffer.GetGPUVirtualAddress() },
+            StrideInBytes: std::mem::size_of::<TexturedVertex>() as u32,
+            SizeInBytes: std::mem::size_of_val(&vertices) as u32,
+        };
+
+        Ok(TexturedQuad {
+            root_signature,
+            pso,
+            texture,
+            srv_heap,
+            vertex_buffer,
+            vbv,
+        })
+    }
+
+    pub fn texture(&self) -> &ID3D12Resource {
+        &self.texture
+    }
+
+    pub fn vertex_buffer(&self) -> &ID3D12Resource {
+        &self.vertex_buffer
+    }
+
+    /// 在直接命令列表上绘制方块，调用前需要已经设置好视口、裁剪矩形和渲染目标。
+    pub fn draw(&self, command_list: &ID3D12GraphicsCommandList) {
+        unsafe {
+            command_list.SetPipelineState(&self.pso);
+            command_list.SetGraphicsRootSignature(&self.root_signature);
+            command_list.SetDescriptorHeaps(&[Some(self.srv_heap.clone())]);
+            command_list.SetGraphicsRootDescriptorTable(
+                TEXTURE_ROOT_PARAMETER,
+                self.srv_heap.GetGPUDescriptorHandleForHeapStart(),
+            );
+            command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP);
+            command_list.IASetVertexBuffers(0, Some(&[self.vbv]));
+            command_list.DrawInstanced(4, 1, 0, 0);
+        }
+    }
+}
+
+/// 输入布局是二维位置加纹理坐标（TEXCOORD）。方块不做深度测试，但 `DSVFormat` 要与绑定的深度/模板视图一致。
+pub fn create_textured_pipeline_state(
+    device: &ID3D12Device,
+    root_signature: &ID3D12RootSignature,
+    targets: &RenderTargetFormats,
+    verbose: bool,
+) -> Result<ID3D12PipelineState> {
+    let shaders_hlsl = shader_path("shaders.hlsl");
+    let defines = [("TEXTURED_QUAD", "1")];
+    let vertex_shader =
+        compile_shader(&shaders_hlsl, &defines, s!("VSTexturedQuad"), s!("vs_5_0"))?;
+    let pixel_shader = compile_shader(&shaders_hlsl, &defines, s!("PSTexturedQuad"), s!("ps_5_0"))?;
+
+    let input_element_descs = TexturedVertex::input_layout();
+    let mut desc = D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+        InputLayout: D3D12_INPUT_LAYOUT_DESC {
+            pInputElementDescs: input_element_descs.as_ptr(),
+            NumElements: input_element_descs.len() as u32,
+        },
+        pRootSignature: Some(root_signature.clone()),
+        VS: D3D12_SHADER_BYTECODE {
+            pShaderBytecode: unsafe { vertex_shader.GetBufferPointer() },
+            BytecodeLength: unsafe { vertex_shader.GetBufferSize() },
+        },
+        PS: D3D12_SHADER_BYTECODE {
+            pShaderBytecode: unsafe { pixel_shader.GetBufferPointer() },
+            BytecodeLength: unsafe { pixel_shader.GetBufferSize() },
+        },
+        RasterizerState: D3D12_RASTERIZER_DESC {
+            FillMode: D3D12_FILL_MODE_SOLID,
+            CullMode: D3D12_CULL_MODE_NONE,
+            ..Default::default()
+        },
+        BlendState: BlendMode::Opaque.blend_desc(),
+        SampleMask: u32::MAX,
+        PrimitiveTopologyType: D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
+        NumRenderTargets: targets.num_render_targets,
+        SampleDesc: targets.sample_desc,
+        ..Default::default()
+    };
+    desc.RTVFormats = targets.rtv_formats;
+    desc.DSVFormat = targets.dsv_format;
+
+    create_graphics_pipeline_state(device, &desc, verbose)
+}
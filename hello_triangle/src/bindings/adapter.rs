@@ -25,15 +25,36 @@ impl AdapterDesc {
     }
 }
 
+// 逐个字段转换，而不是 transmute：windows-rs 中结构体的布局一旦变化，这里会编译失败而不是产生未定义行为。
 impl From<DXGI_ADAPTER_DESC> for AdapterDesc {
     fn from(desc: DXGI_ADAPTER_DESC) -> AdapterDesc {
-        unsafe { std::mem::transmute(desc) }
+        AdapterDesc {
+            description: desc.Description,
+            vendor_id: desc.VendorId,
+            device_id: desc.DeviceId,
+            subsys_id: desc.SubSysId,
+            revision: desc.Revision,
+            dedicated_video_memory: desc.DedicatedVideoMemory,
+            dedicated_system_memory: desc.DedicatedSystemMemory,
+            shared_system_memory: desc.SharedSystemMemory,
+            adapter_luid: desc.AdapterLuid,
+        }
     }
 }
 
 impl From<AdapterDesc> for DXGI_ADAPTER_DESC {
     fn from(desc: AdapterDesc) -> DXGI_ADAPTER_DESC {
-        unsafe { std::mem::transmute(desc) }
+        DXGI_ADAPTER_DESC {
+            Description: desc.description,
+            VendorId: desc.vendor_id,
+            DeviceId: desc.device_id,
+            SubSysId: desc.subsys_id,
+            Revision: desc.revision,
+            DedicatedVideoMemory: desc.dedicated_video_memory,
+            DedicatedSystemMemory: desc.dedicated_system_memory,
+            SharedSystemMemory: desc.shared_system_memory,
+            AdapterLuid: desc.adapter_luid,
+        }
     }
 }
 
@@ -62,45 +83,265 @@ impl std::fmt::Debug for AdapterDesc {
     }
 }
 
-/// 打印显卡调试信息
-pub fn print_adapter_info(factory: &IDXGIFactory4) -> Result<()> {
+/// 按枚举顺序列出所有适配器的描述，包括软件适配器和不支持 Direct3D 12 的适配器，供界面或选择逻辑使用。
+pub fn enumerate_adapters(factory: &IDXGIFactory4) -> Result<Vec<AdapterDesc>> {
+    let mut descs = Vec::new();
     for i in 0.. {
-        let adapter_result: Result<IDXGIAdapter1> = unsafe { factory.EnumAdapters1(i) };
-        if let Ok(adapter) = adapter_result {
-            let desc: DXGI_ADAPTER_DESC = unsafe { adapter.GetDesc()? };
-            let adapter_desc: AdapterDesc = desc.into();
-            println!("adapter: {:?}", adapter_desc);
-        } else {
-            break;
-        }
+        let adapter = match unsafe { factory.EnumAdapters1(i) } {
+            Ok(adapter) => adapter,
+            // 枚举到末尾时 EnumAdapters1 返回 DXGI_ERROR_NOT_FOUND，其他错误照常返回
+            Err(error) if error.code() == DXGI_ERROR_NOT_FOUND => break,
+            Err(error) => return Err(error),
+        };
+        descs.push(unsafe { adapter.GetDesc()? }.into());
     }
-    Ok(())
+    Ok(descs)
 }
-/// 拿到硬件适配器
-pub fn get_hardware_adapter(factory: &IDXGIFactory4) -> Result<IDXGIAdapter1> {
+
+/// 按枚举顺序列出适配器连接的所有输出（显示器）的描述。WARP 之类的软件适配器没有输出，返回空列表。
+pub fn enumerate_outputs(adapter: &IDXGIAdapter1) -> Result<Vec<DXGI_OUTPUT_DESC>> {
+    let mut descs = Vec::new();
     for i in 0.. {
-        let adapter = unsafe { factory.EnumAdapters1(i)? };
+        let output = match unsafe { adapter.EnumOutputs(i) } {
+            Ok(output) => output,
+            // 与 EnumAdapters1 一样，枚举到末尾时返回 DXGI_ERROR_NOT_FOUND
+            Err(error) if error.code() == DXGI_ERROR_NOT_FOUND => break,
+            Err(error) => return Err(error),
+        };
+        descs.push(unsafe { output.GetDesc()? });
+    }
+    Ok(descs)
+}
 
-        let desc = unsafe { adapter.GetDesc1()? };
+/// 输出的设备名，例如 "\\.\DISPLAY1"。
+pub fn output_name(desc: &DXGI_OUTPUT_DESC) -> String {
+    let len = wstrlens(&desc.DeviceName);
+    String::from_utf16_lossy(&desc.DeviceName[..len])
+}
 
-        if (DXGI_ADAPTER_FLAG(desc.Flags) & DXGI_ADAPTER_FLAG_SOFTWARE) != DXGI_ADAPTER_FLAG_NONE {
-            // Don't select the Basic Render Driver adapter. If you want a
-            // software adapter, pass in "/warp" on the command line.
-            continue;
-        }
+/// 输出的设备名和它在桌面上的区域，例如 "\\.\DISPLAY1 1920x1080 at (0, 0)"。
+pub fn format_output(desc: &DXGI_OUTPUT_DESC) -> String {
+    let rect = desc.DesktopCoordinates;
+    format!(
+        "{} {}x{} at ({}, {})",
+        output_name(desc),
+        rect.right - rect.left,
+        rect.bottom - rect.top,
+        rect.left,
+        rect.top
+    )
+}
 
-        // Check to see whether the adapter supports Direct3D 12, but don't create the actual device yet.
-        if unsafe {
-            D3D12CreateDevice(
-                &adapter,
-                D3D_FEATURE_LEVEL_11_0,
-                std::ptr::null_mut::<Option<ID3D12Device>>(),
-            )
+/// 打印适配器的所有输出，没有输出时也打印一行说明。
+pub fn print_outputs(adapter: &IDXGIAdapter1) -> Result<()> {
+    let outputs = enumerate_outputs(adapter)?;
+    if outputs.is_empty() {
+        println!("  output: none");
+    }
+    for desc in &outputs {
+        println!("  output: {}", format_output(desc));
+    }
+    Ok(())
+}
+
+/// 打印显卡调试信息。所选适配器的输出由 `create_device` 用 [`print_outputs`] 打印，这里不再重复。
+pub fn print_adapter_info(factory: &IDXGIFactory4) -> Result<()> {
+    for adapter_desc in enumerate_adapters(factory)? {
+        println!("adapter: {:?}", adapter_desc);
+        // 只有 IDXGIAdapter3 才能查询实时的显存用量，较老的系统上跳过这一行
+        let adapter3: Result<IDXGIAdapter3> =
+            unsafe { factory.EnumAdapterByLuid(adapter_desc.adapter_luid) };
+        if let Ok(adapter3) = adapter3 {
+            match query_video_memory(&adapter3) {
+                Ok(info) => println!("  {}", format_video_memory(&info)),
+                Err(e) => println!("  video memory budget unavailable: {}", e.message()),
+            }
         }
-        .is_ok()
-        {
-            return Ok(adapter);
+    }
+    Ok(())
+}
+
+/// 查询适配器第 0 个节点的本地显存（独立显卡的显存，集成显卡则是分给它的系统内存）的实时用量和预算。
+///
+/// 预算由操作系统根据所有进程的需求动态调整，用量超过预算时系统可能把资源换出显存，造成卡顿。
+pub fn query_video_memory(adapter: &IDXGIAdapter3) -> Result<DXGI_QUERY_VIDEO_MEMORY_INFO> {
+    unsafe { adapter.QueryVideoMemoryInfo(0, DXGI_MEMORY_SEGMENT_GROUP_LOCAL) }
+}
+
+pub fn format_video_memory(info: &DXGI_QUERY_VIDEO_MEMORY_INFO) -> String {
+    format!(
+        "local video memory: {:?} used of {:?} budget",
+        MemoryDbgHelper(info.CurrentUsage),
+        MemoryDbgHelper(info.Budget)
+    )
+}
+
+/// 是否为支持 Direct3D 12 的硬件适配器。
+fn is_hardware_d3d12_adapter(adapter: &IDXGIAdapter1) -> Result<bool> {
+    let desc = unsafe { adapter.GetDesc1()? };
+
+    if (DXGI_ADAPTER_FLAG(desc.Flags) & DXGI_ADAPTER_FLAG_SOFTWARE) != DXGI_ADAPTER_FLAG_NONE {
+        // Don't select the Basic Render Driver adapter. If you want a
+        // software adapter, pass in "/warp" on the command line.
+        return Ok(false);
+    }
+
+    // Check to see whether the adapter supports Direct3D 12, but don't create the actual device yet.
+    Ok(unsafe {
+        D3D12CreateDevice(
+            adapter,
+            D3D_FEATURE_LEVEL_11_0,
+            std::ptr::null_mut::<Option<ID3D12Device>>(),
+        )
+    }
+    .is_ok())
+}
+
+/// 拿到第 `index` 个（从 0 开始，按枚举顺序）支持 Direct3D 12 的硬件适配器，
+/// 软件适配器和不支持 Direct3D 12 的适配器不参与编号。
+pub fn get_hardware_adapter(factory: &IDXGIFactory4, index: u32) -> Result<IDXGIAdapter1> {
+    nth_adapter(hardware_adapters(factory)?, index)
+}
+
+/// 从枚举到的适配器中取出第 `index` 个。一个都没有时（例如无头服务器或不支持 D3D12 的机器）
+/// 返回 DXGI_ERROR_NOT_FOUND，提示改用 `-warp`。
+fn nth_adapter<T>(mut adapters: Vec<T>, index: u32) -> Result<T> {
+    let count = adapters.len();
+    if count == 0 {
+        return Err(Error::new(
+            DXGI_ERROR_NOT_FOUND,
+            "no D3D12-capable hardware adapter, pass -warp to use the software adapter".into(),
+        ));
+    }
+    if (index as usize) < count {
+        return Ok(adapters.swap_remove(index as usize));
+    }
+    Err(Error::new(
+        DXGI_ERROR_NOT_FOUND,
+        format!(
+            "hardware adapter {} not found: {} Direct3D 12 hardware adapter(s) available",
+            index, count
+        )
+        .into(),
+    ))
+}
+
+/// 按枚举顺序列出所有支持 Direct3D 12 的硬件适配器。
+pub fn hardware_adapters(factory: &IDXGIFactory4) -> Result<Vec<IDXGIAdapter1>> {
+    let mut adapters = Vec::new();
+    for i in 0.. {
+        let adapter = match unsafe { factory.EnumAdapters1(i) } {
+            Ok(adapter) => adapter,
+            // 枚举到末尾时 EnumAdapters1 返回 DXGI_ERROR_NOT_FOUND，其他错误照常返回
+            Err(error) if error.code() == DXGI_ERROR_NOT_FOUND => break,
+            Err(error) => return Err(error),
+        };
+        if is_hardware_d3d12_adapter(&adapter)? {
+            adapters.push(adapter);
         }
     }
-    unreachable!()
+    Ok(adapters)
+}
+
+/// 适配器的名称，例如 "NVIDIA GeForce RTX 3080"。
+pub fn adapter_name(adapter: &IDXGIAdapter1) -> Result<String> {
+    let desc: AdapterDesc = unsafe { adapter.GetDesc()? }.into();
+    Ok(desc.description())
+}
+
+#[test]
+fn adapter_desc_round_trip() {
+    let mut description = [0u16; 128];
+    for (dst, src) in description.iter_mut().zip("WARP".encode_utf16()) {
+        *dst = src;
+    }
+    let desc = DXGI_ADAPTER_DESC {
+        Description: description,
+        VendorId: 0x1414,
+        DeviceId: 0x8c,
+        SubSysId: 1,
+        Revision: 2,
+        DedicatedVideoMemory: 3,
+        DedicatedSystemMemory: 4,
+        SharedSystemMemory: 5,
+        AdapterLuid: Foundation::LUID {
+            LowPart: 6,
+            HighPart: 7,
+        },
+    };
+    let adapter_desc = AdapterDesc::from(desc);
+    assert_eq!(adapter_desc.description(), "WARP");
+    assert_eq!(adapter_desc.vendor_id, 0x1414);
+    assert_eq!(adapter_desc.shared_system_memory, 5);
+    assert_eq!(DXGI_ADAPTER_DESC::from(adapter_desc), desc);
+    assert_eq!(
+        std::mem::size_of::<AdapterDesc>(),
+        std::mem::size_of::<DXGI_ADAPTER_DESC>()
+    );
+}
+
+#[test]
+fn missing_hardware_adapter_is_an_error() {
+    let error = nth_adapter(Vec::<IDXGIAdapter1>::new(), 0).unwrap_err();
+    assert_eq!(error.code(), DXGI_ERROR_NOT_FOUND);
+
+    // 真实的工厂上也一样：没有这么多适配器时返回错误而不是 panic
+    let factory = crate::devices::create_factory().unwrap();
+    let error = get_hardware_adapter(&factory, u32::MAX).unwrap_err();
+    assert_eq!(error.code(), DXGI_ERROR_NOT_FOUND);
+}
+
+#[test]
+fn warp_adapter_is_enumerated() {
+    let factory = crate::devices::create_factory().unwrap();
+    let warp: IDXGIAdapter1 = unsafe { factory.EnumWarpAdapter() }.unwrap();
+    let warp_desc: AdapterDesc = unsafe { warp.GetDesc() }.unwrap().into();
+    let adapters = enumerate_adapters(&factory).unwrap();
+    assert!(adapters
+        .iter()
+        .any(|desc| desc.adapter_luid == warp_desc.adapter_luid));
+}
+
+#[test]
+fn warp_adapter_has_no_outputs() {
+    let factory = crate::devices::create_factory().unwrap();
+    let warp: IDXGIAdapter1 = unsafe { factory.EnumWarpAdapter() }.unwrap();
+    assert!(enumerate_outputs(&warp).unwrap().is_empty());
+}
+
+#[test]
+fn output_is_formatted_with_its_desktop_area() {
+    let mut desc = DXGI_OUTPUT_DESC {
+        DesktopCoordinates: Foundation::RECT {
+            left: 1920,
+            top: 0,
+            right: 4480,
+            bottom: 1440,
+        },
+        ..Default::default()
+    };
+    for (dst, src) in desc
+        .DeviceName
+        .iter_mut()
+        .zip("\\\\.\\DISPLAY2".encode_utf16())
+    {
+        *dst = src;
+    }
+    assert_eq!(
+        format_output(&desc),
+        "\\\\.\\DISPLAY2 2560x1440 at (1920, 0)"
+    );
+}
+
+#[test]
+fn video_memory_is_formatted_with_units() {
+    let info = DXGI_QUERY_VIDEO_MEMORY_INFO {
+        Budget: 8 * 1024 * 1024 * 1024,
+        CurrentUsage: 512 * 1024 * 1024,
+        ..Default::default()
+    };
+    assert_eq!(
+        format_video_memory(&info),
+        "local video memory: 512MB used of 8.00GB budget"
+    );
 }
@@ -0,0 +1,225 @@
+use crate::fence::FenceValue;
+use crate::set_name;
+use windows::{core::*, Win32::Graphics::Direct3D12::*};
+
+/// 一个命令分配器和从它录制的命令列表，以及最近一次提交它们之后 Signal 的围栏值。
+///
+/// 命令列表提交之后就可以重置并重新录制，但它录制的命令仍然存放在分配器的内存中，
+/// 分配器要等 GPU 执行完这些命令才能重置。所以每个上下文都记着自己的围栏值，
+/// [`CommandContext::reset`] 在围栏还没到达这个值时直接 panic，而不是让 GPU 读到被覆盖的命令。
+pub struct CommandContext {
+    allocator: ID3D12CommandAllocator,
+    command_list: ID3D12GraphicsCommandList,
+    /// 最近一次提交这个上下文录制的命令后 Signal 的围栏值，还没有提交过时为 [`FenceValue::INITIAL`]。
+    fence_value: FenceValue,
+}
+
+impl CommandContext {
+    /// 创建 `list_type` 类型的分配器和命令列表，命令列表创建后处于关闭状态，录制前要先 [`CommandContext::reset`]。
+    pub fn new(device: &ID3D12Device, list_type: D3D12_COMMAND_LIST_TYPE) -> Result<Self> {
+        let allocator: ID3D12CommandAllocator =
+            unsafe { device.CreateCommandAllocator(list_type) }?;
+        let command_list: ID3D12GraphicsCommandList =
+            unsafe { device.CreateCommandList(0, list_type, &allocator, None) }?;
+        unsafe { command_list.Close() }?;
+        Ok(CommandContext {
+            allocator,
+            command_list,
+            fence_value: FenceValue::INITIAL,
+        })
+    }
+
+    pub fn command_list(&self) -> &ID3D12GraphicsCommandList {
+        &self.command_list
+    }
+
+    /// 分配器和命令列表的调试名称分别为 `{name} allocator` 和 `{name} list`。
+    pub fn set_name(&self, name: &str) {
+        set_name(&self.allocator, &format!("{} allocator", name));
+        set_name(&self.command_list, &format!("{} list", name));
+    }
+
+    /// 最近一次提交后 Signal 的围栏值。
+    pub fn fence_value(&self) -> FenceValue {
+        self.fence_value
+    }
+
+    /// 重置分配器和命令列表，开始新的录制，`initial_state` 是命令列表最初使用的 PSO。
+    ///
+    /// `fence` 是提交这个上下文的队列所用的围栏，GPU 必须已经把它推进到 [`CommandContext::fence_value`]。
+    pub fn reset(
+        &self,
+        fence: &ID3D12Fence,
+        initial_state: Option<&ID3D12PipelineState>,
+    ) -> Result<&ID3D12GraphicsCommandList> {
+        let completed_value = unsafe { fence.GetCompletedValue() };
+        assert!(
+            self.fence_value.is_completed(completed_value),
+            "command context reset while fence value {} is still in flight (completed {})",
+            self.fence_value.get(),
+            completed_value
+        );
+        unsafe {
+            self.allocator.Reset()?;
+            self.command_list.Reset(&self.allocator, initial_state)?;
+        }
+        Ok(&self.command_list)
+    }
+
+    /// 提交录制的命令并 Signal 之后调用，记下这次的围栏值。
+    pub fn set_fence_value(&mut self, fence_value: FenceValue) {
+        debug_assert!(
+            fence_value >= self.fence_value,
+            "fence value went backwards"
+        );
+        self.fence_value = fence_value;
+    }
+}
+
+/// 每个在飞行中的帧各有一个 [`CommandContext`]，按帧的下标取用。
+///
+/// 切换到下一帧时只需等待那一帧的上下文上次提交的命令执行完，参见 [`CommandContextPool::fence_value`]。
+/// 将来要多线程录制时，每个线程也可以各自从池中取一个上下文。
+pub struct CommandContextPool {
+    contexts: Vec<CommandContext>,
+}
+
+impl CommandContextPool {
+    pub fn new(
+        device: &ID3D12Device,
+        list_type: D3D12_COMMAND_LIST_TYPE,
+        frame_count: usize,
+    ) -> Result<Self> {
+        assert!(
+            frame_count > 0,
+            "a command context pool needs at least one frame"
+        );
+        let contexts = (0..frame_count)
+            .map(|_| CommandContext::new(device, list_type))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(CommandContextPool { contexts })
+    }
+
+    /// 池中上下文的数量，也就是最多同时在飞行中的帧数。
+    pub fn frame_count(&self) -> usize {
+        self.contexts.len()
+    }
+
+    pub fn context(&self, frame_slot: usize) -> &CommandContext {
+        &self.contexts[frame_slot]
+    }
+
+    pub fn context_mut(&mut self, frame_slot: usize) -> &mut CommandContext {
+        &mut self.contexts[frame_slot]
+    }
+
+    /// 第 `frame_slot` 帧的上下文最近一次提交后 Signal 的围栏值，重用这个上下文之前要等围栏到达它。
+    pub fn fence_value(&self, frame_slot: usize) -> FenceValue {
+        self.contexts[frame_slot].fence_value()
+    }
+}
+
+/// 录制一次、之后每帧都用 `ExecuteBundle` 执行的一小段命令（`D3D12_COMMAND_LIST_TYPE_BUNDLE`）。
+///
+/// 驱动在录制时就把 bundle 中的命令转换好，执行时几乎没有额外开销，适合每帧重复的绘制序列。它有这些限制：
+/// - 不能调用 `ResourceBarrier`、`OMSetRenderTargets`、`RSSetViewports`、`RSSetScissorRects`、`Clear*`，
+///   也不能在 bundle 中再执行 bundle，这些都要在调用它的直接命令列表中完成；
+/// - 不继承调用者的 PSO 和图元拓扑：PSO 在录制时给出，拓扑要在 bundle 中设置；
+/// - 在 bundle 中设置与调用者相同的根签名时，继承调用者已经设置的根参数，
+///   所以每帧变化的根常量、根 CBV 仍然在直接命令列表中设置；
+/// - 用到描述符表时，调用者绑定的描述符堆必须与 bundle 中的相同。
+///
+/// bundle 中设置的状态在执行后留在调用者中。录制完的内容不能修改，引用的缓冲区视图、PSO 等变化后要重新录制，
+/// 而且要等 GPU 执行完使用它的命令列表之后才能释放。
+pub struct Bundle {
+    allocator: ID3D12CommandAllocator,
+    command_list: ID3D12GraphicsCommandList,
+}
+
+impl Bundle {
+    /// 创建 bundle 的分配器和命令列表，用 `record` 录制命令后关闭。`initial_state` 是 bundle 使用的 PSO。
+    pub fn record(
+        device: &ID3D12Device,
+        initial_state: Option<&ID3D12PipelineState>,
+        record: impl FnOnce(&ID3D12GraphicsCommandList),
+    ) -> Result<Self> {
+        let allocator: ID3D12CommandAllocator =
+            unsafe { device.CreateCommandAllocator(D3D12_COMMAND_LIST_TYPE_BUNDLE) }?;
+        let command_list: ID3D12GraphicsCommandList = unsafe {
+            device.CreateCommandList(0, D3D12_COMMAND_LIST_TYPE_BUNDLE, &allocator, initial_state)
+        }?;
+        record(&command_list);
+        unsafe { command_list.Close() }?;
+        Ok(Bundle {
+            allocator,
+            command_list,
+        })
+    }
+
+    /// 传给 `ExecuteBundle` 的命令列表。
+    pub fn command_list(&self) -> &ID3D12GraphicsCommandList {
+        &self.command_list
+    }
+
+    /// 分配器和命令列表的调试名称分别为 `{name} allocator` 和 `{name} list`。
+    pub fn set_name(&self, name: &str) {
+        set_name(&self.allocator, &format!("{} allocator", name));
+        set_name(&self.command_list, &format!("{} list", name));
+    }
+}
+
+#[test]
+fn context_is_not_reset_while_in_flight() {
+    use crate::devices::warp_device_and_queue;
+    use crate::fence::Fence;
+
+    let (device, queue) = warp_device_and_queue();
+    let mut fence = Fence::new(&device).unwrap();
+    let mut pool = CommandContextPool::new(&device, D3D12_COMMAND_LIST_TYPE_DIRECT, 2).unwrap();
+    assert_eq!(pool.frame_count(), 2);
+
+    let command_list = pool.context(0).reset(fence.fence(), None).unwrap();
+    unsafe { command_list.Close() }.unwrap();
+    let command_list = ID3D12CommandList::from(pool.context(0).command_list());
+    unsafe { queue.ExecuteCommandLists(&[Some(command_list)]) };
+    let submitted = fence.signal(&queue).unwrap();
+    pool.context_mut(0).set_fence_value(submitted);
+    fence.wait(submitted).unwrap();
+    pool.context(0).reset(fence.fence(), None).unwrap();
+    unsafe { pool.context(0).command_list().Close() }.unwrap();
+
+    // 围栏还没有 Signal 过这个值，上下文的命令在 GPU 看来仍在执行
+    pool.context_mut(1)
+        .set_fence_value(fence.last_signaled().next());
+    let reset = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _ = pool.context(1).reset(fence.fence(), None);
+    }));
+    assert!(reset.is_err());
+}
+
+#[test]
+fn bundle_executes_from_a_direct_list() {
+    use crate::devices::warp_device_and_queue;
+    use crate::fence::Fence;
+    use windows::Win32::Graphics::Direct3D::D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST;
+
+    let (device, queue) = warp_device_and_queue();
+    let bundle = Bundle::record(&device, None, |command_list| unsafe {
+        command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+    })
+    .unwrap();
+
+    // 同一个 bundle 可以在多个命令列表中反复执行
+    let mut fence = Fence::new(&device).unwrap();
+    let context = CommandContext::new(&device, D3D12_COMMAND_LIST_TYPE_DIRECT).unwrap();
+    for _ in 0..2 {
+        let command_list = context.reset(fence.fence(), None).unwrap();
+        unsafe {
+            command_list.ExecuteBundle(bundle.command_list());
+            command_list.Close().unwrap();
+            queue.ExecuteCommandLists(&[Some(ID3D12CommandList::from(command_list))]);
+        }
+        let submitted = fence.signal(&queue).unwrap();
+        fence.wait(submitted).unwrap();
+    }
+}
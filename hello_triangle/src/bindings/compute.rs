@@ -0,0 +1,140 @@
+use crate::devices::{compile_shader_for_model, shader_path, CompiledShader};
+use crate::readback::read_back_buffer;
+use crate::root_signature::{root_constants, RootSignatureBuilder};
+use crate::upload::execute_and_wait;
+use crate::ShaderModel;
+use windows::{core::*, Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::Common::*};
+
+/// 与 `CSFill` 的 `numthreads` 保持一致。
+const FILL_THREADS_PER_GROUP: u32 = 64;
+
+/// 计算流水线只有一个计算着色器阶段，因此它的 PSO 比图形 PSO 简单得多。
+/// `compute_shader` 可以是 FXC 编译的 `cs_5_0`，也可以是 DXC 编译的 `cs_6_0`。
+pub fn create_compute_pipeline_state(
+    device: &ID3D12Device,
+    root_signature: &ID3D12RootSignature,
+    compute_shader: &CompiledShader,
+) -> Result<ID3D12PipelineState> {
+    let desc = D3D12_COMPUTE_PIPELINE_STATE_DESC {
+        pRootSignature: Some(root_signature.clone()),
+        CS: compute_shader.bytecode(),
+        ..Default::default()
+    };
+    unsafe { device.CreateComputePipelineState(&desc) }
+}
+
+/// 最简单的计算示例：在 GPU 上把 `count` 个 `u32` 依次写成 `i * multiplier`，再读回 CPU。
+///
+/// 计算着色器通过 u0 上的根 UAV 写入默认堆中的缓冲区，b2 上的两个 32 位根常量传递 `count` 和 `multiplier`。
+/// 线程组的数量向上取整，最后一组多出来的线程在着色器中直接返回。计算命令也可以提交到直接队列，
+/// 缓冲区在 `Dispatch` 时从 COMMON 隐式提升为 UNORDERED_ACCESS，命令执行完后又衰退回 COMMON，
+/// 所以 `Dispatch` 前后都不需要屏障；读回时由 [`read_back_buffer`] 把它从 COMMON 转换到 COPY_SOURCE，复制完再转换回来。
+/// `count` 为 0 时不创建任何资源，直接返回空的数组。
+pub fn fill_buffer_on_gpu(
+    device: &ID3D12Device,
+    command_queue: &ID3D12CommandQueue,
+    count: u32,
+    multiplier: u32,
+    shader_model: ShaderModel,
+) -> Result<Vec<u32>> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    // 根参数 0：b2 上的 count 和 multiplier；根参数 1：u0 上的根 UAV。计算着色器不使用输入装配器
+    let root_signature = RootSignatureBuilder::default()
+        .parameter(root_constants(2, 2, D3D12_SHADER_VISIBILITY_ALL))
+        .parameter(D3D12_ROOT_PARAMETER {
+            ParameterType: D3D12_ROOT_PARAMETER_TYPE_UAV,
+            Anonymous: D3D12_ROOT_PARAMETER_0 {
+                Descriptor: D3D12_ROOT_DESCRIPTOR {
+                    ShaderRegister: 0,
+                    RegisterSpace: 0,
+                },
+            },
+            ShaderVisibility: D3D12_SHADER_VISIBILITY_ALL,
+        })
+        .flags(D3D12_ROOT_SIGNATURE_FLAG_NONE)
+        .build(device)?;
+    let compute_shader = compile_shader_for_model(
+        &shader_path("shaders.hlsl"),
+        &[("FILL_BUFFER", "1")],
+        "CSFill",
+        "cs",
+        shader_model,
+    )?;
+    let pso = create_compute_pipeline_state(device, &root_signature, &compute_shader)?;
+
+    let size = count as u64 * std::mem::size_of::<u32>() as u64;
+    let buffer = create_uav_buffer(device, size)?;
+    execute_and_wait(device, command_queue, |command_list| unsafe {
+        command_list.SetPipelineState(&pso);
+        command_list.SetComputeRootSignature(&root_signature);
+        command_list.SetComputeRoot32BitConstants(0, 2, [count, multiplier].as_ptr() as _, 0);
+        command_list.SetComputeRootUnorderedAccessView(1, buffer.GetGPUVirtualAddress());
+        command_list.Dispatch(count.div_ceil(FILL_THREADS_PER_GROUP), 1, 1);
+    })?;
+
+    let bytes = read_back_buffer(device, command_queue, &buffer, D3D12_RESOURCE_STATE_COMMON)?;
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|value| u32::from_ne_bytes([value[0], value[1], value[2], value[3]]))
+        .collect())
+}
+
+/// 在默认堆上创建一个可以被着色器作为 UAV 写入的缓冲区。
+/// 缓冲区以 COMMON 状态创建：它会在第一次被访问时隐式提升为所需的状态，
+/// 并在 ExecuteCommandLists 执行完毕后衰退回 COMMON，因此可以不加屏障地在不同队列之间传递。
+pub fn create_uav_buffer(device: &ID3D12Device, size: u64) -> Result<ID3D12Resource> {
+    let mut buffer: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: D3D12_HEAP_TYPE_DEFAULT,
+                ..Default::default()
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &D3D12_RESOURCE_DESC {
+                Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+                Width: size,
+                Height: 1,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                Flags: D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS,
+                ..Default::default()
+            },
+            D3D12_RESOURCE_STATE_COMMON,
+            None,
+            &mut buffer,
+        )?
+    };
+    Ok(buffer.unwrap())
+}
+
+#[test]
+fn compute_shader_fills_a_buffer() {
+    use crate::devices::warp_device_and_queue;
+    use crate::dxc::dxc_available;
+
+    let (device, command_queue) = warp_device_and_queue();
+
+    // 100 不是 64 的整数倍，最后一个线程组有 28 个线程不能写入
+    let expected = (0..100).map(|i| i * 3).collect::<Vec<u32>>();
+    let values = fill_buffer_on_gpu(&device, &command_queue, 100, 3, ShaderModel::Sm5_0).unwrap();
+    assert_eq!(values, expected);
+    if dxc_available() {
+        let values =
+            fill_buffer_on_gpu(&device, &command_queue, 100, 3, ShaderModel::Sm6_0).unwrap();
+        assert_eq!(values, expected);
+    }
+    assert!(
+        fill_buffer_on_gpu(&device, &command_queue, 0, 3, ShaderModel::Sm5_0)
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,94 @@
+use std::marker::PhantomData;
+use windows::{core::*, Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::Common::*};
+
+/// 把 `size` 向上取整到 `alignment` 的整数倍，`alignment` 必须是 2 的幂。
+pub const fn align_up(size: u64, alignment: u64) -> u64 {
+    (size + alignment - 1) & !(alignment - 1)
+}
+
+/// 位于上传堆中的常量缓冲区，存放一个 `T`。
+///
+/// 常量缓冲区视图要求缓冲区大小是 256 字节（D3D12_CONSTANT_BUFFER_DATA_PLACEMENT_ALIGNMENT）的整数倍，
+/// 所以资源的大小会向上对齐。上传堆的资源可以一直保持映射，CPU 每次更新直接写入映射的内存即可，
+/// 但要保证 GPU 已经不再读取旧的数据，通常每个在飞行中的帧各用一个常量缓冲区。
+///
+/// `T` 按字节复制到 GPU 可见的内存中，所以必须是 `Copy`：带有 `Drop` 或堆指针的类型复制过去没有意义。
+pub struct ConstantBuffer<T: Copy> {
+    resource: ID3D12Resource,
+    mapped: *mut T,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Copy> ConstantBuffer<T> {
+    pub fn new(device: &ID3D12Device) -> Result<Self> {
+        let size = align_up(
+            std::mem::size_of::<T>() as u64,
+            D3D12_CONSTANT_BUFFER_DATA_PLACEMENT_ALIGNMENT as u64,
+        );
+        let mut resource: Option<ID3D12Resource> = None;
+        unsafe {
+            device.CreateCommittedResource(
+                &D3D12_HEAP_PROPERTIES {
+                    Type: D3D12_HEAP_TYPE_UPLOAD,
+                    ..Default::default()
+                },
+                D3D12_HEAP_FLAG_NONE,
+                &D3D12_RESOURCE_DESC {
+                    Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+                    Width: size,
+                    Height: 1,
+                    DepthOrArraySize: 1,
+                    MipLevels: 1,
+                    SampleDesc: DXGI_SAMPLE_DESC {
+                        Count: 1,
+                        Quality: 0,
+                    },
+                    Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                    ..Default::default()
+                },
+                D3D12_RESOURCE_STATE_GENERIC_READ,
+                None,
+                &mut resource,
+            )?
+        };
+        let resource = resource.unwrap();
+
+        // 读取范围为空，表示 CPU 不会读取这块内存；资源释放前不需要 Unmap
+        let mut mapped = std::ptr::null_mut();
+        unsafe {
+            resource.Map(
+                0,
+                Some(&D3D12_RANGE { Begin: 0, End: 0 }),
+                Some(&mut mapped),
+            )?
+        };
+        Ok(ConstantBuffer {
+            resource,
+            mapped: mapped as *mut T,
+            _marker: PhantomData,
+        })
+    }
+
+    /// 把 `data` 复制到映射的内存中，GPU 之后读取到的就是新的值。
+    pub fn update(&self, data: &T) {
+        unsafe { std::ptr::copy_nonoverlapping(data, self.mapped, 1) };
+    }
+
+    pub fn resource(&self) -> &ID3D12Resource {
+        &self.resource
+    }
+
+    /// 绑定为根 CBV 时使用的 GPU 虚拟地址。
+    pub fn gpu_virtual_address(&self) -> u64 {
+        unsafe { self.resource.GetGPUVirtualAddress() }
+    }
+}
+
+#[test]
+fn align_up_to_constant_buffer_alignment() {
+    assert_eq!(align_up(0, 256), 0);
+    assert_eq!(align_up(1, 256), 256);
+    assert_eq!(align_up(64, 256), 256);
+    assert_eq!(align_up(256, 256), 256);
+    assert_eq!(align_up(257, 256), 512);
+}
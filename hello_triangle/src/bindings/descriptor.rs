@@ -0,0 +1,135 @@
+use crate::pipeline_state::MAX_RENDER_TARGETS;
+use windows::{core::*, Win32::Foundation::*, Win32::Graphics::Direct3D12::*};
+
+/// 描述符堆以及它的描述符大小。
+///
+/// 描述符在堆中紧密排列，第 `i` 个描述符的句柄是堆起始句柄加上 `i` 个描述符大小。
+/// 描述符大小因硬件和描述符类型而异，只能通过 `GetDescriptorHandleIncrementSize` 查询，这里创建时查一次缓存起来。
+/// 下标超出堆的容量时直接 panic，而不是得到一个指向堆外的句柄。
+pub struct DescriptorHeap {
+    heap: ID3D12DescriptorHeap,
+    descriptor_size: usize,
+    num_descriptors: u32,
+    /// 下一个由 [`DescriptorHeap::allocate`] 分配的下标。
+    next: u32,
+}
+
+impl DescriptorHeap {
+    /// `shader_visible` 为 `true` 时着色器可以通过 GPU 句柄访问其中的描述符，只有 CBV/SRV/UAV 和采样器堆可以这样创建。
+    pub fn new(
+        device: &ID3D12Device,
+        heap_type: D3D12_DESCRIPTOR_HEAP_TYPE,
+        num_descriptors: u32,
+        shader_visible: bool,
+    ) -> Result<Self> {
+        let heap = unsafe {
+            device.CreateDescriptorHeap(&D3D12_DESCRIPTOR_HEAP_DESC {
+                Type: heap_type,
+                NumDescriptors: num_descriptors,
+                Flags: if shader_visible {
+                    D3D12_DESCRIPTOR_HEAP_FLAG_SHADER_VISIBLE
+                } else {
+                    D3D12_DESCRIPTOR_HEAP_FLAG_NONE
+                },
+                ..Default::default()
+            })
+        }?;
+        let descriptor_size =
+            unsafe { device.GetDescriptorHandleIncrementSize(heap_type) } as usize;
+        Ok(DescriptorHeap {
+            heap,
+            descriptor_size,
+            num_descriptors,
+            next: 0,
+        })
+    }
+
+    pub fn heap(&self) -> &ID3D12DescriptorHeap {
+        &self.heap
+    }
+
+    /// 按顺序分配下一个还没有用过的描述符，返回它的下标；堆已满时返回错误。
+    pub fn allocate(&mut self) -> Result<u32> {
+        if self.next >= self.num_descriptors {
+            return Err(Error::new(
+                E_OUTOFMEMORY,
+                format!(
+                    "descriptor heap is full ({} descriptors)",
+                    self.num_descriptors
+                )
+                .into(),
+            ));
+        }
+        let index = self.next;
+        self.next += 1;
+        Ok(index)
+    }
+
+    /// 第 `index` 个描述符的 CPU 句柄，用于创建视图、绑定渲染目标等。
+    pub fn cpu_handle(&self, index: u32) -> D3D12_CPU_DESCRIPTOR_HANDLE {
+        let start = unsafe { self.heap.GetCPUDescriptorHandleForHeapStart() };
+        D3D12_CPU_DESCRIPTOR_HANDLE {
+            ptr: start.ptr + self.offset(index),
+        }
+    }
+
+    /// 第 `index` 个描述符的 GPU 句柄，用于设置描述符表，堆必须是着色器可见的。
+    pub fn gpu_handle(&self, index: u32) -> D3D12_GPU_DESCRIPTOR_HANDLE {
+        let start = unsafe { self.heap.GetGPUDescriptorHandleForHeapStart() };
+        D3D12_GPU_DESCRIPTOR_HANDLE {
+            ptr: start.ptr + self.offset(index) as u64,
+        }
+    }
+
+    fn offset(&self, index: u32) -> usize {
+        assert!(
+            index < self.num_descriptors,
+            "descriptor index {} out of range for a heap of {} descriptors",
+            index,
+            self.num_descriptors
+        );
+        index as usize * self.descriptor_size
+    }
+}
+
+/// 绑定 `rtv_handles` 中的渲染目标，第 i 个写入像素着色器的 `SV_Target{i}`，顺序和格式要与 PSO 的 `RTVFormats` 一致。
+/// `dsv_handle` 为 `None` 时不绑定深度/模板缓冲区。
+///
+/// 句柄逐个给出，不要求在描述符堆中连续，所以 `RTsSingleHandleToDescriptorRange` 总是 `false`。
+pub fn set_render_targets(
+    command_list: &ID3D12GraphicsCommandList,
+    rtv_handles: &[D3D12_CPU_DESCRIPTOR_HANDLE],
+    dsv_handle: Option<&D3D12_CPU_DESCRIPTOR_HANDLE>,
+) {
+    assert!(
+        rtv_handles.len() <= MAX_RENDER_TARGETS,
+        "at most {} render targets can be bound, got {}",
+        MAX_RENDER_TARGETS,
+        rtv_handles.len()
+    );
+    unsafe {
+        command_list.OMSetRenderTargets(
+            rtv_handles.len() as u32,
+            Some(rtv_handles.as_ptr()),
+            false,
+            dsv_handle.map(|handle| handle as *const _),
+        )
+    };
+}
+
+#[test]
+fn descriptor_handles_are_offset_and_bounded() {
+    use crate::devices::warp_device;
+
+    let device = warp_device();
+    let mut heap = DescriptorHeap::new(&device, D3D12_DESCRIPTOR_HEAP_TYPE_RTV, 2, false).unwrap();
+
+    assert_eq!(heap.allocate().unwrap(), 0);
+    assert_eq!(heap.allocate().unwrap(), 1);
+    assert!(heap.allocate().is_err());
+    assert_eq!(
+        heap.cpu_handle(1).ptr - heap.cpu_handle(0).ptr,
+        heap.descriptor_size
+    );
+    assert!(std::panic::catch_unwind(|| heap.cpu_handle(2)).is_err());
+}
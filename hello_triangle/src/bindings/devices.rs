@@ -1,7 +1,19 @@
-use crate::{adapter, SampleCommandLine};
+use crate::dred::enable_dred;
+use crate::dxc::compile_shader_dxc;
+use crate::info_queue::{configure_info_queue, info_queue};
+use crate::pipeline_state::{create_graphics_pipeline_state, RenderTargetFormats};
+use crate::present::format_name;
+use crate::root_signature::{descriptor_range, root_constants, RootSignatureBuilder};
+use crate::vertex::InputLayout;
+use crate::{
+    adapter, resolve_asset, BlendMode, CoordinateSystem, SampleCommandLine, ShaderModel,
+    StencilMode,
+};
+use std::path::{Path, PathBuf};
 
 use windows::{
-    core::*, Win32::Graphics::Direct3D::Fxc::*, Win32::Graphics::Direct3D::*,
+    core::*, Win32::Foundation::*, Win32::Graphics::Direct3D::Dxc::*,
+    Win32::Graphics::Direct3D::Fxc::*, Win32::Graphics::Direct3D::*,
     Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::Common::*, Win32::Graphics::Dxgi::*,
 };
 
@@ -9,14 +21,37 @@ use windows::{
 /// 此设备代表着一个显示适配器。一般来说，显示适配器是一种 3D 图形硬件（如显卡）。
 /// Direct3D 12 设备既可检测系统环境对功能的支持情况，又能创建所有其他的 Direct3D 接口对象（如资源、视图和命令列表）。
 pub fn create_device(command_line: &SampleCommandLine) -> Result<(IDXGIFactory4, ID3D12Device)> {
+    // DRED 与调试层一样要在创建设备之前开启，它在发布版本中也有用，GPU 超时同样会发生
+    enable_dred();
     // debug 开启调试
     if cfg!(debug_assertions) {
         unsafe {
             let mut debug: Option<ID3D12Debug> = None;
             if let Some(debug) = D3D12GetDebugInterface(&mut debug).ok().and(debug) {
                 debug.EnableDebugLayer();
+                // GPU 验证在着色器中插入检查代码，在 GPU 执行时验证资源状态和描述符，
+                // 能发现 CPU 端的调试层看不到的错误，代价是绘制慢上许多倍
+                if command_line.gpu_validation {
+                    match debug.cast::<ID3D12Debug1>() {
+                        Ok(debug1) => {
+                            debug1.SetEnableGPUBasedValidation(true);
+                            eprintln!("warning: GPU-based validation is enabled, rendering will be very slow");
+                        }
+                        Err(_) => eprintln!("warning: GPU-based validation requires ID3D12Debug1"),
+                    }
+                }
+            } else {
+                // 调试层由“图形工具”这一可选功能提供，没有安装时拿不到调试接口，也就没有任何验证信息。
+                eprintln!(
+                    "warning: D3D12 debug layer is unavailable, validation is disabled. \
+                     Install \"Graphics Tools\" via Settings > Apps > Optional features > Add a feature."
+                );
             }
         }
+    } else if command_line.gpu_validation {
+        eprintln!(
+            "warning: -gpuvalidation needs the debug layer, which is only enabled in debug builds"
+        );
     }
     let dxgi_factory = create_factory()?;
 
@@ -24,21 +59,64 @@ pub fn create_device(command_line: &SampleCommandLine) -> Result<(IDXGIFactory4,
     let adapter = if command_line.use_warp_device {
         unsafe { dxgi_factory.EnumWarpAdapter() }
     } else {
-        adapter::get_hardware_adapter(&dxgi_factory)
+        adapter::get_hardware_adapter(&dxgi_factory, command_line.adapter_index.unwrap_or(0))
     }?;
+    println!("adapter: {}", adapter::adapter_name(&adapter)?);
+    adapter::print_outputs(&adapter)?;
 
-    let mut device: Option<ID3D12Device> = None;
-
-    // 指定在创建设备时所用的显示适配器。若将此参数设定为空指针，则使用主显示适配器。
-    // 我们在本书的示例中总是采用主适配器。在 4.1.10 节中，我们已展示了怎样枚举系统中所有的显示适配器。
-    unsafe { D3D12CreateDevice(&adapter, D3D_FEATURE_LEVEL_11_0, &mut device) }?;
     // 调用 D3D12CreateDevice 失败后，程序将回退到一种软件适配器：WARP 设备。
     // if !command_line.use_warp_device && device.is_none() {
     //     adapter = unsafe { dxgi_factory.EnumWarpAdapter() }?;
     //     unsafe { D3D12CreateDevice(&adapter, D3D_FEATURE_LEVEL_11_0, &mut device) }?;
     // }
 
-    Ok((dxgi_factory, device.unwrap()))
+    let device = create_device_on_adapter(&adapter)?;
+    let feature_level = max_feature_level(&device);
+    println!(
+        "feature level: {} ({:#x})",
+        feature_level_name(feature_level),
+        feature_level.0
+    );
+    Ok((dxgi_factory, device))
+}
+
+/// 测试共用的 WARP 设备，不依赖机器上的显卡。
+#[cfg(test)]
+pub fn warp_device() -> ID3D12Device {
+    let command_line = SampleCommandLine {
+        use_warp_device: true,
+        ..Default::default()
+    };
+    create_device(&command_line).unwrap().1
+}
+
+/// 测试共用的 WARP 设备和它的直接命令队列。
+#[cfg(test)]
+pub fn warp_device_and_queue() -> (ID3D12Device, ID3D12CommandQueue) {
+    let device = warp_device();
+    let queue = unsafe {
+        device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
+            Type: D3D12_COMMAND_LIST_TYPE_DIRECT,
+            ..Default::default()
+        })
+    }
+    .unwrap();
+    (device, queue)
+}
+
+/// 在指定的适配器上创建设备。调试层是进程级的，之后在其他适配器上创建的设备同样会开启它。
+pub fn create_device_on_adapter(adapter: &IDXGIAdapter1) -> Result<ID3D12Device> {
+    let mut device: Option<ID3D12Device> = None;
+
+    // 指定在创建设备时所用的显示适配器。若将此参数设定为空指针，则使用主显示适配器。
+    // 我们在本书的示例中总是采用主适配器。在 4.1.10 节中，我们已展示了怎样枚举系统中所有的显示适配器。
+    unsafe { D3D12CreateDevice(adapter, D3D_FEATURE_LEVEL_11_0, &mut device) }?;
+    let device = device.unwrap();
+    // 开启了调试层的设备才有 info queue
+    if let Some(info_queue) = info_queue(&device) {
+        configure_info_queue(&info_queue)?;
+    }
+    Ok(device)
 }
 
 pub fn create_factory() -> Result<IDXGIFactory4> {
@@ -53,27 +131,152 @@ pub fn create_factory() -> Result<IDXGIFactory4> {
     unsafe { CreateDXGIFactory2(dxgi_factory_flags) }
 }
 
-pub fn check_sample_support(device: &ID3D12Device) -> Result<u32> {
-    let mut features_architecture = D3D12_FEATURE_DATA_MULTISAMPLE_QUALITY_LEVELS {
-        SampleCount: 4,
-        Format: DXGI_FORMAT_R32G32B32A32_UINT,
-        Flags: D3D12_MULTISAMPLE_QUALITY_LEVELS_FLAG_NONE,
-        NumQualityLevels: 0,
+/// 查询 `format` 在 4X MSAA 下支持的质量级别数量，返回 0 表示不支持。
+pub fn check_sample_support(device: &ID3D12Device, format: DXGI_FORMAT) -> Result<u32> {
+    let query =
+        FeatureQuery::MultisampleQualityLevels(D3D12_FEATURE_DATA_MULTISAMPLE_QUALITY_LEVELS {
+            SampleCount: 4,
+            Format: format,
+            Flags: D3D12_MULTISAMPLE_QUALITY_LEVELS_FLAG_NONE,
+            NumQualityLevels: 0,
+        });
+    let FeatureQuery::MultisampleQualityLevels(quality_levels) = query_feature(device, query)?
+    else {
+        unreachable!("query_feature returns the variant it was given")
     };
+    // 在一切支持 Direct3D 11 的设备上，所有的渲染目标格式就皆已支持 4X MSAA 了。因此，凡是支持 Direct3D 11 的硬件，
+    // 都会保证此项功能的正常开启，我们也就无须再对此进行检验了。但是，对质量级别的检测还是不可或缺。
+    // 个别格式（例如整数格式）不一定能多重采样，这里不再断言，由调用方决定是否回退到单采样。
+    Ok(quality_levels.NumQualityLevels)
+}
+
+/// 查询设备对 `format` 的支持情况，`Support1`/`Support2` 中的每一位表示一种用途。
+pub fn check_format_support(
+    device: &ID3D12Device,
+    format: DXGI_FORMAT,
+) -> Result<D3D12_FEATURE_DATA_FORMAT_SUPPORT> {
+    let query = FeatureQuery::FormatSupport(D3D12_FEATURE_DATA_FORMAT_SUPPORT {
+        Format: format,
+        ..Default::default()
+    });
+    let FeatureQuery::FormatSupport(support) = query_feature(device, query)? else {
+        unreachable!("query_feature returns the variant it was given")
+    };
+    Ok(support)
+}
+
+/// 用作交换链后台缓冲区的格式必须具备的用途：能作为渲染目标，也能用于显示。
+const BACK_BUFFER_SUPPORT: [(D3D12_FORMAT_SUPPORT1, &str); 2] = [
+    (D3D12_FORMAT_SUPPORT1_RENDER_TARGET, "render target"),
+    (D3D12_FORMAT_SUPPORT1_DISPLAY, "display"),
+];
+
+fn missing_back_buffer_support(support: D3D12_FORMAT_SUPPORT1) -> Vec<&'static str> {
+    BACK_BUFFER_SUPPORT
+        .iter()
+        .filter(|(flag, _)| (support & *flag) != *flag)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// 创建交换链之前确认 `format` 可以用作后台缓冲区，否则返回指明格式和所缺用途的错误。
+pub fn check_back_buffer_format(device: &ID3D12Device, format: DXGI_FORMAT) -> Result<()> {
+    let support = check_format_support(device, format)?;
+    let missing = missing_back_buffer_support(support.Support1);
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(
+            E_INVALIDARG,
+            format!(
+                "{} cannot be used as a back buffer format, missing support for: {}",
+                format_name(format),
+                missing.join(", ")
+            )
+            .into(),
+        ))
+    }
+}
+
+/// 按从低到高的顺序列出的候选功能级别，设备至少支持创建时要求的 11_0。
+const FEATURE_LEVELS: [D3D_FEATURE_LEVEL; 5] = [
+    D3D_FEATURE_LEVEL_11_0,
+    D3D_FEATURE_LEVEL_11_1,
+    D3D_FEATURE_LEVEL_12_0,
+    D3D_FEATURE_LEVEL_12_1,
+    D3D_FEATURE_LEVEL_12_2,
+];
+
+/// 设备支持的最高功能级别。创建设备时只要求 11_0，需要 12_x 功能的示例可以据此决定是否开启。
+///
+/// 较老的运行时不认识列表中较新的功能级别，会让整个查询失败，这时去掉最高的那个再试；
+/// 都失败时返回创建设备时要求的 11_0。
+pub fn max_feature_level(device: &ID3D12Device) -> D3D_FEATURE_LEVEL {
+    for count in (1..=FEATURE_LEVELS.len()).rev() {
+        let mut levels = D3D12_FEATURE_DATA_FEATURE_LEVELS {
+            NumFeatureLevels: count as u32,
+            pFeatureLevelsRequested: FEATURE_LEVELS.as_ptr(),
+            MaxSupportedFeatureLevel: D3D_FEATURE_LEVEL_11_0,
+        };
+        if unsafe { check_feature(device, D3D12_FEATURE_FEATURE_LEVELS, &mut levels) }.is_ok() {
+            return levels.MaxSupportedFeatureLevel;
+        }
+    }
+    D3D_FEATURE_LEVEL_11_0
+}
+
+/// 功能级别的可读形式，例如 `D3D_FEATURE_LEVEL_12_1`（0xc100）是 "12_1"。
+pub fn feature_level_name(level: D3D_FEATURE_LEVEL) -> String {
+    format!("{}_{}", (level.0 >> 12) & 0xf, (level.0 >> 8) & 0xf)
+}
+
+/// 一项功能查询和它的数据结构。
+///
+/// `CheckFeatureSupport` 只接受一个 `D3D12_FEATURE` 和一块内存，结构体与枚举值不匹配时
+/// 运行时要么返回 `E_INVALIDARG`，要么按错误的布局读写这块内存。这里每个变体都带着自己的结构体，
+/// 枚举值由变体决定，不会配错。结构体中的输入字段（例如 `Format`、`NodeIndex`）在查询前填好，
+/// 其余字段由 [`query_feature`] 填写。
+///
+/// 带指针的查询（例如 `D3D12_FEATURE_FEATURE_LEVELS`）没有放进来，仍然用 [`check_feature`]。
+#[derive(Clone, Copy, Debug)]
+pub enum FeatureQuery {
+    Options(D3D12_FEATURE_DATA_D3D12_OPTIONS),
+    Architecture(D3D12_FEATURE_DATA_ARCHITECTURE),
+    FormatSupport(D3D12_FEATURE_DATA_FORMAT_SUPPORT),
+    MultisampleQualityLevels(D3D12_FEATURE_DATA_MULTISAMPLE_QUALITY_LEVELS),
+    RootSignature(D3D12_FEATURE_DATA_ROOT_SIGNATURE),
+}
+
+impl FeatureQuery {
+    pub fn feature(&self) -> D3D12_FEATURE {
+        match self {
+            FeatureQuery::Options(_) => D3D12_FEATURE_D3D12_OPTIONS,
+            FeatureQuery::Architecture(_) => D3D12_FEATURE_ARCHITECTURE,
+            FeatureQuery::FormatSupport(_) => D3D12_FEATURE_FORMAT_SUPPORT,
+            FeatureQuery::MultisampleQualityLevels(_) => D3D12_FEATURE_MULTISAMPLE_QUALITY_LEVELS,
+            FeatureQuery::RootSignature(_) => D3D12_FEATURE_ROOT_SIGNATURE,
+        }
+    }
+}
+
+/// 执行 `query`，返回同一个变体，其中的结构体已经填好。
+pub fn query_feature(device: &ID3D12Device, mut query: FeatureQuery) -> Result<FeatureQuery> {
+    let feature = query.feature();
+    // 结构体的类型和大小都来自变体本身，与 feature 一定匹配
     unsafe {
-        check_feature::<D3D12_FEATURE_DATA_MULTISAMPLE_QUALITY_LEVELS>(
-            device,
-            D3D12_FEATURE_MULTISAMPLE_QUALITY_LEVELS,
-            &mut features_architecture,
-        )
+        match &mut query {
+            FeatureQuery::Options(data) => check_feature(device, feature, data),
+            FeatureQuery::Architecture(data) => check_feature(device, feature, data),
+            FeatureQuery::FormatSupport(data) => check_feature(device, feature, data),
+            FeatureQuery::MultisampleQualityLevels(data) => check_feature(device, feature, data),
+            FeatureQuery::RootSignature(data) => check_feature(device, feature, data),
+        }
     }?;
-    println!("check_sample_support: {:?}", &features_architecture);
-    // 在一切支持 Direct3D 11 的设备上，所有的渲染目标格式就皆已支持 4X MSAA 了。因此，凡是支持 Direct3D 11 的硬件，
-    // 都会保证此项功能的正常开启，我们也就无须再对此进行检验了。但是，对质量级别的检测还是不可或缺
-    assert!(features_architecture.NumQualityLevels > 0);
-    Ok(features_architecture.NumQualityLevels)
+    Ok(query)
 }
 
+/// `CheckFeatureSupport` 的原始形式，`T` 必须是 `feature` 对应的数据结构。
+/// 优先使用 [`query_feature`]，它不支持的查询才用这个函数。
 #[allow(clippy::missing_safety_doc)]
 pub unsafe fn check_feature<T>(
     device: &ID3D12Device,
@@ -117,19 +320,189 @@ pub fn test(device: &ID3D12Device) {
 /// 如果我们把着色器程序当作一个函数，而将输入资源看作着色器的函数参数，那么根签名则定义了函数签名
 /// （其实这就是“根签名”一词的由来）。通过绑定不同的资源作为参数，着色器的输出也将有所差别。
 /// 例如，顶点着色器的输出取决于实际向它输入的顶点数据以及为它绑定的具体资源。
-pub fn create_root_signature(device: &ID3D12Device) -> Result<ID3D12RootSignature> {
-    // 根签名由一组根参数构成
-    let desc = D3D12_ROOT_SIGNATURE_DESC {
-        Flags: D3D12_ROOT_SIGNATURE_FLAG_ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT,
-        ..Default::default()
-    };
+///
+/// `textured` 时再追加一个指向 t0 的 SRV 描述符表，以及 s0 上的静态采样器，供采样纹理的像素着色器使用。
+pub fn create_root_signature(device: &ID3D12Device, textured: bool) -> Result<ID3D12RootSignature> {
+    // 根签名由一组根参数构成。
+    // 根参数 0：1 个 32 位根常量（经过的时间），对应像素着色器的 b0。
+    // 根常量直接存放在根签名里，是最廉价的绑定方式，适合每帧都变化的少量数据。
+    // 根参数 1：根 CBV，对应顶点着色器的 b1，存放 MVP 矩阵。
+    // 根参数 2（仅 textured）：描述符表，包含一个从 t0 开始的 SRV。
+    let mut builder = RootSignatureBuilder::default()
+        .parameter(time_root_constant())
+        .parameter(object_constants_root_descriptor());
+    if textured {
+        builder = builder
+            .descriptor_table(
+                &[descriptor_range(D3D12_DESCRIPTOR_RANGE_TYPE_SRV, 1, 0)],
+                D3D12_SHADER_VISIBILITY_PIXEL,
+            )
+            .static_sampler(linear_wrap_static_sampler());
+    }
+    builder.build(device)
+}
 
-    let mut signature = None;
+/// s0 上的静态采样器：缩小、放大和 mipmap 之间都使用线性过滤，纹理坐标超出 [0, 1] 时重复（WRAP）。
+pub fn linear_wrap_static_sampler() -> D3D12_STATIC_SAMPLER_DESC {
+    D3D12_STATIC_SAMPLER_DESC {
+        Filter: D3D12_FILTER_MIN_MAG_MIP_LINEAR,
+        AddressU: D3D12_TEXTURE_ADDRESS_MODE_WRAP,
+        AddressV: D3D12_TEXTURE_ADDRESS_MODE_WRAP,
+        AddressW: D3D12_TEXTURE_ADDRESS_MODE_WRAP,
+        MipLODBias: 0.0,
+        MaxAnisotropy: 0,
+        ComparisonFunc: D3D12_COMPARISON_FUNC_NEVER,
+        BorderColor: D3D12_STATIC_BORDER_COLOR_TRANSPARENT_BLACK,
+        MinLOD: 0.0,
+        MaxLOD: D3D12_FLOAT32_MAX,
+        ShaderRegister: 0,
+        RegisterSpace: 0,
+        ShaderVisibility: D3D12_SHADER_VISIBILITY_PIXEL,
+    }
+}
+
+/// 像素着色器 b0 上的一个 32 位根常量，用来传递经过的时间。
+pub fn time_root_constant() -> D3D12_ROOT_PARAMETER {
+    root_constants(0, 1, D3D12_SHADER_VISIBILITY_PIXEL)
+}
 
-    let signature = unsafe {
-        D3D12SerializeRootSignature(&desc, D3D_ROOT_SIGNATURE_VERSION_1, &mut signature, None)
+/// 顶点着色器 b1 上的根 CBV，指向存放 MVP 矩阵的常量缓冲区。
+/// 根描述符直接记录常量缓冲区的 GPU 虚拟地址，不需要在描述符堆中创建 CBV。
+pub fn object_constants_root_descriptor() -> D3D12_ROOT_PARAMETER {
+    D3D12_ROOT_PARAMETER {
+        ParameterType: D3D12_ROOT_PARAMETER_TYPE_CBV,
+        Anonymous: D3D12_ROOT_PARAMETER_0 {
+            Descriptor: D3D12_ROOT_DESCRIPTOR {
+                ShaderRegister: 1,
+                RegisterSpace: 0,
+            },
+        },
+        ShaderVisibility: D3D12_SHADER_VISIBILITY_VERTEX,
+    }
+}
+
+/// 设备支持的最高根签名版本。Windows 10 1607 之前的运行时不认识这项查询，这时只能用 1.0。
+pub fn root_signature_version(device: &ID3D12Device) -> D3D_ROOT_SIGNATURE_VERSION {
+    let query = FeatureQuery::RootSignature(D3D12_FEATURE_DATA_ROOT_SIGNATURE {
+        HighestVersion: D3D_ROOT_SIGNATURE_VERSION_1_1,
+    });
+    match query_feature(device, query) {
+        Ok(FeatureQuery::RootSignature(data)) => data.HighestVersion,
+        _ => D3D_ROOT_SIGNATURE_VERSION_1_0,
     }
-    .map(|()| signature.unwrap())?;
+}
+
+/// 把 1.0 的根签名描述按 `version` 序列化。
+///
+/// 1.1 时逐个根参数转换为 1.1 的结构，并显式写出 1.0 隐含的约定，而不是取 1.1 更严格的默认值（NONE）：
+/// 描述符是易变的（DESCRIPTORS_VOLATILE），录制后、执行前仍可以改写；描述符和根描述符指向的数据
+/// 在命令列表执行期间、参数仍然绑定时保持不变（DATA_STATIC_WHILE_SET_AT_EXECUTE）。
+/// 采样器没有“数据”，它的描述符范围只标记 DESCRIPTORS_VOLATILE。这样转换前后根签名的行为一致，
+/// 需要 1.1 的优化时应直接填写 1.1 的结构。
+fn serialize_root_signature(
+    desc: &D3D12_ROOT_SIGNATURE_DESC,
+    version: D3D_ROOT_SIGNATURE_VERSION,
+) -> Result<ID3DBlob> {
+    let mut signature = None;
+    if version == D3D_ROOT_SIGNATURE_VERSION_1_0 {
+        unsafe { D3D12SerializeRootSignature(desc, version, &mut signature, None) }?;
+        return Ok(signature.unwrap());
+    }
+
+    let parameters =
+        unsafe { std::slice::from_raw_parts(desc.pParameters, desc.NumParameters as usize) };
+    // 每个描述符表转换后的描述符范围，要在序列化期间一直存活
+    let ranges: Vec<Vec<D3D12_DESCRIPTOR_RANGE1>> = parameters
+        .iter()
+        .map(|parameter| {
+            if parameter.ParameterType != D3D12_ROOT_PARAMETER_TYPE_DESCRIPTOR_TABLE {
+                return vec![];
+            }
+            let table = unsafe { parameter.Anonymous.DescriptorTable };
+            unsafe {
+                std::slice::from_raw_parts(
+                    table.pDescriptorRanges,
+                    table.NumDescriptorRanges as usize,
+                )
+            }
+            .iter()
+            .map(|range| D3D12_DESCRIPTOR_RANGE1 {
+                RangeType: range.RangeType,
+                NumDescriptors: range.NumDescriptors,
+                BaseShaderRegister: range.BaseShaderRegister,
+                RegisterSpace: range.RegisterSpace,
+                Flags: if range.RangeType == D3D12_DESCRIPTOR_RANGE_TYPE_SAMPLER {
+                    D3D12_DESCRIPTOR_RANGE_FLAG_DESCRIPTORS_VOLATILE
+                } else {
+                    D3D12_DESCRIPTOR_RANGE_FLAG_DESCRIPTORS_VOLATILE
+                        | D3D12_DESCRIPTOR_RANGE_FLAG_DATA_STATIC_WHILE_SET_AT_EXECUTE
+                },
+                OffsetInDescriptorsFromTableStart: range.OffsetInDescriptorsFromTableStart,
+            })
+            .collect()
+        })
+        .collect();
+    let parameters1: Vec<D3D12_ROOT_PARAMETER1> = parameters
+        .iter()
+        .zip(&ranges)
+        .map(|(parameter, ranges)| D3D12_ROOT_PARAMETER1 {
+            ParameterType: parameter.ParameterType,
+            Anonymous: match parameter.ParameterType {
+                D3D12_ROOT_PARAMETER_TYPE_DESCRIPTOR_TABLE => D3D12_ROOT_PARAMETER1_0 {
+                    DescriptorTable: D3D12_ROOT_DESCRIPTOR_TABLE1 {
+                        NumDescriptorRanges: ranges.len() as u32,
+                        pDescriptorRanges: ranges.as_ptr(),
+                    },
+                },
+                D3D12_ROOT_PARAMETER_TYPE_32BIT_CONSTANTS => D3D12_ROOT_PARAMETER1_0 {
+                    Constants: unsafe { parameter.Anonymous.Constants },
+                },
+                // 其余都是根描述符（CBV、SRV、UAV）
+                _ => {
+                    let descriptor = unsafe { parameter.Anonymous.Descriptor };
+                    D3D12_ROOT_PARAMETER1_0 {
+                        Descriptor: D3D12_ROOT_DESCRIPTOR1 {
+                            ShaderRegister: descriptor.ShaderRegister,
+                            RegisterSpace: descriptor.RegisterSpace,
+                            Flags: D3D12_ROOT_DESCRIPTOR_FLAG_DATA_STATIC_WHILE_SET_AT_EXECUTE,
+                        },
+                    }
+                }
+            },
+            ShaderVisibility: parameter.ShaderVisibility,
+        })
+        .collect();
+    let versioned_desc = D3D12_VERSIONED_ROOT_SIGNATURE_DESC {
+        Version: version,
+        Anonymous: D3D12_VERSIONED_ROOT_SIGNATURE_DESC_0 {
+            Desc_1_1: D3D12_ROOT_SIGNATURE_DESC1 {
+                NumParameters: parameters1.len() as u32,
+                pParameters: parameters1.as_ptr(),
+                NumStaticSamplers: desc.NumStaticSamplers,
+                pStaticSamplers: desc.pStaticSamplers,
+                Flags: desc.Flags,
+            },
+        },
+    };
+    unsafe { D3D12SerializeVersionedRootSignature(&versioned_desc, &mut signature, None) }?;
+    Ok(signature.unwrap())
+}
+
+/// 序列化根签名描述并创建根签名，`desc` 中的根参数数组必须在调用期间保持存活。
+/// 设备支持时按 1.1 序列化，否则按 1.0，调用方不需要关心是哪一种。
+pub fn create_root_signature_from_desc(
+    device: &ID3D12Device,
+    desc: &D3D12_ROOT_SIGNATURE_DESC,
+) -> Result<ID3D12RootSignature> {
+    create_root_signature_with_version(device, desc, root_signature_version(device))
+}
+
+fn create_root_signature_with_version(
+    device: &ID3D12Device,
+    desc: &D3D12_ROOT_SIGNATURE_DESC,
+    version: D3D_ROOT_SIGNATURE_VERSION,
+) -> Result<ID3D12RootSignature> {
+    let signature = serialize_root_signature(desc, version)?;
 
     // Direct3D 12 规定，必须先将根签名的描述布局进行序列化处理（serialize），待其转换为以 ID3DBlob 接口表示的序列化
     // 数据格式后，才可将它传入 CreateRootSignature 方法，正式创建根签名。
@@ -144,138 +517,283 @@ pub fn create_root_signature(device: &ID3D12Device) -> Result<ID3D12RootSignatur
     }
 }
 
-/// ID3D12PipelineState 对象集合了大量的流水线状态信息。为了保证性能，我们将所有这些对
-/// 象都集总在一起，一并送至渲染流水线。通过这样的一个集合，Direct3D 便可以确定所有的状态是否彼
-/// 此兼容，而驱动程序则能够据此而提前生成硬件本地指令及其状态。在 Direct3D 11 的状态模型中，这些
-/// 渲染状态片段都是要分开配置的。然而这些状态实际都有一定的联系，以致如果其中的一个状态发生改
-/// 变，那么驱动程序可能就要为了另一个相关的独立状态而对硬件重新进行编程。由于一些状态在配置流
-/// 水线时需要改变，因而硬件状态也就可能被频繁地改写。为了避免这些冗余的操作，驱动程序往往会推
-/// 迟针对硬件状态的编程动作，直到明确整条流水线的状态发起绘制调用后，才正式生成对应的本地指令
-/// 与状态。但是，这种延迟操作需要驱动在运行时进行额外的记录工作，即追踪状态的变化，而后才能在
-/// 运行时生成改写硬件状态的本地代码。在 Direct3D 12 的新模型中，驱动程序可以在初始化期间生成对流
-/// 水线状态编程的全部代码，这便是我们将大多数的流水线状态指定为一个集合所带来的好处。
-pub fn create_pipeline_state(
-    device: &ID3D12Device,
-    root_signature: &ID3D12RootSignature,
-) -> Result<ID3D12PipelineState> {
+/// 着色器文件由 build.rs 复制到可执行文件旁边，查找顺序见 [`resolve_asset`]。
+pub fn shader_path(name: &str) -> HSTRING {
+    resolve_asset(name).to_str().unwrap().into()
+}
+
+/// 编译 HLSL 文件中的一个入口函数。`defines` 会以 `#define NAME VALUE` 的形式传给编译器，
+/// 这样同一份着色器源码就能通过 `#ifdef` 编译出不同的变体。
+///
+/// 编译失败时，返回的 `Error` 的消息是编译器输出的诊断信息（行号和错误描述）。
+pub fn compile_shader(
+    path: &HSTRING,
+    defines: &[(&str, &str)],
+    entry_point: PCSTR,
+    target: PCSTR,
+) -> Result<ID3DBlob> {
     let compile_flags = if cfg!(debug_assertions) {
         D3DCOMPILE_DEBUG | D3DCOMPILE_SKIP_OPTIMIZATION
     } else {
         0
     };
 
-    let exe_path = std::env::current_exe().ok().unwrap();
-    let asset_path = exe_path.parent().unwrap();
-    let shaders_hlsl_path = asset_path.join("shaders.hlsl");
-    let shaders_hlsl = shaders_hlsl_path.to_str().unwrap();
-    let shaders_hlsl: HSTRING = shaders_hlsl.into();
-    let mut vertex_shader = None;
-    let vertex_shader = unsafe {
+    // D3D_SHADER_MACRO 数组以一对空指针结尾，字符串必须在编译期间一直存活。
+    let define_strings: Vec<(std::ffi::CString, std::ffi::CString)> = defines
+        .iter()
+        .map(|(name, value)| {
+            (
+                std::ffi::CString::new(*name).unwrap(),
+                std::ffi::CString::new(*value).unwrap(),
+            )
+        })
+        .collect();
+    let mut macros: Vec<D3D_SHADER_MACRO> = define_strings
+        .iter()
+        .map(|(name, value)| D3D_SHADER_MACRO {
+            Name: PCSTR(name.as_ptr() as _),
+            Definition: PCSTR(value.as_ptr() as _),
+        })
+        .collect();
+    macros.push(D3D_SHADER_MACRO::default());
+
+    // D3D_COMPILE_STANDARD_FILE_INCLUDE 在 C++ 中是 `(ID3DInclude*)1`，让编译器使用默认的
+    // 文件包含处理，以当前着色器文件所在的目录为基准解析 `#include`。
+    // ID3DInclude 不是 COM 接口，没有引用计数，所以直接用这个哨兵指针构造它是安全的。
+    let standard_file_include: ID3DInclude = unsafe { std::mem::transmute(1usize) };
+
+    let mut shader = None;
+    let mut errors: Option<ID3DBlob> = None;
+    unsafe {
         D3DCompileFromFile(
-            &shaders_hlsl,
-            None,
-            None,
-            s!("VSMain"),
-            s!("vs_5_0"),
+            path,
+            Some(macros.as_ptr()),
+            &standard_file_include,
+            entry_point,
+            target,
             compile_flags,
             0,
-            &mut vertex_shader,
-            None,
+            &mut shader,
+            Some(&mut errors),
         )
     }
-    .map(|()| vertex_shader.unwrap())?;
+    .map_err(|e| match &errors {
+        // 错误信息是以 NUL 结尾的 ANSI 字符串
+        Some(errors) => {
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    errors.GetBufferPointer() as *const u8,
+                    errors.GetBufferSize(),
+                )
+            };
+            let message = String::from_utf8_lossy(bytes);
+            Error::new(e.code(), message.trim_end_matches(['\0', '\n']).into())
+        }
+        None => e,
+    })
+    .map(|()| shader.unwrap())
+}
 
-    let mut pixel_shader = None;
-    let pixel_shader = unsafe {
-        D3DCompileFromFile(
-            &shaders_hlsl,
-            None,
-            None,
-            s!("PSMain"),
-            s!("ps_5_0"),
-            compile_flags,
-            0,
-            &mut pixel_shader,
-            None,
+/// FXC 编译出的 DXBC、DXC 编译出的 DXIL，或者从 .cso 文件读出的字节码。创建 PSO 时只需要字节码的地址和长度。
+pub enum CompiledShader {
+    Dxbc(ID3DBlob),
+    Dxil(IDxcBlob),
+    Precompiled(Vec<u8>),
+}
+
+impl CompiledShader {
+    /// 指向 blob 内部的字节码，blob 必须活到 PSO 创建完成。
+    pub fn bytecode(&self) -> D3D12_SHADER_BYTECODE {
+        let (pointer, length) = match self {
+            CompiledShader::Dxbc(blob) => unsafe {
+                (blob.GetBufferPointer() as *const _, blob.GetBufferSize())
+            },
+            CompiledShader::Dxil(blob) => unsafe {
+                (blob.GetBufferPointer() as *const _, blob.GetBufferSize())
+            },
+            CompiledShader::Precompiled(bytes) => (bytes.as_ptr() as *const _, bytes.len()),
+        };
+        D3D12_SHADER_BYTECODE {
+            pShaderBytecode: pointer,
+            BytecodeLength: length,
+        }
+    }
+}
+
+/// 按 `shader_model` 选择编译器：5.x 用 FXC（[`compile_shader`]），6.x 用 DXC（[`compile_shader_dxc`]）。
+/// `stage` 是目标字符串的前缀，例如 `vs`、`ps`。
+pub fn compile_shader_for_model(
+    path: &HSTRING,
+    defines: &[(&str, &str)],
+    entry_point: &str,
+    stage: &str,
+    shader_model: ShaderModel,
+) -> Result<CompiledShader> {
+    let target = shader_model.target(stage);
+    if shader_model.uses_dxc() {
+        compile_shader_dxc(path, defines, entry_point, &target).map(CompiledShader::Dxil)
+    } else {
+        let entry_point = std::ffi::CString::new(entry_point).unwrap();
+        let target = std::ffi::CString::new(target).unwrap();
+        compile_shader(
+            path,
+            defines,
+            PCSTR(entry_point.as_ptr() as _),
+            PCSTR(target.as_ptr() as _),
         )
+        .map(CompiledShader::Dxbc)
     }
-    .map(|()| pixel_shader.unwrap())?;
-
-    let mut input_element_descs: [D3D12_INPUT_ELEMENT_DESC; 2] = [
-        D3D12_INPUT_ELEMENT_DESC {
-            SemanticName: PCSTR(b"POSITION\0".as_ptr()),
-            SemanticIndex: 0,
-            Format: DXGI_FORMAT_R32G32B32_FLOAT,
-            InputSlot: 0,
-            AlignedByteOffset: 0,
-            InputSlotClass: D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA,
-            InstanceDataStepRate: 0,
-        },
-        D3D12_INPUT_ELEMENT_DESC {
-            SemanticName: PCSTR(b"COLOR\0".as_ptr()),
-            SemanticIndex: 0,
-            Format: DXGI_FORMAT_R32G32B32A32_FLOAT,
-            InputSlot: 0,
-            AlignedByteOffset: 12,
-            InputSlotClass: D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA,
-            InstanceDataStepRate: 0,
-        },
-    ];
+}
+
+/// build.rs 在设置了 `PRECOMPILE_SHADERS` 环境变量时，用 fxc 把 shaders.hlsl 中不带任何宏的
+/// `VSMain`、`PSMain` 预编译成这两个文件，放在可执行文件旁边。
+pub const PRECOMPILED_VERTEX_SHADER: &str = "shaders_vs.cso";
+pub const PRECOMPILED_PIXEL_SHADER: &str = "shaders_ps.cso";
+
+/// 读取 `fxc /Fo` 或 `dxc -Fo` 输出的 .cso 文件，内容就是可以直接交给 PSO 的字节码。
+/// DXBC 和 DXIL 都装在以 `DXBC` 开头的容器中，不是这样开头的文件返回错误。
+pub fn load_shader_blob(path: &Path) -> Result<Vec<u8>> {
+    let bytes = std::fs::read(path).map_err(|error| {
+        Error::new(
+            E_FAIL,
+            format!("failed to read {}: {}", path.display(), error).into(),
+        )
+    })?;
+    if !bytes.starts_with(b"DXBC") {
+        return Err(Error::new(
+            E_INVALIDARG,
+            format!("{} is not compiled shader bytecode", path.display()).into(),
+        ));
+    }
+    Ok(bytes)
+}
+
+/// 调用方选择了 `prefer_precompiled`、没有宏、使用默认的着色器模型时，如果预编译的两个 .cso 文件都存在
+/// （`resolve` 给出它们的路径）就直接加载它们，省去启动时的编译；否则返回 `None`，由调用方在运行时编译。
+/// 着色器的变体（`#ifdef`）和 SM6 总是在运行时编译。
+fn load_precompiled_shaders(
+    defines: &[(&str, &str)],
+    shader_model: ShaderModel,
+    prefer_precompiled: bool,
+    resolve: impl Fn(&str) -> PathBuf,
+) -> Result<Option<(CompiledShader, CompiledShader)>> {
+    if !prefer_precompiled || !defines.is_empty() || shader_model != ShaderModel::default() {
+        return Ok(None);
+    }
+    let vertex_shader = resolve(PRECOMPILED_VERTEX_SHADER);
+    let pixel_shader = resolve(PRECOMPILED_PIXEL_SHADER);
+    if !vertex_shader.exists() || !pixel_shader.exists() {
+        return Ok(None);
+    }
+    Ok(Some((
+        CompiledShader::Precompiled(load_shader_blob(&vertex_shader)?),
+        CompiledShader::Precompiled(load_shader_blob(&pixel_shader)?),
+    )))
+}
+
+/// ID3D12PipelineState 对象集合了大量的流水线状态信息。为了保证性能，我们将所有这些对
+/// 象都集总在一起，一并送至渲染流水线。通过这样的一个集合，Direct3D 便可以确定所有的状态是否彼
+/// 此兼容，而驱动程序则能够据此而提前生成硬件本地指令及其状态。在 Direct3D 11 的状态模型中，这些
+/// 渲染状态片段都是要分开配置的。然而这些状态实际都有一定的联系，以致如果其中的一个状态发生改
+/// 变，那么驱动程序可能就要为了另一个相关的独立状态而对硬件重新进行编程。由于一些状态在配置流
+/// 水线时需要改变，因而硬件状态也就可能被频繁地改写。为了避免这些冗余的操作，驱动程序往往会推
+/// 迟针对硬件状态的编程动作，直到明确整条流水线的状态发起绘制调用后，才正式生成对应的本地指令
+/// 与状态。但是，这种延迟操作需要驱动在运行时进行额外的记录工作，即追踪状态的变化，而后才能在
+/// 运行时生成改写硬件状态的本地代码。在 Direct3D 12 的新模型中，驱动程序可以在初始化期间生成对流
+/// 水线状态编程的全部代码，这便是我们将大多数的流水线状态指定为一个集合所带来的好处。
+///
+/// `cull_mode` 为 `BACK` 时，光栅器按 `coordinate_system` 约定的环绕顺序判断正面：左手系以顺时针为正面，
+/// 右手系以逆时针为正面，背面的三角形在光栅化之前就被丢弃，封闭的网格因此少画大约一半的像素。
+/// 几何体的环绕顺序必须与之相符，否则剔除掉的恰好是朝向观察者的那一面，整个物体看上去消失或只剩内侧。
+/// 怀疑环绕顺序有问题时可以先改用 `NONE`。
+///
+/// `prefer_precompiled` 为 `true` 时允许使用构建时预编译的 .cso（见 [`PRECOMPILED_VERTEX_SHADER`]），
+/// 它们来自构建时的 shaders.hlsl，之后对源文件的修改不会反映在其中，所以热重载等需要最新源码的地方必须传 `false`。
+#[allow(clippy::too_many_arguments)]
+pub fn create_pipeline_state<L: InputLayout>(
+    device: &ID3D12Device,
+    root_signature: &ID3D12RootSignature,
+    defines: &[(&str, &str)],
+    shader_model: ShaderModel,
+    prefer_precompiled: bool,
+    coordinate_system: CoordinateSystem,
+    fill_mode: D3D12_FILL_MODE,
+    cull_mode: D3D12_CULL_MODE,
+    blend_mode: BlendMode,
+    stencil_mode: StencilMode,
+    targets: &RenderTargetFormats,
+    verbose: bool,
+) -> Result<ID3D12PipelineState> {
+    // 字节码要一直存活到 PSO 创建完成，desc 中只有指向它们的指针
+    let (vertex_shader, pixel_shader) =
+        match load_precompiled_shaders(defines, shader_model, prefer_precompiled, resolve_asset)? {
+            Some(shaders) => {
+                if verbose {
+                    println!(
+                        "pso: using precompiled {} and {}",
+                        PRECOMPILED_VERTEX_SHADER, PRECOMPILED_PIXEL_SHADER
+                    );
+                }
+                shaders
+            }
+            None => {
+                let shaders_hlsl = shader_path("shaders.hlsl");
+                (
+                    compile_shader_for_model(&shaders_hlsl, defines, "VSMain", "vs", shader_model)?,
+                    compile_shader_for_model(&shaders_hlsl, defines, "PSMain", "ps", shader_model)?,
+                )
+            }
+        };
+
+    let input_layout = L::input_layout();
 
     // 大多数控制图形流水线状态的对象被统称为流水线状态对象（Pipeline State Object，PSO），用 ID3D12PipelineState 接口来表示。
     // 要创建 PSO，我们首先要填写一份描述其细节的 D3D12_GRAPHICS_PIPELINE_STATE_DESC 结构体实例。
     let mut desc = D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+        // 顶点缓冲区的布局由 L 给出（实例化时还包括第 1 个槽中的实例数据），要与顶点着色器的输入相符。
         InputLayout: D3D12_INPUT_LAYOUT_DESC {
-            pInputElementDescs: input_element_descs.as_mut_ptr(),
-            NumElements: input_element_descs.len() as u32,
+            pInputElementDescs: input_layout.as_ptr(),
+            NumElements: input_layout.len() as u32,
         },
         // 指向一个与此 PSO 相绑定的根签名的指针。该根签名一定要与此 PSO 指定的着色器相兼容。
         pRootSignature: Some(root_signature.clone()),
         // 待绑定的顶点着色器。此成员由结构体 D3D12_SHADER_BYTECODE 表示，这个结构体存
         // 有指向已编译好的字节码数据的指针，以及该字节码数据所占的字节大小。
-        VS: D3D12_SHADER_BYTECODE {
-            pShaderBytecode: unsafe { vertex_shader.GetBufferPointer() },
-            BytecodeLength: unsafe { vertex_shader.GetBufferSize() },
-        },
+        VS: vertex_shader.bytecode(),
         // 待绑定的像素着色器
-        PS: D3D12_SHADER_BYTECODE {
-            pShaderBytecode: unsafe { pixel_shader.GetBufferPointer() },
-            BytecodeLength: unsafe { pixel_shader.GetBufferSize() },
-        },
+        PS: pixel_shader.bytecode(),
         // 指定用来配置光栅器的光栅化状态。
+        // 正面的环绕顺序由坐标系约定决定，剔除哪一面由调用方选择。
+        // 填充模式为 WIREFRAME 时只绘制三角形的边。
         RasterizerState: D3D12_RASTERIZER_DESC {
-            FillMode: D3D12_FILL_MODE_SOLID,
-            CullMode: D3D12_CULL_MODE_NONE,
+            FillMode: fill_mode,
+            CullMode: cull_mode,
+            FrontCounterClockwise: coordinate_system.front_counter_clockwise().into(),
             ..Default::default()
         },
         // 指定混合（blending）操作所用的混合状态。
-        BlendState: D3D12_BLEND_DESC {
-            AlphaToCoverageEnable: false.into(),
-            IndependentBlendEnable: false.into(),
-            RenderTarget: [
-                D3D12_RENDER_TARGET_BLEND_DESC {
-                    BlendEnable: false.into(),
-                    LogicOpEnable: false.into(),
-                    SrcBlend: D3D12_BLEND_ONE,
-                    DestBlend: D3D12_BLEND_ZERO,
-                    BlendOp: D3D12_BLEND_OP_ADD,
-                    SrcBlendAlpha: D3D12_BLEND_ONE,
-                    DestBlendAlpha: D3D12_BLEND_ZERO,
-                    BlendOpAlpha: D3D12_BLEND_OP_ADD,
-                    LogicOp: D3D12_LOGIC_OP_NOOP,
-                    RenderTargetWriteMask: D3D12_COLOR_WRITE_ENABLE_ALL.0 as u8,
-                },
-                D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                D3D12_RENDER_TARGET_BLEND_DESC::default(),
-            ],
-        },
+        BlendState: blend_mode.blend_desc(),
         // 指定用于配置深度/模板测试的深度/模板状态。
-        DepthStencilState: D3D12_DEPTH_STENCIL_DESC::default(),
+        // 有深度缓冲区（dsv_format 不是 UNKNOWN）时开启深度测试：离观察者更近的像素才能通过并写入深度，
+        // 一般是深度值更小的，反转 Z 时则是更大的。
+        // 半透明的几何体仍做深度测试，但不写入深度，这样相互重叠的半透明物体都能混合上去。
+        // 模板测试由 stencil_mode 决定，读写全部 8 位，正面和背面使用同样的操作。
+        DepthStencilState: D3D12_DEPTH_STENCIL_DESC {
+            DepthEnable: (targets.dsv_format != DXGI_FORMAT_UNKNOWN).into(),
+            DepthWriteMask: if blend_mode.is_translucent() {
+                D3D12_DEPTH_WRITE_MASK_ZERO
+            } else {
+                D3D12_DEPTH_WRITE_MASK_ALL
+            },
+            DepthFunc: targets.depth_mode.comparison_func(),
+            StencilEnable: stencil_mode.is_enabled().into(),
+            StencilReadMask: D3D12_DEFAULT_STENCIL_READ_MASK as u8,
+            StencilWriteMask: D3D12_DEFAULT_STENCIL_WRITE_MASK as u8,
+            FrontFace: stencil_mode.face_desc(),
+            BackFace: stencil_mode.face_desc(),
+        },
+        // 深度/模板缓冲区的格式，必须与绘制时绑定的深度/模板视图相匹配。
+        DSVFormat: targets.dsv_format,
         // 多重采样最多可采集 32 个样本。借此参数的 32 位整数值，即可设置每个采样点的采集情况（采集或禁止采集）。
         // 例如，若禁用了第 5 位（将第 5 位设置为 0），则将不会对第 5 个样本进行采样。当然，要禁止采集第 5 个样本的前提是，
         // 所用的多重采样至少要有 5个样本。假如一个应用程序仅使用了单采样（single sampling），那么只能针对该参数的第 1 位
@@ -284,16 +802,159 @@ pub fn create_pipeline_state(
         // 指定图元的拓扑类型。
         PrimitiveTopologyType: D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
         // 同时所用的渲染目标数量（即 RTVFormats 数组中渲染目标格式的数量）。
-        NumRenderTargets: 1,
+        NumRenderTargets: targets.num_render_targets,
         // 描述多重采样对每个像素采样的数量及其质量级别。此参数应与渲染目标的对应设置相匹配。
-        SampleDesc: DXGI_SAMPLE_DESC {
-            Count: 1,
-            ..Default::default()
-        },
+        SampleDesc: targets.sample_desc,
         ..Default::default()
     };
     // 渲染目标的格式。利用该数组实现向多渲染目标同时进行写操作。使用此 PSO 的渲染目标的格式设定应当与此参数相匹配。
-    desc.RTVFormats[0] = DXGI_FORMAT_R8G8B8A8_UNORM;
+    desc.RTVFormats = targets.rtv_formats;
+
+    create_graphics_pipeline_state(device, &desc, verbose)
+}
+
+#[test]
+fn shader_compile_error_carries_diagnostics() {
+    let path = std::env::temp_dir().join("hello_triangle_broken_shader.hlsl");
+    std::fs::write(
+        &path,
+        "float4 PSMain() : SV_TARGET { return undeclared; }\n",
+    )
+    .unwrap();
+    let error = compile_shader(
+        &path.to_str().unwrap().into(),
+        &[],
+        s!("PSMain"),
+        s!("ps_5_0"),
+    )
+    .unwrap_err();
+    let _ = std::fs::remove_file(&path);
+
+    let message = error.message().to_string();
+    assert!(message.contains("undeclared"), "{}", message);
+    assert!(crate::explain_error(&error).contains(&message));
+}
+
+#[test]
+fn shader_blob_must_be_a_dxbc_container() {
+    let path = std::env::temp_dir().join("hello_triangle_shader.cso");
+    std::fs::write(&path, b"DXBC\0\0\0\0").unwrap();
+    assert_eq!(load_shader_blob(&path).unwrap().len(), 8);
+    std::fs::write(&path, b"float4 PSMain()").unwrap();
+    assert_eq!(load_shader_blob(&path).unwrap_err().code(), E_INVALIDARG);
+    let _ = std::fs::remove_file(&path);
+    assert!(load_shader_blob(&path).is_err());
+}
+
+#[test]
+fn precompiled_shaders_are_opt_in() {
+    let dir = std::env::temp_dir().join("hello_triangle_precompiled");
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in [PRECOMPILED_VERTEX_SHADER, PRECOMPILED_PIXEL_SHADER] {
+        std::fs::write(dir.join(name), b"DXBC\0\0\0\0").unwrap();
+    }
+    let resolve = |name: &str| dir.join(name);
+    let load = |defines: &[(&str, &str)], prefer_precompiled| {
+        load_precompiled_shaders(defines, ShaderModel::default(), prefer_precompiled, resolve)
+            .unwrap()
+            .is_some()
+    };
+    assert!(load(&[], true));
+    // 热重载传 false：即使 .cso 存在也要重新编译修改后的 shaders.hlsl
+    assert!(!load(&[], false));
+    assert!(!load(&[("CHECKERBOARD", "1")], true));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn missing_back_buffer_support_is_named() {
+    assert!(missing_back_buffer_support(
+        D3D12_FORMAT_SUPPORT1_RENDER_TARGET | D3D12_FORMAT_SUPPORT1_DISPLAY
+    )
+    .is_empty());
+    assert_eq!(
+        missing_back_buffer_support(D3D12_FORMAT_SUPPORT1_RENDER_TARGET),
+        ["display"]
+    );
+    assert_eq!(
+        missing_back_buffer_support(D3D12_FORMAT_SUPPORT1_NONE),
+        ["render target", "display"]
+    );
+}
+
+#[test]
+fn warp_reports_its_feature_level() {
+    assert_eq!(feature_level_name(D3D_FEATURE_LEVEL_11_0), "11_0");
+    assert_eq!(feature_level_name(D3D_FEATURE_LEVEL_12_1), "12_1");
+
+    let device = warp_device();
+    // WARP 至少支持 12_0
+    assert!(max_feature_level(&device).0 >= D3D_FEATURE_LEVEL_12_0.0);
+}
+
+#[test]
+fn warp_answers_typed_feature_queries() {
+    let device = warp_device();
+
+    let query = FeatureQuery::Architecture(D3D12_FEATURE_DATA_ARCHITECTURE {
+        NodeIndex: 0,
+        ..Default::default()
+    });
+    let Ok(FeatureQuery::Architecture(architecture)) = query_feature(&device, query) else {
+        panic!("architecture query failed");
+    };
+    // WARP 在 CPU 上运行，显存就是系统内存
+    assert!(architecture.UMA.as_bool());
 
-    unsafe { device.CreateGraphicsPipelineState(&desc) }
+    let query = FeatureQuery::Options(Default::default());
+    let Ok(FeatureQuery::Options(options)) = query_feature(&device, query) else {
+        panic!("options query failed");
+    };
+    // 功能级别 11_0 以上的设备至少是资源绑定层级 1
+    assert!(options.ResourceBindingTier.0 >= D3D12_RESOURCE_BINDING_TIER_1.0);
+}
+
+#[test]
+fn root_signature_serializes_with_both_versions() {
+    let device = warp_device();
+    let ranges = [D3D12_DESCRIPTOR_RANGE {
+        RangeType: D3D12_DESCRIPTOR_RANGE_TYPE_SRV,
+        NumDescriptors: 1,
+        BaseShaderRegister: 0,
+        RegisterSpace: 0,
+        OffsetInDescriptorsFromTableStart: D3D12_DESCRIPTOR_RANGE_OFFSET_APPEND,
+    }];
+    // 三种根参数各一个：根常量、根描述符和描述符表
+    let parameters = [
+        time_root_constant(),
+        object_constants_root_descriptor(),
+        D3D12_ROOT_PARAMETER {
+            ParameterType: D3D12_ROOT_PARAMETER_TYPE_DESCRIPTOR_TABLE,
+            Anonymous: D3D12_ROOT_PARAMETER_0 {
+                DescriptorTable: D3D12_ROOT_DESCRIPTOR_TABLE {
+                    NumDescriptorRanges: ranges.len() as u32,
+                    pDescriptorRanges: ranges.as_ptr(),
+                },
+            },
+            ShaderVisibility: D3D12_SHADER_VISIBILITY_PIXEL,
+        },
+    ];
+    let desc = D3D12_ROOT_SIGNATURE_DESC {
+        NumParameters: parameters.len() as u32,
+        pParameters: parameters.as_ptr(),
+        Flags: D3D12_ROOT_SIGNATURE_FLAG_ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT,
+        ..Default::default()
+    };
+
+    // WARP 支持 1.1
+    assert_eq!(
+        root_signature_version(&device),
+        D3D_ROOT_SIGNATURE_VERSION_1_1
+    );
+    for version in [
+        D3D_ROOT_SIGNATURE_VERSION_1_0,
+        D3D_ROOT_SIGNATURE_VERSION_1_1,
+    ] {
+        create_root_signature_with_version(&device, &desc, version).unwrap();
+    }
 }
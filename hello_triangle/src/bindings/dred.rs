@@ -0,0 +1,133 @@
+use crate::explain_error;
+use windows::{core::*, Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::*};
+
+/// 开启 DRED（Device Removed Extended Data）的自动面包屑和页面错误报告，必须在创建设备之前调用。
+///
+/// 自动面包屑会记录每个命令列表中的操作以及 GPU 执行到了哪一个，设备被移除后据此就能知道
+/// GPU 卡在了哪条命令上；页面错误报告则记录 GPU 访问了哪个已释放或不存在的资源。
+/// 系统不支持 DRED（Windows 10 1809 之前）时什么也不做。
+pub fn enable_dred() {
+    let mut settings: Option<ID3D12DeviceRemovedExtendedDataSettings> = None;
+    if let Some(settings) = unsafe { D3D12GetDebugInterface(&mut settings) }
+        .ok()
+        .and(settings)
+    {
+        unsafe {
+            settings.SetAutoBreadcrumbsEnablement(D3D12_DRED_ENABLEMENT_FORCED_ON);
+            settings.SetPageFaultEnablement(D3D12_DRED_ENABLEMENT_FORCED_ON);
+        }
+    }
+}
+
+/// 这些错误码表示设备已经丢失，之后对它的任何调用都会失败。
+pub fn is_device_lost(error: &Error) -> bool {
+    matches!(
+        error.code(),
+        DXGI_ERROR_DEVICE_REMOVED | DXGI_ERROR_DEVICE_RESET | DXGI_ERROR_DEVICE_HUNG
+    )
+}
+
+fn breadcrumb_op_name(op: D3D12_AUTO_BREADCRUMB_OP) -> String {
+    let name = match op {
+        D3D12_AUTO_BREADCRUMB_OP_SETMARKER => "SetMarker",
+        D3D12_AUTO_BREADCRUMB_OP_BEGINEVENT => "BeginEvent",
+        D3D12_AUTO_BREADCRUMB_OP_ENDEVENT => "EndEvent",
+        D3D12_AUTO_BREADCRUMB_OP_DRAWINSTANCED => "DrawInstanced",
+        D3D12_AUTO_BREADCRUMB_OP_DRAWINDEXEDINSTANCED => "DrawIndexedInstanced",
+        D3D12_AUTO_BREADCRUMB_OP_EXECUTEINDIRECT => "ExecuteIndirect",
+        D3D12_AUTO_BREADCRUMB_OP_DISPATCH => "Dispatch",
+        D3D12_AUTO_BREADCRUMB_OP_COPYBUFFERREGION => "CopyBufferRegion",
+        D3D12_AUTO_BREADCRUMB_OP_COPYTEXTUREREGION => "CopyTextureRegion",
+        D3D12_AUTO_BREADCRUMB_OP_COPYRESOURCE => "CopyResource",
+        D3D12_AUTO_BREADCRUMB_OP_RESOLVESUBRESOURCE => "ResolveSubresource",
+        D3D12_AUTO_BREADCRUMB_OP_CLEARRENDERTARGETVIEW => "ClearRenderTargetView",
+        D3D12_AUTO_BREADCRUMB_OP_CLEARUNORDEREDACCESSVIEW => "ClearUnorderedAccessView",
+        D3D12_AUTO_BREADCRUMB_OP_CLEARDEPTHSTENCILVIEW => "ClearDepthStencilView",
+        D3D12_AUTO_BREADCRUMB_OP_RESOURCEBARRIER => "ResourceBarrier",
+        D3D12_AUTO_BREADCRUMB_OP_EXECUTEBUNDLE => "ExecuteBundle",
+        D3D12_AUTO_BREADCRUMB_OP_PRESENT => "Present",
+        D3D12_AUTO_BREADCRUMB_OP_BEGINSUBMISSION => "BeginSubmission",
+        D3D12_AUTO_BREADCRUMB_OP_ENDSUBMISSION => "EndSubmission",
+        _ => return format!("op {}", op.0),
+    };
+    name.to_string()
+}
+
+/// 把 DRED 给出的可能为空的宽字符串名字转换为 `String`。
+fn debug_name(name: PCWSTR) -> String {
+    if name.is_null() {
+        "<unnamed>".to_string()
+    } else {
+        unsafe { name.to_string() }.unwrap_or_default()
+    }
+}
+
+/// 设备已被移除时，打印移除的原因、DRED 记录的每个命令列表的操作以及 GPU 执行到的位置，
+/// 还有页面错误的地址和相关的资源；设备正常时什么也不做。
+///
+/// 标记为 `<-` 的操作是 GPU 最后开始执行但没有完成的那个，通常就是导致超时或错误的命令。
+pub fn log_device_removed(device: &ID3D12Device) {
+    let Err(reason) = (unsafe { device.GetDeviceRemovedReason() }) else {
+        return;
+    };
+    eprintln!("device removed: {}", explain_error(&reason));
+
+    let Ok(dred) = device.cast::<ID3D12DeviceRemovedExtendedData>() else {
+        eprintln!("DRED is not available on this system");
+        return;
+    };
+
+    match unsafe { dred.GetAutoBreadcrumbsOutput() } {
+        Ok(output) => {
+            let mut node = output.pHeadAutoBreadcrumbNode;
+            while let Some(current) = unsafe { node.as_ref() } {
+                let completed = unsafe { current.pLastBreadcrumbValue.as_ref() }
+                    .copied()
+                    .unwrap_or(0);
+                // 所有操作都已完成的命令列表与这次错误无关
+                if completed < current.BreadcrumbCount {
+                    eprintln!(
+                        "command list {} on queue {}: {} of {} operation(s) completed",
+                        debug_name(current.pCommandListDebugNameW),
+                        debug_name(current.pCommandQueueDebugNameW),
+                        completed,
+                        current.BreadcrumbCount
+                    );
+                    let history = unsafe {
+                        std::slice::from_raw_parts(
+                            current.pCommandHistory,
+                            current.BreadcrumbCount as usize,
+                        )
+                    };
+                    for (i, op) in history.iter().enumerate() {
+                        let marker = if i as u32 == completed { " <-" } else { "" };
+                        eprintln!("  [{}] {}{}", i, breadcrumb_op_name(*op), marker);
+                    }
+                }
+                node = current.pNext;
+            }
+        }
+        Err(e) => eprintln!("no DRED breadcrumbs: {}", explain_error(&e)),
+    }
+
+    if let Ok(page_fault) = unsafe { dred.GetPageFaultAllocationOutput() } {
+        if page_fault.PageFaultVA != 0 {
+            eprintln!("page fault at GPU VA {:#x}", page_fault.PageFaultVA);
+            for (label, head) in [
+                ("existing", page_fault.pHeadExistingAllocationNode),
+                ("recently freed", page_fault.pHeadRecentFreedAllocationNode),
+            ] {
+                let mut node = head;
+                while let Some(allocation) = unsafe { node.as_ref() } {
+                    eprintln!(
+                        "  {} allocation: {} (type {})",
+                        label,
+                        debug_name(allocation.ObjectNameW),
+                        allocation.AllocationType.0
+                    );
+                    node = allocation.pNext;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::OnceLock;
+use windows::{
+    core::*, Win32::Foundation::*, Win32::Graphics::Direct3D::Dxc::*,
+    Win32::System::LibraryLoader::*,
+};
+
+/// `DxcCreateInstance` 的函数签名。
+type DxcCreateInstanceFn =
+    unsafe extern "system" fn(*const GUID, *const GUID, *mut *mut std::ffi::c_void) -> HRESULT;
+
+/// 在运行时加载 `dxcompiler.dll` 并找到 `DxcCreateInstance`。
+///
+/// DXC 不是系统组件，需要从 Windows SDK 或 DirectXShaderCompiler 的发布包中把 `dxcompiler.dll`
+/// 和 `dxil.dll` 一起放到可执行文件旁边：没有 `dxil.dll` 时编译出的 DXIL 没有签名，创建 PSO 时会被拒绝。
+/// 这里不在链接时导入它，否则缺少这个 DLL 时程序根本无法启动，哪怕只用 FXC。
+///
+/// 只在第一次调用时加载，之后复用同一个函数指针。DLL 一直留在进程中，不调用 `FreeLibrary`，
+/// 否则已经取得的函数指针和 DXC 创建的对象都会失效。加载失败的结果也会被记住，不会每次都重试。
+fn dxc_create_instance_fn() -> Result<DxcCreateInstanceFn> {
+    static CREATE_INSTANCE: OnceLock<std::result::Result<DxcCreateInstanceFn, HRESULT>> =
+        OnceLock::new();
+    let create_instance =
+        CREATE_INSTANCE.get_or_init(|| load_dxc_create_instance_fn().map_err(|e| e.code()));
+    create_instance.map_err(|code| {
+        Error::new(
+            code,
+            "dxcompiler.dll not found, copy dxcompiler.dll and dxil.dll next to the executable"
+                .into(),
+        )
+    })
+}
+
+fn load_dxc_create_instance_fn() -> Result<DxcCreateInstanceFn> {
+    let module = unsafe { LoadLibraryA(s!("dxcompiler.dll")) }?;
+    let proc =
+        unsafe { GetProcAddress(module, s!("DxcCreateInstance")) }.ok_or_else(Error::from_win32)?;
+    Ok(unsafe {
+        std::mem::transmute::<unsafe extern "system" fn() -> isize, DxcCreateInstanceFn>(proc)
+    })
+}
+
+fn dxc_create_instance<T: Interface>(clsid: &GUID) -> Result<T> {
+    let create_instance = dxc_create_instance_fn()?;
+    let mut instance = None;
+    unsafe { create_instance(clsid, &T::IID, &mut instance as *mut _ as *mut _) }.and_some(instance)
+}
+
+/// 能否加载 DXC，不能时 `-shadermodel 6_0` 会在编译着色器时失败。
+pub fn dxc_available() -> bool {
+    dxc_create_instance_fn().is_ok()
+}
+
+/// 用 DXC 编译 HLSL 文件中的一个入口函数，`target` 是 `vs_6_0` 这样的目标字符串，得到 DXIL 字节码。
+/// `defines` 与 FXC 的 [`compile_shader`](crate::devices::compile_shader) 一样以 `-D NAME=VALUE` 传给编译器，
+/// `#include` 相对于着色器文件所在的目录解析。
+///
+/// 编译失败时，返回的 `Error` 的消息是编译器输出的诊断信息。
+pub fn compile_shader_dxc(
+    path: &HSTRING,
+    defines: &[(&str, &str)],
+    entry_point: &str,
+    target: &str,
+) -> Result<IDxcBlob> {
+    // IDxcUtils 与旧的 IDxcLibrary 共用同一个 CLSID
+    let utils: IDxcUtils = dxc_create_instance(&CLSID_DxcLibrary)?;
+    let compiler: IDxcCompiler3 = dxc_create_instance(&CLSID_DxcCompiler)?;
+
+    let source = unsafe { utils.LoadFile(path, None) }?;
+    let source_buffer = DxcBuffer {
+        Ptr: unsafe { source.GetBufferPointer() },
+        Size: unsafe { source.GetBufferSize() },
+        // shaders.hlsl 的注释里有中文，按 UTF-8 读取
+        Encoding: DXC_CP_UTF8.0,
+    };
+
+    // 参数和宏都是宽字符串指针，指向的 HSTRING 必须在编译期间一直存活
+    let include_dir = std::path::Path::new(&path.to_string_lossy())
+        .parent()
+        .map(|dir| HSTRING::from(dir.to_string_lossy().as_ref()))
+        .unwrap_or_default();
+    let mut arguments = vec![HSTRING::from("-I"), include_dir];
+    if cfg!(debug_assertions) {
+        // 与 FXC 的 D3DCOMPILE_DEBUG | D3DCOMPILE_SKIP_OPTIMIZATION 相同，调试信息嵌入到字节码中
+        arguments.extend(["-Zi", "-Qembed_debug", "-Od"].map(HSTRING::from));
+    }
+    let argument_ptrs: Vec<PWSTR> = arguments
+        .iter()
+        .map(|argument| PWSTR(argument.as_ptr() as *mut _))
+        .collect();
+    let define_strings: Vec<(HSTRING, HSTRING)> = defines
+        .iter()
+        .map(|(name, value)| (HSTRING::from(*name), HSTRING::from(*value)))
+        .collect();
+    let dxc_defines: Vec<DxcDefine> = define_strings
+        .iter()
+        .map(|(name, value)| DxcDefine {
+            Name: name.into(),
+            Value: value.into(),
+        })
+        .collect();
+    let compiler_args = unsafe {
+        utils.BuildArguments(
+            path,
+            &HSTRING::from(entry_point),
+            &HSTRING::from(target),
+            Some(&argument_ptrs),
+            &dxc_defines,
+        )
+    }?;
+    let compiler_arguments = unsafe {
+        std::slice::from_raw_parts(
+            compiler_args.GetArguments(),
+            compiler_args.GetCount() as usize,
+        )
+    };
+
+    let include_handler = unsafe { utils.CreateDefaultIncludeHandler() }?;
+    let mut result: Option<IDxcResult> = None;
+    unsafe {
+        compiler.Compile(
+            &source_buffer,
+            Some(compiler_arguments),
+            &include_handler,
+            &IDxcResult::IID,
+            &mut result as *mut _ as *mut _,
+        )
+    }?;
+    let result = result.unwrap();
+
+    // 编译本身的失败体现在状态里，Compile 只在无法运行编译器时返回错误
+    let status = unsafe { result.GetStatus() }?;
+    if status.is_err() {
+        let mut errors: Option<IDxcBlobUtf8> = None;
+        unsafe { result.GetOutput(DXC_OUT_ERRORS, std::ptr::null_mut(), &mut errors) }?;
+        let message = errors
+            .map(|errors| unsafe {
+                let bytes = std::slice::from_raw_parts(
+                    errors.GetStringPointer().0,
+                    errors.GetStringLength(),
+                );
+                String::from_utf8_lossy(bytes).trim_end().to_string()
+            })
+            .unwrap_or_default();
+        return Err(Error::new(status, message.into()));
+    }
+
+    let mut object: Option<IDxcBlob> = None;
+    unsafe { result.GetOutput(DXC_OUT_OBJECT, std::ptr::null_mut(), &mut object) }?;
+    object.ok_or_else(|| Error::new(E_FAIL, "DXC produced no shader object".into()))
+}
+
+#[test]
+fn dxc_compiles_shader_model_6() {
+    if !dxc_available() {
+        eprintln!("dxcompiler.dll is not available, skipping");
+        return;
+    }
+    // WaveActiveSum 只有 6.0 及以上的着色器模型才有
+    let path = std::env::temp_dir().join("hello_triangle_wave_shader.hlsl");
+    std::fs::write(
+        &path,
+        "float4 PSMain(float4 position : SV_POSITION) : SV_TARGET \
+         { return WaveActiveSum(position.x) * VALUE; }\n",
+    )
+    .unwrap();
+    let path: HSTRING = path.to_str().unwrap().into();
+    let shader = compile_shader_dxc(&path, &[("VALUE", "0.5")], "PSMain", "ps_6_0").unwrap();
+    assert_ne!(unsafe { shader.GetBufferSize() }, 0);
+
+    let error = compile_shader_dxc(&path, &[], "PSMain", "ps_6_0").unwrap_err();
+    assert!(error.message().to_string().contains("VALUE"), "{}", error);
+    let _ = std::fs::remove_file(path.to_string_lossy());
+}
@@ -0,0 +1,147 @@
+use windows::{
+    core::*, Win32::Foundation::*, Win32::Graphics::Direct3D12::*, Win32::System::Threading::*,
+    Win32::System::WindowsProgramming::*,
+};
+
+/// 围栏值。
+///
+/// 一个围栏上的值必须严格单调递增：CPU 通过 `Signal` 让 GPU 在执行到某处时把围栏设为新值，
+/// 再用 `GetCompletedValue` 得到的值与之比较，判断 GPU 是否已经越过这个点。
+/// 内部的 `u64` 不对外暴露，新的值只能通过 [`FenceValue::next`] 得到，这样“要 Signal 的值”和
+/// “已经 Signal 过的值”就不会被混用，也就无法写出让围栏值倒退的代码。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FenceValue(u64);
+
+impl FenceValue {
+    /// 围栏创建时的初始值，对应 `CreateFence(0, ..)`。
+    pub const INITIAL: FenceValue = FenceValue(0);
+
+    /// 紧随其后的下一个围栏值，用于下一次 `Signal`。
+    pub fn next(self) -> FenceValue {
+        FenceValue(self.0.checked_add(1).expect("fence value overflow"))
+    }
+
+    /// 传给 D3D12 API 的原始值。
+    pub fn get(self) -> u64 {
+        self.0
+    }
+
+    /// GPU 已完成的围栏值（`GetCompletedValue`）是否已经达到这个值。
+    pub fn is_completed(self, completed_value: u64) -> bool {
+        completed_value >= self.0
+    }
+}
+
+/// 一个队列专用的围栏：`ID3D12Fence`、等待它时使用的事件，以及最近一次 Signal 的值。
+///
+/// 事件只创建一次，每次等待都复用它，释放时一并关闭。
+pub struct Fence {
+    fence: ID3D12Fence,
+    event: HANDLE,
+    /// 最近一次 Signal 的围栏值。
+    value: FenceValue,
+}
+
+impl Fence {
+    pub fn new(device: &ID3D12Device) -> Result<Self> {
+        let fence =
+            unsafe { device.CreateFence(FenceValue::INITIAL.get(), D3D12_FENCE_FLAG_NONE) }?;
+        // windows-rs 会检查返回的句柄，创建失败（空句柄或 INVALID_HANDLE_VALUE）时返回 GetLastError 对应的错误，
+        // 这里用 ? 把它传出去，而不是留下一个无效的事件让之后的等待悄无声息地出错。
+        let event = unsafe { CreateEventA(None, false, false, None) }?;
+        Ok(Fence {
+            fence,
+            event,
+            value: FenceValue::INITIAL,
+        })
+    }
+
+    pub fn fence(&self) -> &ID3D12Fence {
+        &self.fence
+    }
+
+    /// 最近一次 Signal 的围栏值，还没有 Signal 过时为 [`FenceValue::INITIAL`]。
+    pub fn last_signaled(&self) -> FenceValue {
+        self.value
+    }
+
+    /// 向队列中添加一条把围栏设为下一个值的命令，返回这个值。
+    ///
+    /// 这条命令由 GPU 执行，所以在 GPU 处理完队列中此前的所有命令之前，围栏不会到达这个值。
+    pub fn signal(&mut self, queue: &ID3D12CommandQueue) -> Result<FenceValue> {
+        let value = self.value.next();
+        unsafe { queue.Signal(&self.fence, value.get()) }?;
+        self.value = value;
+        Ok(value)
+    }
+
+    /// 在 CPU 端阻塞，直到 GPU 把围栏推进到 `value`；已经到达时立即返回。
+    pub fn wait(&self, value: FenceValue) -> Result<()> {
+        if value.is_completed(unsafe { self.fence.GetCompletedValue() }) {
+            return Ok(());
+        }
+        // GPU 执行到对应的 Signal 时激发事件
+        unsafe { self.fence.SetEventOnCompletion(value.get(), self.event) }?;
+        // 等待失败（例如事件句柄无效）时立即报错，否则会被当成 GPU 已经完成
+        if unsafe { WaitForSingleObject(self.event, INFINITE) } != WAIT_OBJECT_0 {
+            return Err(Error::from_win32());
+        }
+        Ok(())
+    }
+
+    /// Signal 一个新的值并等待 GPU 执行到它，此后队列中此前提交的命令都已完成。
+    pub fn flush(&mut self, queue: &ID3D12CommandQueue) -> Result<()> {
+        let value = self.signal(queue)?;
+        self.wait(value)
+    }
+}
+
+impl Drop for Fence {
+    /// 关闭事件句柄。围栏本身不知道哪些队列还会 Signal 它，等待 GPU 空闲由持有队列的一方负责，
+    /// 参见 `Resources` 的 `Drop`。
+    fn drop(&mut self) {
+        if self.event.is_invalid() {
+            return;
+        }
+        let closed = unsafe { CloseHandle(self.event) };
+        debug_assert!(closed.as_bool(), "CloseHandle on the fence event failed");
+    }
+}
+
+#[test]
+fn fence_value_ordering() {
+    let initial = FenceValue::INITIAL;
+    let first = initial.next();
+    let second = first.next();
+    assert_eq!(initial.get(), 0);
+    assert_eq!(first.get(), 1);
+    assert!(initial < first && first < second);
+    assert_eq!(first, FenceValue::INITIAL.next());
+
+    assert!(initial.is_completed(0));
+    assert!(!first.is_completed(0));
+    assert!(first.is_completed(1));
+    assert!(first.is_completed(2));
+    assert!(!second.is_completed(1));
+}
+
+#[test]
+fn signaled_fence_is_waited_promptly() {
+    use crate::devices::warp_device_and_queue;
+
+    let (device, queue) = warp_device_and_queue();
+    let mut fence = Fence::new(&device).unwrap();
+
+    let start = std::time::Instant::now();
+    let first = fence.signal(&queue).unwrap();
+    fence.wait(first).unwrap();
+    fence.flush(&queue).unwrap();
+    assert_eq!(fence.last_signaled(), first.next());
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+    // 释放围栏后事件句柄已经关闭，不能再查询它的信息
+    let event = fence.event;
+    drop(fence);
+    let mut flags = 0;
+    assert!(!unsafe { GetHandleInformation(event, &mut flags) }.as_bool());
+}
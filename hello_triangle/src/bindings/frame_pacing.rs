@@ -0,0 +1,130 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// 一段时间内的帧间隔统计。
+#[derive(Debug, PartialEq)]
+pub struct FramePacingReport {
+    pub frames: usize,
+    pub average_ms: f64,
+    pub max_ms: f64,
+    /// 帧间隔的标准差，越小说明帧节奏越平稳。
+    pub std_dev_ms: f64,
+    /// 平均每帧 CPU 在 `move_to_next_frame` 中等待围栏的时间。
+    pub fence_wait_ms: f64,
+    /// 平均每帧 CPU 录制命令列表的时间。
+    pub record_ms: f64,
+}
+
+impl FramePacingReport {
+    pub fn new(frame_times_ms: &[f64], fence_wait_ms: f64, record_ms: f64) -> Self {
+        let frames = frame_times_ms.len();
+        if frames == 0 {
+            return FramePacingReport {
+                frames,
+                average_ms: 0.0,
+                max_ms: 0.0,
+                std_dev_ms: 0.0,
+                fence_wait_ms: 0.0,
+                record_ms: 0.0,
+            };
+        }
+        let average_ms = frame_times_ms.iter().sum::<f64>() / frames as f64;
+        let max_ms = frame_times_ms.iter().copied().fold(0.0, f64::max);
+        let variance = frame_times_ms
+            .iter()
+            .map(|time| (time - average_ms).powi(2))
+            .sum::<f64>()
+            / frames as f64;
+        FramePacingReport {
+            frames,
+            average_ms,
+            max_ms,
+            std_dev_ms: variance.sqrt(),
+            fence_wait_ms: fence_wait_ms / frames as f64,
+            record_ms: record_ms / frames as f64,
+        }
+    }
+}
+
+impl fmt::Display for FramePacingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames, avg {:.2} ms, max {:.2} ms, std dev {:.2} ms, fence wait {:.2} ms/frame, \
+             record {:.3} ms/frame",
+            self.frames,
+            self.average_ms,
+            self.max_ms,
+            self.std_dev_ms,
+            self.fence_wait_ms,
+            self.record_ms
+        )
+    }
+}
+
+/// `-framestats`：记录相邻两帧之间的间隔，以及 CPU 等待 GPU 释放命令分配器所花的时间，每秒汇总一次。
+///
+/// CPU 每帧都等待 GPU 完全空闲时，围栏等待会占掉帧时间的一大块；
+/// 有多个帧在飞行中时，CPU 录制下一帧与 GPU 执行上一帧重叠，等待时间应当接近 0。
+/// 用不同的 `-framesinflight` 运行同样的场景即可对比。
+///
+/// 同时统计每帧录制命令列表的 CPU 时间，与 `-nobundle` 对比可以看出 bundle 省下了多少录制开销。
+pub struct FramePacing {
+    last_frame: Option<Instant>,
+    frame_times_ms: Vec<f64>,
+    fence_wait_ms: f64,
+    record_ms: f64,
+    window_start: Instant,
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        FramePacing {
+            last_frame: None,
+            frame_times_ms: Vec::new(),
+            fence_wait_ms: 0.0,
+            record_ms: 0.0,
+            window_start: Instant::now(),
+        }
+    }
+}
+
+impl FramePacing {
+    /// 每帧结束时调用，`fence_wait` 是这一帧在 CPU 端等待围栏的时间，`record` 是录制命令列表的时间；
+    /// 每过一秒返回一次统计结果。
+    pub fn end_frame(
+        &mut self,
+        fence_wait: Duration,
+        record: Duration,
+    ) -> Option<FramePacingReport> {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            self.frame_times_ms
+                .push((now - last_frame).as_secs_f64() * 1000.0);
+            self.fence_wait_ms += fence_wait.as_secs_f64() * 1000.0;
+            self.record_ms += record.as_secs_f64() * 1000.0;
+        }
+
+        if self.window_start.elapsed().as_secs_f64() < 1.0 || self.frame_times_ms.is_empty() {
+            return None;
+        }
+        let report =
+            FramePacingReport::new(&self.frame_times_ms, self.fence_wait_ms, self.record_ms);
+        self.frame_times_ms.clear();
+        self.fence_wait_ms = 0.0;
+        self.record_ms = 0.0;
+        self.window_start = now;
+        Some(report)
+    }
+}
+
+#[test]
+fn frame_pacing_report_summarizes_frame_times() {
+    let report = FramePacingReport::new(&[10.0, 20.0, 10.0, 20.0], 8.0, 0.4);
+    assert_eq!(report.frames, 4);
+    assert_eq!(report.average_ms, 15.0);
+    assert_eq!(report.max_ms, 20.0);
+    assert_eq!(report.std_dev_ms, 5.0);
+    assert_eq!(report.fence_wait_ms, 2.0);
+    assert_eq!(report.record_ms, 0.1);
+}
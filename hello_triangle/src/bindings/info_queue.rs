@@ -0,0 +1,183 @@
+use windows::{
+    core::*, Win32::Graphics::Direct3D12::*, Win32::System::Diagnostics::Debug::IsDebuggerPresent,
+};
+
+/// 已知无害、不需要存储的消息。
+///
+/// - 清除渲染目标或深度模板缓冲区时用的值与创建资源时的优化清除值不同，只是少了快速清除，结果仍然正确。
+///   `-clearcolor` 会改变清除颜色，这条消息每帧都会出现。
+/// - `Map`/`Unmap` 传入空的读取或写入范围，上传缓冲区只写不读，这正是文档推荐的用法。
+pub const DENIED_MESSAGE_IDS: &[D3D12_MESSAGE_ID] = &[
+    D3D12_MESSAGE_ID_CLEARRENDERTARGETVIEW_MISMATCHINGCLEARVALUE,
+    D3D12_MESSAGE_ID_CLEARDEPTHSTENCILVIEW_MISMATCHINGCLEARVALUE,
+    D3D12_MESSAGE_ID_MAP_INVALID_NULLRANGE,
+    D3D12_MESSAGE_ID_UNMAP_INVALID_NULLRANGE,
+];
+
+/// 从调试层的 info queue 中读出的一条消息。
+#[derive(Debug)]
+pub struct InfoQueueMessage {
+    pub severity: D3D12_MESSAGE_SEVERITY,
+    pub id: D3D12_MESSAGE_ID,
+    pub description: String,
+}
+
+impl InfoQueueMessage {
+    /// ERROR 和 CORRUPTION 级别的消息意味着 API 被错误地使用了。
+    pub fn is_error(&self) -> bool {
+        self.severity == D3D12_MESSAGE_SEVERITY_ERROR
+            || self.severity == D3D12_MESSAGE_SEVERITY_CORRUPTION
+    }
+}
+
+pub fn severity_name(severity: D3D12_MESSAGE_SEVERITY) -> &'static str {
+    match severity {
+        D3D12_MESSAGE_SEVERITY_CORRUPTION => "CORRUPTION",
+        D3D12_MESSAGE_SEVERITY_ERROR => "ERROR",
+        D3D12_MESSAGE_SEVERITY_WARNING => "WARNING",
+        D3D12_MESSAGE_SEVERITY_INFO => "INFO",
+        _ => "MESSAGE",
+    }
+}
+
+/// 只有开启了调试层的设备才能查询到 `ID3D12InfoQueue`。
+pub fn info_queue(device: &ID3D12Device) -> Option<ID3D12InfoQueue> {
+    device.cast().ok()
+}
+
+/// 设置刚创建的设备的 info queue：不存储 [`DENIED_MESSAGE_IDS`] 中的消息；
+/// 调试版本在附加了调试器时遇到 ERROR 和 CORRUPTION 立即中断，停在出错的 API 调用上。
+///
+/// 没有调试器时中断会直接结束进程，所以只在 `IsDebuggerPresent` 时开启，控制台运行仍然靠 [`drain_messages`] 打印消息。
+pub fn configure_info_queue(info_queue: &ID3D12InfoQueue) -> Result<()> {
+    let mut denied_ids = DENIED_MESSAGE_IDS.to_vec();
+    let filter = D3D12_INFO_QUEUE_FILTER {
+        DenyList: D3D12_INFO_QUEUE_FILTER_DESC {
+            NumIDs: denied_ids.len() as u32,
+            pIDList: denied_ids.as_mut_ptr(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    unsafe { info_queue.PushStorageFilter(&filter) }?;
+    if cfg!(debug_assertions) && unsafe { IsDebuggerPresent() }.as_bool() {
+        unsafe {
+            info_queue.SetBreakOnSeverity(D3D12_MESSAGE_SEVERITY_CORRUPTION, true)?;
+            info_queue.SetBreakOnSeverity(D3D12_MESSAGE_SEVERITY_ERROR, true)?;
+        }
+    }
+    Ok(())
+}
+
+/// 打印并清空设备的 info queue 中已存储的消息，返回消息的数量。没有开启调试层时什么也不做。
+///
+/// 调试层的消息只会输出到调试器的输出窗口，在控制台中运行时看不到，所以每帧结束时调用它把消息打印出来。
+/// 渲染循环中使用 [`FrameMessages`]，它以同样的方式打印并清空消息，再标上帧序号。
+pub fn drain_messages(device: &ID3D12Device) -> Result<usize> {
+    let Some(info_queue) = info_queue(device) else {
+        return Ok(0);
+    };
+    drain_info_queue(&info_queue, "")
+}
+
+/// 打印并清空 `info_queue` 中已存储的消息，每条消息前加上 `prefix`，返回消息的数量。
+fn drain_info_queue(info_queue: &ID3D12InfoQueue, prefix: &str) -> Result<usize> {
+    let messages = stored_messages(info_queue, 0)?;
+    unsafe { info_queue.ClearStoredMessages() };
+    for message in &messages {
+        println!(
+            "{}D3D12 {}: {}",
+            prefix,
+            severity_name(message.severity),
+            message.description
+        );
+    }
+    Ok(messages.len())
+}
+
+/// 读取从第 `start` 条开始的所有已存储消息。
+pub fn stored_messages(info_queue: &ID3D12InfoQueue, start: u64) -> Result<Vec<InfoQueueMessage>> {
+    let count = unsafe { info_queue.GetNumStoredMessages() };
+    let mut messages = Vec::new();
+    for index in start..count {
+        // 先查询消息的字节大小，再分配足够（且按 8 字节对齐）的内存取出消息本体
+        let mut byte_length = 0;
+        unsafe { info_queue.GetMessage(index, None, &mut byte_length) }?;
+        let mut buffer = vec![0u64; byte_length.div_ceil(8)];
+        let message = buffer.as_mut_ptr() as *mut D3D12_MESSAGE;
+        unsafe { info_queue.GetMessage(index, Some(message), &mut byte_length) }?;
+        let message = unsafe { &*message };
+        let description = unsafe {
+            std::slice::from_raw_parts(message.pDescription, message.DescriptionByteLength)
+        };
+        messages.push(InfoQueueMessage {
+            severity: message.Severity,
+            id: message.ID,
+            description: String::from_utf8_lossy(description)
+                .trim_end_matches('\0')
+                .to_string(),
+        });
+    }
+    Ok(messages)
+}
+
+/// 按帧切分 info queue 中的消息：每帧结束时像 [`drain_messages`] 一样打印并清空已存储的消息，
+/// 每条消息标上帧序号；下一帧读到的就只有之后新产生的消息，便于定位是哪一帧引入了验证错误。
+///
+/// 必须清空：info queue 能存储的消息数量有上限，存满后 `GetNumStoredMessages` 不再增长，
+/// 只比较数量的话之后的验证错误就再也报告不出来了。两帧之间（例如改变窗口大小时）产生的消息算在下一帧中。
+pub struct FrameMessages {
+    info_queue: ID3D12InfoQueue,
+    frame_index: u64,
+}
+
+impl FrameMessages {
+    pub fn new(info_queue: ID3D12InfoQueue) -> Self {
+        Self {
+            info_queue,
+            frame_index: 0,
+        }
+    }
+
+    /// 打印并清空这一帧新增的消息，把帧序号加一，返回消息的数量。
+    pub fn end_frame(&mut self) -> Result<usize> {
+        let count = drain_info_queue(&self.info_queue, &format!("frame {}: ", self.frame_index))?;
+        self.frame_index += 1;
+        Ok(count)
+    }
+}
+
+#[test]
+fn denied_messages_are_not_stored() {
+    use crate::devices::warp_device;
+
+    let device = warp_device();
+    // 没有安装调试层时没有 info queue
+    let Some(info_queue) = info_queue(&device) else {
+        return;
+    };
+    drain_messages(&device).unwrap();
+    unsafe {
+        info_queue
+            .AddMessage(
+                D3D12_MESSAGE_CATEGORY_MISCELLANEOUS,
+                D3D12_MESSAGE_SEVERITY_INFO,
+                DENIED_MESSAGE_IDS[0],
+                s!("denied"),
+            )
+            .unwrap();
+        info_queue
+            .AddMessage(
+                D3D12_MESSAGE_CATEGORY_MISCELLANEOUS,
+                D3D12_MESSAGE_SEVERITY_INFO,
+                D3D12_MESSAGE_ID_UNKNOWN,
+                s!("kept"),
+            )
+            .unwrap();
+    }
+    let messages = stored_messages(&info_queue, 0).unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].description, "kept");
+    assert_eq!(drain_messages(&device).unwrap(), 1);
+    assert_eq!(unsafe { info_queue.GetNumStoredMessages() }, 0);
+}
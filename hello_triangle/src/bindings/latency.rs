@@ -0,0 +1,73 @@
+use crate::query_performance_counter;
+use std::collections::VecDeque;
+use windows::{Win32::Graphics::Dxgi::*, Win32::System::Performance::*};
+
+/// 估算从读取输入到画面真正呈现在屏幕上的延迟。
+///
+/// 消息循环会在调用 `render` 之前处理完所有按键，所以把每帧开始的时刻当作读取输入的时刻，
+/// 并记下这一帧的 `Present` 将得到的编号（`GetLastPresentCount` + 1）。
+/// `GetFrameStatistics` 报告最近一次与垂直同步对齐的呈现的编号及其 QPC 时间，
+/// 编号对上时两者之差就是这一帧的输入到呈现的延迟。结果按秒取平均。
+pub struct LatencyMeter {
+    frequency: i64,
+    /// 尚未被呈现统计确认的帧：(Present 编号, 帧开始时的 QPC)。
+    pending: VecDeque<(u32, i64)>,
+    total_ms: f64,
+    samples: u32,
+    window_start: std::time::Instant,
+}
+
+impl Default for LatencyMeter {
+    fn default() -> Self {
+        let mut frequency = 0;
+        unsafe { QueryPerformanceFrequency(&mut frequency) };
+        LatencyMeter {
+            frequency,
+            pending: VecDeque::new(),
+            total_ms: 0.0,
+            samples: 0,
+            window_start: std::time::Instant::now(),
+        }
+    }
+}
+
+impl LatencyMeter {
+    /// 在帧开始（读取输入之后、录制命令之前）调用。
+    pub fn begin_frame(&mut self, swap_chain: &IDXGISwapChain3) {
+        // 交换链还没有呈现过任何帧时 GetLastPresentCount 会失败，此时下一帧的编号是 1
+        let present_id = unsafe { swap_chain.GetLastPresentCount() }.unwrap_or(0) + 1;
+        self.pending
+            .push_back((present_id, query_performance_counter()));
+        // 呈现统计长时间不可用（例如窗口被遮挡）时，丢掉过旧的记录
+        while self.pending.len() > 16 {
+            self.pending.pop_front();
+        }
+    }
+
+    /// 在 `Present` 之后调用，每过一秒返回一次这一秒内的平均延迟（毫秒）。
+    pub fn end_frame(&mut self, swap_chain: &IDXGISwapChain3) -> Option<f64> {
+        // 窗口模式下统计信息可能暂时不可用（DXGI_ERROR_FRAME_STATISTICS_DISJOINT），直接跳过
+        if let Ok(stats) = unsafe { swap_chain.GetFrameStatistics() } {
+            while let Some(&(present_id, start)) = self.pending.front() {
+                if present_id > stats.PresentCount {
+                    break;
+                }
+                self.pending.pop_front();
+                if present_id == stats.PresentCount && stats.SyncQPCTime > start {
+                    self.total_ms +=
+                        (stats.SyncQPCTime - start) as f64 * 1000.0 / self.frequency as f64;
+                    self.samples += 1;
+                }
+            }
+        }
+
+        if self.window_start.elapsed().as_secs_f64() < 1.0 || self.samples == 0 {
+            return None;
+        }
+        let average = self.total_ms / self.samples as f64;
+        self.total_ms = 0.0;
+        self.samples = 0;
+        self.window_start = std::time::Instant::now();
+        Some(average)
+    }
+}
@@ -0,0 +1,34 @@
+use crate::MemoryDbgHelper;
+use windows::Win32::Graphics::Direct3D12::*;
+
+/// 驱动为这个资源实际分配的大小（包含对齐），通常比资源描述中的数据大小略大。
+pub fn allocation_size(device: &ID3D12Device, resource: &ID3D12Resource) -> u64 {
+    let desc = unsafe { resource.GetDesc() };
+    unsafe { device.GetResourceAllocationInfo(0, &[desc]) }.SizeInBytes
+}
+
+/// 按类别汇总并打印资源占用的显存。`resources` 中相同类别的资源会被加在一起，按首次出现的顺序输出。
+pub fn print_memory_usage(device: &ID3D12Device, resources: &[(&str, &ID3D12Resource)]) {
+    let mut categories: Vec<(&str, usize, u64)> = Vec::new();
+    for (category, resource) in resources {
+        let size = allocation_size(device, resource);
+        match categories.iter_mut().find(|(name, _, _)| name == category) {
+            Some((_, count, total)) => {
+                *count += 1;
+                *total += size;
+            }
+            None => categories.push((category, 1, size)),
+        }
+    }
+
+    for (category, count, total) in &categories {
+        println!(
+            "vram: {}: {} resource(s), {:?}",
+            category,
+            count,
+            MemoryDbgHelper(*total)
+        );
+    }
+    let total = categories.iter().map(|(_, _, total)| total).sum();
+    println!("vram: total: {:?}", MemoryDbgHelper(total));
+}
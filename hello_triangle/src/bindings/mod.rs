@@ -1,2 +1,22 @@
 pub mod adapter;
+pub mod command;
+pub mod compute;
+pub mod constant_buffer;
+pub mod descriptor;
 pub mod devices;
+pub mod dred;
+pub mod dxc;
+pub mod fence;
+pub mod frame_pacing;
+pub mod info_queue;
+pub mod latency;
+pub mod memory_usage;
+pub mod multi_node;
+pub mod pipeline_state;
+pub mod present;
+pub mod readback;
+pub mod resource_state;
+pub mod root_signature;
+pub mod texture;
+pub mod upload;
+pub mod vertex;
@@ -0,0 +1,112 @@
+use crate::fence::Fence;
+use windows::{core::*, Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::Common::*};
+
+fn create_buffer(
+    device: &ID3D12Device,
+    heap_type: D3D12_HEAP_TYPE,
+    creation_node_mask: u32,
+    visible_node_mask: u32,
+    size: u64,
+    initial_state: D3D12_RESOURCE_STATES,
+) -> Result<ID3D12Resource> {
+    let mut buffer: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: heap_type,
+                CreationNodeMask: creation_node_mask,
+                VisibleNodeMask: visible_node_mask,
+                ..Default::default()
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &D3D12_RESOURCE_DESC {
+                Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+                Width: size,
+                Height: 1,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                ..Default::default()
+            },
+            initial_state,
+            None,
+            &mut buffer,
+        )?
+    };
+    Ok(buffer.unwrap())
+}
+
+/// 链接节点（linked-node）模式下，多块相同的 GPU 以一个 `ID3D12Device` 的形式出现，每块 GPU 是一个节点。
+/// 命令队列、命令列表和资源通过 `NodeMask`（第 i 位对应第 i 个节点）决定它们属于哪个节点，
+/// 资源还可以通过 `VisibleNodeMask` 让其他节点也能访问。
+///
+/// 这里用最简单的交替帧渲染（AFR）来演示：第 n 帧交给第 n % node_count 个节点，
+/// 每个节点把共享的上传缓冲区复制到自己节点上的缓冲区。单 GPU 系统会直接跳过。
+pub fn run_multi_node_demo(device: &ID3D12Device) -> Result<()> {
+    let node_count = unsafe { device.GetNodeCount() };
+    if node_count <= 1 {
+        println!("multinode: device has a single node, skipping the linked-node demo");
+        return Ok(());
+    }
+    println!("multinode: device has {} linked nodes", node_count);
+
+    const BUFFER_SIZE: u64 = 64 * 1024;
+    let all_nodes = (1u32 << node_count) - 1;
+    // 上传堆只能在一个节点上创建，但可以对所有节点可见
+    let upload = create_buffer(
+        device,
+        D3D12_HEAP_TYPE_UPLOAD,
+        1,
+        all_nodes,
+        BUFFER_SIZE,
+        D3D12_RESOURCE_STATE_GENERIC_READ,
+    )?;
+
+    let mut nodes = Vec::new();
+    for node in 0..node_count {
+        let node_mask = 1 << node;
+        let queue: ID3D12CommandQueue = unsafe {
+            device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
+                Type: D3D12_COMMAND_LIST_TYPE_DIRECT,
+                NodeMask: node_mask,
+                ..Default::default()
+            })
+        }?;
+        let allocator: ID3D12CommandAllocator =
+            unsafe { device.CreateCommandAllocator(D3D12_COMMAND_LIST_TYPE_DIRECT) }?;
+        let command_list: ID3D12GraphicsCommandList = unsafe {
+            device.CreateCommandList(node_mask, D3D12_COMMAND_LIST_TYPE_DIRECT, &allocator, None)
+        }?;
+        unsafe { command_list.Close() }?;
+        let buffer = create_buffer(
+            device,
+            D3D12_HEAP_TYPE_DEFAULT,
+            node_mask,
+            node_mask,
+            BUFFER_SIZE,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+        )?;
+        nodes.push((queue, allocator, command_list, buffer));
+    }
+
+    let mut fence = Fence::new(device)?;
+    for frame in 0..(node_count as u64 * 2) {
+        let node = (frame % node_count as u64) as usize;
+        let (queue, allocator, command_list, buffer) = &nodes[node];
+        unsafe {
+            allocator.Reset()?;
+            command_list.Reset(allocator, None)?;
+            command_list.CopyBufferRegion(buffer, 0, &upload, 0, BUFFER_SIZE);
+            command_list.Close()?;
+            queue.ExecuteCommandLists(&[Some(command_list.cast()?)]);
+        }
+        // 各个节点的队列共用一个围栏，Signal 的值依次递增
+        fence.flush(queue)?;
+        println!("multinode: frame {} executed on node {}", frame, node);
+    }
+    Ok(())
+}
@@ -0,0 +1,297 @@
+use crate::{explain_error, DepthMode};
+use std::fmt::Write;
+use windows::{core::*, Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::Common::*};
+
+/// 一次最多能同时绑定的渲染目标数，也是 PSO 中 `RTVFormats` 数组的长度。
+pub const MAX_RENDER_TARGETS: usize = D3D12_SIMULTANEOUS_RENDER_TARGET_COUNT as usize;
+
+/// PSO 中必须与绘制时绑定的渲染目标和深度/模板缓冲区相匹配的部分。
+#[derive(Clone, Copy)]
+pub struct RenderTargetFormats {
+    /// 第 i 个格式对应 `OMSetRenderTargets` 绑定的第 i 个渲染目标，也就是像素着色器的 `SV_Target{i}`。
+    /// 只有前 `num_render_targets` 个有效，其余必须是 `DXGI_FORMAT_UNKNOWN`。
+    pub rtv_formats: [DXGI_FORMAT; MAX_RENDER_TARGETS],
+    pub num_render_targets: u32,
+    /// 没有深度/模板缓冲区时为 `DXGI_FORMAT_UNKNOWN`。
+    pub dsv_format: DXGI_FORMAT,
+    /// 开启 MSAA 时，渲染目标、深度/模板缓冲区和 PSO 的采样设置必须一致。
+    pub sample_desc: DXGI_SAMPLE_DESC,
+    /// 决定 PSO 的深度比较函数，要与深度/模板缓冲区的清除值一致。
+    pub depth_mode: DepthMode,
+}
+
+impl RenderTargetFormats {
+    /// 单采样、没有深度/模板缓冲区。
+    pub fn color_only(rtv_format: DXGI_FORMAT) -> Self {
+        RenderTargetFormats::multiple(&[rtv_format])
+    }
+
+    /// 同时写入多个渲染目标（MRT），例如延迟着色的 G-buffer。单采样、没有深度/模板缓冲区。
+    ///
+    /// 各个渲染目标的格式可以不同，但大小和采样设置必须相同。超过 8 个时 panic。
+    pub fn multiple(rtv_formats: &[DXGI_FORMAT]) -> Self {
+        assert!(
+            rtv_formats.len() <= MAX_RENDER_TARGETS,
+            "at most {} render targets can be bound, got {}",
+            MAX_RENDER_TARGETS,
+            rtv_formats.len()
+        );
+        let mut formats = [DXGI_FORMAT_UNKNOWN; MAX_RENDER_TARGETS];
+        formats[..rtv_formats.len()].copy_from_slice(rtv_formats);
+        RenderTargetFormats {
+            rtv_formats: formats,
+            num_render_targets: rtv_formats.len() as u32,
+            dsv_format: DXGI_FORMAT_UNKNOWN,
+            sample_desc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            depth_mode: DepthMode::Standard,
+        }
+    }
+
+    /// 加上按 `depth_mode` 的约定创建的深度/模板缓冲区。
+    pub fn with_depth(self, depth_mode: DepthMode) -> Self {
+        RenderTargetFormats {
+            dsv_format: depth_mode.format(),
+            depth_mode,
+            ..self
+        }
+    }
+
+    /// 第 0 个渲染目标的格式，只有一个渲染目标时就是后台缓冲区的格式。
+    pub fn rtv_format(&self) -> DXGI_FORMAT {
+        self.rtv_formats[0]
+    }
+}
+
+fn fill_mode_name(fill_mode: D3D12_FILL_MODE) -> &'static str {
+    match fill_mode {
+        D3D12_FILL_MODE_WIREFRAME => "wireframe",
+        D3D12_FILL_MODE_SOLID => "solid",
+        _ => "unknown",
+    }
+}
+
+fn cull_mode_name(cull_mode: D3D12_CULL_MODE) -> &'static str {
+    match cull_mode {
+        D3D12_CULL_MODE_NONE => "none",
+        D3D12_CULL_MODE_FRONT => "front",
+        D3D12_CULL_MODE_BACK => "back",
+        _ => "unknown",
+    }
+}
+
+fn topology_type_name(topology_type: D3D12_PRIMITIVE_TOPOLOGY_TYPE) -> &'static str {
+    match topology_type {
+        D3D12_PRIMITIVE_TOPOLOGY_TYPE_UNDEFINED => "undefined",
+        D3D12_PRIMITIVE_TOPOLOGY_TYPE_POINT => "point",
+        D3D12_PRIMITIVE_TOPOLOGY_TYPE_LINE => "line",
+        D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE => "triangle",
+        D3D12_PRIMITIVE_TOPOLOGY_TYPE_PATCH => "patch",
+        _ => "unknown",
+    }
+}
+
+/// 把 PSO 描述中最常出问题的字段整理成多行文本：着色器字节码长度、输入布局、
+/// 光栅化/混合/深度模板状态、渲染目标格式和多重采样设置。
+pub fn pso_desc_summary(desc: &D3D12_GRAPHICS_PIPELINE_STATE_DESC) -> String {
+    let mut summary = String::new();
+    let _ = writeln!(
+        summary,
+        "root signature: {}",
+        if desc.pRootSignature.is_some() {
+            "set"
+        } else {
+            "none"
+        }
+    );
+    for (name, shader) in [
+        ("VS", &desc.VS),
+        ("PS", &desc.PS),
+        ("DS", &desc.DS),
+        ("HS", &desc.HS),
+        ("GS", &desc.GS),
+    ] {
+        if shader.BytecodeLength != 0 {
+            let _ = writeln!(summary, "{}: {} bytes", name, shader.BytecodeLength);
+        }
+    }
+
+    let input_layout = &desc.InputLayout;
+    let _ = writeln!(
+        summary,
+        "input layout: {} element(s)",
+        input_layout.NumElements
+    );
+    if !input_layout.pInputElementDescs.is_null() {
+        let elements = unsafe {
+            std::slice::from_raw_parts(
+                input_layout.pInputElementDescs,
+                input_layout.NumElements as usize,
+            )
+        };
+        for element in elements {
+            let _ = writeln!(
+                summary,
+                "  {}{}: {:?}, slot {}, offset {}, {:?}",
+                unsafe { element.SemanticName.to_string() }.unwrap_or_default(),
+                element.SemanticIndex,
+                element.Format,
+                element.InputSlot,
+                element.AlignedByteOffset,
+                element.InputSlotClass
+            );
+        }
+    }
+
+    let rasterizer = &desc.RasterizerState;
+    let _ = writeln!(
+        summary,
+        "rasterizer: fill {}, cull {}, front ccw {}, depth bias {}, depth clip {}",
+        fill_mode_name(rasterizer.FillMode),
+        cull_mode_name(rasterizer.CullMode),
+        rasterizer.FrontCounterClockwise.as_bool(),
+        rasterizer.DepthBias,
+        rasterizer.DepthClipEnable.as_bool()
+    );
+
+    let blend = &desc.BlendState;
+    let _ = writeln!(
+        summary,
+        "blend: alpha to coverage {}, independent {}",
+        blend.AlphaToCoverageEnable.as_bool(),
+        blend.IndependentBlendEnable.as_bool()
+    );
+    // 未开启独立混合时只有第 0 个渲染目标的混合状态生效
+    let blend_targets = if blend.IndependentBlendEnable.as_bool() {
+        desc.NumRenderTargets as usize
+    } else {
+        1
+    };
+    for (i, target) in blend.RenderTarget.iter().take(blend_targets).enumerate() {
+        let _ = writeln!(
+            summary,
+            "  rt{}: enable {}, color {:?} {:?} {:?}, alpha {:?} {:?} {:?}, write mask {:#x}",
+            i,
+            target.BlendEnable.as_bool(),
+            target.SrcBlend,
+            target.BlendOp,
+            target.DestBlend,
+            target.SrcBlendAlpha,
+            target.BlendOpAlpha,
+            target.DestBlendAlpha,
+            target.RenderTargetWriteMask
+        );
+    }
+
+    let depth_stencil = &desc.DepthStencilState;
+    let _ = writeln!(
+        summary,
+        "depth stencil: depth {}, write {:?}, func {:?}, stencil {}, format {:?}",
+        depth_stencil.DepthEnable.as_bool(),
+        depth_stencil.DepthWriteMask,
+        depth_stencil.DepthFunc,
+        depth_stencil.StencilEnable.as_bool(),
+        desc.DSVFormat
+    );
+    if depth_stencil.StencilEnable.as_bool() {
+        let front = &depth_stencil.FrontFace;
+        let _ = writeln!(
+            summary,
+            "  stencil front: func {:?}, pass {:?}, depth fail {:?}, fail {:?}, read mask {:#x}, write mask {:#x}",
+            front.StencilFunc,
+            front.StencilPassOp,
+            front.StencilDepthFailOp,
+            front.StencilFailOp,
+            depth_stencil.StencilReadMask,
+            depth_stencil.StencilWriteMask
+        );
+    }
+
+    let _ = writeln!(
+        summary,
+        "topology type: {}",
+        topology_type_name(desc.PrimitiveTopologyType)
+    );
+    let _ = writeln!(
+        summary,
+        "render targets: {} {:?}",
+        desc.NumRenderTargets,
+        &desc.RTVFormats[..(desc.NumRenderTargets as usize).min(desc.RTVFormats.len())]
+    );
+    let _ = write!(
+        summary,
+        "sample desc: count {}, quality {}, mask {:#x}",
+        desc.SampleDesc.Count, desc.SampleDesc.Quality, desc.SampleMask
+    );
+    summary
+}
+
+/// 打印 PSO 描述的摘要，方便查看实际提交给驱动的配置。
+pub fn dump_pso_desc(desc: &D3D12_GRAPHICS_PIPELINE_STATE_DESC) {
+    for line in pso_desc_summary(desc).lines() {
+        println!("pso: {}", line);
+    }
+}
+
+/// 创建图形 PSO。`verbose` 时在创建前打印描述；创建失败时无论如何都会打印，好看清被拒绝的是什么配置。
+pub fn create_graphics_pipeline_state(
+    device: &ID3D12Device,
+    desc: &D3D12_GRAPHICS_PIPELINE_STATE_DESC,
+    verbose: bool,
+) -> Result<ID3D12PipelineState> {
+    if verbose {
+        dump_pso_desc(desc);
+    }
+    let result = unsafe { device.CreateGraphicsPipelineState(desc) };
+    if let Err(error) = &result {
+        eprintln!(
+            "pso: CreateGraphicsPipelineState failed: {}",
+            explain_error(error)
+        );
+        if !verbose {
+            dump_pso_desc(desc);
+        }
+    }
+    result
+}
+
+#[test]
+fn pso_desc_summary_lists_key_fields() {
+    let mut desc = D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+        RasterizerState: D3D12_RASTERIZER_DESC {
+            FillMode: D3D12_FILL_MODE_SOLID,
+            CullMode: D3D12_CULL_MODE_BACK,
+            ..Default::default()
+        },
+        PrimitiveTopologyType: D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
+        NumRenderTargets: 1,
+        ..Default::default()
+    };
+    desc.SampleDesc.Count = 1;
+    let summary = pso_desc_summary(&desc);
+    assert!(summary.contains("root signature: none"));
+    assert!(summary.contains("input layout: 0 element(s)"));
+    assert!(summary.contains("fill solid, cull back"));
+    assert!(summary.contains("topology type: triangle"));
+    assert!(summary.contains("sample desc: count 1, quality 0"));
+}
+
+#[test]
+fn render_target_formats_fill_unused_slots() {
+    let targets = RenderTargetFormats::multiple(&[
+        DXGI_FORMAT_R8G8B8A8_UNORM,
+        DXGI_FORMAT_R16G16B16A16_FLOAT,
+    ]);
+    assert_eq!(targets.num_render_targets, 2);
+    assert_eq!(targets.rtv_format(), DXGI_FORMAT_R8G8B8A8_UNORM);
+    assert_eq!(targets.rtv_formats[1], DXGI_FORMAT_R16G16B16A16_FLOAT);
+    assert!(targets.rtv_formats[2..]
+        .iter()
+        .all(|format| *format == DXGI_FORMAT_UNKNOWN));
+    assert!(std::panic::catch_unwind(|| {
+        RenderTargetFormats::multiple(&[DXGI_FORMAT_R8G8B8A8_UNORM; MAX_RENDER_TARGETS + 1])
+    })
+    .is_err());
+}
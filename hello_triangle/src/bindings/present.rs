@@ -0,0 +1,239 @@
+use crate::devices::check_format_support;
+use crate::DisplayMode;
+use windows::{
+    core::*, Win32::Foundation::*, Win32::Graphics::Direct3D12::*,
+    Win32::Graphics::Dxgi::Common::*, Win32::Graphics::Dxgi::*, Win32::Graphics::Gdi::*,
+};
+
+/// 常见的交换链后台缓冲区格式。
+const CANDIDATE_FORMATS: [DXGI_FORMAT; 4] = [
+    DXGI_FORMAT_R8G8B8A8_UNORM,
+    DXGI_FORMAT_B8G8R8A8_UNORM,
+    DXGI_FORMAT_R10G10B10A2_UNORM,
+    DXGI_FORMAT_R16G16B16A16_FLOAT,
+];
+
+pub fn format_name(format: DXGI_FORMAT) -> &'static str {
+    match format {
+        DXGI_FORMAT_R8G8B8A8_UNORM => "R8G8B8A8_UNORM",
+        DXGI_FORMAT_B8G8R8A8_UNORM => "B8G8R8A8_UNORM",
+        DXGI_FORMAT_R10G10B10A2_UNORM => "R10G10B10A2_UNORM",
+        DXGI_FORMAT_R16G16B16A16_FLOAT => "R16G16B16A16_FLOAT",
+        _ => "UNKNOWN",
+    }
+}
+
+/// 查询是否支持关闭垂直同步时的画面撕裂（可变刷新率显示器需要它）。
+pub fn check_tearing_support(factory: &IDXGIFactory4) -> bool {
+    let mut allow_tearing = BOOL::default();
+    factory
+        .cast::<IDXGIFactory5>()
+        .and_then(|factory| unsafe {
+            factory.CheckFeatureSupport(
+                DXGI_FEATURE_PRESENT_ALLOW_TEARING,
+                &mut allow_tearing as *mut _ as *mut _,
+                std::mem::size_of::<BOOL>() as _,
+            )
+        })
+        .is_ok()
+        && allow_tearing.as_bool()
+}
+
+/// 取得设备所在适配器的第一个输出（显示器），WARP 之类没有输出的适配器返回 None。
+fn primary_output(factory: &IDXGIFactory4, device: &ID3D12Device) -> Option<IDXGIOutput> {
+    let adapter: IDXGIAdapter1 =
+        unsafe { factory.EnumAdapterByLuid(device.GetAdapterLuid()) }.ok()?;
+    unsafe { adapter.EnumOutputs(0) }.ok()
+}
+
+fn primary_output_desc(
+    factory: &IDXGIFactory4,
+    device: &ID3D12Device,
+) -> Option<DXGI_OUTPUT_DESC1> {
+    let output: IDXGIOutput6 = primary_output(factory, device)?.cast().ok()?;
+    unsafe { output.GetDesc1() }.ok()
+}
+
+/// 第一个输出当前是否处于 HDR10（ST.2084 + BT.2020）色彩空间，也就是系统设置中打开了 HDR。
+pub fn output_supports_hdr(factory: &IDXGIFactory4, device: &ID3D12Device) -> bool {
+    primary_output_desc(factory, device)
+        .is_some_and(|desc| desc.ColorSpace == DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020)
+}
+
+/// 把交换链的色彩空间设为 HDR10。交换链不能以这个色彩空间呈现时返回 false，保持默认的 sRGB 色彩空间。
+pub fn set_hdr10_color_space(swap_chain: &IDXGISwapChain3) -> Result<bool> {
+    let support =
+        unsafe { swap_chain.CheckColorSpaceSupport(DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020) }?;
+    if support & DXGI_SWAP_CHAIN_COLOR_SPACE_SUPPORT_FLAG_PRESENT.0 as u32 == 0 {
+        return Ok(false);
+    }
+    unsafe { swap_chain.SetColorSpace1(DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020) }?;
+    Ok(true)
+}
+
+/// 输出在指定格式下支持的所有显示模式。先传空指针查询数量，再取出模式列表。
+pub fn list_display_modes(
+    output: &IDXGIOutput,
+    format: DXGI_FORMAT,
+) -> Result<Vec<DXGI_MODE_DESC>> {
+    let mut count = 0;
+    unsafe { output.GetDisplayModeList(format, 0, &mut count, None) }?;
+    let mut modes = vec![DXGI_MODE_DESC::default(); count as usize];
+    unsafe { output.GetDisplayModeList(format, 0, &mut count, Some(modes.as_mut_ptr())) }?;
+    modes.truncate(count as usize);
+    Ok(modes)
+}
+
+pub fn refresh_rate(mode: &DXGI_MODE_DESC) -> f32 {
+    if mode.RefreshRate.Denominator == 0 {
+        0.0
+    } else {
+        mode.RefreshRate.Numerator as f32 / mode.RefreshRate.Denominator as f32
+    }
+}
+
+/// 打印主显示器支持的显示模式，供 `-mode` 参考。
+pub fn print_display_modes(
+    factory: &IDXGIFactory4,
+    device: &ID3D12Device,
+    format: DXGI_FORMAT,
+) -> Result<()> {
+    let Some(output) = primary_output(factory, device) else {
+        println!("modes: adapter has no output attached");
+        return Ok(());
+    };
+    let modes = list_display_modes(&output, format)?;
+    println!(
+        "modes: {} display modes for {}",
+        modes.len(),
+        format_name(format)
+    );
+    for mode in &modes {
+        println!(
+            "modes: {}x{}@{:.2}",
+            mode.Width,
+            mode.Height,
+            refresh_rate(mode)
+        );
+    }
+    Ok(())
+}
+
+/// 输出当前的分辨率和刷新率。DXGI_OUTPUT_DESC 只有桌面区域，不含刷新率，
+/// 所以按输出的设备名向 GDI 查询当前的显示设置。返回的模式不指定格式。
+pub fn current_output_mode(output: &IDXGIOutput) -> Result<DXGI_MODE_DESC> {
+    let desc = unsafe { output.GetDesc() }?;
+    let mut devmode = DEVMODEW {
+        dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+        ..Default::default()
+    };
+    if !unsafe {
+        EnumDisplaySettingsW(
+            PCWSTR(desc.DeviceName.as_ptr()),
+            ENUM_CURRENT_SETTINGS,
+            &mut devmode,
+        )
+    }
+    .as_bool()
+    {
+        return Err(Error::new(
+            DXGI_ERROR_NOT_FOUND,
+            "the output has no current display settings".into(),
+        ));
+    }
+    Ok(DXGI_MODE_DESC {
+        Width: devmode.dmPelsWidth,
+        Height: devmode.dmPelsHeight,
+        RefreshRate: DXGI_RATIONAL {
+            Numerator: devmode.dmDisplayFrequency,
+            Denominator: 1,
+        },
+        ..Default::default()
+    })
+}
+
+/// 打印窗口所在输出的名称和当前显示模式。窗口不在任何输出上（例如 WARP 适配器）时打印一行说明。
+pub fn print_containing_output(swap_chain: &IDXGISwapChain3) -> Result<()> {
+    let Ok(output) = (unsafe { swap_chain.GetContainingOutput() }) else {
+        println!("output: the window is not on an output of this adapter");
+        return Ok(());
+    };
+    let desc = unsafe { output.GetDesc() }?;
+    let mode = current_output_mode(&output)?;
+    println!(
+        "output: window is on {}, {}x{}@{:.2}",
+        crate::adapter::output_name(&desc),
+        mode.Width,
+        mode.Height,
+        refresh_rate(&mode)
+    );
+    Ok(())
+}
+
+/// 在输出支持的模式中找出与请求最接近的一个，传入设备以保证该模式可以被它使用。
+pub fn find_closest_mode(
+    output: &IDXGIOutput,
+    device: &ID3D12Device,
+    mode: DisplayMode,
+    format: DXGI_FORMAT,
+) -> Result<DXGI_MODE_DESC> {
+    let mut closest = DXGI_MODE_DESC::default();
+    unsafe { output.FindClosestMatchingMode(&mode.to_mode_desc(format), &mut closest, device) }?;
+    Ok(closest)
+}
+
+/// 支持 `D3D12_FORMAT_SUPPORT1_DISPLAY` 的格式才能用作交换链的后台缓冲区。
+pub fn supports_display(device: &ID3D12Device, format: DXGI_FORMAT) -> bool {
+    check_format_support(device, format).is_ok_and(|support| {
+        (support.Support1 & D3D12_FORMAT_SUPPORT1_DISPLAY) == D3D12_FORMAT_SUPPORT1_DISPLAY
+    })
+}
+
+/// 打印当前系统的呈现能力（撕裂、HDR、可用的后台缓冲区格式），并给出推荐的交换链配置。
+pub fn print_present_info(factory: &IDXGIFactory4, device: &ID3D12Device) -> Result<()> {
+    let allow_tearing = check_tearing_support(factory);
+    println!("present: tearing supported: {}", allow_tearing);
+
+    let hdr = match primary_output_desc(factory, device) {
+        Some(desc) => {
+            let hdr = desc.ColorSpace == DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020;
+            println!(
+                "present: output bits per color: {}, HDR: {}, luminance: {}..{} nits",
+                desc.BitsPerColor, hdr, desc.MinLuminance, desc.MaxLuminance
+            );
+            hdr
+        }
+        None => {
+            println!("present: adapter has no output attached");
+            false
+        }
+    };
+
+    let supported_formats: Vec<DXGI_FORMAT> = CANDIDATE_FORMATS
+        .into_iter()
+        .filter(|format| supports_display(device, *format))
+        .collect();
+    let names: Vec<&str> = supported_formats.iter().map(|f| format_name(*f)).collect();
+    println!("present: swap chain formats: {}", names.join(", "));
+
+    let preferred = if hdr {
+        DXGI_FORMAT_R10G10B10A2_UNORM
+    } else {
+        DXGI_FORMAT_R8G8B8A8_UNORM
+    };
+    let format = if supported_formats.contains(&preferred) {
+        preferred
+    } else {
+        supported_formats
+            .first()
+            .copied()
+            .unwrap_or(DXGI_FORMAT_R8G8B8A8_UNORM)
+    };
+    println!(
+        "present: recommended: format {}, swap effect FLIP_DISCARD, present interval {}, tearing {}",
+        format_name(format),
+        if allow_tearing { 0 } else { 1 },
+        allow_tearing
+    );
+    Ok(())
+}
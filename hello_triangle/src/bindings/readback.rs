@@ -0,0 +1,266 @@
+use crate::present::format_name;
+use crate::resource_state::transition_barrier;
+use crate::upload::{create_buffer, execute_and_wait};
+use windows::{
+    core::*, Win32::Foundation::*, Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::Common::*,
+};
+
+/// 从 GPU 读回的二维纹理，每行紧密排列，没有对齐填充。
+pub struct ReadbackImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: DXGI_FORMAT,
+    pub data: Vec<u8>,
+}
+
+impl ReadbackImage {
+    /// 转换为 RGBA8 像素，只支持 8 位的 RGBA/BGRA 格式。
+    /// 无类型格式的纹理是以 sRGB 视图渲染的，其中的字节已经是 sRGB 编码，与 PNG 一致。
+    pub fn to_rgba8(&self) -> Result<Vec<u8>> {
+        match self.format {
+            DXGI_FORMAT_R8G8B8A8_UNORM
+            | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+            | DXGI_FORMAT_R8G8B8A8_TYPELESS => Ok(self.data.clone()),
+            DXGI_FORMAT_B8G8R8A8_UNORM
+            | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB
+            | DXGI_FORMAT_B8G8R8A8_TYPELESS => Ok(self
+                .data
+                .chunks_exact(4)
+                .flat_map(|p| [p[2], p[1], p[0], p[3]])
+                .collect()),
+            format => Err(Error::new(
+                E_INVALIDARG,
+                format!(
+                    "cannot convert {} to RGBA8, use -format rgba8 or bgra8",
+                    format_name(format)
+                )
+                .into(),
+            )),
+        }
+    }
+}
+
+/// 把处于 `state` 状态的二维纹理的第 0 个子资源复制到回读堆，等待复制完成后读到内存中。
+///
+/// 复制到缓冲区时每行的字节数要按 `D3D12_TEXTURE_DATA_PITCH_ALIGNMENT`（256 字节）对齐，
+/// `GetCopyableFootprints` 给出的 `RowPitch` 已经包含了这部分填充，读取时要逐行去掉。
+/// 复制在 `command_queue` 上执行，排在之前提交的命令之后，所以读到的是那些命令渲染的结果。
+pub fn read_back_texture(
+    device: &ID3D12Device,
+    command_queue: &ID3D12CommandQueue,
+    texture: &ID3D12Resource,
+    state: D3D12_RESOURCE_STATES,
+) -> Result<ReadbackImage> {
+    let desc = unsafe { texture.GetDesc() };
+    let mut footprint = D3D12_PLACED_SUBRESOURCE_FOOTPRINT::default();
+    let mut row_size = 0;
+    let mut total_bytes = 0;
+    unsafe {
+        device.GetCopyableFootprints(
+            &desc,
+            0,
+            1,
+            0,
+            Some(&mut footprint),
+            None,
+            Some(&mut row_size),
+            Some(&mut total_bytes),
+        )
+    };
+
+    let readback = copy_to_readback(
+        device,
+        command_queue,
+        texture,
+        state,
+        &footprint,
+        total_bytes,
+        None,
+    )?;
+
+    let width = footprint.Footprint.Width;
+    let height = footprint.Footprint.Height;
+    let row_pitch = footprint.Footprint.RowPitch as usize;
+    let row_size = row_size as usize;
+    let mut data = Vec::with_capacity(row_size * height as usize);
+    unsafe {
+        let mut mapped = std::ptr::null_mut();
+        readback.Map(0, None, Some(&mut mapped))?;
+        for y in 0..height as usize {
+            let row = (mapped as *const u8).add(footprint.Offset as usize + y * row_pitch);
+            data.extend_from_slice(std::slice::from_raw_parts(row, row_size));
+        }
+        // CPU 没有写入任何数据，写入范围为空
+        readback.Unmap(0, Some(&D3D12_RANGE::default()));
+    }
+
+    Ok(ReadbackImage {
+        width,
+        height,
+        format: desc.Format,
+        data,
+    })
+}
+
+/// 读回处于 `state` 状态的二维纹理在 (`x`, `y`) 处的一个像素，转换为 RGBA8，只支持 8 位的 RGBA/BGRA 格式。
+///
+/// 只复制这一个像素，回读缓冲区也只需要一行：即使只有 4 个字节，行跨度仍要按 256 字节对齐。
+/// 坐标超出纹理时返回错误。
+pub fn read_back_pixel(
+    device: &ID3D12Device,
+    command_queue: &ID3D12CommandQueue,
+    texture: &ID3D12Resource,
+    state: D3D12_RESOURCE_STATES,
+    x: u32,
+    y: u32,
+) -> Result<[u8; 4]> {
+    let desc = unsafe { texture.GetDesc() };
+    if x as u64 >= desc.Width || y >= desc.Height {
+        return Err(Error::new(
+            E_INVALIDARG,
+            format!(
+                "pixel ({}, {}) is outside the {}x{} texture",
+                x, y, desc.Width, desc.Height
+            )
+            .into(),
+        ));
+    }
+
+    let footprint = D3D12_PLACED_SUBRESOURCE_FOOTPRINT {
+        Offset: 0,
+        Footprint: D3D12_SUBRESOURCE_FOOTPRINT {
+            Format: desc.Format,
+            Width: 1,
+            Height: 1,
+            Depth: 1,
+            RowPitch: D3D12_TEXTURE_DATA_PITCH_ALIGNMENT,
+        },
+    };
+    let source_box = D3D12_BOX {
+        left: x,
+        top: y,
+        front: 0,
+        right: x + 1,
+        bottom: y + 1,
+        back: 1,
+    };
+    let readback = copy_to_readback(
+        device,
+        command_queue,
+        texture,
+        state,
+        &footprint,
+        D3D12_TEXTURE_DATA_PITCH_ALIGNMENT as u64,
+        Some(&source_box),
+    )?;
+
+    let mut data = [0u8; 4];
+    unsafe {
+        let mut mapped = std::ptr::null_mut();
+        readback.Map(0, None, Some(&mut mapped))?;
+        std::ptr::copy_nonoverlapping(mapped as *const u8, data.as_mut_ptr(), data.len());
+        readback.Unmap(0, Some(&D3D12_RANGE::default()));
+    }
+
+    let pixel = ReadbackImage {
+        width: 1,
+        height: 1,
+        format: desc.Format,
+        data: data.to_vec(),
+    }
+    .to_rgba8()?;
+    Ok([pixel[0], pixel[1], pixel[2], pixel[3]])
+}
+
+/// 把处于 `state` 状态的整个缓冲区复制到回读堆，等待复制完成后读到内存中。
+///
+/// 缓冲区没有行跨度的对齐要求，按字节原样复制即可。
+pub fn read_back_buffer(
+    device: &ID3D12Device,
+    command_queue: &ID3D12CommandQueue,
+    buffer: &ID3D12Resource,
+    state: D3D12_RESOURCE_STATES,
+) -> Result<Vec<u8>> {
+    let size = unsafe { buffer.GetDesc() }.Width;
+    let readback = create_buffer(
+        device,
+        D3D12_HEAP_TYPE_READBACK,
+        size,
+        D3D12_RESOURCE_STATE_COPY_DEST,
+    )?;
+    execute_and_wait(device, command_queue, |command_list| unsafe {
+        command_list.ResourceBarrier(&[transition_barrier(
+            buffer,
+            state,
+            D3D12_RESOURCE_STATE_COPY_SOURCE,
+        )]);
+        command_list.CopyResource(&readback, buffer);
+        command_list.ResourceBarrier(&[transition_barrier(
+            buffer,
+            D3D12_RESOURCE_STATE_COPY_SOURCE,
+            state,
+        )]);
+    })?;
+
+    let mut data = vec![0u8; size as usize];
+    unsafe {
+        let mut mapped = std::ptr::null_mut();
+        readback.Map(0, None, Some(&mut mapped))?;
+        std::ptr::copy_nonoverlapping(mapped as *const u8, data.as_mut_ptr(), data.len());
+        readback.Unmap(0, Some(&D3D12_RANGE::default()));
+    }
+    Ok(data)
+}
+
+/// 把纹理第 0 个子资源中 `source_box` 范围内（`None` 表示整个子资源）的像素按 `footprint` 复制到
+/// 新建的回读缓冲区，等待复制完成后返回这个缓冲区。纹理在复制前后都处于 `state` 状态。
+fn copy_to_readback(
+    device: &ID3D12Device,
+    command_queue: &ID3D12CommandQueue,
+    texture: &ID3D12Resource,
+    state: D3D12_RESOURCE_STATES,
+    footprint: &D3D12_PLACED_SUBRESOURCE_FOOTPRINT,
+    size: u64,
+    source_box: Option<&D3D12_BOX>,
+) -> Result<ID3D12Resource> {
+    // 回读堆中的缓冲区必须以 COPY_DEST 状态创建
+    let readback = create_buffer(
+        device,
+        D3D12_HEAP_TYPE_READBACK,
+        size,
+        D3D12_RESOURCE_STATE_COPY_DEST,
+    )?;
+    execute_and_wait(device, command_queue, |command_list| unsafe {
+        command_list.ResourceBarrier(&[transition_barrier(
+            texture,
+            state,
+            D3D12_RESOURCE_STATE_COPY_SOURCE,
+        )]);
+        command_list.CopyTextureRegion(
+            &D3D12_TEXTURE_COPY_LOCATION {
+                pResource: Some(readback.clone()),
+                Type: D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
+                Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                    PlacedFootprint: *footprint,
+                },
+            },
+            0,
+            0,
+            0,
+            &D3D12_TEXTURE_COPY_LOCATION {
+                pResource: Some(texture.clone()),
+                Type: D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+                Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                    SubresourceIndex: 0,
+                },
+            },
+            source_box.map(|source_box| source_box as *const _),
+        );
+        command_list.ResourceBarrier(&[transition_barrier(
+            texture,
+            D3D12_RESOURCE_STATE_COPY_SOURCE,
+            state,
+        )]);
+    })?;
+    Ok(readback)
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use windows::{core::*, Win32::Graphics::Direct3D12::*};
+
+/// 记录每个被跟踪资源最后已知的状态，转换时由它填写屏障的 `StateBefore`，
+/// 避免手写的前一个状态与资源的实际状态不符（调试层之外不会报错，只会出现难以排查的渲染问题）。
+///
+/// 状态在录制命令时更新，所以只适用于按录制顺序提交到同一个队列的命令列表。
+/// 只按资源的地址记录，不持有引用：资源被释放或重新创建（例如 `ResizeBuffers` 之前）时要调用
+/// [`ResourceState::untrack`] 或 [`ResourceState::clear`]，否则新资源可能复用旧地址。
+#[derive(Debug, Default)]
+pub struct ResourceState {
+    states: HashMap<usize, D3D12_RESOURCE_STATES>,
+}
+
+impl ResourceState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key(resource: &ID3D12Resource) -> usize {
+        resource.as_raw() as usize
+    }
+
+    /// 开始跟踪资源，`state` 是它当前的状态，通常是创建资源时的初始状态。
+    pub fn track(&mut self, resource: &ID3D12Resource, state: D3D12_RESOURCE_STATES) {
+        self.states.insert(Self::key(resource), state);
+    }
+
+    pub fn untrack(&mut self, resource: &ID3D12Resource) {
+        self.states.remove(&Self::key(resource));
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+
+    /// 资源最后已知的状态，未跟踪时返回 `None`。
+    pub fn state(&self, resource: &ID3D12Resource) -> Option<D3D12_RESOURCE_STATES> {
+        self.states.get(&Self::key(resource)).copied()
+    }
+
+    /// 生成把资源从最后已知的状态转换到 `new_state` 的屏障，并记下新状态。
+    /// 资源已经处于 `new_state` 时不需要屏障，返回 `None`。转换未跟踪的资源是调用者的错误，会 panic。
+    pub fn transition(
+        &mut self,
+        resource: &ID3D12Resource,
+        new_state: D3D12_RESOURCE_STATES,
+    ) -> Option<D3D12_RESOURCE_BARRIER> {
+        let state = self
+            .states
+            .get_mut(&Self::key(resource))
+            .expect("transition of a resource that is not tracked");
+        if *state == new_state {
+            return None;
+        }
+        let barrier = transition_barrier(resource, *state, new_state);
+        *state = new_state;
+        Some(barrier)
+    }
+}
+
+/// 通过命令列表设置转换资源屏障（transition resource barrier）数组，即可指定资源的转换；当我们希
+/// 望以一次 API 调用来转换多个资源的时候，这种数组就派上了用场。
+/// 我们可以将此资源屏障转换看作是一条告知 GPU 某资源状态正在进行转换的命令。所以在执行后续的命令时，GPU 便会采取必要措施以防资源冒险。
+pub fn transition_barrier(
+    resource: &ID3D12Resource,
+    state_before: D3D12_RESOURCE_STATES,
+    state_after: D3D12_RESOURCE_STATES,
+) -> D3D12_RESOURCE_BARRIER {
+    D3D12_RESOURCE_BARRIER {
+        Type: D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
+        Flags: D3D12_RESOURCE_BARRIER_FLAG_NONE,
+        Anonymous: D3D12_RESOURCE_BARRIER_0 {
+            Transition: std::mem::ManuallyDrop::new(D3D12_RESOURCE_TRANSITION_BARRIER {
+                pResource: Some(resource.clone()),
+                StateBefore: state_before,
+                StateAfter: state_after,
+                Subresource: D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+            }),
+        },
+    }
+}
+
+/// 记录一组屏障，跳过不需要的转换；全部都不需要时不调用 `ResourceBarrier`。
+pub fn resource_barriers(
+    command_list: &ID3D12GraphicsCommandList,
+    barriers: impl IntoIterator<Item = Option<D3D12_RESOURCE_BARRIER>>,
+) {
+    let barriers: Vec<_> = barriers.into_iter().flatten().collect();
+    if !barriers.is_empty() {
+        unsafe { command_list.ResourceBarrier(&barriers) };
+    }
+}
+
+#[test]
+fn redundant_transitions_are_elided() {
+    use crate::devices::warp_device;
+    use crate::upload::create_buffer;
+
+    let device = warp_device();
+    let buffer = create_buffer(
+        &device,
+        D3D12_HEAP_TYPE_DEFAULT,
+        256,
+        D3D12_RESOURCE_STATE_COPY_DEST,
+    )
+    .unwrap();
+
+    let mut states = ResourceState::new();
+    states.track(&buffer, D3D12_RESOURCE_STATE_COPY_DEST);
+    assert!(states
+        .transition(&buffer, D3D12_RESOURCE_STATE_COPY_DEST)
+        .is_none());
+
+    let barrier = states
+        .transition(&buffer, D3D12_RESOURCE_STATE_COPY_SOURCE)
+        .unwrap();
+    let transition = unsafe { &barrier.Anonymous.Transition };
+    assert_eq!(transition.StateBefore, D3D12_RESOURCE_STATE_COPY_DEST);
+    assert_eq!(transition.StateAfter, D3D12_RESOURCE_STATE_COPY_SOURCE);
+    assert_eq!(
+        states.state(&buffer),
+        Some(D3D12_RESOURCE_STATE_COPY_SOURCE)
+    );
+    // 第二次转换到同一个状态时已经没有需要做的事
+    assert!(states
+        .transition(&buffer, D3D12_RESOURCE_STATE_COPY_SOURCE)
+        .is_none());
+
+    states.untrack(&buffer);
+    assert_eq!(states.state(&buffer), None);
+}
@@ -0,0 +1,227 @@
+use crate::devices::create_root_signature_from_desc;
+use windows::{core::*, Win32::Foundation::*, Win32::Graphics::Direct3D12::*};
+
+/// 根签名最多占用 64 个 DWORD：每个 32 位根常量占 1 个，根描述符占 2 个，描述符表占 1 个。
+const MAX_ROOT_SIGNATURE_DWORDS: u32 = 64;
+
+enum RootParameter {
+    /// 根常量或根描述符，不引用其他数组。
+    Inline(D3D12_ROOT_PARAMETER),
+    /// 描述符表，根参数中的指针要到创建时才指向这里的描述符范围。
+    Table {
+        ranges: Vec<D3D12_DESCRIPTOR_RANGE>,
+        visibility: D3D12_SHADER_VISIBILITY,
+    },
+}
+
+impl RootParameter {
+    /// 这个根参数在根签名中占用的 DWORD 数。
+    fn cost(&self) -> u32 {
+        match self {
+            RootParameter::Inline(parameter)
+                if parameter.ParameterType == D3D12_ROOT_PARAMETER_TYPE_32BIT_CONSTANTS =>
+            {
+                unsafe { parameter.Anonymous.Constants }.Num32BitValues
+            }
+            RootParameter::Inline(_) => 2,
+            RootParameter::Table { .. } => 1,
+        }
+    }
+}
+
+/// 按顺序追加根参数来创建根签名，根参数的下标就是追加的顺序。
+///
+/// `D3D12_ROOT_SIGNATURE_DESC` 和描述符表里都只是裸指针，指向的数组必须活到序列化完成，
+/// 在函数里临时拼出的数组很容易提前释放。构建器自己持有所有数组，只在 [`RootSignatureBuilder::build`] 中
+/// 才把指针填进描述，这样就不会出现悬垂指针。
+pub struct RootSignatureBuilder {
+    parameters: Vec<RootParameter>,
+    static_samplers: Vec<D3D12_STATIC_SAMPLER_DESC>,
+    flags: D3D12_ROOT_SIGNATURE_FLAGS,
+}
+
+impl Default for RootSignatureBuilder {
+    /// 没有任何根参数，允许使用输入装配器的输入布局。
+    fn default() -> Self {
+        RootSignatureBuilder {
+            parameters: vec![],
+            static_samplers: vec![],
+            flags: D3D12_ROOT_SIGNATURE_FLAG_ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT,
+        }
+    }
+}
+
+impl RootSignatureBuilder {
+    /// 追加一个根常量或根描述符。描述符表要用 [`RootSignatureBuilder::descriptor_table`]。
+    pub fn parameter(mut self, parameter: D3D12_ROOT_PARAMETER) -> Self {
+        assert_ne!(
+            parameter.ParameterType, D3D12_ROOT_PARAMETER_TYPE_DESCRIPTOR_TABLE,
+            "use descriptor_table for descriptor tables"
+        );
+        self.parameters.push(RootParameter::Inline(parameter));
+        self
+    }
+
+    /// 追加一个由 `ranges` 组成的描述符表，只对 `visibility` 指定的着色器阶段可见。
+    /// 多个纹理可以放在同一个表中，绘制时只需设置一次表的起始句柄。
+    pub fn descriptor_table(
+        mut self,
+        ranges: &[D3D12_DESCRIPTOR_RANGE],
+        visibility: D3D12_SHADER_VISIBILITY,
+    ) -> Self {
+        self.parameters.push(RootParameter::Table {
+            ranges: ranges.to_vec(),
+            visibility,
+        });
+        self
+    }
+
+    /// 静态采样器直接写在根签名里，不占用根参数，也不需要采样器描述符堆。
+    pub fn static_sampler(mut self, sampler: D3D12_STATIC_SAMPLER_DESC) -> Self {
+        self.static_samplers.push(sampler);
+        self
+    }
+
+    pub fn flags(mut self, flags: D3D12_ROOT_SIGNATURE_FLAGS) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// 所有根参数一共占用的 DWORD 数。
+    pub fn cost(&self) -> u32 {
+        self.parameters.iter().map(RootParameter::cost).sum()
+    }
+
+    /// 创建根签名。根参数超过 64 个 DWORD 的上限时返回错误，而不是留给序列化时报出含糊的失败。
+    pub fn build(&self, device: &ID3D12Device) -> Result<ID3D12RootSignature> {
+        let cost = self.cost();
+        if cost > MAX_ROOT_SIGNATURE_DWORDS {
+            return Err(Error::new(
+                E_INVALIDARG,
+                format!(
+                    "root signature uses {} DWORDs, the limit is {}",
+                    cost, MAX_ROOT_SIGNATURE_DWORDS
+                )
+                .into(),
+            ));
+        }
+        let parameters: Vec<D3D12_ROOT_PARAMETER> = self
+            .parameters
+            .iter()
+            .map(|parameter| match parameter {
+                RootParameter::Inline(parameter) => *parameter,
+                RootParameter::Table { ranges, visibility } => D3D12_ROOT_PARAMETER {
+                    ParameterType: D3D12_ROOT_PARAMETER_TYPE_DESCRIPTOR_TABLE,
+                    Anonymous: D3D12_ROOT_PARAMETER_0 {
+                        DescriptorTable: D3D12_ROOT_DESCRIPTOR_TABLE {
+                            NumDescriptorRanges: ranges.len() as u32,
+                            pDescriptorRanges: ranges.as_ptr(),
+                        },
+                    },
+                    ShaderVisibility: *visibility,
+                },
+            })
+            .collect();
+        let desc = D3D12_ROOT_SIGNATURE_DESC {
+            NumParameters: parameters.len() as u32,
+            pParameters: parameters.as_ptr(),
+            NumStaticSamplers: self.static_samplers.len() as u32,
+            pStaticSamplers: self.static_samplers.as_ptr(),
+            Flags: self.flags,
+        };
+        create_root_signature_from_desc(device, &desc)
+    }
+}
+
+/// `shader_register` 上的 `num_32bit_values` 个 32 位根常量。
+///
+/// 根常量直接存放在根签名里，是最廉价的绑定方式，适合每次绘制都变化的少量数据，例如时间或下标；
+/// 代价是每个值都占用根签名的 1 个 DWORD。
+pub fn root_constants(
+    shader_register: u32,
+    num_32bit_values: u32,
+    visibility: D3D12_SHADER_VISIBILITY,
+) -> D3D12_ROOT_PARAMETER {
+    D3D12_ROOT_PARAMETER {
+        ParameterType: D3D12_ROOT_PARAMETER_TYPE_32BIT_CONSTANTS,
+        Anonymous: D3D12_ROOT_PARAMETER_0 {
+            Constants: D3D12_ROOT_CONSTANTS {
+                ShaderRegister: shader_register,
+                RegisterSpace: 0,
+                Num32BitValues: num_32bit_values,
+            },
+        },
+        ShaderVisibility: visibility,
+    }
+}
+
+/// 把 `data` 按 32 位拆开，设置到第 `root_parameter_index` 个根参数（必须是根常量）中，从第 0 个值开始。
+pub fn set_graphics_root_constants<T: Copy>(
+    command_list: &ID3D12GraphicsCommandList,
+    root_parameter_index: u32,
+    data: &T,
+) {
+    let size = std::mem::size_of::<T>();
+    assert_eq!(size % 4, 0, "root constants must be whole 32-bit values");
+    unsafe {
+        command_list.SetGraphicsRoot32BitConstants(
+            root_parameter_index,
+            (size / 4) as u32,
+            data as *const T as *const _,
+            0,
+        )
+    };
+}
+
+/// 从 `base_register` 开始的 `num_descriptors` 个连续寄存器，在表中紧接着上一个范围。
+pub fn descriptor_range(
+    range_type: D3D12_DESCRIPTOR_RANGE_TYPE,
+    num_descriptors: u32,
+    base_register: u32,
+) -> D3D12_DESCRIPTOR_RANGE {
+    D3D12_DESCRIPTOR_RANGE {
+        RangeType: range_type,
+        NumDescriptors: num_descriptors,
+        BaseShaderRegister: base_register,
+        RegisterSpace: 0,
+        OffsetInDescriptorsFromTableStart: D3D12_DESCRIPTOR_RANGE_OFFSET_APPEND,
+    }
+}
+
+#[test]
+fn descriptor_table_with_several_ranges_is_created() {
+    use crate::devices::{time_root_constant, warp_device};
+
+    let device = warp_device();
+    // 构建器在 build 之前就已离开创建它的作用域，范围数组仍由构建器持有
+    let builder = {
+        let ranges = [
+            descriptor_range(D3D12_DESCRIPTOR_RANGE_TYPE_SRV, 4, 0),
+            descriptor_range(D3D12_DESCRIPTOR_RANGE_TYPE_CBV, 1, 2),
+            descriptor_range(D3D12_DESCRIPTOR_RANGE_TYPE_UAV, 1, 0),
+        ];
+        RootSignatureBuilder::default()
+            .parameter(time_root_constant())
+            .descriptor_table(&ranges, D3D12_SHADER_VISIBILITY_ALL)
+    };
+    builder.build(&device).unwrap();
+}
+
+#[test]
+fn root_signature_cost_is_limited() {
+    use crate::devices::{object_constants_root_descriptor, warp_device};
+
+    let fits = RootSignatureBuilder::default()
+        .parameter(root_constants(0, 61, D3D12_SHADER_VISIBILITY_ALL))
+        .parameter(object_constants_root_descriptor())
+        .descriptor_table(
+            &[descriptor_range(D3D12_DESCRIPTOR_RANGE_TYPE_SRV, 8, 0)],
+            D3D12_SHADER_VISIBILITY_PIXEL,
+        );
+    assert_eq!(fits.cost(), 64);
+    let too_large = fits.parameter(root_constants(1, 1, D3D12_SHADER_VISIBILITY_ALL));
+    assert_eq!(too_large.cost(), 65);
+
+    let device = warp_device();
+    assert_eq!(too_large.build(&device).unwrap_err().code(), E_INVALIDARG);
+}
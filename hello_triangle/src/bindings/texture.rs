@@ -0,0 +1,360 @@
+use crate::resource_state::transition_barrier;
+use crate::upload::{create_buffer, execute_and_wait};
+use std::path::Path;
+use windows::{
+    core::*, Win32::Foundation::*, Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::Common::*,
+};
+
+const DDS_MAGIC: u32 = 0x2053_4444; // "DDS "
+const DDS_HEADER_SIZE: usize = 124;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+/// DX10 扩展头中的 `D3D10_RESOURCE_DIMENSION_TEXTURE2D`。
+const DDS_DIMENSION_TEXTURE2D: u32 = 3;
+
+const fn four_cc(code: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*code)
+}
+
+fn dds_error(message: &str) -> Error {
+    Error::new(E_INVALIDARG, format!("dds: {}", message).into())
+}
+
+/// 一个像素（或压缩格式的一个 4x4 块）占用的字节数。
+enum FormatLayout {
+    Pixel(usize),
+    Block(usize),
+}
+
+/// 支持的 DXGI 格式：常见的 8 位 RGBA/BGRA、半精度/单精度浮点以及 BC1~BC7 压缩格式。
+fn format_layout(format: DXGI_FORMAT) -> Option<FormatLayout> {
+    match format {
+        DXGI_FORMAT_R8G8B8A8_UNORM
+        | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+        | DXGI_FORMAT_B8G8R8A8_UNORM
+        | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB
+        | DXGI_FORMAT_B8G8R8X8_UNORM
+        | DXGI_FORMAT_R10G10B10A2_UNORM => Some(FormatLayout::Pixel(4)),
+        DXGI_FORMAT_R16G16B16A16_FLOAT => Some(FormatLayout::Pixel(8)),
+        DXGI_FORMAT_R32G32B32A32_FLOAT => Some(FormatLayout::Pixel(16)),
+        DXGI_FORMAT_BC1_UNORM
+        | DXGI_FORMAT_BC1_UNORM_SRGB
+        | DXGI_FORMAT_BC4_UNORM
+        | DXGI_FORMAT_BC4_SNORM => Some(FormatLayout::Block(8)),
+        DXGI_FORMAT_BC2_UNORM
+        | DXGI_FORMAT_BC2_UNORM_SRGB
+        | DXGI_FORMAT_BC3_UNORM
+        | DXGI_FORMAT_BC3_UNORM_SRGB
+        | DXGI_FORMAT_BC5_UNORM
+        | DXGI_FORMAT_BC5_SNORM
+        | DXGI_FORMAT_BC7_UNORM
+        | DXGI_FORMAT_BC7_UNORM_SRGB => Some(FormatLayout::Block(16)),
+        _ => None,
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// 从 DDS 文件中解析出的第 0 个 mipmap 层级。
+#[derive(Debug, PartialEq)]
+pub struct DdsImage<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub format: DXGI_FORMAT,
+    /// 文件中相邻两行（压缩格式为相邻两行块）之间的字节数，没有任何填充。
+    pub row_pitch: usize,
+    /// 行数，压缩格式为块的行数。
+    pub rows: usize,
+    pub data: &'a [u8],
+}
+
+/// 解析 DDS 文件头，只取第 0 个 mipmap 层级。
+///
+/// 文件以 "DDS " 开头，紧跟 124 字节的 `DDS_HEADER`；像素格式的 FourCC 为 "DX10" 时，
+/// 后面还有 20 字节的 `DDS_HEADER_DXT10`，其中直接给出了 DXGI 格式。
+/// 旧式文件用 FourCC（DXT1/DXT3/DXT5）或 RGB 掩码描述格式，这里只识别其中最常见的几种。
+pub fn parse_dds(bytes: &[u8]) -> Result<DdsImage<'_>> {
+    if bytes.len() < 4 + DDS_HEADER_SIZE || read_u32(bytes, 0) != DDS_MAGIC {
+        return Err(dds_error("not a DDS file"));
+    }
+    let header = &bytes[4..4 + DDS_HEADER_SIZE];
+    if read_u32(header, 0) as usize != DDS_HEADER_SIZE {
+        return Err(dds_error("invalid header size"));
+    }
+    let height = read_u32(header, 8);
+    let width = read_u32(header, 12);
+    let pixel_format_flags = read_u32(header, 76);
+    let fourcc = read_u32(header, 80);
+    let rgb_bit_count = read_u32(header, 84);
+    let masks = [
+        read_u32(header, 88),
+        read_u32(header, 92),
+        read_u32(header, 96),
+        read_u32(header, 100),
+    ];
+
+    let mut data_offset = 4 + DDS_HEADER_SIZE;
+    let format = if pixel_format_flags & DDPF_FOURCC != 0 {
+        match fourcc {
+            x if x == four_cc(b"DX10") => {
+                if bytes.len() < data_offset + DDS_DX10_HEADER_SIZE {
+                    return Err(dds_error("truncated DX10 header"));
+                }
+                let dx10 = &bytes[data_offset..data_offset + DDS_DX10_HEADER_SIZE];
+                if read_u32(dx10, 4) != DDS_DIMENSION_TEXTURE2D || read_u32(dx10, 12) > 1 {
+                    return Err(dds_error("only single 2D textures are supported"));
+                }
+                data_offset += DDS_DX10_HEADER_SIZE;
+                DXGI_FORMAT(read_u32(dx10, 0))
+            }
+            x if x == four_cc(b"DXT1") => DXGI_FORMAT_BC1_UNORM,
+            x if x == four_cc(b"DXT3") => DXGI_FORMAT_BC2_UNORM,
+            x if x == four_cc(b"DXT5") => DXGI_FORMAT_BC3_UNORM,
+            _ => return Err(dds_error("unsupported FourCC")),
+        }
+    } else if pixel_format_flags & DDPF_RGB != 0 && rgb_bit_count == 32 {
+        match masks {
+            [0xff, 0xff00, 0xff_0000, 0xff00_0000] => DXGI_FORMAT_R8G8B8A8_UNORM,
+            [0xff_0000, 0xff00, 0xff, 0xff00_0000] => DXGI_FORMAT_B8G8R8A8_UNORM,
+            [0xff_0000, 0xff00, 0xff, 0] => DXGI_FORMAT_B8G8R8X8_UNORM,
+            _ => return Err(dds_error("unsupported RGB masks")),
+        }
+    } else {
+        return Err(dds_error("unsupported pixel format"));
+    };
+
+    let (row_pitch, rows) = match format_layout(format) {
+        Some(FormatLayout::Pixel(bytes_per_pixel)) => (
+            (width as usize).checked_mul(bytes_per_pixel),
+            height as usize,
+        ),
+        Some(FormatLayout::Block(bytes_per_block)) => (
+            (width as usize).div_ceil(4).checked_mul(bytes_per_block),
+            (height as usize).div_ceil(4),
+        ),
+        None => return Err(dds_error(&format!("unsupported format {:?}", format))),
+    };
+    // 宽高来自文件头，随意写入的值也不能让大小的计算溢出
+    let (row_pitch, data_end) = row_pitch
+        .and_then(|row_pitch| {
+            let data_end = row_pitch.checked_mul(rows)?.checked_add(data_offset)?;
+            Some((row_pitch, data_end))
+        })
+        .ok_or_else(|| dds_error("image dimensions are too large"))?;
+    let data = bytes
+        .get(data_offset..data_end)
+        .ok_or_else(|| dds_error("truncated pixel data"))?;
+
+    Ok(DdsImage {
+        width,
+        height,
+        format,
+        row_pitch,
+        rows,
+        data,
+    })
+}
+
+/// 读取 DDS 文件，把第 0 个 mipmap 层级上传到默认堆中的 2D 纹理，返回纹理和对应的 SRV 描述。
+///
+/// 纹理在默认堆中的布局由驱动决定，每一行都要按 D3D12_TEXTURE_DATA_PITCH_ALIGNMENT（256 字节）对齐，
+/// 所以先用 `GetCopyableFootprints` 算出上传缓冲区中的布局，逐行复制像素，
+/// 再用 `CopyTextureRegion` 复制到纹理中，最后转换为像素着色器资源状态。
+pub fn load_dds(
+    device: &ID3D12Device,
+    command_queue: &ID3D12CommandQueue,
+    path: &Path,
+) -> Result<(ID3D12Resource, D3D12_SHADER_RESOURCE_VIEW_DESC)> {
+    let bytes = std::fs::read(path).map_err(|error| {
+        Error::new(
+            E_FAIL,
+            format!("failed to read {}: {}", path.display(), error).into(),
+        )
+    })?;
+    let image = parse_dds(&bytes)?;
+
+    let texture_desc = D3D12_RESOURCE_DESC {
+        Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+        Width: image.width as u64,
+        Height: image.height,
+        DepthOrArraySize: 1,
+        MipLevels: 1,
+        Format: image.format,
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        Layout: D3D12_TEXTURE_LAYOUT_UNKNOWN,
+        ..Default::default()
+    };
+    let mut texture: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: D3D12_HEAP_TYPE_DEFAULT,
+                ..Default::default()
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &texture_desc,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+            None,
+            &mut texture,
+        )?
+    };
+    let texture = texture.unwrap();
+
+    let mut footprint = D3D12_PLACED_SUBRESOURCE_FOOTPRINT::default();
+    let mut num_rows = 0;
+    let mut row_size = 0;
+    let mut total_bytes = 0;
+    unsafe {
+        device.GetCopyableFootprints(
+            &texture_desc,
+            0,
+            1,
+            0,
+            Some(&mut footprint),
+            Some(&mut num_rows),
+            Some(&mut row_size),
+            Some(&mut total_bytes),
+        )
+    };
+    let staging = create_buffer(
+        device,
+        D3D12_HEAP_TYPE_UPLOAD,
+        total_bytes,
+        D3D12_RESOURCE_STATE_GENERIC_READ,
+    )?;
+    unsafe {
+        let mut mapped = std::ptr::null_mut();
+        staging.Map(0, None, Some(&mut mapped))?;
+        let destination = (mapped as *mut u8).add(footprint.Offset as usize);
+        for row in 0..num_rows as usize {
+            std::ptr::copy_nonoverlapping(
+                image.data.as_ptr().add(row * image.row_pitch),
+                destination.add(row * footprint.Footprint.RowPitch as usize),
+                row_size as usize,
+            );
+        }
+        staging.Unmap(0, None);
+    }
+
+    execute_and_wait(device, command_queue, |command_list| unsafe {
+        command_list.CopyTextureRegion(
+            &D3D12_TEXTURE_COPY_LOCATION {
+                pResource: Some(texture.clone()),
+                Type: D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+                Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                    SubresourceIndex: 0,
+                },
+            },
+            0,
+            0,
+            0,
+            &D3D12_TEXTURE_COPY_LOCATION {
+                pResource: Some(staging.clone()),
+                Type: D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
+                Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                    PlacedFootprint: footprint,
+                },
+            },
+            None,
+        );
+        command_list.ResourceBarrier(&[transition_barrier(
+            &texture,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+            D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+        )]);
+    })?;
+
+    let srv_desc = D3D12_SHADER_RESOURCE_VIEW_DESC {
+        Format: image.format,
+        ViewDimension: D3D12_SRV_DIMENSION_TEXTURE2D,
+        Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+        Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+            Texture2D: D3D12_TEX2D_SRV {
+                MostDetailedMip: 0,
+                MipLevels: 1,
+                PlaneSlice: 0,
+                ResourceMinLODClamp: 0.0,
+            },
+        },
+    };
+    Ok((texture, srv_desc))
+}
+
+#[cfg(test)]
+fn dds_header(width: u32, height: u32, pixel_format_flags: u32, fourcc: &[u8; 4]) -> Vec<u8> {
+    let mut bytes = vec![0u8; 4 + DDS_HEADER_SIZE];
+    let mut write = |offset: usize, value: u32| {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    };
+    write(0, DDS_MAGIC);
+    write(4, DDS_HEADER_SIZE as u32);
+    write(4 + 8, height);
+    write(4 + 12, width);
+    write(4 + 76, pixel_format_flags);
+    write(4 + 80, four_cc(fourcc));
+    bytes
+}
+
+#[test]
+fn parse_dds_rgba8() {
+    let mut bytes = dds_header(2, 3, DDPF_RGB, &[0; 4]);
+    for (offset, value) in [
+        (84, 32u32),
+        (88, 0xff),
+        (92, 0xff00),
+        (96, 0xff_0000),
+        (100, 0xff00_0000),
+    ] {
+        bytes[4 + offset..4 + offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+    bytes.extend((0..2 * 3 * 4).map(|i| i as u8));
+
+    let image = parse_dds(&bytes).unwrap();
+    assert_eq!((image.width, image.height), (2, 3));
+    assert_eq!(image.format, DXGI_FORMAT_R8G8B8A8_UNORM);
+    assert_eq!((image.row_pitch, image.rows), (8, 3));
+    assert_eq!(image.data.len(), 24);
+
+    bytes.pop();
+    assert!(parse_dds(&bytes).is_err());
+
+    // 宽高都取最大值时数据的大小超出了 usize，应当报错而不是溢出
+    let mut bytes = dds_header(u32::MAX, u32::MAX, DDPF_FOURCC, b"DX10");
+    for value in [
+        DXGI_FORMAT_R32G32B32A32_FLOAT.0,
+        DDS_DIMENSION_TEXTURE2D,
+        0,
+        1,
+        0,
+    ] {
+        bytes.extend(value.to_le_bytes());
+    }
+    assert_eq!(parse_dds(&bytes).unwrap_err().code(), E_INVALIDARG);
+}
+
+#[test]
+fn parse_dds_block_compressed() {
+    // 5x5 的 BC1 纹理占 2x2 个 4x4 块，每块 8 字节
+    let mut bytes = dds_header(5, 5, DDPF_FOURCC, b"DXT1");
+    bytes.extend([0u8; 32]);
+    let image = parse_dds(&bytes).unwrap();
+    assert_eq!(image.format, DXGI_FORMAT_BC1_UNORM);
+    assert_eq!((image.row_pitch, image.rows), (16, 2));
+
+    let mut bytes = dds_header(4, 4, DDPF_FOURCC, b"DX10");
+    for value in [DXGI_FORMAT_BC7_UNORM.0, DDS_DIMENSION_TEXTURE2D, 0, 1, 0] {
+        bytes.extend(value.to_le_bytes());
+    }
+    bytes.extend([0u8; 16]);
+    let image = parse_dds(&bytes).unwrap();
+    assert_eq!(image.format, DXGI_FORMAT_BC7_UNORM);
+    assert_eq!((image.row_pitch, image.rows), (16, 1));
+
+    assert!(parse_dds(b"not a dds file").is_err());
+}
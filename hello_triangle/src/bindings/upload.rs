@@ -0,0 +1,271 @@
+use crate::constant_buffer::align_up;
+use crate::fence::{Fence, FenceValue};
+use crate::resource_state::transition_barrier;
+use std::collections::VecDeque;
+use windows::{core::*, Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::Common::*};
+
+/// 创建一个缓冲区资源。上传堆中的缓冲区必须以 GENERIC_READ 状态创建。
+pub fn create_buffer(
+    device: &ID3D12Device,
+    heap_type: D3D12_HEAP_TYPE,
+    size: u64,
+    initial_state: D3D12_RESOURCE_STATES,
+) -> Result<ID3D12Resource> {
+    let mut buffer: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: heap_type,
+                ..Default::default()
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &D3D12_RESOURCE_DESC {
+                Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+                Width: size,
+                Height: 1,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                ..Default::default()
+            },
+            initial_state,
+            None,
+            &mut buffer,
+        )?
+    };
+    Ok(buffer.unwrap())
+}
+
+/// 把 `data` 放进默认堆中的缓冲区，返回的资源处于 `state_after` 状态。
+///
+/// CPU 不能直接写默认堆，所以先把数据复制到上传堆中的临时缓冲区（staging buffer），
+/// 再在 `command_queue` 上用 `CopyBufferRegion` 复制到默认堆。函数会等待复制执行完毕才返回，
+/// 临时缓冲区在此之前必须一直存活，返回后即可释放。适合创建后就不再变化的数据，例如静态几何体：
+/// GPU 每次读取默认堆都是读本地显存，而上传堆中的数据每次都要经过总线。
+pub fn upload_buffer_to_default_heap<T: Copy>(
+    device: &ID3D12Device,
+    command_queue: &ID3D12CommandQueue,
+    data: &[T],
+    state_after: D3D12_RESOURCE_STATES,
+) -> Result<ID3D12Resource> {
+    let size = std::mem::size_of_val(data) as u64;
+
+    // 缓冲区总是以 COMMON 状态创建，复制时会隐式提升为 COPY_DEST
+    let buffer = create_buffer(
+        device,
+        D3D12_HEAP_TYPE_DEFAULT,
+        size,
+        D3D12_RESOURCE_STATE_COMMON,
+    )?;
+    let staging = create_buffer(
+        device,
+        D3D12_HEAP_TYPE_UPLOAD,
+        size,
+        D3D12_RESOURCE_STATE_GENERIC_READ,
+    )?;
+    unsafe {
+        let mut mapped = std::ptr::null_mut();
+        staging.Map(0, None, Some(&mut mapped))?;
+        std::ptr::copy_nonoverlapping(data.as_ptr(), mapped as *mut T, data.len());
+        staging.Unmap(0, None);
+    }
+
+    execute_and_wait(device, command_queue, |command_list| unsafe {
+        command_list.CopyBufferRegion(&buffer, 0, &staging, 0, size);
+        command_list.ResourceBarrier(&[transition_barrier(
+            &buffer,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+            state_after,
+        )]);
+    })?;
+
+    // 复制已经完成，临时缓冲区可以随函数返回一起释放
+    drop(staging);
+    Ok(buffer)
+}
+
+/// 用一个临时的命令列表录制 `record` 中的命令，在 `command_queue` 上执行，并在 CPU 端等待它执行完毕。
+/// 只适合初始化时的一次性工作，例如上传静态数据。
+pub fn execute_and_wait(
+    device: &ID3D12Device,
+    command_queue: &ID3D12CommandQueue,
+    record: impl FnOnce(&ID3D12GraphicsCommandList),
+) -> Result<()> {
+    let command_type = unsafe { command_queue.GetDesc() }.Type;
+    let command_allocator: ID3D12CommandAllocator =
+        unsafe { device.CreateCommandAllocator(command_type) }?;
+    let command_list: ID3D12GraphicsCommandList =
+        unsafe { device.CreateCommandList(0, command_type, &command_allocator, None) }?;
+    record(&command_list);
+    unsafe {
+        command_list.Close()?;
+        command_queue.ExecuteCommandLists(&[Some(command_list.cast()?)]);
+    }
+
+    Fence::new(device)?.flush(command_queue)
+}
+
+/// 上传堆中的环形缓冲区，存放每帧都会变化的数据，例如在 CPU 上生成的顶点或常量。
+///
+/// 每帧从上一次分配的末尾往后顺序分配，末尾放不下时回到开头；帧结束时调用 [`UploadRingBuffer::end_frame`]
+/// 记下这一帧 Signal 的围栏值。GPU 执行完一帧之前，这一帧分配的区域不会再分配出去：
+/// 空间不够时先回收围栏已经越过的帧，仍然不够就在 CPU 端等待最早的那一帧。
+/// 资源一直保持映射，写入分配到的内存后即可在这一帧的命令中使用它的 GPU 虚拟地址。
+pub struct UploadRingBuffer {
+    resource: ID3D12Resource,
+    mapped: *mut u8,
+    size: u64,
+    /// 下一次分配从这里开始查找。
+    head: u64,
+    /// GPU 可能还在读取的字节数，包括回绕时跳过的末尾部分；它们紧挨在 `head` 之前。
+    used: u64,
+    /// 当前帧到目前为止占用的字节数。
+    frame_bytes: u64,
+    /// 已经结束、可能还在飞行中的帧：结束时 Signal 的围栏值和它占用的字节数，按时间顺序排列。
+    in_flight: VecDeque<(FenceValue, u64)>,
+}
+
+impl UploadRingBuffer {
+    pub fn new(device: &ID3D12Device, size: u64) -> Result<Self> {
+        let resource = create_buffer(
+            device,
+            D3D12_HEAP_TYPE_UPLOAD,
+            size,
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+        )?;
+        // 读取范围为空，表示 CPU 不会读取这块内存
+        let mut mapped = std::ptr::null_mut();
+        unsafe {
+            resource.Map(
+                0,
+                Some(&D3D12_RANGE { Begin: 0, End: 0 }),
+                Some(&mut mapped),
+            )?
+        };
+        Ok(UploadRingBuffer {
+            resource,
+            mapped: mapped as *mut u8,
+            size,
+            head: 0,
+            used: 0,
+            frame_bytes: 0,
+            in_flight: VecDeque::new(),
+        })
+    }
+
+    /// 分配 `size` 字节、起点按 `alignment`（2 的幂）对齐的区域，返回它的 GPU 虚拟地址和映射的 CPU 指针。
+    ///
+    /// `fence` 是 [`UploadRingBuffer::end_frame`] 中的围栏值所属的围栏，空间不够时要等待它。
+    /// 只靠当前帧的分配就放不下时直接 panic：那说明缓冲区对每帧的数据量来说太小了，等待也无济于事。
+    pub fn suballocate(
+        &mut self,
+        fence: &Fence,
+        size: u64,
+        alignment: u64,
+    ) -> Result<(u64, *mut u8)> {
+        assert!(
+            alignment.is_power_of_two(),
+            "alignment must be a power of two"
+        );
+        let completed_value = unsafe { fence.fence().GetCompletedValue() };
+        while let Some(&(fence_value, bytes)) = self.in_flight.front() {
+            if !fence_value.is_completed(completed_value) {
+                break;
+            }
+            self.retire_oldest_frame(bytes);
+        }
+
+        let (offset, needed) = loop {
+            let (offset, needed) = self.placement(size, alignment);
+            if self.used + needed <= self.size {
+                break (offset, needed);
+            }
+            let Some(&(fence_value, bytes)) = self.in_flight.front() else {
+                panic!(
+                    "upload ring buffer of {} bytes cannot fit {} more bytes in one frame ({} already used)",
+                    self.size, size, self.frame_bytes
+                );
+            };
+            // 最早的那一帧还在飞行中，等 GPU 读完它的数据
+            fence.wait(fence_value)?;
+            self.retire_oldest_frame(bytes);
+        };
+        self.head = offset + size;
+        self.used += needed;
+        self.frame_bytes += needed;
+        let gpu_virtual_address = unsafe { self.resource.GetGPUVirtualAddress() } + offset;
+        Ok((gpu_virtual_address, unsafe {
+            self.mapped.add(offset as usize)
+        }))
+    }
+
+    /// 把 `data` 复制到新分配的区域中，返回它的 GPU 虚拟地址，按 `T` 的对齐要求对齐。
+    /// 常量缓冲区要按 256 字节对齐，请直接用 [`UploadRingBuffer::suballocate`]。
+    pub fn upload<T: Copy>(&mut self, fence: &Fence, data: &[T]) -> Result<u64> {
+        let (gpu_virtual_address, mapped) = self.suballocate(
+            fence,
+            std::mem::size_of_val(data) as u64,
+            std::mem::align_of::<T>() as u64,
+        )?;
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), mapped as *mut T, data.len()) };
+        Ok(gpu_virtual_address)
+    }
+
+    /// 当前帧的命令已经提交，并在同一队列上 Signal 了 `fence_value`。这一帧分配的区域要等围栏越过它才能重用。
+    pub fn end_frame(&mut self, fence_value: FenceValue) {
+        if self.frame_bytes > 0 {
+            self.in_flight.push_back((fence_value, self.frame_bytes));
+            self.frame_bytes = 0;
+        }
+    }
+
+    /// 新的分配放在哪里、连同对齐和回绕跳过的字节一共占用多少字节。
+    fn placement(&mut self, size: u64, alignment: u64) -> (u64, u64) {
+        if self.used == 0 {
+            // 没有任何数据在使用中，从头开始，省得在末尾浪费空间
+            self.head = 0;
+        }
+        let aligned = align_up(self.head, alignment);
+        if aligned + size <= self.size {
+            (aligned, aligned - self.head + size)
+        } else {
+            (0, self.size - self.head + size)
+        }
+    }
+
+    fn retire_oldest_frame(&mut self, bytes: u64) {
+        self.in_flight.pop_front();
+        self.used -= bytes;
+    }
+}
+
+#[test]
+fn ring_buffer_wraps_after_frames_complete() {
+    use crate::devices::warp_device_and_queue;
+    use crate::fence::Fence;
+
+    let (device, queue) = warp_device_and_queue();
+    let mut fence = Fence::new(&device).unwrap();
+    let mut ring = UploadRingBuffer::new(&device, 256).unwrap();
+    let base = unsafe { ring.resource.GetGPUVirtualAddress() };
+
+    let first = ring.upload(&fence, &[1u32; 25]).unwrap();
+    let (second, _) = ring.suballocate(&fence, 100, 16).unwrap();
+    assert_eq!(first, base);
+    assert_eq!(second, base + 112);
+    ring.end_frame(fence.signal(&queue).unwrap());
+
+    // 末尾只剩 44 字节，要回到开头，这需要等上一帧在 GPU 上执行完
+    let (third, _) = ring.suballocate(&fence, 200, 4).unwrap();
+    assert_eq!(third, base);
+
+    // 同一帧的分配之间不会互相等待，放不下就是缓冲区太小了
+    let overflow = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _ = ring.suballocate(&fence, 100, 4);
+    }));
+    assert!(overflow.is_err());
+}
@@ -0,0 +1,236 @@
+use std::marker::PhantomData;
+use std::mem::offset_of;
+use windows::{core::*, Win32::Graphics::Direct3D12::*, Win32::Graphics::Dxgi::Common::*};
+
+/// PSO 的输入布局：顶点结构体的每个字段一个输入元素，实例化绘制时还包括第 1 个输入槽中的实例数据。
+/// 创建 PSO 时用它取得 `D3D12_INPUT_LAYOUT_DESC` 中的元素。
+///
+/// 输入元素的 `AlignedByteOffset` 必须与字段在结构体中的偏移一致，否则着色器读到的是错位的数据，
+/// 而且不会有任何报错。顶点结构体实现时用 `offset_of!` 取得偏移，而不是手写 0、12 这样的数字，
+/// 调整字段顺序或类型后布局也会跟着变。结构体要加上 `#[repr(C)]`，字段才会按声明的顺序排列。
+/// 元素按字段的顺序排列，交给 [`checked_input_layout`] 检查，加了字段却忘了加输入元素时会立即发现。
+pub trait InputLayout {
+    fn input_layout() -> Vec<D3D12_INPUT_ELEMENT_DESC>;
+}
+
+/// 检查顶点结构体 `V` 的输入元素：最后一个元素的偏移加上它的大小正好是 `V` 的大小。
+///
+/// 不相等说明结构体末尾有布局中没有的字段，或者某个元素的格式与字段的类型大小不符。
+pub fn checked_input_layout<V>(
+    elements: &[D3D12_INPUT_ELEMENT_DESC],
+) -> Vec<D3D12_INPUT_ELEMENT_DESC> {
+    let last = elements.last().expect("vertex layout has no elements");
+    let end = last.AlignedByteOffset as usize + format_size(last.Format);
+    assert_eq!(
+        end,
+        std::mem::size_of::<V>(),
+        "input layout of {} ends at byte {}, but the struct is {} bytes",
+        std::any::type_name::<V>(),
+        end,
+        std::mem::size_of::<V>()
+    );
+    elements.to_vec()
+}
+
+/// 实例化绘制的输入布局：第 0 个输入槽逐顶点读取 `V`，第 1 个输入槽逐实例读取 `I`。
+///
+/// 输入装配器按 `SV_InstanceID` 从第 1 个槽取出这个实例的数据，顶点着色器看到的每个顶点都同时带着两部分输入。
+/// 绘制时两个槽要分别用 `IASetVertexBuffers` 绑定。
+pub struct Instanced<V, I>(PhantomData<(V, I)>);
+
+impl<V: InputLayout, I: InputLayout> InputLayout for Instanced<V, I> {
+    fn input_layout() -> Vec<D3D12_INPUT_ELEMENT_DESC> {
+        let vertex = V::input_layout();
+        let instance = I::input_layout();
+        assert!(
+            vertex.iter().all(|element| element.InputSlot == 0
+                && element.InputSlotClass == D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA),
+            "{} must be per-vertex data in slot 0",
+            std::any::type_name::<V>()
+        );
+        assert!(
+            instance.iter().all(|element| element.InputSlot == 1
+                && element.InputSlotClass == D3D12_INPUT_CLASSIFICATION_PER_INSTANCE_DATA),
+            "{} must be per-instance data in slot 1",
+            std::any::type_name::<I>()
+        );
+        [vertex, instance].concat()
+    }
+}
+
+/// 顶点元素格式的字节数，只支持顶点数据常用的格式。
+fn format_size(format: DXGI_FORMAT) -> usize {
+    match format {
+        DXGI_FORMAT_R32G32B32A32_FLOAT => 16,
+        DXGI_FORMAT_R32G32B32_FLOAT => 12,
+        DXGI_FORMAT_R32G32_FLOAT => 8,
+        DXGI_FORMAT_R32_FLOAT | DXGI_FORMAT_R8G8B8A8_UNORM | DXGI_FORMAT_R32_UINT => 4,
+        _ => panic!("unsupported vertex element format {:?}", format),
+    }
+}
+
+/// 第 0 个输入槽中逐顶点读取的元素，`offset` 是字段在顶点结构体中的字节偏移。
+pub const fn per_vertex_element(
+    semantic_name: PCSTR,
+    format: DXGI_FORMAT,
+    offset: usize,
+) -> D3D12_INPUT_ELEMENT_DESC {
+    D3D12_INPUT_ELEMENT_DESC {
+        SemanticName: semantic_name,
+        SemanticIndex: 0,
+        Format: format,
+        InputSlot: 0,
+        AlignedByteOffset: offset as u32,
+        InputSlotClass: D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA,
+        InstanceDataStepRate: 0,
+    }
+}
+
+/// 第 1 个输入槽中逐实例读取的元素，每画完一个实例前进一次（`InstanceDataStepRate` 为 1）。
+pub const fn per_instance_element(
+    semantic_name: PCSTR,
+    format: DXGI_FORMAT,
+    offset: usize,
+) -> D3D12_INPUT_ELEMENT_DESC {
+    D3D12_INPUT_ELEMENT_DESC {
+        SemanticName: semantic_name,
+        SemanticIndex: 0,
+        Format: format,
+        InputSlot: 1,
+        AlignedByteOffset: offset as u32,
+        InputSlotClass: D3D12_INPUT_CLASSIFICATION_PER_INSTANCE_DATA,
+        InstanceDataStepRate: 1,
+    }
+}
+
+/// 位置和颜色，`shaders.hlsl` 中 `VSMain` 的输入，三角形和立方体都用它。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VertexPC {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl InputLayout for VertexPC {
+    fn input_layout() -> Vec<D3D12_INPUT_ELEMENT_DESC> {
+        checked_input_layout::<VertexPC>(&[
+            per_vertex_element(
+                s!("POSITION"),
+                DXGI_FORMAT_R32G32B32_FLOAT,
+                offset_of!(VertexPC, position),
+            ),
+            per_vertex_element(
+                s!("COLOR"),
+                DXGI_FORMAT_R32G32B32A32_FLOAT,
+                offset_of!(VertexPC, color),
+            ),
+        ])
+    }
+}
+
+/// 位置、法线和纹理坐标，光照和贴图需要的顶点数据。
+///
+/// 着色器中对应的输入是 `float3 position : POSITION; float3 normal : NORMAL; float2 texcoord : TEXCOORD;`。
+/// 法线贴图还需要切线，到时在 `normal` 之后加一个 `TANGENT` 字段即可。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VertexPNT {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub texcoord: [f32; 2],
+}
+
+impl InputLayout for VertexPNT {
+    fn input_layout() -> Vec<D3D12_INPUT_ELEMENT_DESC> {
+        checked_input_layout::<VertexPNT>(&[
+            per_vertex_element(
+                s!("POSITION"),
+                DXGI_FORMAT_R32G32B32_FLOAT,
+                offset_of!(VertexPNT, position),
+            ),
+            per_vertex_element(
+                s!("NORMAL"),
+                DXGI_FORMAT_R32G32B32_FLOAT,
+                offset_of!(VertexPNT, normal),
+            ),
+            per_vertex_element(
+                s!("TEXCOORD"),
+                DXGI_FORMAT_R32G32_FLOAT,
+                offset_of!(VertexPNT, texcoord),
+            ),
+        ])
+    }
+}
+
+/// 每个实例的平移、缩放和颜色，位于第 1 个输入槽，与 [`VertexPC`] 一起组成 `Instanced<VertexPC, InstanceData>`。
+///
+/// 顶点着色器先把顶点位置乘以 `scale` 再加上 `offset`，顶点颜色乘以 `color`。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InstanceData {
+    pub offset: [f32; 2],
+    pub scale: f32,
+    pub color: [f32; 4],
+}
+
+impl InputLayout for InstanceData {
+    fn input_layout() -> Vec<D3D12_INPUT_ELEMENT_DESC> {
+        checked_input_layout::<InstanceData>(&[
+            per_instance_element(
+                s!("INSTANCE_OFFSET"),
+                DXGI_FORMAT_R32G32_FLOAT,
+                offset_of!(InstanceData, offset),
+            ),
+            per_instance_element(
+                s!("INSTANCE_SCALE"),
+                DXGI_FORMAT_R32_FLOAT,
+                offset_of!(InstanceData, scale),
+            ),
+            per_instance_element(
+                s!("INSTANCE_COLOR"),
+                DXGI_FORMAT_R32G32B32A32_FLOAT,
+                offset_of!(InstanceData, color),
+            ),
+        ])
+    }
+}
+
+#[test]
+fn instanced_layout_puts_instance_data_in_slot_1() {
+    let layout = Instanced::<VertexPC, InstanceData>::input_layout();
+    let slots = layout
+        .iter()
+        .map(|element| (element.InputSlot, element.AlignedByteOffset))
+        .collect::<Vec<_>>();
+    assert_eq!(slots, [(0, 0), (0, 12), (1, 0), (1, 8), (1, 12)]);
+    assert!(layout[2..]
+        .iter()
+        .all(|element| element.InstanceDataStepRate == 1));
+    // 顶点数据不能放在实例的槽中
+    assert!(std::panic::catch_unwind(Instanced::<VertexPC, VertexPC>::input_layout).is_err());
+}
+
+#[test]
+fn input_layout_offsets_follow_the_struct() {
+    let offsets = |elements: &[D3D12_INPUT_ELEMENT_DESC]| {
+        elements
+            .iter()
+            .map(|element| element.AlignedByteOffset)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(offsets(&VertexPC::input_layout()), [0, 12]);
+    assert_eq!(offsets(&VertexPNT::input_layout()), [0, 12, 24]);
+
+    // 多了一个布局中没有的字段
+    #[repr(C)]
+    struct Extended {
+        _vertex: VertexPC,
+        _weight: f32,
+    }
+    impl InputLayout for Extended {
+        fn input_layout() -> Vec<D3D12_INPUT_ELEMENT_DESC> {
+            checked_input_layout::<Extended>(&VertexPC::input_layout())
+        }
+    }
+    assert!(std::panic::catch_unwind(Extended::input_layout).is_err());
+}
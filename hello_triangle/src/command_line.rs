@@ -1,19 +1,507 @@
-#[derive(Clone)]
+use crate::{
+    BackBufferFormat, BlendMode, CoordinateSystem, DepthMode, DisplayMode, SampleKind, ShaderModel,
+};
+use std::path::Path;
+
+#[derive(Clone, Default)]
 pub struct SampleCommandLine {
+    /// `-sample triangle|box|outline`，要运行的示例。
+    pub sample: SampleKind,
     /// WARP 意为 Windows Advanced Rasterization Platform（Windows 高级光栅化平台）。
     pub use_warp_device: bool,
+    /// 打印当前系统推荐的呈现配置（格式、交换效果、呈现间隔、撕裂）。
+    pub print_present_info: bool,
+    /// 进入渲染循环前弹框暂停，方便挂上调试器或 PIX/RenderDoc 抓取第一帧。
+    pub pause_on_start: bool,
+    /// 用独立的计算队列生成顶点偏移，演示跨队列同步。
+    pub async_compute: bool,
+    /// `-coords lh|rh`，决定几何体的环绕顺序和光栅器的正面朝向。
+    pub coordinate_system: CoordinateSystem,
+    /// `-blend opaque|alpha|additive`，三角形的混合方式；半透明时绘制两个相互重叠的三角形。
+    pub blend_mode: BlendMode,
+    /// `-shadermodel 5_0|6_0`，5.0 用 FXC 编译，6.0 用 DXC 编译。
+    pub shader_model: ShaderModel,
+    /// `-format rgba8|bgra8|rgb10a2|rgba16f`，交换链后台缓冲区的格式。
+    pub back_buffer_format: BackBufferFormat,
+    /// `-hdr`：显示器处于 HDR 模式时改用 10 位的后台缓冲区，并以 HDR10 色彩空间呈现，会覆盖 `-format`。
+    pub hdr: bool,
+    /// `-srgb`：交换链缓冲区仍是线性格式，渲染目标视图和 PSO 改用对应的 sRGB 格式，
+    /// 着色器输出按线性值写入并由硬件做伽马编码。只支持 `rgba8` 和 `bgra8`。
+    pub srgb: bool,
+    /// `-reversez`：以反转 Z 和浮点深度格式渲染，见 [`DepthMode`]。
+    pub depth_mode: DepthMode,
+    /// `-mode WxH@Hz`，以最接近的显示模式进入独占全屏。
+    pub display_mode: Option<DisplayMode>,
+    /// 打印主显示器在当前后台缓冲区格式下支持的所有显示模式。
+    pub list_display_modes: bool,
+    /// `-width N`、`-height N`，窗口客户区（也就是后台缓冲区）的大小，只给出一边时另一边为 0，
+    /// 表示使用示例的默认值，见 [`SampleCommandLine::resolve_window_size`]。超过 `MAX_WINDOW_SIZE`（16384）的值被当作无效值忽略。
+    pub window_size: Option<(u32, u32)>,
+    /// `-monitor N`，把窗口放到第 N 个显示器（从 0 开始）上并铺满它的工作区。
+    pub monitor: Option<u32>,
+    /// `-adapter N`，使用第 N 个（从 0 开始）支持 Direct3D 12 的硬件适配器，默认使用第一个。
+    pub adapter_index: Option<u32>,
+    /// `-assets DIR`，优先从这个目录加载着色器等资源。
+    pub assets: Option<String>,
+    /// `-texture FILE`，在右下角的方块上显示这个 DDS 纹理；未指定时若资源目录中有 texture.dds 就使用它。
+    pub texture: Option<String>,
+    /// 创建完所有资源后，按类别打印它们占用的显存估计值。
+    pub print_vram_usage: bool,
+    /// 开启调试层的 GPU 验证（GPU-based validation），能发现资源状态和描述符方面的错误，但非常慢。
+    pub gpu_validation: bool,
+    /// 创建 PSO 前打印它的描述摘要。
+    pub verbose: bool,
+    /// 使用可等待交换链（帧延迟事件），降低输入到呈现的延迟。
+    pub waitable: bool,
+    /// `-novsync`：呈现时不等待垂直同步，支持撕裂时以 `DXGI_PRESENT_ALLOW_TEARING` 呈现，帧率不受刷新率限制。
+    pub no_vsync: bool,
+    /// 不创建窗口，在 WARP 设备上自检所有主要的代码路径，失败时以非零值退出。
+    pub self_test: bool,
+    /// `-headless FILE`，不创建窗口，按 `-width`/`-height` 离屏渲染几帧，把最后一帧保存为 PNG。
+    /// 只有支持离屏渲染的示例可以使用，见 [`crate::run_sample`]。
+    pub headless: Option<String>,
+    /// 在链接节点（多 GPU）设备上演示按节点提交工作。
+    pub multi_node: bool,
+    /// 三角形的索引缓冲区使用 32 位索引，默认是 16 位。
+    pub index32: bool,
+    /// 三角形的顶点缓冲区经由上传堆复制到默认堆，而不是直接放在上传堆中。
+    pub use_default_heap: bool,
+    /// 每帧在 CPU 上重新生成三角形的顶点，写入上传堆中的环形缓冲区。
+    pub dynamic_vertices: bool,
+    /// 每帧直接在命令列表中录制三角形的绘制命令，不使用初始化时录制好的 bundle。
+    pub no_bundle: bool,
+    /// 开启 4X MSAA：渲染到多重采样的渲染目标，再解析（resolve）到后台缓冲区。
+    pub msaa: bool,
+    /// 立方体同时把法线和位置写入两个渲染目标（G-buffer），后台缓冲区中显示的是法线。
+    pub gbuffer: bool,
+    /// 每秒打印一次帧间隔统计和 CPU 等待围栏的时间。
+    pub frame_stats: bool,
+    /// `-particles N`，用计算着色器更新并实例化绘制 N 个粒子，0 表示关闭。
+    pub particles: u32,
+    /// `-points N`，用点列表绘制 N 个点，并在几何着色器中扩展成点精灵，0 表示关闭。
+    pub points: u32,
+    /// `-instances N`，用一次实例化绘制画出 N 个三角形排成的网格，0 或 1 表示只画一个。
+    /// 超过 `MAX_INSTANCES`（2^20）的值被当作无效值忽略。
+    pub instances: u32,
+    /// `-cycleadapters secs=N`，每隔 N 秒在下一个硬件适配器上重新创建设备，并打印上一个适配器的帧率。
+    pub cycle_adapters: Option<u32>,
+    /// `-buffers N`，交换链后台缓冲区的数量，0 表示使用默认值，见 [`SampleCommandLine::buffer_count`]。
+    pub buffers: u32,
+    /// `-framesinflight N`，CPU 最多领先 GPU 的帧数，0 表示使用默认值，见 [`SampleCommandLine::max_frames_in_flight`]。
+    pub frames_in_flight: u32,
+}
+
+/// 翻转模型的交换链至少需要 2 个、至多 16 个（DXGI_MAX_SWAP_CHAIN_BUFFERS）后台缓冲区。
+const BUFFER_COUNT_RANGE: (u32, u32) = (2, 16);
+const DEFAULT_BUFFER_COUNT: u32 = 2;
+/// 再多的帧也只会增加输入延迟，3 帧已经足以让 CPU 和 GPU 充分并行。
+const FRAMES_IN_FLIGHT_RANGE: (u32, u32) = (1, 3);
+const DEFAULT_FRAMES_IN_FLIGHT: u32 = 2;
+/// `-instances` 的上限。实例缓冲区视图的 `SizeInBytes` 是 u32，这样它远不会溢出；
+/// 何况这么密的网格中每个三角形已经不到一个像素。
+const MAX_INSTANCES: u32 = 1 << 20;
+/// `-width`、`-height` 的上限，即 Direct3D 12 二维纹理的最大边长（`D3D12_REQ_TEXTURE2D_U_OR_V_DIMENSION`），
+/// 后台缓冲区不能比它更大；这也保证了之后转换为 `i32` 的窗口大小不会变成负数。
+const MAX_WINDOW_SIZE: u32 = 16384;
+
+fn clamp_or_default(value: u32, (min, max): (u32, u32), default: u32) -> u32 {
+    if value == 0 {
+        default
+    } else {
+        value.clamp(min, max)
+    }
+}
+
+/// 命令行参数既可以写成 `-name`，也可以写成 `/name`，不区分大小写。
+fn option_name(arg: &str) -> Option<String> {
+    arg.strip_prefix('-')
+        .or_else(|| arg.strip_prefix('/'))
+        .map(|name| name.to_ascii_lowercase())
+}
+
+/// 这些选项后面紧跟一个值，例如 `-config sample.toml`。
+fn takes_value(name: &str) -> bool {
+    matches!(
+        name,
+        "config"
+            | "saveconfig"
+            | "sample"
+            | "coords"
+            | "blend"
+            | "shadermodel"
+            | "format"
+            | "mode"
+            | "monitor"
+            | "width"
+            | "height"
+            | "adapter"
+            | "assets"
+            | "texture"
+            | "headless"
+            | "particles"
+            | "points"
+            | "instances"
+            | "buffers"
+            | "framesinflight"
+            | "cycleadapters"
+    )
 }
 
-impl Default for SampleCommandLine {
-    fn default() -> Self {
-        let mut use_warp_device = false;
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "" | "true" | "1" | "on" => Some(true),
+        "false" | "0" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// `-cycleadapters` 的值写成 `secs=N`，也可以直接写 `N`；间隔至少 1 秒。
+fn parse_cycle_seconds(value: &str) -> Option<u32> {
+    let seconds = value.strip_prefix("secs=").unwrap_or(value);
+    seconds.parse().ok().filter(|&seconds| seconds > 0)
+}
+
+impl SampleCommandLine {
+    /// 先读取 `-config` 指定的配置文件，再用命令行上的参数覆盖其中的值；
+    /// 若给出了 `-saveconfig`，则把最终生效的配置写回文件。
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Self {
+        let mut options = Vec::new();
+        while let Some(arg) = args.next() {
+            if let Some(name) = option_name(&arg) {
+                let value = if takes_value(&name) {
+                    args.next().unwrap_or_default()
+                } else {
+                    String::new()
+                };
+                options.push((name, value));
+            }
+        }
+
+        let mut command_line = SampleCommandLine::default();
+        for (_, path) in options.iter().filter(|(name, _)| name == "config") {
+            if let Err(error) = command_line.load_config(Path::new(path)) {
+                eprintln!("failed to load config {}: {}", path, error);
+            }
+        }
+        for (name, value) in &options {
+            if name != "config" && name != "saveconfig" {
+                command_line.set_option(name, value);
+            }
+        }
+        for (_, path) in options.iter().filter(|(name, _)| name == "saveconfig") {
+            if let Err(error) = command_line.save_config(Path::new(path)) {
+                eprintln!("failed to save config {}: {}", path, error);
+            }
+        }
+        command_line
+    }
+
+    /// 命令行参数和配置文件共用这一套选项名。
+    fn set_option(&mut self, name: &str, value: &str) {
+        let parsed = match name {
+            "warp" => parse_bool(value).map(|v| self.use_warp_device = v),
+            "presentinfo" => parse_bool(value).map(|v| self.print_present_info = v),
+            "pauseonstart" => parse_bool(value).map(|v| self.pause_on_start = v),
+            "asynccompute" => parse_bool(value).map(|v| self.async_compute = v),
+            "selftest" => parse_bool(value).map(|v| self.self_test = v),
+            "multinode" => parse_bool(value).map(|v| self.multi_node = v),
+            "particles" => value.parse().ok().map(|v| self.particles = v),
+            "index32" => parse_bool(value).map(|v| self.index32 = v),
+            "defaultheap" => parse_bool(value).map(|v| self.use_default_heap = v),
+            "dynamicvertices" => parse_bool(value).map(|v| self.dynamic_vertices = v),
+            "nobundle" => parse_bool(value).map(|v| self.no_bundle = v),
+            "msaa" => parse_bool(value).map(|v| self.msaa = v),
+            "gbuffer" => parse_bool(value).map(|v| self.gbuffer = v),
+            "framestats" => parse_bool(value).map(|v| self.frame_stats = v),
+            "points" => value.parse().ok().map(|v| self.points = v),
+            "instances" => value
+                .parse()
+                .ok()
+                .filter(|&v| v <= MAX_INSTANCES)
+                .map(|v| self.instances = v),
+            "buffers" => value.parse().ok().map(|v| self.buffers = v),
+            "framesinflight" => value.parse().ok().map(|v| self.frames_in_flight = v),
+            "cycleadapters" => parse_cycle_seconds(value).map(|v| self.cycle_adapters = Some(v)),
+            "sample" => SampleKind::from_name(value).map(|v| self.sample = v),
+            "coords" => CoordinateSystem::from_name(value).map(|v| self.coordinate_system = v),
+            "blend" => BlendMode::from_name(value).map(|v| self.blend_mode = v),
+            "shadermodel" => ShaderModel::from_name(value).map(|v| self.shader_model = v),
+            "mode" => DisplayMode::from_name(value).map(|v| self.display_mode = Some(v)),
+            "assets" => (!value.is_empty()).then(|| self.assets = Some(value.to_string())),
+            "texture" => (!value.is_empty()).then(|| self.texture = Some(value.to_string())),
+            "headless" => (!value.is_empty()).then(|| self.headless = Some(value.to_string())),
+            "monitor" => value.parse().ok().map(|v| self.monitor = Some(v)),
+            "width" => value
+                .parse()
+                .ok()
+                .filter(|&v| v <= MAX_WINDOW_SIZE)
+                .map(|v| {
+                    self.window_size = Some((v, self.window_size.unwrap_or_default().1));
+                }),
+            "height" => value
+                .parse()
+                .ok()
+                .filter(|&v| v <= MAX_WINDOW_SIZE)
+                .map(|v| {
+                    self.window_size = Some((self.window_size.unwrap_or_default().0, v));
+                }),
+            "adapter" => value.parse().ok().map(|v| self.adapter_index = Some(v)),
+            "list-modes" => parse_bool(value).map(|v| self.list_display_modes = v),
+            "verbose" => parse_bool(value).map(|v| self.verbose = v),
+            "gpuvalidation" => parse_bool(value).map(|v| self.gpu_validation = v),
+            "waitable" => parse_bool(value).map(|v| self.waitable = v),
+            "novsync" => parse_bool(value).map(|v| self.no_vsync = v),
+            "hdr" => parse_bool(value).map(|v| self.hdr = v),
+            "srgb" => parse_bool(value).map(|v| self.srgb = v),
+            "reversez" => parse_bool(value).map(|v| {
+                self.depth_mode = if v {
+                    DepthMode::ReversedZ
+                } else {
+                    DepthMode::Standard
+                };
+            }),
+            "vram" => parse_bool(value).map(|v| self.print_vram_usage = v),
+            "format" => BackBufferFormat::from_name(value).map(|v| self.back_buffer_format = v),
+            _ => {
+                eprintln!("unknown option: {}", name);
+                return;
+            }
+        };
+        if parsed.is_none() {
+            eprintln!("invalid value for {}: {}", name, value);
+            if name == "sample" {
+                let names: Vec<&str> = SampleKind::ALL.iter().map(|kind| kind.name()).collect();
+                eprintln!("available samples: {}", names.join(", "));
+            }
+        }
+    }
+
+    /// 当前生效的所有选项，按 `name = value` 的形式保存。
+    fn options(&self) -> Vec<(&'static str, String)> {
+        let mut options = vec![
+            ("warp", self.use_warp_device.to_string()),
+            ("presentinfo", self.print_present_info.to_string()),
+            ("pauseonstart", self.pause_on_start.to_string()),
+            ("asynccompute", self.async_compute.to_string()),
+            ("sample", self.sample.name().to_string()),
+            ("coords", self.coordinate_system.name().to_string()),
+            ("blend", self.blend_mode.name().to_string()),
+            ("shadermodel", self.shader_model.name().to_string()),
+            ("format", self.back_buffer_format.name().to_string()),
+            ("multinode", self.multi_node.to_string()),
+            ("vram", self.print_vram_usage.to_string()),
+            ("verbose", self.verbose.to_string()),
+            ("gpuvalidation", self.gpu_validation.to_string()),
+            ("waitable", self.waitable.to_string()),
+            ("novsync", self.no_vsync.to_string()),
+            ("hdr", self.hdr.to_string()),
+            ("srgb", self.srgb.to_string()),
+            (
+                "reversez",
+                (self.depth_mode == DepthMode::ReversedZ).to_string(),
+            ),
+            ("index32", self.index32.to_string()),
+            ("defaultheap", self.use_default_heap.to_string()),
+            ("dynamicvertices", self.dynamic_vertices.to_string()),
+            ("nobundle", self.no_bundle.to_string()),
+            ("msaa", self.msaa.to_string()),
+            ("gbuffer", self.gbuffer.to_string()),
+            ("framestats", self.frame_stats.to_string()),
+            ("particles", self.particles.to_string()),
+            ("points", self.points.to_string()),
+            ("instances", self.instances.to_string()),
+            ("buffers", self.buffers.to_string()),
+            ("framesinflight", self.frames_in_flight.to_string()),
+        ];
+        if let Some(display_mode) = self.display_mode {
+            options.push(("mode", display_mode.name()));
+        }
+        if let Some((width, height)) = self.window_size {
+            if width > 0 {
+                options.push(("width", width.to_string()));
+            }
+            if height > 0 {
+                options.push(("height", height.to_string()));
+            }
+        }
+        if let Some(monitor) = self.monitor {
+            options.push(("monitor", monitor.to_string()));
+        }
+        if let Some(adapter_index) = self.adapter_index {
+            options.push(("adapter", adapter_index.to_string()));
+        }
+        if let Some(seconds) = self.cycle_adapters {
+            options.push(("cycleadapters", format!("secs={}", seconds)));
+        }
+        if let Some(assets) = &self.assets {
+            options.push(("assets", assets.clone()));
+        }
+        if let Some(texture) = &self.texture {
+            options.push(("texture", texture.clone()));
+        }
+        if let Some(headless) = &self.headless {
+            options.push(("headless", headless.clone()));
+        }
+        options
+    }
+
+    /// 交换链的后台缓冲区数量，决定了有多少张图像可以排队等待显示。
+    pub fn buffer_count(&self) -> u32 {
+        clamp_or_default(self.buffers, BUFFER_COUNT_RANGE, DEFAULT_BUFFER_COUNT)
+    }
 
-        for arg in std::env::args() {
-            if arg.eq_ignore_ascii_case("-warp") || arg.eq_ignore_ascii_case("/warp") {
-                use_warp_device = true;
+    /// 同时在飞行中的帧数，决定了命令分配器的数量以及 CPU 最多能领先 GPU 几帧。
+    ///
+    /// 它与后台缓冲区数量相互独立：前者限制 CPU 录制命令的进度，后者限制排队显示的图像数。
+    /// 没有空闲的后台缓冲区时 `Present` 本身也会阻塞，所以实际的排队深度由两者中更紧的那个决定；
+    /// 帧数多于后台缓冲区数减一时，多出来的那部分通常会被 `Present` 的阻塞吞掉。
+    pub fn max_frames_in_flight(&self) -> u32 {
+        clamp_or_default(
+            self.frames_in_flight,
+            FRAMES_IN_FLIGHT_RANGE,
+            DEFAULT_FRAMES_IN_FLIGHT,
+        )
+    }
+
+    /// 窗口客户区的大小：优先使用 `-width`/`-height`，没有给出的一边使用示例的 `default`。
+    pub fn resolve_window_size(&self, default: (i32, i32)) -> (i32, i32) {
+        let (width, height) = self.window_size.unwrap_or_default();
+        let pick = |value: u32, default: i32| if value > 0 { value as i32 } else { default };
+        (pick(width, default.0), pick(height, default.1))
+    }
+
+    /// 配置文件每行一个 `name = value`，`#` 开头的行是注释。
+    pub fn apply_config(&mut self, text: &str) {
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((name, value)) => self.set_option(
+                    &name.trim().to_ascii_lowercase(),
+                    value.trim().trim_matches('"'),
+                ),
+                None => eprintln!("invalid config line: {}", line),
             }
         }
+    }
+
+    pub fn load_config(&mut self, path: &Path) -> std::io::Result<()> {
+        let text = std::fs::read_to_string(path)?;
+        self.apply_config(&text);
+        Ok(())
+    }
 
-        SampleCommandLine { use_warp_device }
+    pub fn to_config(&self) -> String {
+        self.options()
+            .into_iter()
+            .map(|(name, value)| format!("{} = {}\n", name, value))
+            .collect()
     }
+
+    pub fn save_config(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_config())
+    }
+}
+
+#[test]
+fn command_line_config() {
+    let mut command_line = SampleCommandLine::default();
+    command_line.apply_config("# comment\nwarp = true\n\nPauseOnStart = 1\n");
+    assert!(command_line.use_warp_device);
+    assert!(command_line.pause_on_start);
+    assert!(!command_line.print_present_info);
+
+    let mut reloaded = SampleCommandLine::default();
+    reloaded.apply_config(&command_line.to_config());
+    assert!(reloaded.use_warp_device);
+    assert!(reloaded.pause_on_start);
+}
+
+#[test]
+fn command_line_values() {
+    let args = [
+        "-sample",
+        "box",
+        "-coords",
+        "rh",
+        "-mode",
+        "1280x720@60",
+        "-buffers",
+        "3",
+        "-framesinflight",
+        "1",
+        "-cycleadapters",
+        "secs=5",
+        "-adapter",
+        "1",
+        "-reversez",
+        "-warp",
+    ];
+    let command_line = SampleCommandLine::parse(args.into_iter().map(String::from));
+    assert_eq!(command_line.sample, SampleKind::Box);
+    assert_eq!(
+        command_line.coordinate_system,
+        CoordinateSystem::RightHanded
+    );
+    assert_eq!(
+        command_line.display_mode,
+        DisplayMode::from_name("1280x720@60")
+    );
+    assert_eq!(command_line.buffer_count(), 3);
+    assert_eq!(command_line.max_frames_in_flight(), 1);
+    assert_eq!(command_line.cycle_adapters, Some(5));
+    assert_eq!(command_line.adapter_index, Some(1));
+    assert_eq!(command_line.depth_mode, DepthMode::ReversedZ);
+    assert!(command_line.use_warp_device);
+}
+
+#[test]
+fn frame_counts_are_clamped() {
+    let mut command_line = SampleCommandLine::default();
+    assert_eq!(command_line.buffer_count(), 2);
+    assert_eq!(command_line.max_frames_in_flight(), 2);
+
+    command_line.apply_config("buffers = 100\nframesinflight = 100\n");
+    assert_eq!(command_line.buffer_count(), 16);
+    assert_eq!(command_line.max_frames_in_flight(), 3);
+
+    command_line.apply_config("buffers = 1\nframesinflight = 1\n");
+    assert_eq!(command_line.buffer_count(), 2);
+    assert_eq!(command_line.max_frames_in_flight(), 1);
+
+    // 实例缓冲区的大小必须能用 u32 表示，过大的实例数被拒绝
+    command_line.apply_config("instances = 4096\n");
+    assert_eq!(command_line.instances, 4096);
+    command_line.apply_config("instances = 4000000000\n");
+    assert_eq!(command_line.instances, 4096);
+}
+
+#[test]
+fn window_size_overrides_default() {
+    let command_line = SampleCommandLine::default();
+    assert_eq!(command_line.resolve_window_size((1024, 768)), (1024, 768));
+
+    let args = ["-width", "1280", "-height", "720"];
+    let command_line = SampleCommandLine::parse(args.into_iter().map(String::from));
+    assert_eq!(command_line.window_size, Some((1280, 720)));
+    assert_eq!(command_line.resolve_window_size((1024, 768)), (1280, 720));
+
+    let mut width_only = SampleCommandLine::default();
+    width_only.apply_config("width = 640\n");
+    assert_eq!(width_only.resolve_window_size((1024, 768)), (640, 768));
+    assert!(width_only.to_config().contains("width = 640"));
+    assert!(!width_only.to_config().contains("height"));
+
+    // 超出上限的值会在转换为 i32 时变成负数，解析时就忽略它们，保留之前的值
+    let mut too_large = SampleCommandLine::default();
+    too_large.apply_config(
+        "width = 640
+width = 3000000000
+height = 16385
+",
+    );
+    assert_eq!(too_large.window_size, Some((640, 0)));
+    assert_eq!(too_large.resolve_window_size((1024, 768)), (640, 768));
 }
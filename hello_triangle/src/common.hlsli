@@ -0,0 +1,17 @@
+// 多个着色器共用的结构体和常量，由 shaders.hlsl 通过 #include 引入。
+
+struct PSInput
+{
+    float4 position : SV_POSITION;
+    float4 color : COLOR;
+#ifdef GBUFFER
+    // 模型空间的位置，写入 G-buffer
+    float3 localPosition : TEXCOORD0;
+#endif
+};
+
+// 经过的时间，由根常量直接提供，不需要常量缓冲区。
+cbuffer FrameConstants : register(b0)
+{
+    float time;
+};
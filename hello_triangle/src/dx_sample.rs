@@ -1,20 +1,67 @@
-use crate::SampleCommandLine;
+use crate::readback::ReadbackImage;
+use crate::{explain_error, write_png, GameTimer, InputState, SampleCommandLine};
 use std::mem::transmute;
-use windows::Win32::Graphics::Gdi::UpdateWindow;
+use std::path::Path;
+use windows::Win32::Graphics::Gdi::*;
 use windows::{
     core::*, Win32::Foundation::*, Win32::System::LibraryLoader::*,
     Win32::UI::WindowsAndMessaging::*,
 };
 
+/// 产生鼠标按下、抬起消息的按键。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
 pub trait DXSample {
     fn new(command_line: &SampleCommandLine) -> Result<Self>
     where
         Self: Sized;
     fn bind_to_window(&mut self, hwnd: &HWND) -> Result<()>;
-    fn update(&mut self) {}
+    /// 不创建窗口和交换链，改为渲染到 `width`x`height` 的离屏渲染目标，供 [`render_headless`] 使用。
+    fn bind_headless(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Err(Error::new(
+            E_NOTIMPL,
+            "this sample does not support headless rendering".into(),
+        ))
+    }
+    /// 读回最近一次 `render` 的结果，调用时 GPU 必须已经执行完这一帧。
+    fn read_back_frame(&self) -> Result<ReadbackImage> {
+        Err(Error::new(
+            E_NOTIMPL,
+            "this sample does not support reading back frames".into(),
+        ))
+    }
+    /// 每帧在 `render` 之前调用，`timer` 已经在这一帧 tick 过，`input` 是此刻按住的按键。
+    fn update(&mut self, _timer: &GameTimer, _input: &InputState) {}
     fn render(&mut self);
+    /// 按键抬起，`key` 是虚拟键码。
     fn on_key_up(&mut self, _key: u8) {}
+    /// 按键按下，`key` 是虚拟键码。按住不放时会随键盘的重复速率反复调用，需要持续按住的操作请在 `update` 中读取 [`InputState`]。
     fn on_key_down(&mut self, _key: u8) {}
+    /// 鼠标按键在客户区中按下，`x`、`y` 是相对客户区左上角的像素坐标。
+    fn on_mouse_down(&mut self, _button: MouseButton, _x: i32, _y: i32) {}
+    fn on_mouse_up(&mut self, _button: MouseButton, _x: i32, _y: i32) {}
+    fn on_mouse_move(&mut self, _x: i32, _y: i32) {}
+    /// 滚轮转动，`delta` 向前为正，转过一格是 `WHEEL_DELTA`（120）。
+    fn on_mouse_wheel(&mut self, _delta: i32) {}
+    /// 按下了 Alt+Enter，返回窗口要切换到的样式和位置，不需要改变时返回 `None`。
+    ///
+    /// 这里不能直接调整窗口：`SetWindowPos` 会同步发送 `WM_SIZE`，再次进入窗口过程并调用 `on_resize`，
+    /// 而此时示例仍被借用着。窗口过程在这次借用结束之后才应用返回的位置，由随之而来的 `WM_SIZE` 调整交换链。
+    fn toggle_fullscreen(&mut self) -> Option<WindowPlacement> {
+        None
+    }
+    /// 窗口客户区的大小改变了，最小化或大小为 0 时不会调用。
+    fn on_resize(&mut self, _width: u32, _height: u32) {}
+    /// 窗口收到 `WM_DESTROY`，之后消息循环就会退出，只调用一次。
+    ///
+    /// 在这里等待 GPU 执行完所有已提交的命令并释放资源，交换链就会在它的窗口还有效时释放，
+    /// 调试层也不会看到 GPU 仍在使用的资源被释放。
+    fn on_destroy(&mut self) {}
 
     fn title(&self) -> String {
         "DXSample".into()
@@ -23,9 +70,193 @@ pub trait DXSample {
     fn window_size(&self) -> (i32, i32) {
         (1024, 768)
     }
+
+    /// 每帧传给 `Present` 的同步间隔：1 表示每个垂直空白呈现一帧，2 表示半速，最大为 4；
+    /// 0 表示不等待垂直同步，此时若交换链支持撕裂，会同时使用 `DXGI_PRESENT_ALLOW_TEARING`，
+    /// 否则窗口模式下 DWM 合成仍会把帧率限制在刷新率上。
+    fn present_interval(&self) -> u32 {
+        1
+    }
+
+    /// 每帧清除渲染目标所用的 RGBA 颜色。
+    ///
+    /// `-hdr` 时它和着色器输出一样按 HDR10 的 PQ 编码解读，同样的数值会显示成不同的亮度和颜色。
+    fn clear_color(&self) -> [f32; 4] {
+        [0.0, 0.2, 0.4, 1.0]
+    }
+}
+
+/// 窗口的用户数据指向它：窗口过程既要调用示例，也要在每帧开始时推进计时器、记录按住的按键。
+struct SampleWindow<S> {
+    sample: S,
+    timer: GameTimer,
+    input: InputState,
+    /// `toggle_fullscreen` 返回的窗口位置，等窗口过程不再借用这个结构体之后再应用。
+    pending_placement: Option<WindowPlacement>,
+}
+
+/// 按 `EnumDisplayMonitors` 的枚举顺序（从 0 开始）取得第 `index` 个显示器的工作区，即去掉任务栏后的区域。
+fn monitor_work_area(index: u32) -> Result<RECT> {
+    unsafe extern "system" fn collect_monitor(
+        monitor: HMONITOR,
+        _hdc: HDC,
+        _rect: *mut RECT,
+        data: LPARAM,
+    ) -> BOOL {
+        let monitors = &mut *(data.0 as *mut Vec<HMONITOR>);
+        monitors.push(monitor);
+        true.into()
+    }
+
+    let mut monitors: Vec<HMONITOR> = Vec::new();
+    unsafe {
+        EnumDisplayMonitors(
+            HDC::default(),
+            None,
+            Some(collect_monitor),
+            LPARAM(&mut monitors as *mut _ as isize),
+        )
+    };
+    let monitor = monitors.get(index as usize).ok_or_else(|| {
+        Error::new(
+            E_INVALIDARG,
+            format!(
+                "monitor {} is out of range, {} monitor(s) found",
+                index,
+                monitors.len()
+            )
+            .into(),
+        )
+    })?;
+
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    if !unsafe { GetMonitorInfoA(*monitor, &mut info) }.as_bool() {
+        return Err(Error::from_win32());
+    }
+    Ok(info.rcWork)
+}
+
+/// 回车键的虚拟键码（`VK_RETURN`）。
+const VK_RETURN: usize = 0x0D;
+
+/// 拖动调整窗口大小时客户区的最小尺寸。
+const MIN_CLIENT_SIZE: (i32, i32) = (320, 240);
+
+/// 拖动或调整窗口大小期间驱动渲染的计时器。
+const RENDER_TIMER_ID: usize = 1;
+
+/// 窗口的样式和位置（屏幕坐标），用于在无边框全屏和原来的窗口之间切换。
+pub struct WindowPlacement {
+    style: isize,
+    rect: RECT,
+}
+
+/// 计算去掉标题栏和边框、覆盖整个显示器的无边框全屏位置，连同窗口当前的样式和位置一起返回，
+/// 前者用来退出全屏时恢复。这里只读取窗口的状态，由 [`WindowPlacement::apply`] 真正调整窗口。
+///
+/// 与独占全屏不同，无边框全屏下交换链仍然通过桌面窗口管理器合成，切换时不改变显示模式，
+/// 切换到其他窗口也不会退出全屏。窗口跨越多个显示器时，使用与它重叠面积最大的那个。
+pub fn borderless_fullscreen_placement(hwnd: HWND) -> Result<(WindowPlacement, WindowPlacement)> {
+    let style = unsafe { GetWindowLong(hwnd, GWL_STYLE) };
+    let mut rect = RECT::default();
+    if !unsafe { GetWindowRect(hwnd, &mut rect) }.as_bool() {
+        return Err(Error::from_win32());
+    }
+
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    if !unsafe { GetMonitorInfoW(monitor, &mut info) }.as_bool() {
+        return Err(Error::from_win32());
+    }
+
+    let borderless = WINDOW_STYLE(style as u32) & !WS_OVERLAPPEDWINDOW | WS_POPUP;
+    Ok((
+        WindowPlacement { style, rect },
+        WindowPlacement {
+            style: borderless.0 as isize,
+            rect: info.rcMonitor,
+        },
+    ))
+}
+
+impl WindowPlacement {
+    /// 把窗口切换到这个样式和位置。
+    ///
+    /// 修改样式后要带上 SWP_FRAMECHANGED，窗口的边框才会重新计算。
+    /// 窗口大小改变会同步发送 WM_SIZE，后台缓冲区随之通过 ResizeBuffers 调整为新的大小，
+    /// 所以调用时不能持有窗口用户数据中 `SampleWindow` 的引用。
+    pub fn apply(&self, hwnd: HWND) -> Result<()> {
+        let rect = self.rect;
+        unsafe {
+            SetWindowLong(hwnd, GWL_STYLE, self.style);
+            SetWindowPos(
+                hwnd,
+                HWND_TOP,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_FRAMECHANGED | SWP_NOACTIVATE,
+            )
+        }
+        .ok()
+    }
+}
+
+/// 窗口客户区的大小，也就是交换链后台缓冲区应有的大小。
+pub fn client_size(hwnd: &HWND) -> Option<(i32, i32)> {
+    let mut rect = RECT::default();
+    unsafe { GetClientRect(*hwnd, &mut rect) }
+        .as_bool()
+        .then_some((rect.right - rect.left, rect.bottom - rect.top))
+}
+
+/// 不创建窗口，以 `width`x`height` 的大小离屏渲染 `frames` 帧，返回最后一帧的 RGBA8 像素。
+///
+/// 离屏渲染不需要交换链，任何适配器都可以；测试用 `-warp`（`use_warp_device`）创建示例，
+/// 是为了在没有 GPU 的机器上也能运行并直接检查返回的像素。
+pub fn render_headless(
+    sample: &mut impl DXSample,
+    (width, height): (i32, i32),
+    frames: u32,
+) -> Result<Vec<u8>> {
+    sample.bind_headless(width as u32, height as u32)?;
+    let mut timer = GameTimer::default();
+    let input = InputState::default();
+    for _ in 0..frames {
+        timer.tick();
+        sample.update(&timer, &input);
+        sample.render();
+    }
+    sample.read_back_frame()?.to_rgba8()
+}
+
+/// `-headless FILE`：创建示例，按 `-width`/`-height`（没有给出时用示例的默认大小）离屏渲染几帧，
+/// 把最后一帧保存为 PNG。
+pub fn capture_headless<S: DXSample>(command_line: &SampleCommandLine, path: &Path) -> Result<()> {
+    /// 多渲染一帧，确保保存的不是创建资源后的第一帧
+    const FRAMES: u32 = 2;
+
+    let mut sample = S::new(command_line)?;
+    let (width, height) = command_line.resolve_window_size(sample.window_size());
+    let pixels = render_headless(&mut sample, (width, height), FRAMES)?;
+    write_png(path, width as u32, height as u32, &pixels).map_err(|e| {
+        Error::new(
+            E_FAIL,
+            format!("cannot write {}: {}", path.display(), e).into(),
+        )
+    })?;
+    println!("saved {} ({}x{})", path.display(), width, height);
+    Ok(())
 }
 
-pub fn init_sample<S: DXSample>() -> Result<()> {
+pub fn init_sample<S: DXSample>(command_line: &SampleCommandLine) -> Result<()> {
     let instance = unsafe { GetModuleHandleA(None) }.unwrap();
     debug_assert!(!instance.is_invalid());
     // // 第一项任务便是通过填写 WNDCLASS 结构体，并根据其中描述的特征来创建一个窗口
@@ -40,21 +271,48 @@ pub fn init_sample<S: DXSample>() -> Result<()> {
         lpszClassName: PCSTR(b"RustWindowClass\0".as_ptr()),
         ..Default::default()
     };
-    let command_line = SampleCommandLine::default();
-    let mut sample = S::new(&command_line)?;
-    let size = sample.window_size();
+    let mut window = SampleWindow {
+        sample: S::new(command_line)?,
+        timer: GameTimer::default(),
+        input: InputState::default(),
+        pending_placement: None,
+    };
+    let size = command_line.resolve_window_size(window.sample.window_size());
     // 我们要在 Windows 系统中为上述 WNDCLASS 注册一个实例，这样一来，即可据此创建窗口。
     let atom = unsafe { RegisterClassExA(&wc) };
     debug_assert_ne!(atom, 0);
 
-    let window_rect = RECT {
-        left: 0,
-        top: 0,
-        right: size.0,
-        bottom: size.1,
+    // 指定了 -monitor 时，窗口铺满该显示器的工作区；否则由系统决定窗口位置
+    let (x, y, width, height) = match command_line.monitor {
+        Some(index) => {
+            let work_area = monitor_work_area(index)?;
+            (
+                work_area.left,
+                work_area.top,
+                work_area.right - work_area.left,
+                work_area.bottom - work_area.top,
+            )
+        }
+        // 指定了 -width/-height 时，它们是客户区的大小，要加上标题栏和边框才是窗口的大小，
+        // 这样后台缓冲区正好是这个分辨率
+        None if command_line.window_size.is_some() => {
+            let mut window_rect = RECT {
+                left: 0,
+                top: 0,
+                right: size.0,
+                bottom: size.1,
+            };
+            unsafe { AdjustWindowRect(&mut window_rect, WS_OVERLAPPEDWINDOW, false) };
+            (
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                window_rect.right - window_rect.left,
+                window_rect.bottom - window_rect.top,
+            )
+        }
+        None => (CW_USEDEFAULT, CW_USEDEFAULT, size.0, size.1),
     };
-    // unsafe { AdjustWindowRect(&mut window_rect, WS_OVERLAPPEDWINDOW, false) };
-    let mut title = sample.title();
+    let mut title = window.sample.title();
 
     if command_line.use_warp_device {
         title.push_str(" (WARP)");
@@ -64,19 +322,32 @@ pub fn init_sample<S: DXSample>() -> Result<()> {
             Default::default(),
             s!("RustWindowClass"), // 创建此窗口采用的是前面注册的 WNDCLASS 实例
             PCSTR(title.as_ptr()),
-            WS_OVERLAPPEDWINDOW,                  // 窗口的样式标志
-            CW_USEDEFAULT,                        // x 坐标
-            CW_USEDEFAULT,                        // y 坐标
-            window_rect.right - window_rect.left, // 窗口宽度
-            window_rect.bottom - window_rect.top, // 窗口高度
-            None,                                 // no parent window
-            None,                                 // no menus
-            instance,                             // 应用程序实例句柄
-            Some(&mut sample as *mut _ as _),     // 可在此设置一些创建窗口所用的其他参数
+            WS_OVERLAPPEDWINDOW,              // 窗口的样式标志
+            x,                                // x 坐标
+            y,                                // y 坐标
+            width,                            // 窗口宽度
+            height,                           // 窗口高度
+            None,                             // no parent window
+            None,                             // no menus
+            instance,                         // 应用程序实例句柄
+            Some(&mut window as *mut _ as _), // 可在此设置一些创建窗口所用的其他参数
         )
     };
 
-    sample.bind_to_window(&hwnd)?;
+    window.sample.bind_to_window(&hwnd)?;
+    // 创建设备和资源的时间不计入第一帧
+    window.timer.reset();
+
+    if command_line.pause_on_start {
+        unsafe {
+            MessageBoxW(
+                hwnd,
+                w!("Attach debugger now"),
+                w!("DXSample"),
+                MB_OK | MB_ICONINFORMATION,
+            )
+        };
+    }
 
     // 尽管窗口已经创建完毕，但仍没有显示出来。因此，最后一步便是调用下面的两个函数，将刚刚创建的窗口展示出来
     // 并对它进行更新。可以看出，我们为这两个函数都传入了窗口句柄，这样一来，它们就知道需要展示以及更新的窗口是哪一个
@@ -87,7 +358,9 @@ pub fn init_sample<S: DXSample>() -> Result<()> {
         let mut message = MSG::default();
         // 在获取 WM_QUIT 消息之前，该函数会一直保持循环。GetMessage 函数只有在收到 WM_QUIT 消
         // 息时才会返回 0（false），这会造成循环终止；而若发生错误，它便会返回-1。还需注意的一点是，
-        // 在未有信息到来之时，GetMessage 函数会令此应用程序线程进入休眠状态
+        // 在未有信息到来之时，GetMessage 函数会令此应用程序线程进入休眠状态。
+        // 所以这里改用 PeekMessage：没有消息时它立即返回，消息队列一空就直接渲染下一帧，
+        // 帧率不再取决于系统什么时候发送 WM_PAINT，大量的输入消息也只是推迟而不会饿死渲染
         if unsafe { PeekMessageA(&mut message, None, 0, 0, PM_REMOVE) }.into() {
             unsafe {
                 TranslateMessage(&message);
@@ -97,31 +370,127 @@ pub fn init_sample<S: DXSample>() -> Result<()> {
             if message.message == WM_QUIT {
                 break;
             }
+        } else if unsafe { IsIconic(hwnd) }.as_bool() {
+            // 最小化时看不到画面，休眠到下一条消息到来，而不是空转
+            unsafe { WaitMessage() };
+        } else {
+            render_frame(&mut window);
         }
     }
     Ok(())
 }
 
+/// 推进计时器，更新并渲染一帧。
+fn render_frame<S: DXSample>(window: &mut SampleWindow<S>) {
+    window.timer.tick();
+    window.sample.update(&window.timer, &window.input);
+    window.sample.render();
+}
+
 /// 窗口过程会处理窗口所接收到的消息
-fn sample_wndproc<S: DXSample>(sample: &mut S, message: u32, wparam: WPARAM) -> bool {
+fn sample_wndproc<S: DXSample>(
+    hwnd: HWND,
+    window: &mut SampleWindow<S>,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> bool {
+    let sample = &mut window.sample;
     match message {
         WM_KEYDOWN => {
+            window.input.press(wparam.0 as u8);
             sample.on_key_down(wparam.0 as u8);
             true
         }
         WM_KEYUP => {
+            window.input.release(wparam.0 as u8);
             sample.on_key_up(wparam.0 as u8);
             true
         }
+        // 失去焦点后收不到按键抬起的消息，把所有按键都当作已经松开。仍交给 DefWindowProc 处理
+        WM_KILLFOCUS => {
+            window.input.release_all();
+            false
+        }
+        // 按住 Alt 时按键产生的是 WM_SYSKEYDOWN，lparam 的第 29 位表示 Alt 被按下，
+        // 第 30 位表示按键之前就已按下，是按住不放时的重复消息，不再切换。
+        // 其余的系统按键（例如 Alt+F4）交给 DefWindowProc 处理
+        WM_SYSKEYDOWN
+            if wparam.0 == VK_RETURN && lparam.0 & (1 << 29) != 0 && lparam.0 & (1 << 30) == 0 =>
+        {
+            window.pending_placement = sample.toggle_fullscreen();
+            true
+        }
+        WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN => {
+            let (x, y) = mouse_position(lparam);
+            sample.on_mouse_down(mouse_button(message), x, y);
+            true
+        }
+        WM_LBUTTONUP | WM_RBUTTONUP | WM_MBUTTONUP => {
+            let (x, y) = mouse_position(lparam);
+            sample.on_mouse_up(mouse_button(message), x, y);
+            true
+        }
+        WM_MOUSEMOVE => {
+            let (x, y) = mouse_position(lparam);
+            sample.on_mouse_move(x, y);
+            true
+        }
+        // wparam 的高 16 位是有符号的滚动量，相当于 GET_WHEEL_DELTA_WPARAM
+        WM_MOUSEWHEEL => {
+            sample.on_mouse_wheel(((wparam.0 >> 16) & 0xffff) as i16 as i32);
+            true
+        }
+        WM_SIZE => {
+            // lparam 的低 16 位是客户区的新宽度，高 16 位是新高度。最小化时两者都是 0，不能用来创建交换链缓冲区
+            let width = (lparam.0 & 0xffff) as u32;
+            let height = ((lparam.0 >> 16) & 0xffff) as u32;
+            if wparam.0 as u32 != SIZE_MINIMIZED && width != 0 && height != 0 {
+                sample.on_resize(width, height);
+            }
+            true
+        }
+        // 平时由消息循环渲染，这里只负责窗口被遮挡后重新露出、调整大小之后的重绘。
+        // 必须把窗口标记为已绘制，否则系统会不停地发送 WM_PAINT
         WM_PAINT => {
-            sample.update();
-            sample.render();
+            render_frame(window);
+            unsafe { ValidateRect(hwnd, None) };
+            true
+        }
+        // 拖动标题栏或边框时，DefWindowProc 进入自己的模态消息循环，上面的消息循环不再运行。
+        // 这期间改由计时器驱动渲染，画面不会停住
+        WM_ENTERSIZEMOVE => {
+            unsafe { SetTimer(hwnd, RENDER_TIMER_ID, USER_TIMER_MINIMUM, None) };
+            true
+        }
+        WM_EXITSIZEMOVE => {
+            unsafe { KillTimer(hwnd, RENDER_TIMER_ID) };
+            true
+        }
+        WM_TIMER if wparam.0 == RENDER_TIMER_ID => {
+            render_frame(window);
             true
         }
         _ => false,
     }
 }
 
+fn mouse_button(message: u32) -> MouseButton {
+    match message {
+        WM_RBUTTONDOWN | WM_RBUTTONUP => MouseButton::Right,
+        WM_MBUTTONDOWN | WM_MBUTTONUP => MouseButton::Middle,
+        _ => MouseButton::Left,
+    }
+}
+
+/// 相当于 C++ 中的 `GET_X_LPARAM`/`GET_Y_LPARAM`：坐标是有符号的 16 位整数，
+/// 多显示器或鼠标被捕获时可能为负，不能像 `LOWORD`/`HIWORD` 那样按无符号数读取。
+fn mouse_position(lparam: LPARAM) -> (i32, i32) {
+    let x = (lparam.0 & 0xffff) as i16 as i32;
+    let y = ((lparam.0 >> 16) & 0xffff) as i16 as i32;
+    (x, y)
+}
+
 #[allow(non_snake_case)]
 #[cfg(target_pointer_width = "32")]
 unsafe fn SetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX, value: isize) -> isize {
@@ -161,15 +530,45 @@ extern "system" fn wndproc<S: DXSample>(
             LRESULT::default()
         }
         WM_DESTROY => {
+            let user_data = unsafe { GetWindowLong(window, GWLP_USERDATA) };
+            if let Some(mut sample) = std::ptr::NonNull::<SampleWindow<S>>::new(user_data as _) {
+                unsafe { sample.as_mut() }.sample.on_destroy();
+            }
             unsafe { PostQuitMessage(0) };
             LRESULT::default()
         }
+        // 限制拖动调整时窗口的最小尺寸，交换链永远不会被要求创建过小甚至为 0 的缓冲区。
+        // 这条消息在 WM_CREATE 之前就会到来，所以不经过示例处理；最大尺寸不做限制，全屏不受影响
+        WM_GETMINMAXINFO => {
+            let mut window_rect = RECT {
+                left: 0,
+                top: 0,
+                right: MIN_CLIENT_SIZE.0,
+                bottom: MIN_CLIENT_SIZE.1,
+            };
+            unsafe {
+                AdjustWindowRect(&mut window_rect, WS_OVERLAPPEDWINDOW, false);
+                let min_max_info = &mut *(lparam.0 as *mut MINMAXINFO);
+                min_max_info.ptMinTrackSize = POINT {
+                    x: window_rect.right - window_rect.left,
+                    y: window_rect.bottom - window_rect.top,
+                };
+            }
+            LRESULT::default()
+        }
         _ => {
             let user_data = unsafe { GetWindowLong(window, GWLP_USERDATA) };
-            let sample = std::ptr::NonNull::<S>::new(user_data as _);
+            let sample = std::ptr::NonNull::<SampleWindow<S>>::new(user_data as _);
             let handled = sample.map_or(false, |mut s| {
-                sample_wndproc(unsafe { s.as_mut() }, message, wparam)
+                sample_wndproc(window, unsafe { s.as_mut() }, message, wparam, lparam)
             });
+            // 上面的可变借用已经结束，SetWindowPos 引起的 WM_SIZE 重新进入这里时不会与它重叠
+            let placement = sample.and_then(|mut s| unsafe { s.as_mut() }.pending_placement.take());
+            if let Some(placement) = placement {
+                placement
+                    .apply(window)
+                    .unwrap_or_else(|e| panic!("fullscreen toggle failed: {}", explain_error(&e)));
+            }
 
             if handled {
                 LRESULT::default()
@@ -179,3 +578,9 @@ extern "system" fn wndproc<S: DXSample>(
         }
     }
 }
+
+#[test]
+fn mouse_position_is_signed() {
+    assert_eq!(mouse_position(LPARAM(0x0020_0010)), (16, 32));
+    assert_eq!(mouse_position(LPARAM(0xfffe_ffff_u32 as isize)), (-1, -2));
+}
@@ -0,0 +1,31 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static ASSETS_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// 由 `-assets DIR` 指定的资源目录，优先于其他位置。只能设置一次。
+pub fn set_assets_override(dir: &Path) {
+    let _ = ASSETS_OVERRIDE.set(dir.to_path_buf());
+}
+
+/// build.rs 会把着色器等资源复制到可执行文件旁边，这是打包后的默认资源目录。
+pub fn assets_dir() -> Option<PathBuf> {
+    let exe_path = std::env::current_exe().ok()?;
+    Some(exe_path.parent()?.to_path_buf())
+}
+
+/// 依次在 `-assets` 指定的目录、可执行文件所在目录、源码目录（`cargo run` 或在 IDE 中运行时）中查找资源，
+/// 返回第一个存在的路径；都找不到时返回可执行文件旁边的路径，让之后的报错指向最常见的位置。
+pub fn resolve_asset(name: &str) -> PathBuf {
+    let source_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    ASSETS_OVERRIDE
+        .get()
+        .cloned()
+        .into_iter()
+        .chain(assets_dir())
+        .chain(Some(source_dir))
+        .map(|dir| dir.join(name))
+        .find(|path| path.exists())
+        .or_else(|| assets_dir().map(|dir| dir.join(name)))
+        .unwrap_or_else(|| PathBuf::from(name))
+}
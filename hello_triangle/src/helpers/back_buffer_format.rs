@@ -0,0 +1,77 @@
+use windows::Win32::Graphics::Dxgi::Common::*;
+
+/// `-format` 可选的后台缓冲区格式。交换链、渲染目标视图和 PSO 的 `RTVFormats[0]` 都必须使用同一个格式，
+/// 所以格式只在这里选定一次，再传给用到它的各个地方。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BackBufferFormat {
+    #[default]
+    Rgba8,
+    Bgra8,
+    Rgb10a2,
+    Rgba16f,
+}
+
+impl BackBufferFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "rgba8" => Some(BackBufferFormat::Rgba8),
+            "bgra8" => Some(BackBufferFormat::Bgra8),
+            "rgb10a2" => Some(BackBufferFormat::Rgb10a2),
+            "rgba16f" => Some(BackBufferFormat::Rgba16f),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BackBufferFormat::Rgba8 => "rgba8",
+            BackBufferFormat::Bgra8 => "bgra8",
+            BackBufferFormat::Rgb10a2 => "rgb10a2",
+            BackBufferFormat::Rgba16f => "rgba16f",
+        }
+    }
+
+    pub fn dxgi_format(self) -> DXGI_FORMAT {
+        match self {
+            BackBufferFormat::Rgba8 => DXGI_FORMAT_R8G8B8A8_UNORM,
+            BackBufferFormat::Bgra8 => DXGI_FORMAT_B8G8R8A8_UNORM,
+            BackBufferFormat::Rgb10a2 => DXGI_FORMAT_R10G10B10A2_UNORM,
+            BackBufferFormat::Rgba16f => DXGI_FORMAT_R16G16B16A16_FLOAT,
+        }
+    }
+
+    /// `-srgb` 时渲染目标视图使用的格式。只有 8 位格式有对应的 sRGB 格式，其余返回 `None`。
+    pub fn srgb_format(self) -> Option<DXGI_FORMAT> {
+        match self {
+            BackBufferFormat::Rgba8 => Some(DXGI_FORMAT_R8G8B8A8_UNORM_SRGB),
+            BackBufferFormat::Bgra8 => Some(DXGI_FORMAT_B8G8R8A8_UNORM_SRGB),
+            BackBufferFormat::Rgb10a2 | BackBufferFormat::Rgba16f => None,
+        }
+    }
+}
+
+/// sRGB 格式对应的无类型格式，资源以无类型格式创建后才能用 sRGB 视图访问；其他格式原样返回。
+pub fn typeless_format(format: DXGI_FORMAT) -> DXGI_FORMAT {
+    match format {
+        DXGI_FORMAT_R8G8B8A8_UNORM_SRGB => DXGI_FORMAT_R8G8B8A8_TYPELESS,
+        DXGI_FORMAT_B8G8R8A8_UNORM_SRGB => DXGI_FORMAT_B8G8R8A8_TYPELESS,
+        format => format,
+    }
+}
+
+#[test]
+fn only_8_bit_formats_have_srgb_views() {
+    assert_eq!(
+        BackBufferFormat::Rgba8.srgb_format(),
+        Some(DXGI_FORMAT_R8G8B8A8_UNORM_SRGB)
+    );
+    assert_eq!(BackBufferFormat::Rgb10a2.srgb_format(), None);
+    assert_eq!(
+        typeless_format(DXGI_FORMAT_B8G8R8A8_UNORM_SRGB),
+        DXGI_FORMAT_B8G8R8A8_TYPELESS
+    );
+    assert_eq!(
+        typeless_format(DXGI_FORMAT_R16G16B16A16_FLOAT),
+        DXGI_FORMAT_R16G16B16A16_FLOAT
+    );
+}
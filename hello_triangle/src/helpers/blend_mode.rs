@@ -0,0 +1,99 @@
+use windows::Win32::Graphics::Direct3D12::*;
+
+/// `-blend` 可选的混合方式，决定像素着色器的输出如何与渲染目标中已有的颜色合成。
+///
+/// 混合只看像素着色器输出的 alpha，与绘制顺序有关：半透明物体要在不透明物体之后、由远及近地绘制。
+/// 它们通常也不写入深度，否则后画的、位于其后方的半透明物体会被深度测试丢弃。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// 直接覆盖原有颜色。
+    #[default]
+    Opaque,
+    /// `src * src.a + dst * (1 - src.a)`，普通的半透明。
+    AlphaBlend,
+    /// `src * src.a + dst`，颜色越叠越亮，适合火焰、光晕之类的效果。
+    Additive,
+}
+
+impl BlendMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "opaque" => Some(BlendMode::Opaque),
+            "alpha" => Some(BlendMode::AlphaBlend),
+            "additive" => Some(BlendMode::Additive),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BlendMode::Opaque => "opaque",
+            BlendMode::AlphaBlend => "alpha",
+            BlendMode::Additive => "additive",
+        }
+    }
+
+    /// 是否要与已有的颜色混合，也就是绘制的是不是半透明的几何体。
+    pub fn is_translucent(self) -> bool {
+        self != BlendMode::Opaque
+    }
+
+    /// 第一个渲染目标的混合设置。alpha 通道按 `src.a + dst.a * (1 - src.a)` 累积覆盖率。
+    pub fn render_target_blend_desc(self) -> D3D12_RENDER_TARGET_BLEND_DESC {
+        let (src_blend, dest_blend) = match self {
+            BlendMode::Opaque => (D3D12_BLEND_ONE, D3D12_BLEND_ZERO),
+            BlendMode::AlphaBlend => (D3D12_BLEND_SRC_ALPHA, D3D12_BLEND_INV_SRC_ALPHA),
+            BlendMode::Additive => (D3D12_BLEND_SRC_ALPHA, D3D12_BLEND_ONE),
+        };
+        D3D12_RENDER_TARGET_BLEND_DESC {
+            BlendEnable: self.is_translucent().into(),
+            LogicOpEnable: false.into(),
+            SrcBlend: src_blend,
+            DestBlend: dest_blend,
+            BlendOp: D3D12_BLEND_OP_ADD,
+            SrcBlendAlpha: D3D12_BLEND_ONE,
+            DestBlendAlpha: if self.is_translucent() {
+                D3D12_BLEND_INV_SRC_ALPHA
+            } else {
+                D3D12_BLEND_ZERO
+            },
+            BlendOpAlpha: D3D12_BLEND_OP_ADD,
+            LogicOp: D3D12_LOGIC_OP_NOOP,
+            RenderTargetWriteMask: D3D12_COLOR_WRITE_ENABLE_ALL.0 as u8,
+        }
+    }
+
+    /// 只有一个渲染目标的混合状态。不开启 alpha-to-coverage，也不需要每个渲染目标各自设置（IndependentBlendEnable），
+    /// 其余渲染目标的设置因此被忽略。
+    pub fn blend_desc(self) -> D3D12_BLEND_DESC {
+        let mut render_target = [D3D12_RENDER_TARGET_BLEND_DESC::default(); 8];
+        render_target[0] = self.render_target_blend_desc();
+        D3D12_BLEND_DESC {
+            AlphaToCoverageEnable: false.into(),
+            IndependentBlendEnable: false.into(),
+            RenderTarget: render_target,
+        }
+    }
+}
+
+#[test]
+fn blend_modes_fill_the_blend_factors() {
+    for mode in [
+        BlendMode::Opaque,
+        BlendMode::AlphaBlend,
+        BlendMode::Additive,
+    ] {
+        assert_eq!(BlendMode::from_name(mode.name()), Some(mode));
+    }
+
+    let opaque = BlendMode::Opaque.render_target_blend_desc();
+    assert!(!opaque.BlendEnable.as_bool());
+    let alpha = BlendMode::AlphaBlend.render_target_blend_desc();
+    assert!(alpha.BlendEnable.as_bool());
+    assert_eq!(
+        (alpha.SrcBlend, alpha.DestBlend),
+        (D3D12_BLEND_SRC_ALPHA, D3D12_BLEND_INV_SRC_ALPHA)
+    );
+    let additive = BlendMode::Additive.render_target_blend_desc();
+    assert_eq!(additive.DestBlend, D3D12_BLEND_ONE);
+}
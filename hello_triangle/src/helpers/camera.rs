@@ -0,0 +1,137 @@
+use crate::{DepthMode, Mat4, Vec3};
+
+/// 离目标最近的距离，拉近时不会穿过目标。
+const MIN_DISTANCE: f32 = 0.5;
+/// 俯仰角离正上方、正下方至少留出的余量，否则观察方向与上方向平行，观察矩阵无法确定。
+const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// 透视相机，位于 `position`、看向 `target`，y 轴朝上，使用左手坐标系。
+pub struct Camera {
+    pub position: Vec3,
+    pub target: Vec3,
+    /// 垂直视场角（弧度）。
+    pub fov_y: f32,
+    pub aspect_ratio: f32,
+    pub near: f32,
+    pub far: f32,
+    /// 反转 Z 时投影把近平面映射到深度 1、远平面映射到 0。
+    pub depth_mode: DepthMode,
+}
+
+impl Default for Camera {
+    /// 45 度视场角，距离原点恰好让 z = 0 平面上 [-1, 1] 的高度铺满窗口。
+    fn default() -> Self {
+        let fov_y = std::f32::consts::FRAC_PI_4;
+        Camera {
+            position: Vec3::new(0.0, 0.0, -1.0 / (fov_y * 0.5).tan()),
+            target: Vec3::ZERO,
+            fov_y,
+            aspect_ratio: 1.0,
+            near: 0.1,
+            far: 100.0,
+            depth_mode: DepthMode::Standard,
+        }
+    }
+}
+
+impl Camera {
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at_lh(self.position, self.target, Vec3::Y)
+    }
+
+    pub fn projection(&self) -> Mat4 {
+        // 交换近远平面的距离，投影后的深度就从 [0, 1] 翻转为 [1, 0]，x、y 不受影响
+        let (near, far) = match self.depth_mode {
+            DepthMode::Standard => (self.near, self.far),
+            DepthMode::ReversedZ => (self.far, self.near),
+        };
+        Mat4::perspective_fov_lh(self.fov_y, self.aspect_ratio, near, far)
+    }
+
+    /// 先变换到观察空间再投影，行向量乘以它就得到裁剪空间坐标。
+    pub fn view_projection(&self) -> Mat4 {
+        self.view() * self.projection()
+    }
+
+    /// 保持与目标的距离不变，绕目标转动：`yaw` 绕 y 轴，`pitch` 上下俯仰，单位都是弧度。
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        let offset = self.position - self.target;
+        let distance = offset.length();
+        let current_yaw = offset.x.atan2(offset.z);
+        let current_pitch = (offset.y / distance).asin();
+
+        let yaw = current_yaw + yaw;
+        let pitch = (current_pitch + pitch).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        let horizontal = distance * pitch.cos();
+        self.position = Vec3::new(
+            self.target.x + horizontal * yaw.sin(),
+            self.target.y + distance * pitch.sin(),
+            self.target.z + horizontal * yaw.cos(),
+        );
+    }
+
+    /// 相机和目标一起平移，朝向不变：`forward` 沿观察方向，`right` 沿水平向右的方向，`up` 沿世界的 y 轴。
+    pub fn fly(&mut self, forward: f32, right: f32, up: f32) {
+        let forward_direction = (self.target - self.position).normalize();
+        // 左手坐标系中 y 轴叉乘观察方向得到右方
+        let right_direction = Vec3::Y.cross(forward_direction).normalize();
+        let offset = forward_direction * forward + right_direction * right + Vec3::Y * up;
+        self.position = self.position + offset;
+        self.target = self.target + offset;
+    }
+
+    /// 沿观察方向前后移动，`amount` 为正时靠近目标。
+    pub fn dolly(&mut self, amount: f32) {
+        let offset = self.position - self.target;
+        let distance = (offset.length() - amount).max(MIN_DISTANCE);
+        let direction = offset.normalize();
+        self.position = Vec3::new(
+            self.target.x + direction.x * distance,
+            self.target.y + direction.y * distance,
+            self.target.z + direction.z * distance,
+        );
+    }
+}
+
+#[test]
+fn orbit_keeps_distance_to_target() {
+    let mut camera = Camera::default();
+    let distance = (camera.position - camera.target).length();
+    camera.orbit(std::f32::consts::FRAC_PI_2, 0.3);
+    assert!(((camera.position - camera.target).length() - distance).abs() < 1e-5);
+    // 向上俯仰后相机在目标上方，偏航 90 度后从 -z 转到了 -x 一侧
+    assert!(camera.position.y > 0.0);
+    assert!(camera.position.x < 0.0);
+
+    camera.dolly(100.0);
+    assert!(((camera.position - camera.target).length() - MIN_DISTANCE).abs() < 1e-5);
+}
+
+#[test]
+fn fly_moves_camera_and_target_together() {
+    let mut camera = Camera::default();
+    let offset = camera.target - camera.position;
+    // 默认相机看向 +z，右方是 +x
+    camera.fly(1.0, 2.0, 3.0);
+    assert!((camera.target - Vec3::new(2.0, 3.0, 1.0)).length() < 1e-5);
+    assert!((camera.target - camera.position - offset).length() < 1e-5);
+}
+
+#[test]
+fn reversed_z_maps_near_to_one() {
+    use crate::Vec4;
+
+    let depth = |camera: &Camera, z: f32| {
+        let clip = Vec4::point(Vec3::new(0.0, 0.0, z)) * camera.projection();
+        clip.z / clip.w
+    };
+    let mut camera = Camera::default();
+    assert!(depth(&camera, camera.near).abs() < 1e-5);
+    assert!((depth(&camera, camera.far) - 1.0).abs() < 1e-5);
+
+    camera.depth_mode = DepthMode::ReversedZ;
+    assert!((depth(&camera, camera.near) - 1.0).abs() < 1e-5);
+    assert!(depth(&camera, camera.far).abs() < 1e-5);
+    // 离得越远深度越小，正好与 GREATER 的深度测试相配
+    assert!(depth(&camera, 1.0) > depth(&camera, 10.0));
+}
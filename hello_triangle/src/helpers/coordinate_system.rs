@@ -0,0 +1,32 @@
+/// 左手坐标系（D3D 和《DirectX 12 3D 游戏开发实战》的约定）与右手坐标系（OpenGL 及很多数学教材的约定）。
+///
+/// 两种约定下，从正面看过去三角形的顶点环绕顺序相反：左手系以顺时针为正面，右手系以逆时针为正面。
+/// 只要光栅器的 `FrontCounterClockwise` 与几何体的环绕顺序相匹配，开启背面剔除后两种约定的画面完全一样。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CoordinateSystem {
+    #[default]
+    LeftHanded,
+    RightHanded,
+}
+
+impl CoordinateSystem {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "lh" => Some(CoordinateSystem::LeftHanded),
+            "rh" => Some(CoordinateSystem::RightHanded),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CoordinateSystem::LeftHanded => "lh",
+            CoordinateSystem::RightHanded => "rh",
+        }
+    }
+
+    /// 对应 `D3D12_RASTERIZER_DESC::FrontCounterClockwise`。
+    pub fn front_counter_clockwise(self) -> bool {
+        self == CoordinateSystem::RightHanded
+    }
+}
@@ -0,0 +1,44 @@
+use windows::{core::*, Win32::Graphics::Direct3D12::*};
+
+/// 给 D3D12 对象起一个调试名称，调试层的消息和 PIX 捕获中会用它代替 `ID3D12Resource (0x...)` 这样的地址。
+///
+/// `ID3D12Object::SetName` 接受 UTF-16 字符串，名称以 `WKPDID_D3DDebugObjectNameW` 私有数据的形式存放在对象上。
+/// 设备、资源、堆、命令列表、PSO、根签名、围栏等都继承自 `ID3D12Object`；不是的对象（例如交换链）直接忽略。
+/// 名称只用于调试，设置失败也不影响渲染，所以不返回错误。
+pub fn set_name(object: &impl Interface, name: &str) {
+    if let Ok(object) = object.cast::<ID3D12Object>() {
+        let _ = unsafe { object.SetName(&HSTRING::from(name)) };
+    }
+}
+
+#[test]
+fn debug_name_is_stored_on_the_object() {
+    use crate::devices::warp_device;
+    use crate::upload::create_buffer;
+    use windows::Win32::Graphics::Direct3D::WKPDID_D3DDebugObjectNameW;
+
+    let device = warp_device();
+    let buffer = create_buffer(
+        &device,
+        D3D12_HEAP_TYPE_UPLOAD,
+        256,
+        D3D12_RESOURCE_STATE_GENERIC_READ,
+    )
+    .unwrap();
+    set_name(&buffer, "vertex buffer");
+
+    // 调试层报告对象时读取的就是这份私有数据
+    let mut name = [0u16; 64];
+    let mut size = std::mem::size_of_val(&name) as u32;
+    unsafe {
+        buffer.GetPrivateData(
+            &WKPDID_D3DDebugObjectNameW,
+            &mut size,
+            Some(name.as_mut_ptr() as *mut _),
+        )
+    }
+    .unwrap();
+    let len = size as usize / 2;
+    let name = String::from_utf16_lossy(&name[..len]);
+    assert_eq!(name.trim_end_matches('\0'), "vertex buffer");
+}
@@ -0,0 +1,42 @@
+use windows::Win32::Graphics::{Direct3D12::*, Dxgi::Common::*};
+
+/// 深度值的约定，决定深度/模板缓冲区的格式、清除值、PSO 的深度比较函数和投影矩阵的近远映射，四者必须一致。
+///
+/// 透视除法后深度值集中在靠近 1 的一端，定点的 `D24` 格式在远处几乎分辨不出差别。
+/// 反转 Z（reversed-Z）把近平面映射到 1、远平面映射到 0，再配合浮点深度格式：
+/// 浮点数在 0 附近最密，恰好抵消了透视除法造成的不均匀，远处的深度精度因此大幅提高。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DepthMode {
+    /// 近平面深度为 0，远平面为 1，清除为 1，较小的深度通过测试。
+    #[default]
+    Standard,
+    /// `-reversez`：近平面深度为 1，远平面为 0，清除为 0，较大的深度通过测试。
+    ReversedZ,
+}
+
+impl DepthMode {
+    /// 深度/模板缓冲区的格式，两者都带 8 位模板值。
+    /// 反转 Z 使用 32 位浮点深度，模板值放在另外的 32 位中（其中 24 位不用）。
+    pub fn format(self) -> DXGI_FORMAT {
+        match self {
+            DepthMode::Standard => DXGI_FORMAT_D24_UNORM_S8_UINT,
+            DepthMode::ReversedZ => DXGI_FORMAT_D32_FLOAT_S8X24_UINT,
+        }
+    }
+
+    /// 每帧清除深度时使用的值，也是创建资源时的优化清除值：最远处的深度。
+    pub fn clear_value(self) -> f32 {
+        match self {
+            DepthMode::Standard => 1.0,
+            DepthMode::ReversedZ => 0.0,
+        }
+    }
+
+    /// 离观察者更近的像素才能通过深度测试。
+    pub fn comparison_func(self) -> D3D12_COMPARISON_FUNC {
+        match self {
+            DepthMode::Standard => D3D12_COMPARISON_FUNC_LESS,
+            DepthMode::ReversedZ => D3D12_COMPARISON_FUNC_GREATER,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use windows::Win32::Graphics::Dxgi::Common::*;
+
+/// `-mode WxH@Hz` 请求的独占全屏显示模式，刷新率省略或为 0 时表示不限。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayMode {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: u32,
+}
+
+impl DisplayMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (size, refresh_rate) = match name.split_once('@') {
+            Some((size, refresh_rate)) => (size, refresh_rate.parse().ok()?),
+            None => (name, 0),
+        };
+        let (width, height) = size
+            .to_ascii_lowercase()
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))?;
+        Some(DisplayMode {
+            width,
+            height,
+            refresh_rate,
+        })
+    }
+
+    pub fn name(self) -> String {
+        if self.refresh_rate == 0 {
+            format!("{}x{}", self.width, self.height)
+        } else {
+            format!("{}x{}@{}", self.width, self.height, self.refresh_rate)
+        }
+    }
+
+    /// 作为 `FindClosestMatchingMode` 的匹配条件，值为 0 的字段会被忽略。
+    pub fn to_mode_desc(self, format: DXGI_FORMAT) -> DXGI_MODE_DESC {
+        DXGI_MODE_DESC {
+            Width: self.width,
+            Height: self.height,
+            RefreshRate: DXGI_RATIONAL {
+                Numerator: self.refresh_rate,
+                Denominator: if self.refresh_rate == 0 { 0 } else { 1 },
+            },
+            Format: format,
+            ..Default::default()
+        }
+    }
+}
+
+#[test]
+fn display_mode_names() {
+    let mode = DisplayMode::from_name("1920x1080@144").unwrap();
+    assert_eq!(
+        (mode.width, mode.height, mode.refresh_rate),
+        (1920, 1080, 144)
+    );
+    assert_eq!(DisplayMode::from_name(&mode.name()), Some(mode));
+    assert_eq!(DisplayMode::from_name("1280X720").unwrap().refresh_rate, 0);
+    assert_eq!(DisplayMode::from_name("1280x"), None);
+}
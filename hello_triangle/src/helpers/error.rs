@@ -0,0 +1,80 @@
+use windows::core::{Error, HRESULT, HSTRING, PWSTR};
+use windows::w;
+use windows::Win32::Foundation::*;
+use windows::Win32::Graphics::Dxgi::*;
+use windows::Win32::System::Diagnostics::Debug::*;
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+/// 通过 `FormatMessageW` 向系统查询 HRESULT 对应的可读描述，查不到时返回空字符串。
+pub fn format_message(code: HRESULT) -> String {
+    let mut buffer = [0u16; 512];
+    let len = unsafe {
+        FormatMessageW(
+            FORMAT_MESSAGE_FROM_SYSTEM | FORMAT_MESSAGE_IGNORE_INSERTS,
+            None,
+            code.0 as u32,
+            0,
+            PWSTR(buffer.as_mut_ptr()),
+            buffer.len() as u32,
+            None,
+        )
+    };
+    String::from_utf16_lossy(&buffer[..len as usize])
+        .trim_end()
+        .to_string()
+}
+
+/// 把常见的 D3D12/DXGI 错误码翻译成带有排查方向的说明，其余的交给 `FormatMessageW`。
+pub fn explain_error(error: &Error) -> String {
+    let code = error.code();
+    let description = match code {
+        DXGI_ERROR_DEVICE_REMOVED => {
+            "GPU device removed: the driver crashed, was updated, or the GPU hung".to_string()
+        }
+        DXGI_ERROR_DEVICE_HUNG => {
+            "GPU device hung: a command took too long or was malformed".to_string()
+        }
+        DXGI_ERROR_DEVICE_RESET => {
+            "GPU device reset: a badly formed command caused the device to reset".to_string()
+        }
+        DXGI_ERROR_DRIVER_INTERNAL_ERROR => "the graphics driver hit an internal error".to_string(),
+        DXGI_ERROR_NOT_FOUND => {
+            "DXGI object not found (e.g. no more adapters or outputs)".to_string()
+        }
+        DXGI_ERROR_INVALID_CALL => {
+            "invalid DXGI call, enable the debug layer for details".to_string()
+        }
+        DXGI_ERROR_UNSUPPORTED => "the requested feature is not supported".to_string(),
+        D3D12_ERROR_ADAPTER_NOT_FOUND => {
+            "the cached PSO was created on a different adapter".to_string()
+        }
+        D3D12_ERROR_DRIVER_VERSION_MISMATCH => {
+            "the cached PSO was created with a different driver version".to_string()
+        }
+        E_OUTOFMEMORY => "out of memory (video or system memory exhausted)".to_string(),
+        E_INVALIDARG => {
+            "invalid argument, usually reported by D3D12 validation; check the debug layer output"
+                .to_string()
+        }
+        _ => {
+            let description = format_message(code);
+            if description.is_empty() {
+                error.message().to_string()
+            } else {
+                description
+            }
+        }
+    };
+    // 用 `Error::new` 附加了自己的消息时（例如着色器编译器的诊断信息），消息与系统描述不同，也要一并显示
+    let message = error.message().to_string();
+    if code.message() != message && message != description {
+        return format!("{} (HRESULT {:#010X})\n{}", description, code.0, message);
+    }
+    format!("{} (HRESULT {:#010X})", description, code.0)
+}
+
+/// 双击启动的程序没有控制台，初始化失败时用消息框把 HRESULT 和错误描述告诉用户。
+pub fn show_error_message_box(error: &Error) {
+    let text: HSTRING = explain_error(error).into();
+    unsafe { MessageBoxW(HWND::default(), &text, w!("DXSample"), MB_OK | MB_ICONERROR) };
+}
@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+/// 示例用到的按键，由 `WM_KEYDOWN`/`WM_KEYUP` 的虚拟键码（wparam）转换而来。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    W,
+    A,
+    S,
+    D,
+    Q,
+    E,
+    Up,
+    Down,
+    Left,
+    Right,
+    Shift,
+    Space,
+}
+
+impl Key {
+    /// 没有列在 [`Key`] 中的虚拟键码返回 `None`。字母键的虚拟键码就是大写字母的 ASCII 码。
+    pub fn from_virtual_key(key: u8) -> Option<Key> {
+        match key {
+            b'W' => Some(Key::W),
+            b'A' => Some(Key::A),
+            b'S' => Some(Key::S),
+            b'D' => Some(Key::D),
+            b'Q' => Some(Key::Q),
+            b'E' => Some(Key::E),
+            0x26 => Some(Key::Up),    // VK_UP
+            0x28 => Some(Key::Down),  // VK_DOWN
+            0x25 => Some(Key::Left),  // VK_LEFT
+            0x27 => Some(Key::Right), // VK_RIGHT
+            0x10 => Some(Key::Shift), // VK_SHIFT
+            0x20 => Some(Key::Space), // VK_SPACE
+            _ => None,
+        }
+    }
+}
+
+/// 当前按住的按键，由窗口过程在调用 `on_key_down`/`on_key_up` 的同时更新，再传给 `update`。
+/// 平滑移动这类每帧都要知道按键状态的逻辑读取它，再乘以 `delta_time`，而不是依赖按住时系统重复发送的按下消息。
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    held: HashSet<Key>,
+}
+
+impl InputState {
+    /// 记录按下的按键，返回它是否是刚刚按下的。按住不放时系统重复发送的按下消息返回 false，不会重复计数。
+    pub fn press(&mut self, key: u8) -> bool {
+        Key::from_virtual_key(key).is_some_and(|key| self.held.insert(key))
+    }
+
+    pub fn release(&mut self, key: u8) {
+        if let Some(key) = Key::from_virtual_key(key) {
+            self.held.remove(&key);
+        }
+    }
+
+    /// 松开所有按键。窗口失去焦点后收不到抬起消息，不这样做按键就会一直处于按下状态。
+    pub fn release_all(&mut self) {
+        self.held.clear();
+    }
+
+    pub fn is_held(&self, key: Key) -> bool {
+        self.held.contains(&key)
+    }
+
+    /// 一对相反方向的按键组成的轴：只按住 `positive` 时为 1，只按住 `negative` 时为 -1，都按或都不按时为 0。
+    pub fn axis(&self, negative: Key, positive: Key) -> f32 {
+        self.is_held(positive) as i32 as f32 - self.is_held(negative) as i32 as f32
+    }
+}
+
+#[test]
+fn key_repeat_is_not_counted_twice() {
+    let mut input = InputState::default();
+    assert!(input.press(b'W'));
+    // 按住时系统重复发送 WM_KEYDOWN
+    assert!(!input.press(b'W'));
+    assert!(input.is_held(Key::W));
+    assert_eq!(input.axis(Key::S, Key::W), 1.0);
+
+    input.press(b'S');
+    assert_eq!(input.axis(Key::S, Key::W), 0.0);
+    input.release(b'W');
+    assert_eq!(input.axis(Key::S, Key::W), -1.0);
+
+    // 没有对应 Key 的按键不被记录
+    assert!(!input.press(0x74));
+    input.release_all();
+    assert!(!input.is_held(Key::S));
+}
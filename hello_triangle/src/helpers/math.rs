@@ -0,0 +1,251 @@
+use std::ops::{Add, Mul, Sub};
+
+/// 三维向量，用于位置和方向。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub const ZERO: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+    pub const Y: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Vec3 { x, y, z }
+    }
+
+    pub fn dot(self, other: Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Vec3 {
+        self * (1.0 / self.length())
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, scale: f32) -> Vec3 {
+        Vec3::new(self.x * scale, self.y * scale, self.z * scale)
+    }
+}
+
+/// 齐次坐标，`w` 为 1 表示点，为 0 表示方向。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Vec4 {
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Vec4 { x, y, z, w }
+    }
+
+    pub const fn point(v: Vec3) -> Self {
+        Vec4::new(v.x, v.y, v.z, 1.0)
+    }
+}
+
+/// 行向量乘以矩阵，与 HLSL 中的 `mul(v, M)` 相同。
+impl Mul<Mat4> for Vec4 {
+    type Output = Vec4;
+
+    fn mul(self, m: Mat4) -> Vec4 {
+        let v = [self.x, self.y, self.z, self.w];
+        let column = |j: usize| (0..4).map(|k| v[k] * m.rows[k][j]).sum();
+        Vec4::new(column(0), column(1), column(2), column(3))
+    }
+}
+
+/// 4x4 矩阵，逐行存放，与 DirectXMath 一样按行向量使用：`v * M`，`A * B` 表示先做 A 变换再做 B 变换。
+/// 平移位于最后一行。
+///
+/// 直接复制到常量缓冲区时，HLSL 中要声明为 `row_major float4x4` 并用 `mul(v, M)`，这样不需要转置。
+/// HLSL 默认按列主序读取矩阵，不写 `row_major` 时要先 [`Mat4::transpose`]，
+/// 这时着色器看到的是同一个矩阵的列主序形式，也可以改用 `mul(M, v)`。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    pub rows: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        rows: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn translation(x: f32, y: f32, z: f32) -> Mat4 {
+        let mut m = Mat4::IDENTITY;
+        m.rows[3] = [x, y, z, 1.0];
+        m
+    }
+
+    pub fn scale(x: f32, y: f32, z: f32) -> Mat4 {
+        let mut m = Mat4::IDENTITY;
+        m.rows[0][0] = x;
+        m.rows[1][1] = y;
+        m.rows[2][2] = z;
+        m
+    }
+
+    /// 绕 y 轴旋转 `angle` 弧度，相当于 `XMMatrixRotationY`：从 +y 往下看是顺时针，+z 转向 +x。
+    pub fn rotation_y(angle: f32) -> Mat4 {
+        let (sin, cos) = angle.sin_cos();
+        Mat4 {
+            rows: [
+                [cos, 0.0, -sin, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [sin, 0.0, cos, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.rows[j][i];
+            }
+        }
+        Mat4 { rows }
+    }
+
+    /// 左手坐标系的观察矩阵，相当于 `XMMatrixLookAtLH`：把世界空间变换到以 `eye` 为原点、
+    /// 看向 `target` 的观察空间，观察方向为 +z。
+    pub fn look_at_lh(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+        let z = (target - eye).normalize();
+        let x = up.cross(z).normalize();
+        let y = z.cross(x);
+        Mat4 {
+            rows: [
+                [x.x, y.x, z.x, 0.0],
+                [x.y, y.y, z.y, 0.0],
+                [x.z, y.z, z.z, 0.0],
+                [-x.dot(eye), -y.dot(eye), -z.dot(eye), 1.0],
+            ],
+        }
+    }
+
+    /// 左手坐标系的透视投影矩阵，相当于 `XMMatrixPerspectiveFovLH`。`fov_y` 是垂直视场角（弧度），
+    /// 近平面上的点深度为 0，远平面上的为 1。
+    pub fn perspective_fov_lh(fov_y: f32, aspect_ratio: f32, near: f32, far: f32) -> Mat4 {
+        let y_scale = 1.0 / (fov_y * 0.5).tan();
+        let x_scale = y_scale / aspect_ratio;
+        let z_range = far / (far - near);
+        Mat4 {
+            rows: [
+                [x_scale, 0.0, 0.0, 0.0],
+                [0.0, y_scale, 0.0, 0.0],
+                [0.0, 0.0, z_range, 1.0],
+                [0.0, 0.0, -near * z_range, 0.0],
+            ],
+        }
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut rows = [[0.0; 4]; 4];
+        for (row, lhs) in rows.iter_mut().zip(self.rows) {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| lhs[k] * other.rows[k][j]).sum();
+            }
+        }
+        Mat4 { rows }
+    }
+}
+
+#[test]
+fn look_at_and_perspective_match_directxmath() {
+    // 从 z = -5 看向原点时，观察矩阵只是把物体沿 +z 平移 5
+    let view = Mat4::look_at_lh(Vec3::new(0.0, 0.0, -5.0), Vec3::ZERO, Vec3::Y);
+    let mut expected = Mat4::IDENTITY;
+    expected.rows[3][2] = 5.0;
+    assert_eq!(view, expected);
+
+    // 90 度视场角、宽高比 2 时 x 方向缩放为 0.5；近平面深度为 0，远平面深度为 1
+    let projection = Mat4::perspective_fov_lh(std::f32::consts::FRAC_PI_2, 2.0, 1.0, 10.0);
+    assert!((projection.rows[0][0] - 0.5).abs() < 1e-6);
+    assert!((projection.rows[1][1] - 1.0).abs() < 1e-6);
+    let depth = |z: f32| (z * projection.rows[2][2] + projection.rows[3][2]) / z;
+    assert!(depth(1.0).abs() < 1e-6);
+    assert!((depth(10.0) - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn matrix_products_apply_left_to_right() {
+    let a = Mat4 {
+        rows: [
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.0, 14.0, 15.0, 16.0],
+        ],
+    };
+    let b = Mat4::translation(1.0, 2.0, 3.0);
+    // 手算：每行第 4 列的元素乘以平移量加到前三列上，第 4 列不变
+    assert_eq!(
+        (a * b).rows,
+        [
+            [5.0, 10.0, 15.0, 4.0],
+            [13.0, 22.0, 31.0, 8.0],
+            [21.0, 34.0, 47.0, 12.0],
+            [29.0, 46.0, 63.0, 16.0],
+        ]
+    );
+    assert_eq!(a.transpose().transpose(), a);
+    assert_eq!(a.transpose().rows[0], [1.0, 5.0, 9.0, 13.0]);
+
+    // 先缩放、再绕 y 转 90 度、最后平移：(1, 0, 0) -> (2, 0, 0) -> (0, 0, -2) -> (1, 2, 1)
+    let m = Mat4::scale(2.0, 2.0, 2.0)
+        * Mat4::rotation_y(std::f32::consts::FRAC_PI_2)
+        * Mat4::translation(1.0, 2.0, 3.0);
+    let p = Vec4::point(Vec3::new(1.0, 0.0, 0.0)) * m;
+    for (actual, expected) in [(p.x, 1.0), (p.y, 2.0), (p.z, 1.0), (p.w, 1.0)] {
+        assert!((actual - expected).abs() < 1e-6);
+    }
+}
@@ -1,5 +1,39 @@
+mod assets;
+mod back_buffer_format;
+mod blend_mode;
+mod camera;
+mod coordinate_system;
+mod debug_name;
+mod depth_mode;
+mod display_mode;
+mod error;
+mod input;
+mod math;
 mod memory_dbg_helper;
+mod pix;
+mod png;
+mod sample_kind;
+mod shader_model;
+mod stencil_mode;
+mod timer;
+pub use assets::*;
+pub use back_buffer_format::*;
+pub use blend_mode::*;
+pub use camera::*;
+pub use coordinate_system::*;
+pub use debug_name::*;
+pub use depth_mode::*;
+pub use display_mode::*;
+pub use error::*;
+pub use input::*;
+pub use math::*;
 pub use memory_dbg_helper::*;
+pub use pix::*;
+pub use png::*;
+pub use sample_kind::*;
+pub use shader_model::*;
+pub use stencil_mode::*;
+pub use timer::*;
 
 pub fn wstrlens(pwstr: &[u16]) -> usize {
     let mut len = 0;
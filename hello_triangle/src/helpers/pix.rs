@@ -0,0 +1,91 @@
+use windows::Win32::Graphics::Direct3D12::*;
+
+/// `BeginEvent`/`SetMarker` 的 metadata 为 2 时，数据是 PIX 3 格式的事件记录，PIX 据此显示名称和颜色。
+const WINPIX_EVENT_PIX3BLOB_VERSION: u32 = 2;
+
+const PIX_EVENT_BEGIN_EVENT_NO_ARGS: u64 = 0x002;
+const PIX_EVENT_SET_MARKER_NO_ARGS: u64 = 0x008;
+/// 事件类型位于事件头的第 10~19 位。
+const PIX_EVENTS_TYPE_BIT_SHIFT: u64 = 10;
+/// 字符串头：按 8 字节分块复制（第 55~59 位），宽字符（第 54 位为 0）。
+const PIX_EVENTS_STRING_INFO: u64 = 8 << 55;
+
+/// 一条事件记录最多 64 个 8 字节，与 WinPixEventRuntime 中命令列表事件的上限相同，过长的名称会被截断。
+const PIX_EVENT_RECORD_QWORDS: usize = 64;
+
+/// 相当于 `PIX_COLOR(r, g, b)`。
+pub const fn pix_color(r: u8, g: u8, b: u8) -> u64 {
+    0xff00_0000 | (r as u64) << 16 | (g as u64) << 8 | b as u64
+}
+
+/// 按 `PIXBeginEvent`/`PIXSetMarker` 的格式编码一条记录：事件头、颜色、字符串头，
+/// 然后是 UTF-16 字符，每 4 个打包成一个 8 字节，以 0 结尾。
+fn encode_event(
+    event_type: u64,
+    color: u64,
+    name: &str,
+) -> ([u64; PIX_EVENT_RECORD_QWORDS], usize) {
+    let mut record = [0u64; PIX_EVENT_RECORD_QWORDS];
+    record[0] = event_type << PIX_EVENTS_TYPE_BIT_SHIFT;
+    record[1] = color;
+    record[2] = PIX_EVENTS_STRING_INFO;
+    let mut len = 3;
+    let chars: Vec<u16> = name.encode_utf16().chain(Some(0)).collect();
+    // 最后一个 8 字节留给截断时结尾的 0
+    let available = PIX_EVENT_RECORD_QWORDS - len - 1;
+    let chunks = chars.chunks(4);
+    let truncated = chunks.len() > available;
+    for chunk in chunks.take(available) {
+        record[len] = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |qword, (i, c)| qword | (*c as u64) << (16 * i));
+        len += 1;
+    }
+    if truncated {
+        len += 1;
+    }
+    (record, len)
+}
+
+/// 在命令列表上开始一段有名字的区域，PIX 抓帧时会把其中的命令归到这个名字下面，要与 [`end_event`] 配对。
+///
+/// 这些调用只是把一小段数据交给 D3D12 运行时，没有 PIX 之类的工具附加时运行时直接忽略它们，
+/// 所以不需要额外判断 PIX 是否存在。
+pub fn begin_event(command_list: &ID3D12GraphicsCommandList, color: u64, name: &str) {
+    let (record, len) = encode_event(PIX_EVENT_BEGIN_EVENT_NO_ARGS, color, name);
+    unsafe {
+        command_list.BeginEvent(
+            WINPIX_EVENT_PIX3BLOB_VERSION,
+            Some(record.as_ptr() as *const _),
+            (len * 8) as u32,
+        )
+    };
+}
+
+pub fn end_event(command_list: &ID3D12GraphicsCommandList) {
+    unsafe { command_list.EndEvent() };
+}
+
+/// 在命令列表的当前位置插入一个瞬时标记。
+pub fn set_marker(command_list: &ID3D12GraphicsCommandList, color: u64, name: &str) {
+    let (record, len) = encode_event(PIX_EVENT_SET_MARKER_NO_ARGS, color, name);
+    unsafe {
+        command_list.SetMarker(
+            WINPIX_EVENT_PIX3BLOB_VERSION,
+            Some(record.as_ptr() as *const _),
+            (len * 8) as u32,
+        )
+    };
+}
+
+#[test]
+fn encode_pix_event() {
+    let (record, len) = encode_event(PIX_EVENT_BEGIN_EVENT_NO_ARGS, pix_color(255, 0, 0), "Clear");
+    // 5 个字符加上结尾的 0 占 2 个 8 字节
+    assert_eq!(len, 5);
+    assert_eq!(record[0], 0x800);
+    assert_eq!(record[1], 0xffff_0000);
+    assert_eq!(record[3], 0x0061_0065_006c_0043);
+    assert_eq!(record[4], 0x0072);
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+
+/// PNG 文件的 8 字节签名。
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// 未压缩的 deflate 块最多存放 65535 字节。
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+fn crc32(chunks: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for byte in chunks.iter().flat_map(|chunk| chunk.iter()) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    png.extend_from_slice(&crc32(&[kind, data]).to_be_bytes());
+}
+
+/// 把紧密排列的 RGBA8 像素编码为 PNG。
+///
+/// 只是为了截图，不做任何压缩：每行前面加上滤波类型 0，再用未压缩（stored）的 deflate 块存放，
+/// 文件会比较大，但不需要额外的依赖。
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let row_bytes = width as usize * 4;
+    assert_eq!(rgba.len(), row_bytes * height as usize);
+
+    let mut raw = Vec::with_capacity((row_bytes + 1) * height as usize);
+    for row in rgba.chunks_exact(row_bytes) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // zlib 头：deflate、32K 窗口、不使用预设字典
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(last as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 位深 8，颜色类型 6（RGBA），默认的压缩和滤波方式，不隔行扫描
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+pub fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, encode_png(width, height, rgba))
+}
+
+#[test]
+fn encode_png_layout() {
+    let png = encode_png(2, 1, &[255, 0, 0, 255, 0, 0, 255, 128]);
+    assert_eq!(png[..8], PNG_SIGNATURE);
+    // IHDR 紧跟在签名之后，宽高都是大端序
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(png[16..20], 2u32.to_be_bytes());
+    assert_eq!(png[20..24], 1u32.to_be_bytes());
+    // 空的 IEND 块的 CRC 是固定值
+    assert_eq!(
+        png[png.len() - 12..],
+        [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+    );
+    assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+}
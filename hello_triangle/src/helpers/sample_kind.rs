@@ -0,0 +1,41 @@
+/// `-sample` 可选的示例，[`crate::run_sample`] 据此决定用哪个 `DXSample` 创建窗口。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SampleKind {
+    #[default]
+    Triangle,
+    /// 带深度测试、绕 y 轴旋转的彩色立方体。
+    Box,
+    /// 用模板缓冲区给立方体描边。
+    Outline,
+}
+
+impl SampleKind {
+    /// 所有示例，按 `-sample` 的帮助信息中列出的顺序排列。
+    pub const ALL: [SampleKind; 3] = [SampleKind::Triangle, SampleKind::Box, SampleKind::Outline];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "triangle" => Some(SampleKind::Triangle),
+            "box" => Some(SampleKind::Box),
+            "outline" => Some(SampleKind::Outline),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SampleKind::Triangle => "triangle",
+            SampleKind::Box => "box",
+            SampleKind::Outline => "outline",
+        }
+    }
+}
+
+#[test]
+fn sample_names_round_trip() {
+    for kind in SampleKind::ALL {
+        assert_eq!(SampleKind::from_name(kind.name()), Some(kind));
+    }
+    assert_eq!(SampleKind::from_name("BOX"), Some(SampleKind::Box));
+    assert_eq!(SampleKind::from_name("teapot"), None);
+}
@@ -0,0 +1,46 @@
+/// `-shadermodel` 可选的着色器模型，同时决定用哪个编译器。
+///
+/// 5.x 由系统自带的 FXC（`d3dcompiler_47.dll`）编译成 DXBC；6.x 只能由 DXC（`dxcompiler.dll`）编译成 DXIL，
+/// 波操作（wave intrinsics）、16 位类型等新特性都需要 6.x。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ShaderModel {
+    #[default]
+    Sm5_0,
+    Sm6_0,
+}
+
+impl ShaderModel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "5_0" | "5.0" => Some(ShaderModel::Sm5_0),
+            "6_0" | "6.0" => Some(ShaderModel::Sm6_0),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShaderModel::Sm5_0 => "5_0",
+            ShaderModel::Sm6_0 => "6_0",
+        }
+    }
+
+    /// 是否要用 DXC 编译。
+    pub fn uses_dxc(self) -> bool {
+        self != ShaderModel::Sm5_0
+    }
+
+    /// 编译器的目标字符串，`stage` 是着色器阶段的前缀，例如 `vs`、`ps`，得到 `vs_5_0`、`ps_6_0`。
+    pub fn target(self, stage: &str) -> String {
+        format!("{}_{}", stage, self.name())
+    }
+}
+
+#[test]
+fn shader_model_targets() {
+    assert_eq!(ShaderModel::default().target("vs"), "vs_5_0");
+    assert_eq!(ShaderModel::from_name("6.0"), Some(ShaderModel::Sm6_0));
+    assert_eq!(ShaderModel::Sm6_0.target("ps"), "ps_6_0");
+    assert!(ShaderModel::Sm6_0.uses_dxc());
+    assert!(ShaderModel::from_name("4_0").is_none());
+}
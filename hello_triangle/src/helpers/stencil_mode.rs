@@ -0,0 +1,49 @@
+use windows::Win32::Graphics::Direct3D12::*;
+
+/// PSO 中的模板测试设置。参考值不属于 PSO，绘制前用 `OMSetStencilRef` 设置。
+///
+/// 模板缓冲区为每个像素保存一个 8 位整数（`D24_UNORM_S8_UINT` 中的 S8）。模板测试用比较函数比较参考值和缓冲区中的值，
+/// 没有通过的像素直接被丢弃；通过后再按深度测试的结果选择 `StencilPassOp` 或 `StencilDepthFailOp` 修改缓冲区。
+/// 正面和背面可以分别设置，这里两面总是相同。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum StencilMode {
+    /// 不做模板测试，也不修改模板缓冲区。
+    #[default]
+    Disabled,
+    /// 测试总是通过，画到的每个像素都写入参考值（`REPLACE`），即使深度测试失败。
+    Write,
+    /// 只在模板值不等于参考值的像素上通过，不修改模板缓冲区。
+    NotEqual,
+}
+
+impl StencilMode {
+    pub fn is_enabled(self) -> bool {
+        self != StencilMode::Disabled
+    }
+
+    /// 一个面的模板操作，写入模式下深度测试失败也写入，这样被遮挡的部分同样会被描边排除在外。
+    pub fn face_desc(self) -> D3D12_DEPTH_STENCILOP_DESC {
+        let (func, pass_op) = match self {
+            StencilMode::Disabled => (D3D12_COMPARISON_FUNC_ALWAYS, D3D12_STENCIL_OP_KEEP),
+            StencilMode::Write => (D3D12_COMPARISON_FUNC_ALWAYS, D3D12_STENCIL_OP_REPLACE),
+            StencilMode::NotEqual => (D3D12_COMPARISON_FUNC_NOT_EQUAL, D3D12_STENCIL_OP_KEEP),
+        };
+        D3D12_DEPTH_STENCILOP_DESC {
+            StencilFailOp: D3D12_STENCIL_OP_KEEP,
+            StencilDepthFailOp: pass_op,
+            StencilPassOp: pass_op,
+            StencilFunc: func,
+        }
+    }
+}
+
+#[test]
+fn stencil_modes_fill_the_face_ops() {
+    assert!(!StencilMode::default().is_enabled());
+    let write = StencilMode::Write.face_desc();
+    assert_eq!(write.StencilFunc, D3D12_COMPARISON_FUNC_ALWAYS);
+    assert_eq!(write.StencilPassOp, D3D12_STENCIL_OP_REPLACE);
+    let not_equal = StencilMode::NotEqual.face_desc();
+    assert_eq!(not_equal.StencilFunc, D3D12_COMPARISON_FUNC_NOT_EQUAL);
+    assert_eq!(not_equal.StencilPassOp, D3D12_STENCIL_OP_KEEP);
+}
@@ -0,0 +1,85 @@
+use windows::Win32::System::Performance::*;
+
+/// 读取性能计数器（QPC）的当前值，与 `QueryPerformanceFrequency` 一起换算为秒。
+pub fn query_performance_counter() -> i64 {
+    let mut counter = 0;
+    unsafe { QueryPerformanceCounter(&mut counter) };
+    counter
+}
+
+/// 基于性能计数器（QPC）的高精度计时器，记录自 `reset` 以来的总时间和相邻两次 `tick` 之间的时间。
+///
+/// 每帧调用一次 `tick`，动画按 `delta_time` 或 `total_time` 推进，
+/// 这样在帧率不同的机器上也以同样的速度运行。
+pub struct GameTimer {
+    /// 性能计数器每秒的计数次数，系统启动后不会改变。
+    frequency: i64,
+    base_time: i64,
+    previous_time: i64,
+    current_time: i64,
+    delta_time: f64,
+}
+
+impl Default for GameTimer {
+    fn default() -> Self {
+        let mut frequency = 0;
+        unsafe { QueryPerformanceFrequency(&mut frequency) };
+        let now = query_performance_counter();
+        GameTimer {
+            frequency,
+            base_time: now,
+            previous_time: now,
+            current_time: now,
+            delta_time: 0.0,
+        }
+    }
+}
+
+impl GameTimer {
+    /// 从现在开始重新计时，总时间和帧间隔都归零。
+    pub fn reset(&mut self) {
+        let now = query_performance_counter();
+        self.base_time = now;
+        self.previous_time = now;
+        self.current_time = now;
+        self.delta_time = 0.0;
+    }
+
+    /// 每帧调用一次，更新当前时刻和与上一帧的间隔。
+    pub fn tick(&mut self) {
+        self.current_time = query_performance_counter();
+        // 进程在处理器之间切换时计数值可能略有回退，帧间隔不应为负
+        self.delta_time =
+            ((self.current_time - self.previous_time) as f64 / self.frequency as f64).max(0.0);
+        self.previous_time = self.current_time;
+    }
+
+    /// 自 `reset`（或创建）到最近一次 `tick` 经过的秒数。
+    pub fn total_time(&self) -> f32 {
+        ((self.current_time - self.base_time) as f64 / self.frequency as f64) as f32
+    }
+
+    /// 最近两次 `tick` 之间的秒数。
+    pub fn delta_time(&self) -> f32 {
+        self.delta_time as f32
+    }
+}
+
+#[test]
+fn game_timer_advances_monotonically() {
+    let mut timer = GameTimer::default();
+    assert_eq!(timer.total_time(), 0.0);
+
+    let mut previous = timer.total_time();
+    for _ in 0..3 {
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        timer.tick();
+        assert!(timer.total_time() > previous);
+        assert!(timer.delta_time() > 0.0);
+        previous = timer.total_time();
+    }
+
+    timer.reset();
+    assert_eq!(timer.total_time(), 0.0);
+    assert_eq!(timer.delta_time(), 0.0);
+}
@@ -3,6 +3,7 @@ mod bindings;
 mod command_line;
 mod dx_sample;
 mod helpers;
+mod self_test;
 
 pub use app::*;
 pub use bindings::*;
@@ -16,8 +17,28 @@ fn main() -> Result<()> {
     // let factory = devices::create_factory()?;
     // adapter::print_adapter_info(&factory).unwrap();
     // let (_factory, device) = devices::create_device(&SampleCommandLine::default())?;
-    // devices::check_sample_support(&device)?;
+    // devices::check_sample_support(&device, DXGI_FORMAT_R8G8B8A8_UNORM)?;
     // devices::test(&device);
-    dx_sample::init_sample::<hello_triangle::Sample>()?;
+    let command_line = SampleCommandLine::parse(std::env::args().skip(1));
+    if let Some(assets) = &command_line.assets {
+        set_assets_override(std::path::Path::new(assets));
+    }
+    if command_line.self_test {
+        if let Err(error) = self_test::run_self_test() {
+            eprintln!("selftest: failed: {}", explain_error(&error));
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Err(error) = run_sample(&command_line) {
+        // 离屏渲染通常在脚本中运行，不弹出对话框
+        if command_line.headless.is_some() {
+            eprintln!("headless: failed: {}", explain_error(&error));
+            std::process::exit(1);
+        }
+        show_error_message_box(&error);
+        return Err(error);
+    }
     Ok(())
 }
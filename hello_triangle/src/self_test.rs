@@ -0,0 +1,293 @@
+use crate::compute::fill_buffer_on_gpu;
+use crate::constant_buffer::ConstantBuffer;
+use crate::descriptor::set_render_targets;
+use crate::devices::{create_device, create_pipeline_state, create_root_signature};
+use crate::fence::Fence;
+use crate::hello_triangle::{create_vertex_buffer, ObjectConstants};
+use crate::info_queue::{info_queue, severity_name, stored_messages};
+use crate::pipeline_state::RenderTargetFormats;
+use crate::readback::read_back_pixel;
+use crate::textured_quad::create_textured_pipeline_state;
+use crate::vertex::VertexPC;
+use crate::{BlendMode, CoordinateSystem, SampleCommandLine, ShaderModel, StencilMode};
+use windows::{
+    core::*, Win32::Foundation::*, Win32::Graphics::Direct3D::*, Win32::Graphics::Direct3D12::*,
+    Win32::Graphics::Dxgi::Common::*,
+};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+const CLEAR_COLOR: [f32; 4] = [0.0, 0.2, 0.4, 1.0];
+
+fn self_test_error(message: &str) -> Error {
+    Error::new(E_FAIL, message.into())
+}
+
+/// `-selftest`：不创建窗口，在 WARP 设备上把主要的代码路径各走一遍：
+/// 创建设备、根签名、PSO 和顶点缓冲区，向离屏渲染目标绘制一帧，读回像素检查结果，
+/// 用计算着色器填充一个缓冲区并读回，最后确认调试层没有报告任何错误。任何一步失败都会返回错误。
+pub fn run_self_test() -> Result<()> {
+    let command_line = SampleCommandLine {
+        use_warp_device: true,
+        ..Default::default()
+    };
+    let (_dxgi_factory, device) = create_device(&command_line)?;
+    println!("selftest: created WARP device");
+
+    let root_signature = create_root_signature(&device, false)?;
+    let pso = create_pipeline_state::<VertexPC>(
+        &device,
+        &root_signature,
+        &[],
+        ShaderModel::default(),
+        false,
+        CoordinateSystem::default(),
+        D3D12_FILL_MODE_SOLID,
+        // 与三角形示例一致，不做剔除
+        D3D12_CULL_MODE_NONE,
+        BlendMode::Opaque,
+        StencilMode::Disabled,
+        &RenderTargetFormats::color_only(DXGI_FORMAT_R8G8B8A8_UNORM),
+        false,
+    )?;
+    println!("selftest: created root signature and pipeline state");
+
+    // 纹理方块的像素着色器声明了 t0 和 s0，PSO 创建时会检查根签名是否提供了它们
+    let textured_root_signature = create_root_signature(&device, true)?;
+    create_textured_pipeline_state(
+        &device,
+        &textured_root_signature,
+        &RenderTargetFormats::color_only(DXGI_FORMAT_R8G8B8A8_UNORM),
+        false,
+    )?;
+    println!("selftest: created textured root signature and pipeline state");
+
+    let (_vertex_buffer, vbv) = create_vertex_buffer(
+        &device,
+        WIDTH as f32 / HEIGHT as f32,
+        CoordinateSystem::default(),
+        None,
+    )?;
+    println!("selftest: created vertex buffer");
+
+    let object_constants = ConstantBuffer::new(&device)?;
+    object_constants.update(&ObjectConstants::IDENTITY);
+
+    let (command_queue, render_target) =
+        render_offscreen(&device, &root_signature, &pso, &vbv, &object_constants)?;
+    println!("selftest: rendered one frame offscreen");
+
+    let pixel = |x: u32, y: u32| {
+        read_back_pixel(
+            &device,
+            &command_queue,
+            &render_target,
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+            x,
+            y,
+        )
+    };
+    let clear = CLEAR_COLOR.map(|c| (c * 255.0).round() as u8);
+    let matches_clear = |p: [u8; 4]| p.iter().zip(clear).all(|(a, b)| a.abs_diff(b) <= 1);
+    if !matches_clear(pixel(0, 0)?) {
+        return Err(self_test_error(
+            "corner pixel does not match the clear color",
+        ));
+    }
+    // 画面中心与红色的顶点最近，红色的权重是另外两个顶点的两倍
+    let [r, g, b, _] = pixel(WIDTH / 2, HEIGHT / 2)?;
+    if r <= g || r <= b {
+        return Err(self_test_error(&format!(
+            "center pixel should be reddish, got ({}, {}, {})",
+            r, g, b
+        )));
+    }
+    println!("selftest: read back expected pixels");
+
+    // 计算着色器写入的缓冲区同样要读回检查
+    let values = fill_buffer_on_gpu(&device, &command_queue, 100, 3, ShaderModel::default())?;
+    if values
+        .iter()
+        .enumerate()
+        .any(|(i, &value)| value != i as u32 * 3)
+    {
+        return Err(self_test_error(
+            "compute shader did not fill the buffer as expected",
+        ));
+    }
+    println!("selftest: filled a buffer with a compute shader");
+
+    match info_queue(&device) {
+        Some(info_queue) => {
+            let errors: Vec<_> = stored_messages(&info_queue, 0)?
+                .into_iter()
+                .filter(|message| message.is_error())
+                .collect();
+            for message in &errors {
+                eprintln!(
+                    "selftest: D3D12 {}: {}",
+                    severity_name(message.severity),
+                    message.description
+                );
+            }
+            if !errors.is_empty() {
+                return Err(self_test_error("the debug layer reported errors"));
+            }
+            println!("selftest: info queue is clean");
+        }
+        None => println!("selftest: debug layer unavailable, skipping info queue check"),
+    }
+
+    println!("selftest: passed");
+    Ok(())
+}
+
+/// 把三角形绘制到一个离屏渲染目标上并等待 GPU 执行完毕，返回所用的命令队列和仍处于 RENDER_TARGET 状态的渲染目标。
+fn render_offscreen(
+    device: &ID3D12Device,
+    root_signature: &ID3D12RootSignature,
+    pso: &ID3D12PipelineState,
+    vbv: &D3D12_VERTEX_BUFFER_VIEW,
+    object_constants: &ConstantBuffer<ObjectConstants>,
+) -> Result<(ID3D12CommandQueue, ID3D12Resource)> {
+    let render_target_desc = D3D12_RESOURCE_DESC {
+        Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+        Width: WIDTH as u64,
+        Height: HEIGHT,
+        DepthOrArraySize: 1,
+        MipLevels: 1,
+        Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        Flags: D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET,
+        ..Default::default()
+    };
+    let mut render_target: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: D3D12_HEAP_TYPE_DEFAULT,
+                ..Default::default()
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &render_target_desc,
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+            Some(&D3D12_CLEAR_VALUE {
+                Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+                Anonymous: D3D12_CLEAR_VALUE_0 { Color: CLEAR_COLOR },
+            }),
+            &mut render_target,
+        )?
+    };
+    let render_target = render_target.unwrap();
+
+    let rtv_heap: ID3D12DescriptorHeap = unsafe {
+        device.CreateDescriptorHeap(&D3D12_DESCRIPTOR_HEAP_DESC {
+            NumDescriptors: 1,
+            Type: D3D12_DESCRIPTOR_HEAP_TYPE_RTV,
+            ..Default::default()
+        })
+    }?;
+    let rtv_handle = unsafe { rtv_heap.GetCPUDescriptorHandleForHeapStart() };
+    unsafe { device.CreateRenderTargetView(&render_target, None, rtv_handle) };
+
+    let command_queue: ID3D12CommandQueue = unsafe {
+        device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
+            Type: D3D12_COMMAND_LIST_TYPE_DIRECT,
+            ..Default::default()
+        })
+    }?;
+    let command_allocator: ID3D12CommandAllocator =
+        unsafe { device.CreateCommandAllocator(D3D12_COMMAND_LIST_TYPE_DIRECT) }?;
+    let command_list: ID3D12GraphicsCommandList = unsafe {
+        device.CreateCommandList(0, D3D12_COMMAND_LIST_TYPE_DIRECT, &command_allocator, pso)
+    }?;
+
+    unsafe {
+        command_list.SetGraphicsRootSignature(root_signature);
+        // 时间为 0 时像素着色器输出顶点的原色
+        command_list.SetGraphicsRoot32BitConstant(0, 0f32.to_bits(), 0);
+        command_list.SetGraphicsRootConstantBufferView(1, object_constants.gpu_virtual_address());
+        command_list.RSSetViewports(&[D3D12_VIEWPORT {
+            Width: WIDTH as f32,
+            Height: HEIGHT as f32,
+            MaxDepth: D3D12_MAX_DEPTH,
+            ..Default::default()
+        }]);
+        command_list.RSSetScissorRects(&[RECT {
+            left: 0,
+            top: 0,
+            right: WIDTH as i32,
+            bottom: HEIGHT as i32,
+        }]);
+        set_render_targets(&command_list, &[rtv_handle], None);
+        command_list.ClearRenderTargetView(rtv_handle, CLEAR_COLOR.as_ptr(), &[]);
+        command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        command_list.IASetVertexBuffers(0, Some(&[*vbv]));
+        command_list.DrawInstanced(3, 1, 0, 0);
+
+        command_list.Close()?;
+
+        command_queue.ExecuteCommandLists(&[Some(command_list.cast()?)]);
+    }
+    Fence::new(device)?.flush(&command_queue)?;
+
+    Ok((command_queue, render_target))
+}
+
+#[test]
+fn self_test_passes_on_warp() {
+    run_self_test().unwrap();
+}
+
+#[test]
+fn back_facing_triangle_is_culled() {
+    use crate::devices::warp_device;
+
+    let device = warp_device();
+    let root_signature = create_root_signature(&device, false).unwrap();
+    let object_constants = ConstantBuffer::new(&device).unwrap();
+    object_constants.update(&ObjectConstants::IDENTITY);
+    // 按右手系（逆时针）生成的三角形，在左手系的 PSO 看来是背面
+    let (_vertex_buffer, vbv) = create_vertex_buffer(
+        &device,
+        WIDTH as f32 / HEIGHT as f32,
+        CoordinateSystem::RightHanded,
+        None,
+    )
+    .unwrap();
+
+    let clear = CLEAR_COLOR.map(|c| (c * 255.0).round() as u8);
+    for (cull_mode, culled) in [(D3D12_CULL_MODE_NONE, false), (D3D12_CULL_MODE_BACK, true)] {
+        let pso = create_pipeline_state::<VertexPC>(
+            &device,
+            &root_signature,
+            &[],
+            ShaderModel::default(),
+            false,
+            CoordinateSystem::LeftHanded,
+            D3D12_FILL_MODE_SOLID,
+            cull_mode,
+            BlendMode::Opaque,
+            StencilMode::Disabled,
+            &RenderTargetFormats::color_only(DXGI_FORMAT_R8G8B8A8_UNORM),
+            false,
+        )
+        .unwrap();
+        let (command_queue, render_target) =
+            render_offscreen(&device, &root_signature, &pso, &vbv, &object_constants).unwrap();
+        let center = read_back_pixel(
+            &device,
+            &command_queue,
+            &render_target,
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+            WIDTH / 2,
+            HEIGHT / 2,
+        )
+        .unwrap();
+        let is_clear = center.iter().zip(clear).all(|(a, b)| a.abs_diff(b) <= 1);
+        assert_eq!(is_clear, culled, "cull mode {:?}", cull_mode);
+    }
+}
@@ -1,20 +1,267 @@
-struct PSInput
+#include "common.hlsli"
+
+#ifdef ASYNC_COMPUTE
+// 由计算队列写入、图形队列读取的每顶点偏移。
+StructuredBuffer<float4> vertexOffsets : register(t0);
+RWStructuredBuffer<float4> outputOffsets : register(u0);
+
+[numthreads(3, 1, 1)]
+void CSMain(uint3 id : SV_DispatchThreadID)
 {
-    float4 position : SV_POSITION;
-    float4 color : COLOR;
+    // 三个顶点以 120 度的相位差各自绕圈运动
+    float phase = time * 2.0 + id.x * 2.0944;
+    outputOffsets[id.x] = float4(0.05 * cos(phase), 0.05 * sin(phase), 0.0, 0.0);
+}
+#endif
+
+#ifdef FILL_BUFFER
+// 最简单的计算着色器：每个线程把自己的编号乘以 fillMultiplier 写入缓冲区的对应位置。
+cbuffer FillConstants : register(b2)
+{
+    uint fillCount;
+    uint fillMultiplier;
+};
+
+RWStructuredBuffer<uint> fillOutput : register(u0);
+
+[numthreads(64, 1, 1)]
+void CSFill(uint3 id : SV_DispatchThreadID)
+{
+    // 线程组数量向上取整，最后一组多出来的线程不能写到缓冲区之外
+    if (id.x >= fillCount)
+    {
+        return;
+    }
+    fillOutput[id.x] = id.x * fillMultiplier;
+}
+#endif
+
+// row_major 让 HLSL 按行读取 CPU 端逐行存放的矩阵，配合 mul(行向量, 矩阵) 使用，不需要在上传前转置。
+cbuffer ObjectConstants : register(b1)
+{
+    row_major float4x4 mvp;
 };
 
-PSInput VSMain(float4 position : POSITION, float4 color : COLOR)
+PSInput VSMain(float4 position : POSITION, float4 color : COLOR,
+#ifdef INSTANCED
+              // 第 1 个输入槽中逐实例读取的数据，同一个实例的所有顶点看到的值相同
+              float2 instanceOffset : INSTANCE_OFFSET, float instanceScale : INSTANCE_SCALE,
+              float4 instanceColor : INSTANCE_COLOR,
+#endif
+              uint vertexId : SV_VertexID, uint instanceId : SV_InstanceID)
 {
     PSInput result;
 
     result.position = position;
+#ifdef ASYNC_COMPUTE
+    result.position.xy += vertexOffsets[vertexId].xy;
+#endif
+#if defined(INSTANCED)
+    // 每个实例缩小后放进网格中的一格，顶点颜色再乘以实例的颜色
+    result.position.xy = result.position.xy * instanceScale + instanceOffset;
+    color *= instanceColor;
+#elif defined(TRANSLUCENT)
+    // 第二个实例向右错开，与第一个部分重叠
+    result.position.x += instanceId * 0.3;
+#endif
+    result.position = mul(result.position, mvp);
     result.color = color;
+#ifdef GBUFFER
+    result.localPosition = position.xyz;
+#endif
 
     return result;
 }
 
+#ifdef GBUFFER
+// 多渲染目标：SV_Target0 写入 OMSetRenderTargets 绑定的第 0 个渲染目标，SV_Target1 写入第 1 个，
+// 顺序和格式要与 PSO 的 RTVFormats 一致。
+struct GBufferOutput
+{
+    float4 normal : SV_Target0;
+    float4 position : SV_Target1;
+};
+
+GBufferOutput PSMain(PSInput input)
+{
+    // 相邻像素之间位置的差都在三角形所在的平面上，它们的叉积就是面法线，不需要顶点法线。
+    // 屏幕的 y 轴朝下，左手坐标系下这样得到的法线指向三角形的正面
+    float3 normal = normalize(cross(ddx(input.localPosition), ddy(input.localPosition)));
+
+    GBufferOutput output;
+    // 后台缓冲区是 UNORM 格式，把 [-1, 1] 的法线映射到 [0, 1] 才能显示出来
+    output.normal = float4(normal * 0.5 + 0.5, 1.0);
+    output.position = float4(input.localPosition, 1.0);
+    return output;
+}
+#else
 float4 PSMain(PSInput input) : SV_TARGET
+{
+    // 颜色亮度随时间缓慢脉动，time 为 0 时保持原色
+    float pulse = 0.75 + 0.25 * cos(time * 3.0);
+    float alpha = input.color.a;
+#ifdef TRANSLUCENT
+    // 开启混合时半透明，重叠的部分能看到两个三角形的颜色
+    alpha *= 0.5;
+#endif
+#if defined(OUTLINE)
+    // 模板描边的第二遍只输出纯色，不受顶点颜色和脉动影响
+    return float4(1.0, 0.8, 0.0, 1.0);
+#elif defined(CHECKERBOARD)
+    // SV_POSITION 在像素着色器中是屏幕像素坐标，按 32x32 像素划分出棋盘格，不需要任何纹理。
+    uint2 cell = uint2(input.position.xy) / 32;
+    float checker = (cell.x + cell.y) % 2 == 0 ? 1.0 : 0.25;
+    return float4(input.color.rgb * checker * pulse, alpha);
+#else
+    return float4(input.color.rgb * pulse, alpha);
+#endif
+}
+#endif
+
+#ifdef PARTICLES
+struct Particle
+{
+    float2 position;
+    float2 velocity;
+};
+
+cbuffer ParticleConstants : register(b1)
+{
+    float deltaTime;
+    uint particleCount;
+    uint reset;
+};
+
+// 计算着色器以 UAV 读写粒子，顶点着色器以 SRV 只读访问同一个缓冲区。
+RWStructuredBuffer<Particle> particlesRW : register(u0);
+StructuredBuffer<Particle> particles : register(t0);
+
+float hash(uint n)
+{
+    n = (n << 13U) ^ n;
+    n = n * (n * n * 15731U + 789221U) + 1376312589U;
+    return float(n & 0x7fffffffU) / float(0x7fffffff);
+}
+
+[numthreads(64, 1, 1)]
+void CSParticles(uint3 id : SV_DispatchThreadID)
+{
+    if (id.x >= particleCount)
+    {
+        return;
+    }
+
+    Particle p = particlesRW[id.x];
+    if (reset != 0)
+    {
+        // 第一帧由粒子编号生成伪随机的初始位置和速度
+        p.position = float2(hash(id.x * 4) * 2.0 - 1.0, hash(id.x * 4 + 1) * 0.5 + 0.5);
+        p.velocity = float2(hash(id.x * 4 + 2) - 0.5, hash(id.x * 4 + 3) - 0.5);
+    }
+
+    // 受重力加速，碰到视口边缘时反弹
+    p.velocity.y -= 0.8 * deltaTime;
+    p.position += p.velocity * deltaTime;
+    if (abs(p.position.x) > 1.0)
+    {
+        p.position.x = clamp(p.position.x, -1.0, 1.0);
+        p.velocity.x = -p.velocity.x;
+    }
+    if (p.position.y < -1.0)
+    {
+        p.position.y = -1.0;
+        p.velocity.y = -p.velocity.y * 0.9;
+    }
+    particlesRW[id.x] = p;
+}
+
+// 每个实例是由两个三角形组成的小方块，顶点位置由 SV_VertexID 生成，不需要顶点缓冲区。
+static const float2 quadCorners[6] =
+{
+    float2(-1.0, -1.0), float2(-1.0, 1.0), float2(1.0, 1.0),
+    float2(-1.0, -1.0), float2(1.0, 1.0), float2(1.0, -1.0),
+};
+
+PSInput VSParticle(uint vertexId : SV_VertexID, uint instanceId : SV_InstanceID)
+{
+    Particle p = particles[instanceId];
+
+    PSInput result;
+    result.position = float4(p.position + quadCorners[vertexId] * 0.004, 0.0, 1.0);
+    float speed = saturate(length(p.velocity));
+    result.color = float4(1.0, 1.0 - 0.5 * speed, speed, 1.0);
+
+    return result;
+}
+
+float4 PSParticle(PSInput input) : SV_TARGET
 {
     return input.color;
 }
+#endif
+
+#ifdef POINT_SPRITES
+// 精灵在 NDC 中的半宽和半高，由 CPU 根据视口尺寸换算，保证精灵在屏幕上是正方形。
+cbuffer SpriteConstants : register(b2)
+{
+    float2 halfSize;
+};
+
+PSInput VSPointSprite(float2 position : POSITION, float4 color : COLOR)
+{
+    PSInput result;
+    result.position = float4(position, 0.0, 1.0);
+    result.color = color;
+
+    return result;
+}
+
+// 没有固定功能的点大小，每个点在这里扩展成由 4 个顶点组成的三角形带（一个与屏幕对齐的方块）。
+[maxvertexcount(4)]
+void GSPointSprite(point PSInput input[1], inout TriangleStream<PSInput> output)
+{
+    static const float2 corners[4] =
+    {
+        float2(-1.0, 1.0), float2(1.0, 1.0), float2(-1.0, -1.0), float2(1.0, -1.0),
+    };
+
+    [unroll]
+    for (uint i = 0; i < 4; i++)
+    {
+        PSInput vertex = input[0];
+        vertex.position.xy += corners[i] * halfSize * input[0].position.w;
+        output.Append(vertex);
+    }
+}
+
+float4 PSPointSprite(PSInput input) : SV_TARGET
+{
+    return input.color;
+}
+#endif
+
+#ifdef TEXTURED_QUAD
+Texture2D quadTexture : register(t0);
+// 根签名中的静态采样器：线性过滤，WRAP 寻址
+SamplerState linearWrap : register(s0);
+
+struct TexturedPSInput
+{
+    float4 position : SV_POSITION;
+    float2 uv : TEXCOORD;
+};
+
+TexturedPSInput VSTexturedQuad(float2 position : POSITION, float2 uv : TEXCOORD)
+{
+    TexturedPSInput result;
+    result.position = float4(position, 0.0, 1.0);
+    result.uv = uv;
+
+    return result;
+}
+
+float4 PSTexturedQuad(TexturedPSInput input) : SV_TARGET
+{
+    return quadTexture.Sample(linearWrap, input.uv);
+}
+#endif